@@ -0,0 +1,39 @@
+////////////////////////////////////////////////////////////////////////////////
+// MODULE NAME: Pipeline Benchmarks
+// ----------------------------------------------------------------------------
+// DESCRIPTION:
+// Benchmarks the full preprocessing pipeline on a medium-sized source mixing
+// declarations, `%SET`, macros, `%COMMENT`, and inline and block `%IF`s.
+//
+// USAGE:
+// - Run with `cargo bench --bench pipeline_bench`.
+//
+// AUTHOR: FirstLink Consulting Services (FLCS)
+// LICENSE: MIT License
+// DATE: 10/16/2026
+// VERSION: 1.0.0
+////////////////////////////////////////////////////////////////////////////////
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use pli_preprocessor::modules::context::Context;
+use pli_preprocessor::modules::pipeline::{preprocess_string, ProcessOptions};
+
+/// The benchmark input, about 120 lines.
+const MEDIUM_SOURCE: &str = include_str!("data/medium.pli");
+
+/// Benchmarks `preprocess_string` on `MEDIUM_SOURCE` with default options.
+fn bench_preprocess_string(c: &mut Criterion) {
+    let options = ProcessOptions::default();
+    // A source that fails would only measure the error path.
+    assert!(
+        preprocess_string(MEDIUM_SOURCE, &options, &mut Context::new()).is_ok(),
+        "benches/data/medium.pli should preprocess without errors"
+    );
+
+    c.bench_function("preprocess_string/medium", |b| {
+        b.iter(|| preprocess_string(black_box(MEDIUM_SOURCE), &options, &mut Context::new()))
+    });
+}
+
+criterion_group!(benches, bench_preprocess_string);
+criterion_main!(benches);
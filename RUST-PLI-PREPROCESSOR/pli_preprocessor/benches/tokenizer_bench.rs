@@ -0,0 +1,45 @@
+////////////////////////////////////////////////////////////////////////////////
+// MODULE NAME: Tokenizer Benchmarks
+// ----------------------------------------------------------------------------
+// DESCRIPTION:
+// Benchmarks `tokenize_pli` on single lines that each stress a different path
+// of the tokenizer, so a regression shows up in the benchmark of its path.
+//
+// FUNCTIONALITY:
+// - `short_directive`: a typical `%IF` line, dominated by directive lookup.
+// - `long_string_literal`: one long quoted literal with escaped quotes.
+// - `special_characters`: a line made mostly of operators and separators.
+//
+// USAGE:
+// - Run with `cargo bench --bench tokenizer_bench`.
+//
+// AUTHOR: FirstLink Consulting Services (FLCS)
+// LICENSE: MIT License
+// DATE: 10/16/2026
+// VERSION: 1.0.0
+////////////////////////////////////////////////////////////////////////////////
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use pli_preprocessor::modules::tokenizer::tokenize_pli;
+
+/// Benchmarks each representative line under its own name.
+fn bench_tokenize_pli(c: &mut Criterion) {
+    let long_literal = format!("MSG = '{}';", "IT''S A LONG MESSAGE, ".repeat(40));
+    let inputs = [
+        ("short_directive", "%IF DEBUG = 1 %THEN;".to_string()),
+        ("long_string_literal", long_literal),
+        (
+            "special_characters",
+            "X=(A+B)*(C-D)/E**2;Y=^(P&Q)|(R>=S)&(T<=U);Z=V||W->F.G(1,2);".to_string(),
+        ),
+    ];
+
+    let mut group = c.benchmark_group("tokenize_pli");
+    for (name, line) in &inputs {
+        group.bench_function(*name, |b| b.iter(|| tokenize_pli(black_box(line))));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_tokenize_pli);
+criterion_main!(benches);
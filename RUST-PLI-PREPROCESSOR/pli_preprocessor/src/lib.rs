@@ -36,16 +36,24 @@
 // -----------------------------------------------------------------------------
 ////////////////////////////////////////////////////////////////////////////////
 
-#![allow(unused_imports)] // Allows unused imports during development.
-
 pub mod modules {
+    pub mod analysis;
     pub mod conditional;
+    pub mod context;
+    pub mod diagnostics;
+    pub mod directive;
+    pub mod error;
     pub mod evaluator;
+    pub mod formatter;
     pub mod include_handler;
+    pub mod labels;
     pub mod logger;
     pub mod macro_expander;
     pub mod output;
     pub mod parser;
+    pub mod pipeline;
+    pub mod procedures;
+    pub mod symbols;
     pub mod tokenizer;
     pub mod validator;
 }
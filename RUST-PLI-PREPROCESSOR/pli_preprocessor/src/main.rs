@@ -17,6 +17,7 @@
 // - Tokenizes lines from an input file.
 // - Validates preprocessor directives.
 // - Supports macro expansion, include resolution, conditional execution, and more.
+// - Inlines `%INCLUDE`d files, optionally marking where each one begins and ends.
 // - Generates transformed output and detailed logs.
 //
 // Purpose:
@@ -25,9 +26,52 @@
 // practical tool.
 //
 // Usage:
-// $ cargo run <input_file> <output_file> <log_file> [--verbose] [--dry-run]
+// $ cargo run <input_file> <output_file> <log_file> [--verbose] [--dry-run] [--diff] [--stats] [--analyze] [--force] [--include-markers] [--keep-include-directives] [--quiet] [--log-to-stderr] [--keyword-case=upper|lower|preserve] [--warn-indentation] [--dedup-diagnostics] [--trace] [--undefined-vars=error|zero|empty] [--diagnostic-format=human|json|gcc] [--continuation-char=<char>] [--listing=<file>] [--expansion-report=<file>] [--include-path <dir> | -I <dir>]...
 //
-// The results will be written to the specified output and log files.
+// The results will be written to the specified output and log files. Processing
+// is skipped when the output is newer than the input and all of its includes,
+// unless `--force` is given. With `--diff`, the output file is left untouched
+// and a unified diff from it to the fresh output is printed instead.
+//
+// $ cargo run <input_file> --list-includes[=tree|json|dot] [-I <dir>]...
+//
+// Prints the include dependency graph of the input file instead of processing it.
+//
+// $ cargo run <input_file> --dump-ast[=tree|json]
+//
+// Prints the statement and block tree parsed from the input file.
+//
+// $ cargo run <input_file> --format
+//
+// Prints the input file with normalized spacing, directive case, and block
+// indentation, without preprocessing it.
+//
+// $ cargo run <input_file> --count-tokens[=table|json]
+//
+// Prints token, identifier, and directive counts for the input file, for
+// profiling its complexity.
+//
+// $ cargo run <input_file> --emit-tokens[=csv]
+//
+// Prints one CSV row per token of the input file, for spreadsheet analysis.
+//
+// $ cargo run <input_file> --emit=html
+//
+// Prints the input file as an HTML page with its tokens highlighted by
+// category, for documentation and code review.
+//
+// $ cargo run -- --repl
+//
+// Reads lines from stdin and prints each line's tokens, the value of each
+// `%SET`, `%IF` condition, or expression, and macro expansions. Variables and
+// macros persist from one line to the next.
+//
+// $ cargo run -- --explain <code>
+//
+// Prints a longer description and an example fix for a diagnostic code such
+// as `V002`.
+//
+// $ cargo run -- --version | --help
 //
 // Company Mission:
 // At FirstLink Consulting Services (FLCS), we specialize in delivering
@@ -37,118 +81,199 @@
 ////////////////////////////////////////////////////////////////////////////////
 
 use pli_preprocessor::modules::{
-    conditional, evaluator, include_handler, logger, macro_expander, output,
-    tokenizer::{has_tokenizer_error, is_valid_preprocessor_directive, tokenize_pli},
+    conditional,
+    context::{Context, UndefinedPolicy},
+    diagnostics::{self, DiagnosticFormat, Severity},
+    error::PreprocessorError,
+    evaluator, formatter, include_handler, logger,
+    macro_expander::{self, MacroTable},
+    output::{self, KeywordCase, OutputSink},
+    parser,
+    pipeline::{self, ProcessOptions},
+    tokenizer::{
+        self, has_tokenizer_error, is_valid_preprocessor_directive, tokenize_pli, DirectiveCategory,
+    },
     validator,
 };
 
 use chrono::Local; // For timestamps in logging.
-use log::{debug, error, info, warn};
+use log::{debug, error, info, warn, LevelFilter};
+use std::collections::HashMap;
 use std::env; // Handles command-line arguments.
+use std::fmt;
 use std::fs::File; // Enables file operations.
 use std::io::{self, BufRead, Write}; // Provides buffered I/O utilities.
-use std::path::Path; // Allows manipulation of file paths.
+use std::path::{Path, PathBuf}; // Allows manipulation of file paths.
 use std::time::Instant;
 
+/// File extensions accepted for PL/I input files (compared case-insensitively).
+const ALLOWED_EXTENSIONS: [&str; 2] = ["pp", "pli"];
+
+/// Validates that the input file path carries a supported extension.
+///
+/// The extension is extracted with `Path::extension()` and compared
+/// case-insensitively against `ALLOWED_EXTENSIONS`, so `report.PLI` is accepted
+/// while `report.notpli` and extensionless files are rejected. A gzipped input
+/// is judged by the extension before its `.gz`, so `report.pli.gz` is accepted.
+///
+/// # Arguments
+/// - `path`: The input file path as given on the command line.
+///
+/// # Returns
+/// - `Result<(), String>`: Returns `Ok(())` if the extension is supported, or an
+///   `Err(String)` describing why the path was rejected.
+fn validate_input_path(path: &str) -> Result<(), String> {
+    let input = Path::new(path);
+    let inner = if include_handler::is_gzip_path(input) {
+        Path::new(input.file_stem().unwrap_or_default())
+    } else {
+        input
+    };
+    let extension = inner
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .ok_or_else(|| format!("Input file '{}' has no file extension.", path))?;
+
+    if ALLOWED_EXTENSIONS
+        .iter()
+        .any(|allowed| allowed.eq_ignore_ascii_case(extension))
+    {
+        Ok(())
+    } else {
+        Err(format!(
+            "Unsupported input file extension '.{}'. Only .pp and .pli files are allowed.",
+            extension
+        ))
+    }
+}
+
+/// Process exit codes, one per class of failure, so scripts can tell them apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::enum_variant_names)] // The names mirror the documented failure classes.
+enum ExitCode {
+    /// The command line was malformed or named an unsupported input file.
+    UsageError = 2,
+    /// A file could not be read or written.
+    IoError = 3,
+    /// Processing reported errors in the source.
+    SyntaxError = 4,
+    /// An included file could not be resolved or read.
+    IncludeError = 5,
+}
+
+/// A failure of `run`, carrying the exit code `main` should report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct AppError {
+    code: ExitCode,
+    message: String,
+}
+
+impl AppError {
+    fn new(code: ExitCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl From<io::Error> for AppError {
+    fn from(e: io::Error) -> Self {
+        AppError::new(ExitCode::IoError, format!("Error processing file: {}", e))
+    }
+}
+
+impl From<PreprocessorError> for AppError {
+    fn from(e: PreprocessorError) -> Self {
+        match e {
+            PreprocessorError::Io(e) => e.into(),
+            PreprocessorError::Include { .. } => {
+                AppError::new(ExitCode::IncludeError, format!("Error: {}", e))
+            }
+            _ => AppError::new(ExitCode::SyntaxError, format!("Error: {}", e)),
+        }
+    }
+}
+
 /// Processes the input file line by line and applies the preprocessor workflow.
-/// This includes tokenization, validation, macro expansion, conditional evaluation, and more.
+/// The per-line work is delegated to `pipeline::process_reader`; this function
+/// opens the files, times the run, and reports the results.
 ///
 /// # Arguments
 /// - `input_file`: The path to the input PL/I file.
 /// - `output_file`: The path to the file where processed output will be written.
 /// - `log_file`: The path to the log file for detailed logs.
-/// - `verbose`: A boolean flag to control detailed console output.
-/// - `dry_run`: A boolean flag to simulate processing without writing output.
+/// - `options`: The `ProcessOptions` controlling console output, dry-run, and statistics.
+/// - `diagnostic_format`: How diagnostics are printed to stdout after the run;
+///   `Human` leaves them to the log.
+/// - `listing_file`: Where to write the source listing, if anywhere.
+/// - `expansion_report`: Where to write the expansion report, if anywhere.
 ///
 /// # Returns
-/// A `Result` indicating success or an I/O error.
+/// - `Result<Vec<String>, AppError>`: The output lines held back by a dry run
+///   (none otherwise) if the file was processed without errors; an
+///   `IncludeError` if an include cannot be inlined, an `IoError` for other
+///   file problems, or a `SyntaxError` if the source has errors. Includes are
+///   inlined before conditionals are evaluated, so a missing include is an
+///   `IncludeError` even inside a `%IF` branch that is not taken.
 fn process_file(
     input_file: &str,
     output_file: &str,
     log_file: &str,
-    verbose: bool,
-    dry_run: bool,
-) -> io::Result<()> {
+    options: &ProcessOptions,
+    diagnostic_format: DiagnosticFormat,
+    listing_file: Option<&str>,
+    expansion_report: Option<&str>,
+) -> Result<Vec<String>, AppError> {
     // Create `Path` objects for input, output, and log files.
     let path = Path::new(input_file);
-    let log_path = Path::new(log_file);
     let output_path = Path::new(output_file);
 
-    // Open the input file and create buffered readers and writers.
-    let file = File::open(&path)?;
-    let reader = io::BufReader::new(file);
-    let mut _log = File::create(&log_path)?;
-    let mut output = if dry_run {
-        None // Do not create the output file if dry-run is enabled.
+    // Inline the includes, then read the flattened source. The input itself is
+    // read first so that its own problems are not reported as include errors.
+    let input_lines = include_handler::read_file(path)?.lines().count();
+    let (source, includes_resolved) = include_handler::flatten_includes_counted(
+        path,
+        options.inline_options(),
+        &options.include_paths,
+    )?;
+    // The listing covers the inlined includes too, and is skipped in a dry run
+    // like the output.
+    if let Some(listing_file) = listing_file.filter(|_| !options.dry_run) {
+        std::fs::write(listing_file, output::format_listing(&source))?;
+    }
+    let reader = io::Cursor::new(source);
+    let mut held_lines = Vec::<String>::new();
+    let mut output_handle;
+    let sink: &mut dyn OutputSink = if options.dry_run {
+        &mut held_lines // Do not create the output file if dry-run is enabled.
     } else {
-        Some(File::create(&output_path)?)
+        output_handle = File::create(output_path)?;
+        &mut output_handle
     };
 
     // Log the processing start with a timestamp.
     let start_time = Instant::now(); // Start overall time
     info!("Processing started: {}", Local::now());
 
-    // Iterate through each line in the input file.
-    for (line_number, line) in reader.lines().enumerate() {
-        let _line_start_time = Instant::now(); // Start timer for each line
-        match line {
-            Ok(content) => {
-                if content.trim().is_empty() {
-                    continue; // Skip blank lines.
-                }
+    let mut result = pipeline::process_reader(reader, sink, options)?;
+    result.stats.includes_resolved = includes_resolved;
+    let stats = &result.stats;
 
-                if verbose {
-                    info!("Processing line {}: {}", line_number + 1, content);
-                }
-
-                // Phase 1: Tokenization
-                let tokenize_start = Instant::now();
-                let tokens = tokenize_pli(&content);
-                let tokenize_elapsed = tokenize_start.elapsed();
-                debug!(
-                    "Line {} Tokenization took: {:.2?} - Tokens: {:?}",
-                    line_number + 1,
-                    tokenize_elapsed,
-                    tokens
-                );
-                info!("Line {} Tokens: {:?}", line_number + 1, tokens);
-
-                // Phase 2: Validation
-                // TODO: Validate the syntax of the tokenized line.
-                // if validator::validate_syntax(&tokens) {
-                //     writeln!(log, "Line {}: Syntax Valid", line_number + 1)?;
-                // } else {
-                //     writeln!(log, "Line {}: Syntax Error", line_number + 1)?;
-                //     continue; // Skip further processing for invalid lines.
-                // }
-
-                // Phase 3: Macro Expansion
-                // TODO: Expand macros in the line.
-                // macro_expander::expand_macro("...");
-
-                // Phase 4: Expression Evaluation
-                // TODO: Evaluate conditional expressions in the line.
-                // evaluator::evaluate_expression("...");
-
-                // Phase 5: Include Resolution
-                // TODO: Resolve includes to replace lines dynamically.
-                // include_handler::handle_include("...");
-
-                // Phase 6: Conditional Execution
-                // TODO: Process conditional statements.
-                // conditional::process_condition("...");
-
-                // Phase 7: Output Generation
-                if let Some(ref mut output_file) = output {
-                    writeln!(output_file, "{}", content)?; // Write processed line to output file.
-                }
-            }
-            Err(e) => {
-                error!("Error reading line {}: {}", line_number + 1, e);
-            }
-        }
+    // Diagnostics are logged by the pipeline as they are found; machine-readable
+    // formats are printed as well, for editors and CI annotations.
+    if diagnostic_format != DiagnosticFormat::Human {
+        print!(
+            "{}",
+            diagnostics::format_diagnostics(&result.diagnostics, diagnostic_format, input_file)
+        );
     }
-
     // Log processing completion with a timestamp.
     let total_elapsed = start_time.elapsed();
     info!(
@@ -156,13 +281,630 @@ fn process_file(
         Local::now(),
         total_elapsed
     );
+    info!(
+        "Lines read: {}, lines written: {}",
+        stats.lines_read, stats.lines_written
+    );
     info!("Output written to: {}", output_file);
 
-    if verbose {
+    if options.stats {
+        print_directive_stats(&stats.directive_counts);
+    }
+    if let Some(report_file) = expansion_report.filter(|_| !options.dry_run) {
+        std::fs::write(
+            report_file,
+            pipeline::format_expansion_report(input_file, input_lines, stats),
+        )?;
+    }
+
+    if options.verbose {
         println!("Processing completed. Log written to: {}", log_file);
     }
 
-    Ok(())
+    let errors = result
+        .diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .count();
+    if errors > 0 {
+        return Err(AppError::new(
+            ExitCode::SyntaxError,
+            format!("{} error(s) reported; see {}", errors, log_file),
+        ));
+    }
+
+    Ok(held_lines)
+}
+
+/// Renders, for `--diff`, how the freshly processed `lines` differ from the
+/// current content of `output_file`.
+///
+/// A missing output file is compared as empty, so every line shows as added.
+///
+/// # Arguments
+/// - `output_file`: The existing output file.
+/// - `lines`: The output lines of the dry run.
+///
+/// # Returns
+/// - `Result<String, AppError>`: The unified diff, empty if nothing would
+///   change, or an `IoError` if the output file exists but cannot be read.
+fn diff_output(output_file: &str, lines: &[String]) -> Result<String, AppError> {
+    let current = match std::fs::read_to_string(output_file) {
+        Ok(current) => current,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => {
+            return Err(AppError::new(
+                ExitCode::IoError,
+                format!("Error: Failed to read {}: {}", output_file, e),
+            ))
+        }
+    };
+    let fresh: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+    Ok(output::unified_diff(
+        &current,
+        &fresh,
+        output_file,
+        &format!("{} (processed)", output_file),
+    ))
+}
+
+/// Usage line for a processing run.
+const USAGE: &str = "Usage: pli_preprocessor <input_file> <output_file> <log_file> [--verbose] [--dry-run] [--diff] [--stats] [--analyze] [--force] [--include-markers] [--keep-include-directives] [--quiet] [--log-to-stderr] [--keyword-case=upper|lower|preserve] [--warn-indentation] [--dedup-diagnostics] [--trace] [--undefined-vars=error|zero|empty] [--diagnostic-format=human|json|gcc] [--continuation-char=<char>] [--listing=<file>] [--expansion-report=<file>] [--include-path <dir> | -I <dir>]... [--verbosity=<level>]";
+
+/// Usage line for `--list-includes`.
+const LIST_INCLUDES_USAGE: &str =
+    "Usage: pli_preprocessor <input_file> --list-includes[=tree|json|dot] [--include-path <dir> | -I <dir>]...";
+
+/// Usage line for `--dump-ast`.
+const DUMP_AST_USAGE: &str = "Usage: pli_preprocessor <input_file> --dump-ast[=tree|json]";
+
+/// Usage line for `--format`.
+const FORMAT_USAGE: &str = "Usage: pli_preprocessor <input_file> --format";
+
+/// Usage line for `--count-tokens`.
+const COUNT_TOKENS_USAGE: &str = "Usage: pli_preprocessor <input_file> --count-tokens[=table|json]";
+
+/// Usage line for `--emit-tokens`.
+const EMIT_TOKENS_USAGE: &str = "Usage: pli_preprocessor <input_file> --emit-tokens[=csv]";
+
+/// Usage line for `--emit`.
+const EMIT_USAGE: &str = "Usage: pli_preprocessor <input_file> --emit=html";
+
+/// Usage line for `--repl`.
+const REPL_USAGE: &str = "Usage: pli_preprocessor --repl";
+
+/// The prompt `--repl` prints before reading each line.
+const REPL_PROMPT: &str = "pli> ";
+
+/// Usage line for `--explain`.
+const EXPLAIN_USAGE: &str = "Usage: pli_preprocessor --explain <code>";
+
+/// The settings of a processing run, as given on the command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RunConfig {
+    input_file: String,
+    output_file: String,
+    log_file: String,
+    options: ProcessOptions,
+    force: bool,
+    quiet: bool,
+    log_to_stderr: bool,
+    verbosity_level: u8,
+    diagnostic_format: DiagnosticFormat,
+    listing_file: Option<String>,
+    expansion_report: Option<String>,
+    diff: bool,
+}
+
+/// What the command line asks the program to do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Command {
+    /// Print the crate version and exit.
+    Version,
+    /// Print the usage text and exit.
+    Help,
+    /// Print the include dependency graph of `input_file` in `format`.
+    ListIncludes {
+        input_file: String,
+        format: String,
+        include_paths: Vec<PathBuf>,
+    },
+    /// Print the parsed statement tree of `input_file` in `format`.
+    DumpAst { input_file: String, format: String },
+    /// Print `input_file` formatted, without preprocessing it.
+    Format { input_file: String },
+    /// Print the token statistics of `input_file` in `format`.
+    CountTokens { input_file: String, format: String },
+    /// Print every token of `input_file` in `format`.
+    EmitTokens { input_file: String, format: String },
+    /// Print a highlighted view of `input_file` in `format`.
+    Emit { input_file: String, format: String },
+    /// Print the help text of a diagnostic code.
+    Explain { code: String },
+    /// Tokenize and evaluate lines read from stdin.
+    Repl,
+    /// Preprocess a file.
+    Process(RunConfig),
+}
+
+/// Parses the command-line arguments into a `Command`.
+///
+/// `--version` and `--help` win over everything else and need no positional
+/// arguments.
+///
+/// # Arguments
+/// - `args`: The full argument list, including the program name.
+///
+/// # Returns
+/// - `Result<Command, String>`: The requested command, or the usage text if
+///   the arguments are malformed.
+fn parse_args(args: &[String]) -> Result<Command, String> {
+    let has_flag = |flag: &str| args.iter().skip(1).any(|arg| arg == flag);
+
+    if has_flag("--version") {
+        return Ok(Command::Version);
+    }
+    if has_flag("--help") {
+        return Ok(Command::Help);
+    }
+    if has_flag("--repl") {
+        return Ok(Command::Repl);
+    }
+    if let Some(index) = args.iter().position(|arg| arg == "--explain") {
+        return match args.get(index + 1).filter(|code| !code.starts_with('-')) {
+            Some(code) => Ok(Command::Explain { code: code.clone() }),
+            None => Err(EXPLAIN_USAGE.to_string()),
+        };
+    }
+
+    // `--include-path <dir>` and `-I <dir>` may be repeated; the directories
+    // are searched in the order given.
+    let mut include_paths = Vec::new();
+    let mut remaining = args.iter().skip(1);
+    while let Some(arg) = remaining.next() {
+        if arg != "--include-path" && arg != "-I" {
+            continue;
+        }
+        match remaining.next() {
+            Some(dir) if !dir.starts_with('-') => include_paths.push(PathBuf::from(dir)),
+            _ => return Err(format!("{} expects a directory.\n{}", arg, USAGE)),
+        }
+    }
+
+    if let Some(flag) = args.iter().find(|arg| arg.starts_with("--list-includes")) {
+        let format = flag.strip_prefix("--list-includes=").unwrap_or("tree");
+        if !["tree", "json", "dot"].contains(&format) {
+            return Err(LIST_INCLUDES_USAGE.to_string());
+        }
+        return match args.get(1).filter(|arg| !arg.starts_with("--")) {
+            Some(input_file) => Ok(Command::ListIncludes {
+                input_file: input_file.clone(),
+                format: format.to_string(),
+                include_paths,
+            }),
+            None => Err(LIST_INCLUDES_USAGE.to_string()),
+        };
+    }
+
+    if let Some(flag) = args.iter().find(|arg| arg.starts_with("--dump-ast")) {
+        let format = flag.strip_prefix("--dump-ast=").unwrap_or("tree");
+        if !["tree", "json"].contains(&format) {
+            return Err(DUMP_AST_USAGE.to_string());
+        }
+        return match args.get(1).filter(|arg| !arg.starts_with("--")) {
+            Some(input_file) => Ok(Command::DumpAst {
+                input_file: input_file.clone(),
+                format: format.to_string(),
+            }),
+            None => Err(DUMP_AST_USAGE.to_string()),
+        };
+    }
+
+    if let Some(flag) = args.iter().find(|arg| arg.starts_with("--count-tokens")) {
+        let format = flag.strip_prefix("--count-tokens=").unwrap_or("table");
+        if !["table", "json"].contains(&format) {
+            return Err(COUNT_TOKENS_USAGE.to_string());
+        }
+        return match args.get(1).filter(|arg| !arg.starts_with("--")) {
+            Some(input_file) => Ok(Command::CountTokens {
+                input_file: input_file.clone(),
+                format: format.to_string(),
+            }),
+            None => Err(COUNT_TOKENS_USAGE.to_string()),
+        };
+    }
+
+    if let Some(flag) = args.iter().find(|arg| arg.starts_with("--emit-tokens")) {
+        let format = flag.strip_prefix("--emit-tokens=").unwrap_or("csv");
+        if format != "csv" {
+            return Err(EMIT_TOKENS_USAGE.to_string());
+        }
+        return match args.get(1).filter(|arg| !arg.starts_with("--")) {
+            Some(input_file) => Ok(Command::EmitTokens {
+                input_file: input_file.clone(),
+                format: format.to_string(),
+            }),
+            None => Err(EMIT_TOKENS_USAGE.to_string()),
+        };
+    }
+
+    if let Some(flag) = args
+        .iter()
+        .find(|arg| *arg == "--emit" || arg.starts_with("--emit="))
+    {
+        if flag != "--emit=html" {
+            return Err(EMIT_USAGE.to_string());
+        }
+        return match args.get(1).filter(|arg| !arg.starts_with("--")) {
+            Some(input_file) => Ok(Command::Emit {
+                input_file: input_file.clone(),
+                format: "html".to_string(),
+            }),
+            None => Err(EMIT_USAGE.to_string()),
+        };
+    }
+
+    if has_flag("--format") {
+        return match args.get(1).filter(|arg| !arg.starts_with("--")) {
+            Some(input_file) => Ok(Command::Format {
+                input_file: input_file.clone(),
+            }),
+            None => Err(FORMAT_USAGE.to_string()),
+        };
+    }
+
+    // Ensure the input, output, and log files are provided. Each include
+    // directory takes two arguments, and does not count as one of them.
+    if args.len() - 2 * include_paths.len() < 4 {
+        return Err(USAGE.to_string());
+    }
+    if has_flag("--quiet") && has_flag("--verbose") {
+        return Err(format!(
+            "--quiet and --verbose cannot be combined.\n{}",
+            USAGE
+        ));
+    }
+
+    let keyword_case = match args
+        .iter()
+        .find_map(|arg| arg.strip_prefix("--keyword-case="))
+    {
+        Some(case) => case
+            .parse::<KeywordCase>()
+            .map_err(|e| format!("{}. Expected upper, lower, or preserve.\n{}", e, USAGE))?,
+        None => KeywordCase::Preserve,
+    };
+    let undefined_vars = match args
+        .iter()
+        .find_map(|arg| arg.strip_prefix("--undefined-vars="))
+    {
+        Some(policy) => Some(
+            policy
+                .parse::<UndefinedPolicy>()
+                .map_err(|e| format!("{}. Expected error, zero, or empty.\n{}", e, USAGE))?,
+        ),
+        None => None,
+    };
+    let diagnostic_format = match args
+        .iter()
+        .find_map(|arg| arg.strip_prefix("--diagnostic-format="))
+    {
+        Some(format) => format
+            .parse::<DiagnosticFormat>()
+            .map_err(|e| format!("{}. Expected human, json, or gcc.\n{}", e, USAGE))?,
+        None => DiagnosticFormat::Human,
+    };
+    let continuation_char = match args
+        .iter()
+        .find_map(|arg| arg.strip_prefix("--continuation-char="))
+    {
+        Some(indicator) => {
+            let mut chars = indicator.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Some(c),
+                _ => {
+                    return Err(format!(
+                        "--continuation-char expects a single character, got '{}'.\n{}",
+                        indicator, USAGE
+                    ))
+                }
+            }
+        }
+        None => None,
+    };
+
+    let builder = include_paths
+        .into_iter()
+        .fold(ProcessOptions::builder(), |builder, dir| {
+            builder.include_path(dir)
+        });
+    let options = builder
+        .verbose(has_flag("--verbose"))
+        // `--diff` previews the output, so it never writes it.
+        .dry_run(has_flag("--dry-run") || has_flag("--diff"))
+        .stats(has_flag("--stats"))
+        .analyze(has_flag("--analyze"))
+        .include_markers(has_flag("--include-markers"))
+        .keep_include_directives(has_flag("--keep-include-directives"))
+        .keyword_case(keyword_case)
+        .warn_indentation(has_flag("--warn-indentation"))
+        .dedup_diagnostics(has_flag("--dedup-diagnostics"))
+        .trace(has_flag("--trace"))
+        .undefined_vars(undefined_vars)
+        .continuation_char(continuation_char)
+        .build();
+
+    let verbosity_level = args
+        .iter()
+        .find(|arg| arg.starts_with("--verbosity="))
+        .and_then(|arg| arg.split('=').nth(1))
+        .unwrap_or("2") // Default verbosity level
+        .parse::<u8>()
+        .unwrap_or(2); // Default to INFO level if invalid
+
+    // Trace events are only written at the TRACE level.
+    let verbosity_level = if options.trace {
+        verbosity_level.max(32)
+    } else {
+        verbosity_level
+    };
+
+    Ok(Command::Process(RunConfig {
+        input_file: args[1].clone(),
+        output_file: args[2].clone(),
+        log_file: args[3].clone(),
+        options,
+        force: has_flag("--force"),
+        quiet: has_flag("--quiet"),
+        log_to_stderr: has_flag("--log-to-stderr"),
+        verbosity_level,
+        diagnostic_format,
+        listing_file: args
+            .iter()
+            .find_map(|arg| arg.strip_prefix("--listing="))
+            .map(str::to_string),
+        expansion_report: args
+            .iter()
+            .find_map(|arg| arg.strip_prefix("--expansion-report="))
+            .map(str::to_string),
+        diff: has_flag("--diff"),
+    }))
+}
+
+/// Renders the include dependency graph of `input_file` for `--list-includes`.
+///
+/// # Arguments
+/// - `input_file`: The root source file.
+/// - `format`: `tree` (the default), `json`, or `dot`.
+/// - `include_paths`: The include search directories, earliest first.
+///
+/// # Returns
+/// - `Result<String, AppError>`: The rendered graph, or the failure if the
+///   format is unknown or a file cannot be read.
+fn list_includes(
+    input_file: &str,
+    format: &str,
+    include_paths: &[PathBuf],
+) -> Result<String, AppError> {
+    let root = Path::new(input_file);
+    let edges = include_handler::collect_include_edges_with_paths(root, include_paths)?;
+    match format {
+        "tree" => Ok(include_handler::format_include_tree(root, &edges)),
+        "json" => Ok(include_handler::format_include_json(&edges)),
+        "dot" => Ok(include_handler::format_include_dot(&edges)),
+        other => Err(AppError::new(
+            ExitCode::UsageError,
+            format!(
+                "Unknown --list-includes format '{}'. Expected tree, json, or dot.",
+                other
+            ),
+        )),
+    }
+}
+
+/// Renders the statement tree of `input_file` for `--dump-ast`.
+///
+/// # Arguments
+/// - `input_file`: The source file to parse.
+/// - `format`: `tree` (the default) or `json`.
+///
+/// # Returns
+/// - `Result<String, AppError>`: The rendered tree, or the failure if the file
+///   cannot be read, its blocks are unbalanced, or the format is unknown.
+fn dump_ast(input_file: &str, format: &str) -> Result<String, AppError> {
+    let source = include_handler::read_file(Path::new(input_file))?;
+    let nodes = parser::parse_program(&source)?;
+    match format {
+        "tree" => Ok(parser::format_ast_tree(&nodes)),
+        "json" => parser::format_ast_json(&nodes)
+            .map(|json| json + "\n")
+            .map_err(|e| AppError::new(ExitCode::IoError, format!("Error: {}", e))),
+        other => Err(AppError::new(
+            ExitCode::UsageError,
+            format!(
+                "Unknown --dump-ast format '{}'. Expected tree or json.",
+                other
+            ),
+        )),
+    }
+}
+
+/// Renders the token statistics of `input_file` for `--count-tokens`.
+///
+/// Each line is tokenized on its own, as the pipeline does, and the counts
+/// cover the whole file.
+///
+/// # Arguments
+/// - `input_file`: The source file to tokenize.
+/// - `format`: `table` (the default) or `json`.
+///
+/// # Returns
+/// - `Result<String, AppError>`: The rendered statistics, or the failure if the
+///   file cannot be read or the format is unknown.
+fn count_tokens(input_file: &str, format: &str) -> Result<String, AppError> {
+    let source = include_handler::read_file(Path::new(input_file))?;
+    let tokens: Vec<_> = source.lines().flat_map(tokenize_pli).collect();
+    let stats = tokenizer::token_stats(&tokens);
+    match format {
+        "table" => Ok(tokenizer::format_token_stats_table(&stats)),
+        "json" => Ok(tokenizer::format_token_stats_json(&stats)),
+        other => Err(AppError::new(
+            ExitCode::UsageError,
+            format!(
+                "Unknown --count-tokens format '{}'. Expected table or json.",
+                other
+            ),
+        )),
+    }
+}
+
+/// Runs the `--repl` loop: reads lines from `input` until end of input or a
+/// `:quit` line, and writes the replies for each to `output`.
+///
+/// Variables set with `%SET` and macros defined with `%MACRO` persist across
+/// lines. Each non-blank line's replies are described at `repl_line`.
+///
+/// # Arguments
+/// - `input`: Where the lines come from, e.g. locked stdin.
+/// - `output`: Where the prompts and replies go.
+///
+/// # Returns
+/// - `io::Result<()>`: `Ok(())` once the input ends, or the I/O error.
+fn repl(input: impl BufRead, output: &mut impl Write) -> io::Result<()> {
+    let mut context = Context::new();
+    let mut macros = MacroTable::new();
+    write!(output, "{}", REPL_PROMPT)?;
+    output.flush()?;
+    for line in input.lines() {
+        let line = line?;
+        if line.trim() == ":quit" {
+            break;
+        }
+        if !line.trim().is_empty() {
+            for reply in repl_line(&line, &mut context, &mut macros) {
+                writeln!(output, "{}", reply)?;
+            }
+        }
+        write!(output, "{}", REPL_PROMPT)?;
+        output.flush()?;
+    }
+    writeln!(output)
+}
+
+/// Tokenizes and evaluates one `--repl` line, returning the replies to print.
+///
+/// The first reply lists the tokens as `VALUE[Category]`. It is followed by:
+/// - for `%SET name = expression;`, the value assigned, kept in `context`;
+/// - for `%MACRO name; body %ENDMACRO;` on one line, the macro defined in
+///   `macros`;
+/// - for `%IF condition %THEN`, the truth of the condition;
+/// - for other lines, the text after macro expansion if it differs, and the
+///   value of the line as an expression unless it ends in `;`.
+///
+/// Failures are replied as `error: <message>`.
+fn repl_line(line: &str, context: &mut Context, macros: &mut MacroTable) -> Vec<String> {
+    let tokens = tokenize_pli(line);
+    let listed: Vec<String> = tokens
+        .iter()
+        .map(|t| format!("{}[{}]", t.value, t.category.as_str()))
+        .collect();
+    let mut replies = vec![format!("tokens: {}", listed.join(" "))];
+    let until_semicolon = |from: usize| -> Vec<&_> {
+        tokens
+            .iter()
+            .skip(from)
+            .take_while(|t| t.value != ";")
+            .collect()
+    };
+
+    let reply = match tokens.first().map(|t| t.value.as_str()) {
+        Some("%SET") => match (tokens.get(1), tokens.get(2).map(|t| t.value.as_str())) {
+            (Some(name), Some("=")) => evaluator::evaluate_tokens(&until_semicolon(3), context)
+                .map(|value| {
+                    context.set(&name.value, value.clone());
+                    format!("{} = {:?}", name.value, value)
+                }),
+            _ => Err("%SET requires 'name = expression'".to_string()),
+        },
+        Some("%MACRO") => match tokens
+            .get(1)
+            .filter(|t| t.category == tokenizer::TokenCategory::Identifier)
+        {
+            Some(name) => {
+                let remainder = line.split_once(';').map_or("", |(_, rest)| rest);
+                match macro_expander::split_macro_body(remainder) {
+                    (body, true) => {
+                        macros.define(&name.value, &body);
+                        Ok(format!("macro {} = {}", name.value, body))
+                    }
+                    _ => Err(format!(
+                        "%MACRO {} must end with %ENDMACRO on the same line",
+                        name.value
+                    )),
+                }
+            }
+            None => Err("%MACRO requires a name".to_string()),
+        },
+        Some("%IF") => {
+            let condition: Vec<tokenizer::Token> = tokens[1..]
+                .iter()
+                .take_while(|t| t.value != "%THEN")
+                .cloned()
+                .collect();
+            conditional::evaluate_condition_tokens(&condition, context)
+                .map(|truth| format!("condition: {}", truth))
+        }
+        Some(first) if first.starts_with('%') => return replies,
+        _ => {
+            let expanded = macros.expand_line(line);
+            if expanded != line {
+                replies.push(format!("expanded: {}", expanded));
+            }
+            if expanded.trim_end().ends_with(';') {
+                return replies;
+            }
+            let expanded_tokens = tokenize_pli(&expanded);
+            evaluator::evaluate_tokens(&expanded_tokens.iter().collect::<Vec<_>>(), context)
+                .map(|value| format!("=> {:?}", value))
+        }
+    };
+    replies.push(reply.unwrap_or_else(|e| format!("error: {}", e)));
+    replies
+}
+
+/// Returns `true` if `output_file` is newer than `input_file` and all its includes.
+///
+/// If the include graph cannot be built (for example, an include is missing),
+/// the output is treated as stale so that processing reports the problem.
+fn is_up_to_date(input_file: &str, output_file: &str, include_paths: &[PathBuf]) -> bool {
+    let input = Path::new(input_file);
+    match include_handler::collect_include_edges_with_paths(input, include_paths) {
+        Ok(edges) => {
+            let includes: Vec<_> = edges.into_iter().map(|edge| edge.to).collect();
+            !include_handler::needs_rebuild(input, &includes, Path::new(output_file))
+        }
+        Err(_) => false,
+    }
+}
+
+/// Prints the directive category counts gathered for the `--stats` report.
+///
+/// Categories are listed in alphabetical order so the report is stable between runs.
+///
+/// # Arguments
+/// - `histogram`: The directive counts per category, as built by `directive_histogram`.
+fn print_directive_stats(histogram: &HashMap<DirectiveCategory, usize>) {
+    let mut entries: Vec<(&str, usize)> = histogram
+        .iter()
+        .map(|(category, count)| (category.as_str(), *count))
+        .collect();
+    entries.sort();
+
+    println!("Directive statistics:");
+    for (category, count) in entries {
+        println!("  {:<15} {}", category, count);
+        info!("Directive category {}: {}", category, count);
+    }
 }
 
 /// Entry point for the PL/I Preprocessor program.
@@ -176,17 +918,94 @@ fn process_file(
 ///
 /// # Command-Line Usage
 /// ```bash
-/// $ cargo run <input_file> <output_file> <log_file> [--verbose] [--dry-run] [--verbosity=<level>]
+/// $ cargo run <input_file> <output_file> <log_file> [--verbose] [--dry-run] [--stats] [--verbosity=<level>]
+/// $ cargo run <input_file> --list-includes[=tree|json|dot]
+/// $ cargo run <input_file> --dump-ast[=tree|json]
+/// $ cargo run <input_file> --format
+/// $ cargo run <input_file> --count-tokens[=table|json]
+/// $ cargo run <input_file> --emit-tokens[=csv]
+/// $ cargo run <input_file> --emit=html
+/// $ cargo run -- --repl
 /// ```
 ///
 /// ## Positional Arguments:
-/// - `<input_file>`: The path to the input PL/I source file. Only `.pli` and `.pp` extensions are allowed.
+/// - `<input_file>`: The path to the input PL/I source file. Only `.pli` and `.pp` extensions are allowed,
+///   optionally followed by `.gz` for a gzip-compressed file, which is decompressed when read.
 /// - `<output_file>`: The path to the output file where transformed content will be written.
 /// - `<log_file>`: The path to the log file for detailed logs.
 ///
 /// ## Optional Flags:
 /// - `--verbose`: Enables additional console output.
 /// - `--dry-run`: Simulates processing without creating an output file.
+/// - `--diff`: Processes the input as `--dry-run` does, then prints a unified
+///   diff from the current `<output_file>` to the output it would now get.
+///   Nothing is printed if the output would not change.
+/// - `--stats`: Prints the number of directives per category after processing.
+/// - `--force`: Processes the input even if the output is already up to date.
+/// - `--quiet`: Shows only errors on the console and no completion message; the
+///   log file is unaffected. Cannot be combined with `--verbose`.
+/// - `--log-to-stderr`: If the log file cannot be opened, writes the log to
+///   standard error instead of failing.
+/// - `--version`: Prints the crate version and exits.
+/// - `--help`: Prints the usage text and exits.
+/// - `--include-markers`: Brackets inlined include content with
+///   `/* BEGIN INCLUDE file */` and `/* END INCLUDE file */` comment lines.
+///   Includes are inlined before any `%IF` is evaluated, so every included
+///   file must exist, even one named in inactive code.
+/// - `--keep-include-directives`: Keeps each inlined `%INCLUDE` line in the
+///   output as a comment, immediately before the included content.
+/// - `--keyword-case=<policy>`: Spells PL/I keywords and directives in the
+///   output in `upper` or `lower` case, or as written (`preserve`, the default).
+///   Identifiers keep their case.
+/// - `--warn-indentation`: Reports a note for each line whose indentation mixes
+///   tabs and spaces.
+/// - `--dedup-diagnostics`: Reports repeated diagnostics with the same severity
+///   and message once, followed by `(and N more)`.
+/// - `--trace`: Writes a `TRACE` event to the log for every pipeline decision on
+///   each line (tokens, validation, includes, macro expansions, and whether the
+///   line is emitted). Raises the log verbosity to at least `32`.
+/// - `--undefined-vars=<policy>`: What an undefined preprocessor variable in a
+///   condition or `%SET` expression yields: an `error` (the default), `zero`,
+///   or an `empty` string.
+/// - `--diagnostic-format=<format>`: Also prints the run's diagnostics to stdout
+///   as `json` lines or in the `gcc` `file:line:col: severity: message` style;
+///   `human` (the default) leaves them to the log and console.
+/// - `--continuation-char=<char>`: Treats a line ending in `<char>` inside an
+///   open string literal as continuing the literal on the next line, so a long
+///   literal may be split across lines. Off by default.
+/// - `--listing=<file>`: Also writes a numbered listing of the source, with its
+///   includes inlined, to `<file>`. Lines between `%NOPRINT` and `%PRINT` are
+///   left out of the listing but still processed and written to the output.
+/// - `--expansion-report=<file>`: Also writes a report to `<file>` comparing the
+///   input's line count with the output's, with the number of macros expanded,
+///   includes resolved, and the ratio of output to input lines.
+/// - `--include-path <dir>` or `-I <dir>`: Adds `<dir>` to the directories
+///   searched for an `%INCLUDE` file that is not next to the file including it.
+///   May be repeated; earlier directories are searched first.
+/// - `--list-includes[=<format>]`: Prints the include dependency graph as a `tree`
+///   (default), `json`, or `dot` and exits without processing the file.
+/// - `--dump-ast[=<format>]`: Prints the statements and `DO`/`END` blocks parsed
+///   from the input as an indented `tree` (default) or as `json`, instead of
+///   the transformed output.
+/// - `--format`: Prints the input with whitespace between tokens collapsed to
+///   one space, directives in uppercase, and `%DO`/`%IF` blocks indented,
+///   instead of the transformed output. String literals and comments are kept
+///   as written; nothing is evaluated or expanded.
+/// - `--count-tokens[=<format>]`: Prints the total number of tokens, the count
+///   per token category, the number of unique identifiers, and the number of
+///   directives per category as a `table` (default) or as `json`, instead of
+///   the transformed output.
+/// - `--emit-tokens[=csv]`: Prints one row per token with its line, column,
+///   value, category, and directive category as `csv` (the only format),
+///   instead of the transformed output.
+/// - `--emit=html`: Prints the input as an HTML page, each token in a span
+///   classed by its category (`tok-directive`, `tok-literal`, ...), instead of
+///   the transformed output.
+/// - `--repl`: Reads lines from stdin until end of input or `:quit`, printing
+///   each line's tokens and the value of each `%SET`, `%IF` condition, or
+///   expression (a line not ending in `;`). `%SET` variables and one-line
+///   `%MACRO` definitions persist between lines, and later lines are
+///   macro-expanded.
 /// - `--verbosity=<level>`: Configures the verbosity level of the logger. Accepted values:
 ///     - `0`: Logs only errors (`ERROR`).
 ///     - `1`: Logs warnings and errors (`WARN` and `ERROR`).
@@ -197,14 +1016,17 @@ fn process_file(
 /// # Behavior
 /// - Validates input file extensions and logs errors for unsupported formats.
 /// - Initializes the logger to log both console and file messages based on verbosity settings.
+/// - Skips processing when the output is newer than the input and its includes,
+///   unless `--force` or `--dry-run` is given.
 /// - Passes control to `process_file()` for actual processing of the input file.
 ///
 /// # Errors
-/// - Exits the program with an appropriate error code if:
-///   - The logger fails to initialize.
-///   - Required command-line arguments are missing.
-///   - The input file has an unsupported extension.
-/// - Logs all errors to the console and log file for traceability.
+/// - Exits the program with the `ExitCode` of the failure returned by `run`:
+///   - `2` if the command line is malformed or the input has an unsupported extension.
+///   - `3` if the logger cannot be initialized or a file cannot be read or written.
+///   - `4` if processing reported errors in the source.
+///   - `5` if an included file cannot be resolved or read.
+/// - Prints the error message to the console.
 ///
 /// # Example
 /// ```bash
@@ -222,35 +1044,136 @@ fn main() {
     // Collect command-line arguments.
     let args: Vec<String> = env::args().collect();
 
-    // Ensure the correct number of arguments are provided.
-    if args.len() < 4 || args.len() > 7 {
-        eprintln!(
-            "Usage: pli_preprocessor <input_file> <output_file> <log_file> [--verbose] [--dry-run] [--verbosity=<level>]"
-        );
-        std::process::exit(1);
+    if let Err(e) = run(&args) {
+        eprintln!("{}", e);
+        std::process::exit(e.code as i32);
     }
+}
 
-    // Extract input, output, and log file paths from arguments.
-    let input_file = &args[1];
-    let output_file = &args[2];
-    let log_file = &args[3];
-
-    // Check for optional flags.
-    let verbose = args.contains(&"--verbose".to_string());
-    let dry_run = args.contains(&"--dry-run".to_string());
-
-    let verbosity_level = args
-        .iter()
-        .find(|arg| arg.starts_with("--verbosity="))
-        .and_then(|arg| arg.split('=').nth(1))
-        .unwrap_or("2") // Default verbosity level
-        .parse::<u8>()
-        .unwrap_or(2); // Default to INFO level if invalid
+/// Runs the program for the given command-line arguments.
+///
+/// # Arguments
+/// - `args`: The full argument list, including the program name.
+///
+/// # Returns
+/// - `Result<(), AppError>`: `Ok(())` on success, or the failure together with
+///   the exit code `main` should report.
+fn run(args: &[String]) -> Result<(), AppError> {
+    let config = match parse_args(args).map_err(|e| AppError::new(ExitCode::UsageError, e))? {
+        Command::Version => {
+            println!("pli_preprocessor {}", env!("CARGO_PKG_VERSION"));
+            return Ok(());
+        }
+        Command::Help => {
+            println!(
+                "{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}",
+                USAGE,
+                LIST_INCLUDES_USAGE,
+                DUMP_AST_USAGE,
+                FORMAT_USAGE,
+                COUNT_TOKENS_USAGE,
+                EMIT_TOKENS_USAGE,
+                EMIT_USAGE,
+                REPL_USAGE,
+                EXPLAIN_USAGE
+            );
+            println!("Usage: pli_preprocessor --version | --help");
+            return Ok(());
+        }
+        // `--list-includes` only needs the input file and skips processing entirely.
+        Command::ListIncludes {
+            input_file,
+            format,
+            include_paths,
+        } => {
+            print!("{}", list_includes(&input_file, &format, &include_paths)?);
+            return Ok(());
+        }
+        // `--dump-ast` likewise only parses the input file.
+        Command::DumpAst { input_file, format } => {
+            print!("{}", dump_ast(&input_file, &format)?);
+            return Ok(());
+        }
+        // `--format` rewrites the layout of the input file without processing it.
+        Command::Format { input_file } => {
+            let source = include_handler::read_file(Path::new(&input_file))?;
+            print!("{}", formatter::format_source(&source));
+            return Ok(());
+        }
+        // `--count-tokens` only tokenizes the input file.
+        Command::CountTokens { input_file, format } => {
+            print!("{}", count_tokens(&input_file, &format)?);
+            return Ok(());
+        }
+        // `--emit-tokens` likewise only tokenizes the input file; `csv` is
+        // the only format `parse_args` accepts.
+        Command::EmitTokens { input_file, .. } => {
+            let source = include_handler::read_file(Path::new(&input_file))?;
+            let lines: Vec<_> = source.lines().map(tokenize_pli).collect();
+            print!("{}", output::tokens_to_csv(&lines));
+            return Ok(());
+        }
+        // `--emit` renders the input file as written; `html` is the only
+        // format `parse_args` accepts.
+        Command::Emit { input_file, .. } => {
+            let source = include_handler::read_file(Path::new(&input_file))?;
+            print!("{}", output::source_to_html(&source));
+            return Ok(());
+        }
+        // `--explain` only looks the code up in the diagnostics table.
+        Command::Explain { code } => {
+            let help = diagnostics::explain_code(&code)
+                .map_err(|e| AppError::new(ExitCode::UsageError, format!("Error: {}", e)))?;
+            print!("{}", help);
+            return Ok(());
+        }
+        // `--repl` reads its input interactively and processes no file.
+        Command::Repl => {
+            repl(io::stdin().lock(), &mut io::stdout())?;
+            return Ok(());
+        }
+        Command::Process(config) => config,
+    };
+    let RunConfig {
+        input_file,
+        output_file,
+        log_file,
+        options,
+        force,
+        quiet,
+        log_to_stderr,
+        verbosity_level,
+        diagnostic_format,
+        listing_file,
+        expansion_report,
+        diff,
+    } = &config;
 
     // Initialize the logger with the provided log file path and verbosity level.
-    if let Err(e) = logger::init_logger(log_file, verbose, verbosity_level) {
-        eprintln!("Error initializing logger: {}", e);
-        std::process::exit(1);
+    // The console shows warnings and errors, only errors with `--quiet`, and
+    // informational messages too with `--verbose`.
+    // A logger installed by an earlier `run` in the same process is kept.
+    // With `--log-to-stderr`, a log file that cannot be opened is not fatal.
+    let console_level = if *quiet {
+        LevelFilter::Error
+    } else if options.verbose {
+        LevelFilter::Info
+    } else {
+        LevelFilter::Warn
+    };
+    let init = if *log_to_stderr {
+        logger::init_logger_with_stderr_fallback
+    } else {
+        logger::init_logger_with_console
+    };
+    match init(log_file, options.verbose, *verbosity_level, console_level) {
+        Ok(()) | Err(fern::InitError::SetLoggerError(_)) => {}
+        Err(fern::InitError::Io(e)) => {
+            return Err(AppError::new(
+                ExitCode::IoError,
+                format!("Error initializing logger: {}", e),
+            ))
+        }
     }
 
     info!(
@@ -260,23 +1183,729 @@ fn main() {
 
     // Check if the input file exists.
     if !Path::new(input_file).exists() {
-        eprintln!("Error: Input file '{}' does not exist.", input_file);
-        std::process::exit(1);
+        return Err(AppError::new(
+            ExitCode::IoError,
+            format!("Error: Input file '{}' does not exist.", input_file),
+        ));
     }
 
     // Validate the input file's extension.
-    let allowed_extensions = ["pp", "pli"];
-    if !allowed_extensions
-        .iter()
-        .any(|ext| input_file.ends_with(ext))
+    validate_input_path(input_file).map_err(|e| AppError::new(ExitCode::UsageError, e))?;
+
+    // Skip the run if nothing changed since the output was last written.
+    if !force && !options.dry_run && is_up_to_date(input_file, output_file, &options.include_paths)
     {
-        error!("Unsupported input file extension. Only .pp and .pli files are allowed.");
-        std::process::exit(1);
+        info!("Output '{}' is up to date; skipping.", output_file);
+        if options.verbose {
+            println!(
+                "Output '{}' is up to date. Use --force to rebuild.",
+                output_file
+            );
+        }
+        return Ok(());
+    }
+
+    // Start the log afresh. The logger has already opened it, so this only
+    // fails when `--log-to-stderr` is logging to standard error instead.
+    if let Err(e) = File::create(log_file) {
+        if !*log_to_stderr {
+            return Err(AppError::new(
+                ExitCode::IoError,
+                format!("Cannot truncate log file '{}': {}", log_file, e),
+            ));
+        }
+    }
+
+    // Process the file and report any errors.
+    let held_lines = process_file(
+        input_file,
+        output_file,
+        log_file,
+        options,
+        *diagnostic_format,
+        listing_file.as_deref(),
+        expansion_report.as_deref(),
+    )?;
+    if *diff {
+        print!("{}", diff_output(output_file, &held_lines)?);
+    }
+    info!("Processing complete.");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_input_path_accepts_supported_extensions() {
+        assert!(validate_input_path("report.pli").is_ok());
+        assert!(validate_input_path("report.PLI").is_ok());
+        assert!(validate_input_path("dir/report.pp").is_ok());
+        assert!(validate_input_path("dir/report.pli.gz").is_ok());
+        assert!(validate_input_path("report.PP.GZ").is_ok());
+    }
+
+    #[test]
+    fn test_list_includes_rejects_unknown_format() {
+        let dir = std::env::temp_dir().join("pli_list_includes_format");
+        std::fs::create_dir_all(&dir).unwrap();
+        let root = dir.join("main.pli");
+        std::fs::write(&root, "X = 1;\n").unwrap();
+
+        let input = root.to_str().unwrap();
+        assert_eq!(list_includes(input, "json", &[]).unwrap(), "[]\n");
+        assert!(list_includes(input, "xml", &[]).is_err());
+    }
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args_version_and_help() {
+        assert_eq!(
+            parse_args(&args(&["pli_preprocessor", "--version"])),
+            Ok(Command::Version)
+        );
+        assert_eq!(
+            parse_args(&args(&["pli_preprocessor", "in.pli", "--version"])),
+            Ok(Command::Version)
+        );
+        assert_eq!(
+            parse_args(&args(&["pli_preprocessor", "--help"])),
+            Ok(Command::Help)
+        );
+        assert_eq!(
+            parse_args(&args(&["pli_preprocessor", "--repl"])),
+            Ok(Command::Repl)
+        );
     }
 
-    // Process the file and handle any errors.
-    match process_file(input_file, output_file, log_file, verbose, dry_run) {
-        Ok(_) => info!("Processing complete."),
-        Err(e) => error!("Error processing file: {}", e),
+    #[test]
+    fn test_parse_args_quiet_flag() {
+        match parse_args(&args(&["p", "in.pli", "out.pli", "run.log", "--quiet"])) {
+            Ok(Command::Process(config)) => {
+                assert!(config.quiet);
+                assert!(!config.options.verbose);
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        let error = parse_args(&args(&[
+            "p",
+            "in.pli",
+            "out.pli",
+            "run.log",
+            "--quiet",
+            "--verbose",
+        ]))
+        .unwrap_err();
+        assert!(error.starts_with("--quiet and --verbose cannot be combined."));
+        assert_eq!(
+            run(&args(&[
+                "p",
+                "in.pli",
+                "out.pli",
+                "run.log",
+                "--verbose",
+                "--quiet"
+            ]))
+            .unwrap_err()
+            .code,
+            ExitCode::UsageError
+        );
+    }
+
+    #[test]
+    fn test_parse_args_process_and_usage_errors() {
+        let command = parse_args(&args(&[
+            "pli_preprocessor",
+            "in.pli",
+            "out.pli",
+            "run.log",
+            "--stats",
+            "--verbosity=3",
+        ]))
+        .unwrap();
+        match command {
+            Command::Process(config) => {
+                assert_eq!(config.input_file, "in.pli");
+                assert!(config.options.stats && !config.options.dry_run);
+                assert_eq!(config.verbosity_level, 3);
+            }
+            other => panic!("unexpected command {:?}", other),
+        }
+
+        assert_eq!(
+            parse_args(&args(&["pli_preprocessor"])),
+            Err(USAGE.to_string())
+        );
+        assert!(parse_args(&args(&["pli_preprocessor", "--list-includes"])).is_err());
+
+        // There is no upper limit on the number of options.
+        let mut many = args(&["p", "in.pli", "out.pli", "run.log"]);
+        many.extend((0..30).map(|_| "--stats".to_string()));
+        assert!(matches!(parse_args(&many), Ok(Command::Process(_))));
+    }
+
+    #[test]
+    fn test_parse_args_dump_ast() {
+        assert_eq!(
+            parse_args(&args(&["p", "in.pli", "--dump-ast=json"])),
+            Ok(Command::DumpAst {
+                input_file: "in.pli".to_string(),
+                format: "json".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_args(&args(&["p", "in.pli", "--dump-ast"])),
+            Ok(Command::DumpAst {
+                input_file: "in.pli".to_string(),
+                format: "tree".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_args(&args(&["p", "in.pli", "--dump-ast=dot"])),
+            Err(DUMP_AST_USAGE.to_string())
+        );
+        assert_eq!(
+            parse_args(&args(&["p", "--dump-ast"])),
+            Err(DUMP_AST_USAGE.to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_args_count_tokens() {
+        assert_eq!(
+            parse_args(&args(&["p", "in.pli", "--count-tokens=json"])),
+            Ok(Command::CountTokens {
+                input_file: "in.pli".to_string(),
+                format: "json".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_args(&args(&["p", "in.pli", "--count-tokens"])),
+            Ok(Command::CountTokens {
+                input_file: "in.pli".to_string(),
+                format: "table".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_args(&args(&["p", "in.pli", "--count-tokens=csv"])),
+            Err(COUNT_TOKENS_USAGE.to_string())
+        );
+        assert_eq!(
+            parse_args(&args(&["p", "--count-tokens"])),
+            Err(COUNT_TOKENS_USAGE.to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_args_emit_tokens() {
+        let csv = Ok(Command::EmitTokens {
+            input_file: "in.pli".to_string(),
+            format: "csv".to_string(),
+        });
+        assert_eq!(
+            parse_args(&args(&["p", "in.pli", "--emit-tokens=csv"])),
+            csv
+        );
+        assert_eq!(parse_args(&args(&["p", "in.pli", "--emit-tokens"])), csv);
+        assert_eq!(
+            parse_args(&args(&["p", "in.pli", "--emit-tokens=json"])),
+            Err(EMIT_TOKENS_USAGE.to_string())
+        );
+        assert_eq!(
+            parse_args(&args(&["p", "--emit-tokens"])),
+            Err(EMIT_TOKENS_USAGE.to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_args_emit() {
+        assert_eq!(
+            parse_args(&args(&["p", "in.pli", "--emit=html"])),
+            Ok(Command::Emit {
+                input_file: "in.pli".to_string(),
+                format: "html".to_string(),
+            })
+        );
+        for flags in [
+            &["p", "in.pli", "--emit"][..],
+            &["p", "in.pli", "--emit=pdf"],
+            &["p", "--emit=html"],
+        ] {
+            assert_eq!(parse_args(&args(flags)), Err(EMIT_USAGE.to_string()));
+        }
+    }
+
+    #[test]
+    fn test_repl_keeps_state_across_lines() {
+        let script = concat!(
+            "%SET N = 2;\n",
+            "N * 3\n",
+            "\n",
+            "%MACRO TWICE; N + N %ENDMACRO;\n",
+            "TWICE > 3\n",
+            "X = TWICE;\n",
+            "%IF N = 1 %THEN;\n",
+            "MISSING + 1\n",
+            ":quit\n",
+            "N\n",
+        );
+        let mut output = Vec::new();
+        repl(io::Cursor::new(script), &mut output).unwrap();
+        let text = String::from_utf8(output).unwrap();
+        let replies: Vec<&str> = text
+            .split(REPL_PROMPT)
+            .filter(|reply| !reply.trim().is_empty())
+            .collect();
+        assert_eq!(
+            replies,
+            vec![
+                "tokens: %SET[Directive] N[Identifier] =[Operator] 2[Identifier] ;[Separator]\n\
+                 N = Int(2)\n",
+                "tokens: N[Identifier] *[Operator] 3[Identifier]\n=> Int(6)\n",
+                "tokens: %MACRO[Directive] TWICE[Identifier] ;[Separator] N[Identifier] \
+                 +[Operator] N[Identifier] %ENDMACRO[Directive] ;[Separator]\n\
+                 macro TWICE = N + N\n",
+                "tokens: TWICE[Identifier] >[Operator] 3[Identifier]\n\
+                 expanded: N + N > 3\n=> Boolean(true)\n",
+                "tokens: X[Identifier] =[Operator] TWICE[Identifier] ;[Separator]\n\
+                 expanded: X = N + N;\n",
+                "tokens: %IF[Directive] N[Identifier] =[Operator] 1[Identifier] \
+                 %THEN[Directive] ;[Separator]\ncondition: false\n",
+                "tokens: MISSING[Identifier] +[Operator] 1[Identifier]\n\
+                 error: Undefined variable: MISSING\n",
+            ]
+        );
+        // `:quit` ends the session, and the blank line only re-prompts.
+        assert!(!text.contains("tokens: N[Identifier]\n"));
+        assert_eq!(text.matches(REPL_PROMPT).count(), 9);
+    }
+
+    #[test]
+    fn test_parse_args_explain() {
+        assert_eq!(
+            parse_args(&args(&["p", "--explain", "V002"])),
+            Ok(Command::Explain {
+                code: "V002".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_args(&args(&["p", "--explain"])),
+            Err(EXPLAIN_USAGE.to_string())
+        );
+        assert_eq!(
+            parse_args(&args(&["p", "--explain", "--verbose"])),
+            Err(EXPLAIN_USAGE.to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_args_format() {
+        assert_eq!(
+            parse_args(&args(&["p", "in.pli", "--format"])),
+            Ok(Command::Format {
+                input_file: "in.pli".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_args(&args(&["p", "--format"])),
+            Err(FORMAT_USAGE.to_string())
+        );
+        // `--diagnostic-format=` is a processing option, not the format mode.
+        assert!(matches!(
+            parse_args(&args(&[
+                "p",
+                "in.pli",
+                "out.pli",
+                "run.log",
+                "--diagnostic-format=gcc"
+            ])),
+            Ok(Command::Process(_))
+        ));
+    }
+
+    /// Writes `content` to a fresh file under the temp dir and returns its path.
+    fn temp_source(name: &str, content: &str) -> String {
+        let dir = std::env::temp_dir().join("pli_main_run");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, content).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_dump_ast_reads_like_the_pipeline() {
+        let source = "%IF A = 1 %THEN;\nX = 1;\n%ENDIF;\n";
+        let plain = temp_source("ast.pli", source);
+        let gzipped = format!("{}.gz", plain);
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(source.as_bytes()).unwrap();
+        std::fs::write(&gzipped, encoder.finish().unwrap()).unwrap();
+        assert_eq!(
+            dump_ast(&gzipped, "tree").unwrap(),
+            dump_ast(&plain, "tree").unwrap()
+        );
+
+        let binary = temp_source("ast_binary.pli", "");
+        std::fs::write(&binary, b"X = 1;\n\xFF\n").unwrap();
+        let error = dump_ast(&binary, "tree").unwrap_err();
+        assert_eq!(error.code, ExitCode::IoError);
+        assert!(
+            error.message.contains("is not valid UTF-8 text"),
+            "{}",
+            error.message
+        );
+    }
+
+    fn run_code(list: &[&str]) -> Option<ExitCode> {
+        run(&args(list)).err().map(|e| e.code)
+    }
+
+    #[test]
+    fn test_run_maps_failures_to_exit_codes() {
+        let log = std::env::temp_dir().join("pli_main_run.log");
+        let log = log.to_str().unwrap();
+        let out = std::env::temp_dir().join("pli_main_run.out");
+        let out = out.to_str().unwrap();
+
+        assert_eq!(run_code(&["pli_preprocessor"]), Some(ExitCode::UsageError));
+        let bad_extension = temp_source("input.txt", "X = 1;\n");
+        assert_eq!(
+            run_code(&["p", &bad_extension, out, log, "--dry-run"]),
+            Some(ExitCode::UsageError)
+        );
+        assert_eq!(
+            run_code(&["p", "/nonexistent/input.pli", out, log]),
+            Some(ExitCode::IoError)
+        );
+
+        let syntax = temp_source("syntax.pli", "%SET = 1;\n");
+        assert_eq!(
+            run_code(&["p", &syntax, out, log, "--dry-run"]),
+            Some(ExitCode::SyntaxError)
+        );
+
+        let include = temp_source("include.pli", "%INCLUDE 'absent.pli';\n");
+        assert_eq!(
+            run_code(&["p", &include, out, log, "--dry-run"]),
+            Some(ExitCode::IncludeError)
+        );
+        assert_eq!(
+            run_code(&["p", &include, "--list-includes"]),
+            Some(ExitCode::IncludeError)
+        );
+        // Includes are inlined before `%IF` is evaluated, so even an include
+        // in a branch that is not taken must exist.
+        let inactive = temp_source(
+            "inactive_include.pli",
+            "%IF 0 %THEN;\n%INCLUDE 'absent.pli';\n%ENDIF;\n",
+        );
+        assert_eq!(
+            run_code(&["p", &inactive, out, log, "--dry-run"]),
+            Some(ExitCode::IncludeError)
+        );
+
+        let clean = temp_source("clean.pli", "X = 1;\n");
+        assert_eq!(run_code(&["p", &clean, out, log, "--dry-run"]), None);
+        assert_eq!(run_code(&["p", "--version"]), None);
+        assert_eq!(run_code(&["p", "--explain", "V002"]), None);
+        assert_eq!(
+            run_code(&["p", "--explain", "E999"]),
+            Some(ExitCode::UsageError)
+        );
+    }
+
+    #[test]
+    fn test_run_with_unwritable_log_file() {
+        let out = std::env::temp_dir().join("pli_main_log.out");
+        let out = out.to_str().unwrap();
+        let log = "/nonexistent/pli_logs/run.log";
+        let clean = temp_source("log.pli", "X = 1;\n");
+
+        let error = run(&args(&["p", &clean, out, log, "--dry-run"])).unwrap_err();
+        assert_eq!(error.code, ExitCode::IoError);
+        assert!(
+            error
+                .message
+                .contains("Cannot open log file '/nonexistent/pli_logs/run.log'"),
+            "{}",
+            error.message
+        );
+        assert_eq!(
+            run_code(&["p", &clean, out, log, "--dry-run", "--log-to-stderr"]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_run_writes_listing() {
+        let dir = std::env::temp_dir().join("pli_main_listing");
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = temp_source("listing.pli", "A = 1;\n%NOPRINT;\nB = 2;\n%PRINT;\n");
+        let out = dir.join("listing.out").display().to_string();
+        let log = dir.join("listing.log").display().to_string();
+        let listing = dir.join("listing.lst").display().to_string();
+        let listing_flag = format!("--listing={}", listing);
+
+        assert_eq!(
+            run_code(&["p", &input, &out, &log, &listing_flag, "--force"]),
+            None
+        );
+        assert_eq!(std::fs::read_to_string(&listing).unwrap(), "    1 A = 1;\n");
+        assert_eq!(std::fs::read_to_string(&out).unwrap(), "A = 1;\nB = 2;\n");
+    }
+
+    #[test]
+    fn test_run_writes_expansion_report() {
+        let dir = std::env::temp_dir().join("pli_main_expansion_report");
+        std::fs::create_dir_all(&dir).unwrap();
+        temp_source(
+            "triple.pli",
+            "%MACRO TRIPLE; A = 1;\nB = 2;\nC = 3 %ENDMACRO;\n",
+        );
+        // Three input lines become six output lines.
+        let input = temp_source("expand.pli", "%INCLUDE 'triple.pli';\nTRIPLE;\nTRIPLE;\n");
+        let out = dir.join("expand.out").display().to_string();
+        let log = dir.join("expand.log").display().to_string();
+        let report = dir.join("expand.txt").display().to_string();
+        let report_flag = format!("--expansion-report={}", report);
+
+        assert_eq!(
+            run_code(&["p", &input, &out, &log, &report_flag, "--force"]),
+            None
+        );
+        assert_eq!(
+            std::fs::read_to_string(&out).unwrap(),
+            "A = 1;\nB = 2;\nC = 3;\nA = 1;\nB = 2;\nC = 3;\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(&report).unwrap(),
+            format!(
+                "{}\n  Input lines:       3\n  Output lines:      6\n  Macros expanded:   2\n  Includes resolved: 1\n  Expansion ratio:   2.00\n",
+                input
+            )
+        );
+    }
+
+    #[test]
+    fn test_exit_code_values() {
+        assert_eq!(ExitCode::UsageError as i32, 2);
+        assert_eq!(ExitCode::IoError as i32, 3);
+        assert_eq!(ExitCode::SyntaxError as i32, 4);
+        assert_eq!(ExitCode::IncludeError as i32, 5);
+    }
+
+    #[test]
+    fn test_validate_input_path_rejects_unsupported_extensions() {
+        assert!(validate_input_path("report.notpli").is_err());
+        assert!(validate_input_path("report").is_err());
+        assert!(validate_input_path("report.txt.gz").is_err());
+        assert!(validate_input_path("report.gz").is_err());
+    }
+
+    #[test]
+    fn test_parse_args_keyword_case() {
+        match parse_args(&args(&[
+            "p",
+            "in.pli",
+            "out.pli",
+            "run.log",
+            "--keyword-case=lower",
+        ])) {
+            Ok(Command::Process(config)) => {
+                assert_eq!(config.options.keyword_case, KeywordCase::Lower)
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        match parse_args(&args(&["p", "in.pli", "out.pli", "run.log"])) {
+            Ok(Command::Process(config)) => {
+                assert_eq!(config.options.keyword_case, KeywordCase::Preserve)
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        let error = parse_args(&args(&[
+            "p",
+            "in.pli",
+            "out.pli",
+            "run.log",
+            "--keyword-case=title",
+        ]))
+        .unwrap_err();
+        assert!(error.starts_with("Unknown keyword case: title."));
+    }
+
+    #[test]
+    fn test_parse_args_diagnostic_format() {
+        match parse_args(&args(&[
+            "p",
+            "in.pli",
+            "out.pli",
+            "run.log",
+            "--diagnostic-format=gcc",
+        ])) {
+            Ok(Command::Process(config)) => {
+                assert_eq!(config.diagnostic_format, DiagnosticFormat::Gcc)
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        let error = parse_args(&args(&[
+            "p",
+            "in.pli",
+            "out.pli",
+            "run.log",
+            "--diagnostic-format=xml",
+        ]))
+        .unwrap_err();
+        assert!(error.starts_with("Unknown diagnostic format: xml."));
+    }
+
+    #[test]
+    fn test_parse_args_undefined_vars() {
+        match parse_args(&args(&[
+            "p",
+            "in.pli",
+            "out.pli",
+            "run.log",
+            "--undefined-vars=zero",
+        ])) {
+            Ok(Command::Process(config)) => {
+                assert_eq!(config.options.undefined_vars, Some(UndefinedPolicy::Zero))
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        let error = parse_args(&args(&[
+            "p",
+            "in.pli",
+            "out.pli",
+            "run.log",
+            "--undefined-vars=null",
+        ]))
+        .unwrap_err();
+        assert!(error.starts_with("Unknown undefined-variable policy: null."));
+    }
+
+    #[test]
+    fn test_parse_args_include_paths() {
+        let dir = std::env::temp_dir().join("pli_cli_include_paths");
+        let (first, second, src) = (dir.join("first"), dir.join("second"), dir.join("src"));
+        for sub in [&first, &second, &src] {
+            std::fs::create_dir_all(sub).unwrap();
+        }
+        std::fs::write(first.join("defs.pli"), "A = 1;\n").unwrap();
+        std::fs::write(second.join("defs.pli"), "A = 2;\n").unwrap();
+        std::fs::write(src.join("main.pli"), "%INCLUDE 'defs.pli';\n").unwrap();
+
+        let first_arg = first.display().to_string();
+        let second_arg = second.display().to_string();
+        let config = match parse_args(&args(&[
+            "p",
+            "in.pli",
+            "out.pli",
+            "run.log",
+            "-I",
+            &first_arg,
+            "--include-path",
+            &second_arg,
+            "--verbose",
+        ])) {
+            Ok(Command::Process(config)) => config,
+            other => panic!("unexpected result {:?}", other),
+        };
+        assert_eq!(config.options.include_paths, vec![first, second]);
+        assert!(config.options.verbose);
+
+        // The earlier directory wins when both hold the include.
+        let source = include_handler::flatten_includes_with_paths(
+            &src.join("main.pli"),
+            false,
+            &config.options.include_paths,
+        )
+        .unwrap();
+        assert_eq!(source, "A = 1;\n");
+
+        let error = parse_args(&args(&["p", "in.pli", "out.pli", "run.log", "-I"])).unwrap_err();
+        assert!(error.starts_with("-I expects a directory."));
+        let error = parse_args(&args(&[
+            "p",
+            "in.pli",
+            "out.pli",
+            "run.log",
+            "--include-path",
+            "--verbose",
+        ]))
+        .unwrap_err();
+        assert!(error.starts_with("--include-path expects a directory."));
+    }
+
+    #[test]
+    fn test_parse_args_continuation_char() {
+        match parse_args(&args(&[
+            "p",
+            "in.pli",
+            "out.pli",
+            "run.log",
+            "--continuation-char=-",
+        ])) {
+            Ok(Command::Process(config)) => {
+                assert_eq!(config.options.continuation_char, Some('-'))
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        let error = parse_args(&args(&[
+            "p",
+            "in.pli",
+            "out.pli",
+            "run.log",
+            "--continuation-char=--",
+        ]))
+        .unwrap_err();
+        assert!(error.starts_with("--continuation-char expects a single character, got '--'."));
+    }
+
+    #[test]
+    fn test_parse_args_trace_raises_verbosity() {
+        match parse_args(&args(&["p", "in.pli", "out.pli", "run.log", "--trace"])) {
+            Ok(Command::Process(config)) => {
+                assert!(config.options.trace);
+                assert_eq!(config.verbosity_level, 32);
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_run_diff_leaves_output_untouched() {
+        let dir = std::env::temp_dir().join("pli_main_diff");
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = temp_source("diff.pli", "A = 1;\nB = 2;\n");
+        let out = dir.join("diff.out");
+        std::fs::write(&out, "A = 1;\nB = 3;\n").unwrap();
+        let out = out.display().to_string();
+        let log = dir.join("diff.log").display().to_string();
+
+        let command = parse_args(&args(&["p", &input, &out, &log, "--diff"])).unwrap();
+        match command {
+            Command::Process(config) => assert!(config.diff && config.options.dry_run),
+            other => panic!("unexpected command {:?}", other),
+        }
+        assert_eq!(run_code(&["p", &input, &out, &log, "--diff"]), None);
+        assert_eq!(std::fs::read_to_string(&out).unwrap(), "A = 1;\nB = 3;\n");
+
+        let lines = vec!["A = 1;".to_string(), "B = 2;".to_string()];
+        assert_eq!(
+            diff_output(&out, &lines).unwrap(),
+            format!(
+                "--- {0}\n+++ {0} (processed)\n@@ -1,2 +1,2 @@\n A = 1;\n-B = 3;\n+B = 2;\n",
+                out
+            )
+        );
+        let absent = dir.join("absent.out").display().to_string();
+        assert!(diff_output(&absent, &lines)
+            .unwrap()
+            .contains("@@ -0,0 +1,2 @@\n"));
     }
 }
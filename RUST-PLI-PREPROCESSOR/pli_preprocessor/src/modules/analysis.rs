@@ -0,0 +1,119 @@
+#![allow(dead_code)] // Suppress warnings for unused functions in this module.
+
+////////////////////////////////////////////////////////////////////////////////
+// MODULE NAME: Static Analysis
+// ----------------------------------------------------------------------------
+// DESCRIPTION:
+// This module holds optional analysis passes that report questionable but
+// legal constructs in PL/I preprocessor source.
+//
+// FUNCTIONALITY:
+// - Flags `%IF` blocks whose condition is a constant that evaluates false,
+//   since the guarded code can never be active.
+// - Flags lines whose indentation mixes tabs and spaces, which breaks column
+//   positions.
+//
+// USAGE:
+// - Call `check_constant_condition` on each tokenized line, or enable
+//   `ProcessOptions::analyze` to have the pipeline run it.
+// - Call `check_mixed_indentation` on each raw line, or enable
+//   `ProcessOptions::warn_indentation`.
+//
+// AUTHOR: FirstLink Consulting Services (FLCS)
+// LICENSE: MIT License
+// DATE: 10/16/2026
+// VERSION: 1.0.0
+////////////////////////////////////////////////////////////////////////////////
+
+////////////////////////////////////////////////////////////////////////////////
+// IMPORTS
+////////////////////////////////////////////////////////////////////////////////
+
+use crate::modules::diagnostics::{Diagnostic, DiagnosticSink, Severity};
+use crate::modules::evaluator::{evaluate_condition, merge_operator_tokens};
+use crate::modules::tokenizer::{Token, TokenCategory};
+
+////////////////////////////////////////////////////////////////////////////////
+// PUBLIC FUNCTIONS
+////////////////////////////////////////////////////////////////////////////////
+
+/// Pushes a note when a `%IF` line's condition is constant and false.
+///
+/// Only conditions made entirely of numbers and operators are evaluated;
+/// conditions that reference variables or strings are skipped.
+///
+/// # Arguments
+/// - `tokens`: The tokens of one source line.
+/// - `line`: The 1-based line number the tokens came from.
+/// - `sink`: The `DiagnosticSink` receiving the note.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::analysis::{check_constant_condition, check_mixed_indentation};
+/// # use pli_preprocessor::modules::diagnostics::DiagnosticSink;
+/// # use pli_preprocessor::modules::tokenizer::tokenize_pli;
+/// let mut sink = DiagnosticSink::new();
+/// check_constant_condition(&tokenize_pli("%IF 0 = 1 %THEN;"), 4, &mut sink);
+/// assert_eq!(sink.diagnostics()[0].message, "block is never active");
+/// ```
+pub fn check_constant_condition(tokens: &[Token], line: usize, sink: &mut DiagnosticSink) {
+    let directive = match tokens.first() {
+        Some(token) if token.value == "%IF" => token,
+        _ => return,
+    };
+    let condition: Vec<&Token> = tokens[1..]
+        .iter()
+        .take_while(|t| t.value != "%THEN" && t.value != ";")
+        .collect();
+
+    let constant = condition.iter().all(|t| match t.category {
+        TokenCategory::Identifier => t.value.parse::<i64>().is_ok(),
+        TokenCategory::Literal | TokenCategory::Directive => false,
+        _ => true,
+    });
+    if condition.is_empty() || !constant {
+        return;
+    }
+
+    let terms: Vec<String> = merge_operator_tokens(&condition)
+        .into_iter()
+        .map(|t| t.value)
+        .collect();
+    if let Ok(false) = evaluate_condition(&terms.join(" ")) {
+        let mut note =
+            Diagnostic::new(Severity::Note, line, "block is never active").with_code("A001");
+        if directive.column > 0 {
+            note = note.with_column(directive.column);
+        }
+        sink.push(note);
+    }
+}
+
+/// Pushes a note when a line's leading whitespace mixes tabs and spaces.
+///
+/// The check only looks at the raw text, so it is cheap enough to run on
+/// every line.
+///
+/// # Arguments
+/// - `content`: The raw source line.
+/// - `line`: The 1-based line number.
+/// - `sink`: The `DiagnosticSink` receiving the note.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::analysis::{check_constant_condition, check_mixed_indentation};
+/// # use pli_preprocessor::modules::diagnostics::DiagnosticSink;
+/// let mut sink = DiagnosticSink::new();
+/// check_mixed_indentation("\t  A = 1;", 3, &mut sink);
+/// assert_eq!(sink.diagnostics()[0].message, "indentation mixes tabs and spaces");
+/// ```
+pub fn check_mixed_indentation(content: &str, line: usize, sink: &mut DiagnosticSink) {
+    let indent = &content[..content.len() - content.trim_start_matches([' ', '\t']).len()];
+    if indent.contains(' ') && indent.contains('\t') {
+        sink.push(
+            Diagnostic::new(Severity::Note, line, "indentation mixes tabs and spaces")
+                .with_code("A002")
+                .with_column(1),
+        );
+    }
+}
@@ -9,12 +9,22 @@
 // FUNCTIONALITY:
 // - Evaluates conditions in `%IF` and `%ELSE` directives.
 // - Tracks nesting levels of conditional blocks to ensure correct pairing
-//   with `%ENDIF`.
+//   with `%ENDIF`, and rejects nesting deeper than `MAX_IF_DEPTH` levels (or a
+//   caller-supplied limit).
 // - Supports boolean expressions with basic operators (`=`, `!=`, `<`, `>`, etc.).
 //
 // USAGE:
-// - Use `process_condition` to evaluate a single `%IF` condition.
+// - Use `process_condition_with_context` to evaluate a single `%IF` condition
+//   against the preprocessor variables in a `Context`.
+// - Use `process_condition` to evaluate a condition against the built-in
+//   context, in which `DEBUG` is 1.
 // - Call `validate_conditional_structure` to check nesting and block validity.
+// - Call `check_conditional_structure` to report nesting errors with line
+//   numbers into a `DiagnosticSink`.
+// - Use the `*_with_limit` variants to choose a nesting limit other than
+//   `MAX_IF_DEPTH`.
+// - Use `split_inline_if` and `evaluate_condition_tokens` to handle a
+//   single-statement `%IF condition %THEN statement;`.
 //
 // AUTHOR: FirstLink Consulting Services (FLCS)
 // LICENSE: MIT License
@@ -22,11 +32,29 @@
 // VERSION: 1.0.0
 ////////////////////////////////////////////////////////////////////////////////
 
+////////////////////////////////////////////////////////////////////////////////
+// IMPORTS
+////////////////////////////////////////////////////////////////////////////////
+
+use crate::modules::context::{Context, UndefinedPolicy, Value};
+use crate::modules::diagnostics::DiagnosticSink;
+use crate::modules::evaluator::{evaluate_tokens, merge_operator_tokens};
+use crate::modules::tokenizer::{has_tokenizer_error, tokenize_pli, Token};
+
+////////////////////////////////////////////////////////////////////////////////
+// CONSTANTS
+////////////////////////////////////////////////////////////////////////////////
+
+/// The default limit on how deeply `%IF` blocks may nest, which bounds the
+/// stack of open blocks.
+pub const MAX_IF_DEPTH: usize = 256;
+
 ////////////////////////////////////////////////////////////////////////////////
 // PUBLIC FUNCTIONS
 ////////////////////////////////////////////////////////////////////////////////
 
-/// Processes a single `%IF` condition and returns its evaluation result.
+/// Processes a single `%IF` condition against the built-in context, in which
+/// `DEBUG` is 1.
 ///
 /// # Arguments
 /// - `condition`: A `&str` representing the conditional expression to evaluate.
@@ -37,38 +65,146 @@
 ///
 /// # Example
 /// ```rust
+/// # use pli_preprocessor::modules::conditional::process_condition;
 /// let result = process_condition("DEBUG = 1");
-/// assert_eq!(result, Ok(true)); // Assuming DEBUG = 1 in the context
+/// assert_eq!(result, Ok(true));
 /// ```
 pub fn process_condition(condition: &str) -> Result<bool, String> {
+    let mut context = Context::new();
+    context.set("DEBUG", Value::Int(1));
+    process_condition_with_context(condition, &context)
+}
+
+/// Processes a single `VARIABLE op VALUE` condition against `context`.
+///
+/// The condition is split with the tokenizer, so a quoted right-hand side may
+/// contain spaces, as in `MODE = 'FULL SCAN'`. A numeric right-hand side
+/// compares numerically, coercing the variable with `Context::get_int`;
+/// anything else compares as text (a quoted literal loses its quotes and has
+/// `''` unescaped) using `Context::get_str`.
+///
+/// # Arguments
+/// - `condition`: The condition, e.g. `"LEVEL = 2"`. The operator is `=` or `!=`.
+/// - `context`: The preprocessor variables.
+///
+/// # Returns
+/// - `Result<bool, String>`: The result, or an error message if the condition
+///   is malformed, the variable is unknown, or it is not a number when
+///   compared with one.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::conditional::process_condition_with_context;
+/// # use pli_preprocessor::modules::context::{Context, Value};
+/// let mut context = Context::new();
+/// context.set("LEVEL", Value::Str("2".to_string()));
+/// assert_eq!(process_condition_with_context("LEVEL = 2", &context), Ok(true));
+/// ```
+pub fn process_condition_with_context(condition: &str, context: &Context) -> Result<bool, String> {
     if condition.trim().is_empty() {
         return Err("Empty condition".to_string());
     }
 
-    let parts: Vec<&str> = condition.split_whitespace().collect();
-    if parts.len() != 3 {
+    let tokens = tokenize_pli(condition);
+    let parts = merge_operator_tokens(&tokens.iter().collect::<Vec<_>>());
+    if parts.len() != 3 || has_tokenizer_error(&parts) {
         return Err(format!("Invalid condition format: {}", condition));
     }
 
-    let left = parts[0];
-    let operator = parts[1];
-    let right = parts[2];
-
-    let context = vec![("DEBUG", "1")];
-    let left_value = context
-        .iter()
-        .find(|&&(key, _)| key == left)
-        .map(|&(_, val)| val);
-
-    if let Some(value) = left_value {
-        match operator {
-            "=" => Ok(value == right),
-            "!=" => Ok(value != right),
-            _ => Err(format!("Unsupported operator: {}", operator)),
+    let left = parts[0].original.as_str();
+    let operator = parts[1].value.as_str();
+    let right = parts[2].original.as_str();
+
+    if context.get(left).is_none() && context.undefined_policy() == UndefinedPolicy::Error {
+        return Err(format!("Unknown variable: {}", left));
+    }
+    let equal = match right.parse::<i64>() {
+        Ok(number) => context.get_int(left)? == number,
+        Err(_) => {
+            let text = match right.strip_prefix('\'').and_then(|r| r.strip_suffix('\'')) {
+                Some(quoted) => quoted.replace("''", "'"),
+                None => right.to_string(),
+            };
+            context.get_str(left)? == text
         }
-    } else {
-        Err(format!("Unknown variable: {}", left))
+    };
+
+    match operator {
+        "=" => Ok(equal),
+        "!=" => Ok(!equal),
+        _ => Err(format!("Unsupported operator: {}", operator)),
+    }
+}
+
+/// Evaluates a tokenized condition against `context` with the typed evaluator.
+///
+/// The condition may use variables, comparisons, `AND`/`OR`, and `NOT`; a
+/// numeric result is true when nonzero. `AND` and `OR` short-circuit, so the
+/// right side of `0 = 1 AND UNDEFINED > 0` is never looked up.
+///
+/// # Arguments
+/// - `tokens`: The condition's tokens, without `%IF` and `%THEN`.
+/// - `context`: The preprocessor variables.
+///
+/// # Returns
+/// - `Result<bool, String>`: The truth of the condition, or an error message
+///   if it is empty, malformed, or refers to an undefined variable.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::conditional::evaluate_condition_tokens;
+/// # use pli_preprocessor::modules::context::{Context, Value};
+/// # use pli_preprocessor::modules::tokenizer::tokenize_pli;
+/// let mut context = Context::new();
+/// context.set("DEBUG", Value::Int(1));
+/// assert_eq!(evaluate_condition_tokens(&tokenize_pli("DEBUG = 1"), &context), Ok(true));
+/// ```
+pub fn evaluate_condition_tokens(tokens: &[Token], context: &Context) -> Result<bool, String> {
+    if tokens.is_empty() {
+        return Err("Empty condition".to_string());
+    }
+    match evaluate_tokens(&tokens.iter().collect::<Vec<_>>(), context)? {
+        Value::Boolean(flag) => Ok(flag),
+        Value::Int(number) => Ok(number != 0),
+        Value::Decimal(number) => Ok(number != 0.0),
+        Value::Str(text) => text
+            .trim()
+            .parse::<i64>()
+            .map(|number| number != 0)
+            .map_err(|_| format!("Condition is not a truth value: '{}'", text)),
+    }
+}
+
+/// Splits a single-statement `%IF condition %THEN statement;` into its
+/// condition and the statement after `%THEN`.
+///
+/// A `%IF condition %THEN;` that opens an `%ENDIF` block is not inline.
+///
+/// # Arguments
+/// - `tokens`: The tokens of one statement.
+///
+/// # Returns
+/// - `Option<(&[Token], &[Token])>`: The condition tokens and the statement
+///   tokens (with its `;`), or `None` if the statement is not an inline `%IF`.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::conditional::split_inline_if;
+/// # use pli_preprocessor::modules::tokenizer::tokenize_pli;
+/// let tokens = tokenize_pli("%IF DEBUG %THEN A = 1;");
+/// let (condition, statement) = split_inline_if(&tokens).unwrap();
+/// assert_eq!((condition.len(), statement.len()), (1, 4));
+/// ```
+pub fn split_inline_if(tokens: &[Token]) -> Option<(&[Token], &[Token])> {
+    if tokens.first()?.value != "%IF" {
+        return None;
+    }
+    let then = tokens.iter().position(|t| t.value == "%THEN")?;
+    let statement = &tokens[then + 1..];
+    if statement.iter().all(|t| t.value == ";") {
+        return None;
     }
+    Some((&tokens[1..then], statement))
 }
 
 /// Validates the structure of nested conditional blocks.
@@ -78,19 +214,51 @@ pub fn process_condition(condition: &str) -> Result<bool, String> {
 ///
 /// # Returns
 /// - `Result<(), String>`: Returns `Ok(())` if the structure is valid, or an
-///   `Err(String)` with an error message if there are mismatched directives.
+///   `Err(String)` with an error message if there are mismatched directives or
+///   the blocks nest deeper than `MAX_IF_DEPTH`.
 ///
 /// # Example
 /// ```rust
+/// # use pli_preprocessor::modules::conditional::validate_conditional_structure;
 /// let tokens = vec!["%IF".to_string(), "%ENDIF".to_string()];
 /// let result = validate_conditional_structure(&tokens);
 /// assert!(result.is_ok());
 /// ```
 pub fn validate_conditional_structure(tokens: &[String]) -> Result<(), String> {
+    validate_conditional_structure_with_limit(tokens, MAX_IF_DEPTH)
+}
+
+/// Validates the structure of nested conditional blocks, allowing at most
+/// `max_depth` levels of nesting.
+///
+/// # Arguments
+/// - `tokens`: A `&[String]` slice containing tokenized PL/I lines.
+/// - `max_depth`: The deepest nesting allowed.
+///
+/// # Returns
+/// - `Result<(), String>`: `Ok(())` if the structure is valid, or an error
+///   message for mismatched directives or nesting deeper than `max_depth`.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::conditional::validate_conditional_structure_with_limit;
+/// let tokens = vec!["%IF".to_string(), "%IF".to_string()];
+/// assert_eq!(
+///     validate_conditional_structure_with_limit(&tokens, 1),
+///     Err("%IF blocks nested deeper than the limit of 1".to_string())
+/// );
+/// ```
+pub fn validate_conditional_structure_with_limit(
+    tokens: &[String],
+    max_depth: usize,
+) -> Result<(), String> {
     let mut nesting_level = 0;
 
     for token in tokens {
         if token == "%IF" {
+            if nesting_level == max_depth {
+                return Err(depth_error(max_depth));
+            }
             nesting_level += 1;
         } else if token == "%ENDIF" {
             if nesting_level == 0 {
@@ -106,3 +274,99 @@ pub fn validate_conditional_structure(tokens: &[String]) -> Result<(), String> {
         Ok(())
     }
 }
+
+/// Reports unmatched `%IF`/`%ELSE`/`%ENDIF` directives across a whole source.
+///
+/// Unlike `validate_conditional_structure`, every problem is reported with the
+/// line it occurs on: an extra `%ENDIF`, a `%ELSE` outside any block, or a
+/// second `%ELSE` in one block at its own line, and an unclosed `%IF` at the
+/// line that opened it.
+///
+/// # Arguments
+/// - `directives`: `(line, directive)` pairs in source order.
+/// - `sink`: The `DiagnosticSink` receiving the errors.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::conditional::check_conditional_structure;
+/// # use pli_preprocessor::modules::diagnostics::DiagnosticSink;
+/// let mut sink = DiagnosticSink::new();
+/// check_conditional_structure(&[(1, "%IF".to_string())], &mut sink);
+/// assert_eq!(sink.error_count(), 1);
+/// ```
+pub fn check_conditional_structure(directives: &[(usize, String)], sink: &mut DiagnosticSink) {
+    check_conditional_structure_with_limit(directives, MAX_IF_DEPTH, sink);
+}
+
+/// Reports unmatched `%IF`/`%ELSE`/`%ENDIF` directives like
+/// `check_conditional_structure`, allowing at most `max_depth` levels of
+/// nesting.
+///
+/// The `%IF` that first nests too deeply is reported at its line. Blocks past
+/// the limit are only counted, not stacked, so pathological nesting cannot
+/// grow the stack without bound, and their `%ENDIF`s still pair up.
+///
+/// # Arguments
+/// - `directives`: `(line, directive)` pairs in source order.
+/// - `max_depth`: The deepest nesting allowed.
+/// - `sink`: The `DiagnosticSink` receiving the errors.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::conditional::check_conditional_structure_with_limit;
+/// # use pli_preprocessor::modules::diagnostics::DiagnosticSink;
+/// let mut sink = DiagnosticSink::new();
+/// let directives = [(1, "%IF".to_string()), (2, "%IF".to_string())];
+/// check_conditional_structure_with_limit(&directives, 1, &mut sink);
+/// assert_eq!(sink.error_count(), 2);
+/// ```
+pub fn check_conditional_structure_with_limit(
+    directives: &[(usize, String)],
+    max_depth: usize,
+    sink: &mut DiagnosticSink,
+) {
+    // The line of each open block, and whether it has had its `%ELSE`.
+    let mut open_lines: Vec<(usize, bool)> = Vec::new();
+    // The number of open blocks past `max_depth`.
+    let mut overflow = 0;
+
+    for (line, directive) in directives {
+        if directive == "%IF" {
+            if open_lines.len() < max_depth {
+                open_lines.push((*line, false));
+                continue;
+            }
+            if overflow == 0 {
+                sink.error_with_code(*line, "V004", &depth_error(max_depth));
+            }
+            overflow += 1;
+        } else if directive == "%ENDIF" {
+            if overflow > 0 {
+                overflow -= 1;
+            } else if open_lines.pop().is_none() {
+                sink.error_with_code(*line, "V002", "Unmatched %ENDIF directive");
+            }
+        } else if directive == "%ELSE" && overflow == 0 {
+            match open_lines.last_mut() {
+                None => sink.error_with_code(*line, "V007", "%ELSE without matching %IF"),
+                Some((_, true)) => {
+                    sink.error_with_code(*line, "V008", "Duplicate %ELSE in %IF block")
+                }
+                Some((_, seen_else)) => *seen_else = true,
+            }
+        }
+    }
+
+    for (line, _) in open_lines {
+        sink.error_with_code(line, "V003", "Unmatched %IF directive");
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// PRIVATE FUNCTIONS
+////////////////////////////////////////////////////////////////////////////////
+
+/// Builds the error reported when `%IF` blocks nest deeper than `max_depth`.
+pub(crate) fn depth_error(max_depth: usize) -> String {
+    format!("%IF blocks nested deeper than the limit of {}", max_depth)
+}
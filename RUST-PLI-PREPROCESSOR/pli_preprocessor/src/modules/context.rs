@@ -0,0 +1,435 @@
+#![allow(dead_code)] // Suppress warnings for unused functions in this module.
+
+////////////////////////////////////////////////////////////////////////////////
+// MODULE NAME: Preprocessor Context
+// ----------------------------------------------------------------------------
+// DESCRIPTION:
+// This module stores the preprocessor variables of a run: the names declared
+// with `%DECLARE`/`%DCL`, their types, and the values assigned by `%SET`.
+//
+// FUNCTIONALITY:
+// - Defines `Value`, the value of a preprocessor variable: an integer, a
+//   decimal, a string, or a boolean.
+// - Defines `VarType`, the declared type of a variable (`FIXED` or `CHARACTER`).
+// - Provides `Context`, which registers declarations and holds current values.
+// - Parses tokenized `%DECLARE` statements with `parse_declaration`.
+// - Defines `UndefinedPolicy`, which decides what reading an undefined
+//   variable yields: an error, `0`, or an empty string.
+// - Saves a context and macro table to a JSON state file and loads them back,
+//   so a later invocation can resume with the same variables and macros.
+//
+// USAGE:
+// - Create a `Context`, register variables with `declare`, then read and
+//   update them with `get` and `set`.
+// - Use `get_int` and `get_str` to read a value coerced to the type an
+//   expression needs; the evaluator, conditional processor, and pipeline all
+//   share this one context type.
+// - Use `save_state` at the end of a run and `load_state` at the start of the
+//   next one for incremental, multi-invocation builds.
+//
+// AUTHOR: FirstLink Consulting Services (FLCS)
+// LICENSE: MIT License
+// DATE: 10/16/2026
+// VERSION: 1.0.0
+////////////////////////////////////////////////////////////////////////////////
+
+////////////////////////////////////////////////////////////////////////////////
+// IMPORTS
+////////////////////////////////////////////////////////////////////////////////
+
+use crate::modules::error::PreprocessorError;
+use crate::modules::macro_expander::MacroTable;
+use crate::modules::symbols::declared_names;
+use crate::modules::tokenizer::Token;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+////////////////////////////////////////////////////////////////////////////////
+// PUBLIC STRUCTURES
+////////////////////////////////////////////////////////////////////////////////
+
+/// The value of a preprocessor variable.
+///
+/// A `Boolean` displays as `1` or `0`, like a PL/I bit. A `Decimal` holds a
+/// non-integer literal such as `3.5` as an `f64`, and always displays with a
+/// decimal point.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Value {
+    Int(i64),
+    Decimal(f64),
+    Str(String),
+    Boolean(bool),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Int(value) => write!(f, "{}", value),
+            Value::Decimal(value) if value.fract() == 0.0 => write!(f, "{:.1}", value),
+            Value::Decimal(value) => write!(f, "{}", value),
+            Value::Str(value) => write!(f, "{}", value),
+            Value::Boolean(value) => write!(f, "{}", i64::from(*value)),
+        }
+    }
+}
+
+/// The declared type of a preprocessor variable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum VarType {
+    Fixed,
+    Character,
+}
+
+impl VarType {
+    /// Maps a declaration attribute to a type, ignoring case.
+    ///
+    /// `FIXED` maps to `Fixed`; `CHARACTER` and `CHAR` map to `Character`.
+    pub fn from_attribute(attribute: &str) -> Option<VarType> {
+        match attribute.to_uppercase().as_str() {
+            "FIXED" => Some(VarType::Fixed),
+            "CHARACTER" | "CHAR" => Some(VarType::Character),
+            _ => None,
+        }
+    }
+
+    /// Returns the value a freshly declared variable of this type holds.
+    pub fn default_value(&self) -> Value {
+        match self {
+            VarType::Fixed => Value::Int(0),
+            VarType::Character => Value::Str(String::new()),
+        }
+    }
+}
+
+/// What reading an undefined variable yields.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UndefinedPolicy {
+    /// The read fails with an `Undefined variable` error.
+    #[default]
+    Error,
+    /// The variable reads as `0`.
+    Zero,
+    /// The variable reads as an empty string.
+    Empty,
+}
+
+/// Parses `error`, `zero`, or `empty`, ignoring ASCII case.
+impl FromStr for UndefinedPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "error" => Ok(UndefinedPolicy::Error),
+            "zero" => Ok(UndefinedPolicy::Zero),
+            "empty" => Ok(UndefinedPolicy::Empty),
+            _ => Err(format!("Unknown undefined-variable policy: {}", s)),
+        }
+    }
+}
+
+/// A declared (or, outside strict mode, implicitly created) variable.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Variable {
+    var_type: Option<VarType>,
+    value: Value,
+}
+
+/// The preprocessor variables of a run, keyed by uppercase name.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::context::{Context, Value, VarType};
+/// let mut context = Context::new();
+/// context.declare("COUNT", VarType::Fixed);
+/// assert_eq!(context.get("count"), Some(&Value::Int(0)));
+/// context.set("COUNT", Value::Int(3));
+/// assert_eq!(context.get("COUNT"), Some(&Value::Int(3)));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Context {
+    variables: HashMap<String, Variable>,
+    undefined: UndefinedPolicy,
+}
+
+impl Context {
+    /// Creates an empty context.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declares a variable, resetting it to its type's default value.
+    pub fn declare(&mut self, name: &str, var_type: VarType) {
+        self.variables.insert(
+            name.to_uppercase(),
+            Variable {
+                var_type: Some(var_type),
+                value: var_type.default_value(),
+            },
+        );
+    }
+
+    /// Returns `true` if the variable was declared with `declare`.
+    pub fn is_declared(&self, name: &str) -> bool {
+        self.variables
+            .get(&name.to_uppercase())
+            .is_some_and(|v| v.var_type.is_some())
+    }
+
+    /// Returns the declared type of a variable, if any.
+    pub fn var_type(&self, name: &str) -> Option<VarType> {
+        self.variables
+            .get(&name.to_uppercase())
+            .and_then(|v| v.var_type)
+    }
+
+    /// Returns the current value of a variable, if it exists.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.variables.get(&name.to_uppercase()).map(|v| &v.value)
+    }
+
+    /// Sets what `lookup`, `get_int`, and `get_str` yield for undefined names.
+    pub fn set_undefined_policy(&mut self, policy: UndefinedPolicy) {
+        self.undefined = policy;
+    }
+
+    /// Returns the policy applied to undefined names.
+    pub fn undefined_policy(&self) -> UndefinedPolicy {
+        self.undefined
+    }
+
+    /// Returns the value of a variable, applying the `UndefinedPolicy` when it
+    /// does not exist.
+    ///
+    /// # Returns
+    /// - `Result<Value, String>`: The value, `0` or an empty string for an
+    ///   undefined name under the `Zero` or `Empty` policy, or an error message
+    ///   under the `Error` policy.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use pli_preprocessor::modules::context::{Context, UndefinedPolicy, Value};
+    /// let mut context = Context::new();
+    /// assert!(context.lookup("UNDEF").is_err());
+    /// context.set_undefined_policy(UndefinedPolicy::Zero);
+    /// assert_eq!(context.lookup("UNDEF"), Ok(Value::Int(0)));
+    /// ```
+    pub fn lookup(&self, name: &str) -> Result<Value, String> {
+        match (self.get(name), self.undefined) {
+            (Some(value), _) => Ok(value.clone()),
+            (None, UndefinedPolicy::Zero) => Ok(Value::Int(0)),
+            (None, UndefinedPolicy::Empty) => Ok(Value::Str(String::new())),
+            (None, UndefinedPolicy::Error) => {
+                Err(format!("Undefined variable: {}", name.to_uppercase()))
+            }
+        }
+    }
+
+    /// Returns the value of a variable as an integer.
+    ///
+    /// A `Str` value is coerced when it holds a decimal integer, ignoring
+    /// surrounding whitespace, and a `Decimal` when it has no fractional part.
+    /// Undefined names follow the `UndefinedPolicy`.
+    ///
+    /// # Returns
+    /// - `Result<i64, String>`: The integer, or an error message if the
+    ///   variable is undefined (under the `Error` policy) or its value is not
+    ///   an integer.
+    pub fn get_int(&self, name: &str) -> Result<i64, String> {
+        match self.lookup(name)? {
+            Value::Int(value) => Ok(value),
+            Value::Decimal(value) if value.fract() == 0.0 && value.abs() < i64::MAX as f64 => {
+                Ok(value as i64)
+            }
+            Value::Decimal(value) => Err(format!(
+                "Variable {} is not an integer: {}",
+                name.to_uppercase(),
+                value
+            )),
+            Value::Boolean(value) => Ok(i64::from(value)),
+            Value::Str(text) => text.trim().parse().map_err(|_| {
+                format!(
+                    "Variable {} is not a number: '{}'",
+                    name.to_uppercase(),
+                    text
+                )
+            }),
+        }
+    }
+
+    /// Returns the value of a variable as text; integers become decimal text.
+    /// Undefined names follow the `UndefinedPolicy`.
+    ///
+    /// # Returns
+    /// - `Result<String, String>`: The text, or an error message if the
+    ///   variable is undefined under the `Error` policy.
+    pub fn get_str(&self, name: &str) -> Result<String, String> {
+        self.lookup(name).map(|value| value.to_string())
+    }
+
+    /// Assigns a value, creating an undeclared variable if needed.
+    ///
+    /// Callers that require declarations (strict mode) should check
+    /// `is_declared` first.
+    pub fn set(&mut self, name: &str, value: Value) {
+        self.variables
+            .entry(name.to_uppercase())
+            .and_modify(|v| v.value = value.clone())
+            .or_insert(Variable {
+                var_type: None,
+                value,
+            });
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// PRIVATE STRUCTURES
+////////////////////////////////////////////////////////////////////////////////
+
+/// The content of a state file written by `save_state`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SavedState {
+    context: Context,
+    macros: MacroTable,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// PUBLIC FUNCTIONS
+////////////////////////////////////////////////////////////////////////////////
+
+/// Saves the preprocessor variables and macros of a run to a JSON state file.
+///
+/// The file holds each variable's declared type and value, the
+/// `UndefinedPolicy`, and every macro definition with its activation and
+/// rescan settings. An existing file is replaced.
+///
+/// # Arguments
+/// - `path`: Where to write the state file.
+/// - `context`: The variables to save.
+/// - `macros`: The macros to save.
+///
+/// # Returns
+/// - `Result<(), PreprocessorError>`: `Ok(())` once written, an `Io` error if
+///   the file cannot be written, or an `Other` error if the state cannot be
+///   encoded.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::context::{save_state, Context};
+/// # use pli_preprocessor::modules::macro_expander::MacroTable;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let (context, macros) = (Context::new(), MacroTable::new());
+/// let path = std::env::temp_dir().join("pli_doc_save_state.json");
+/// save_state(&path, &context, &macros)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn save_state(
+    path: &Path,
+    context: &Context,
+    macros: &MacroTable,
+) -> Result<(), PreprocessorError> {
+    let state = SavedState {
+        context: context.clone(),
+        macros: macros.clone(),
+    };
+    let json = serde_json::to_string_pretty(&state).map_err(|e| {
+        PreprocessorError::Other(format!("Failed to encode state {}: {}", path.display(), e))
+    })?;
+    fs::write(path, json + "\n")?;
+    Ok(())
+}
+
+/// Loads the variables and macros saved by `save_state`.
+///
+/// # Arguments
+/// - `path`: The state file.
+///
+/// # Returns
+/// - `Result<(Context, MacroTable), PreprocessorError>`: The restored state, an
+///   `Io` error if the file cannot be read, or an `Other` error if it is not a
+///   valid state file.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::context::{load_state, save_state, Context};
+/// # use pli_preprocessor::modules::macro_expander::MacroTable;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let path = std::env::temp_dir().join("pli_doc_load_state.json");
+/// # save_state(&path, &Context::new(), &MacroTable::new())?;
+/// let (mut context, mut macros) = load_state(&path)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn load_state(path: &Path) -> Result<(Context, MacroTable), PreprocessorError> {
+    let json = fs::read_to_string(path)?;
+    let state: SavedState = serde_json::from_str(&json).map_err(|e| {
+        PreprocessorError::Other(format!("Invalid state file {}: {}", path.display(), e))
+    })?;
+    Ok((state.context, state.macros))
+}
+
+/// Parses a tokenized `%DECLARE`/`%DCL` statement into names and types.
+///
+/// Every comma-separated declaration needs a type attribute; a factored list
+/// such as `%DCL (A, B) FIXED;` gives each name the same type.
+///
+/// # Arguments
+/// - `tokens`: The statement's tokens, starting with the `%DECLARE` keyword.
+///
+/// # Returns
+/// - `Result<Vec<(String, VarType)>, String>`: The declared variables, or an
+///   error message if a name or type attribute is missing.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::context::{parse_declaration, VarType};
+/// # use pli_preprocessor::modules::tokenizer::tokenize_pli;
+/// let vars = parse_declaration(&tokenize_pli("%DCL (A, B) FIXED, C CHAR;")).unwrap();
+/// assert_eq!(vars[2], ("C".to_string(), VarType::Character));
+/// ```
+pub fn parse_declaration(tokens: &[Token]) -> Result<Vec<(String, VarType)>, String> {
+    let keyword = match tokens.first() {
+        Some(token) => token,
+        None => return Err("Empty declaration".to_string()),
+    };
+
+    // Split the statement into its comma-separated declarations.
+    let mut segments: Vec<Vec<Token>> = vec![Vec::new()];
+    let mut depth = 0;
+    for token in tokens[1..].iter().take_while(|t| t.value != ";") {
+        match token.value.as_str() {
+            "(" => depth += 1,
+            ")" => depth -= 1,
+            "," if depth == 0 => {
+                segments.push(Vec::new());
+                continue;
+            }
+            _ => {}
+        }
+        if let Some(segment) = segments.last_mut() {
+            segment.push(token.clone());
+        }
+    }
+
+    let mut variables = Vec::new();
+    for segment in segments {
+        let mut statement = vec![keyword.clone()];
+        statement.extend(segment.iter().cloned());
+        let names = declared_names(&statement);
+        if names.is_empty() {
+            return Err(format!("{} requires a variable name", keyword.value));
+        }
+        let var_type = segment
+            .iter()
+            .filter(|t| !names.contains(&t.value))
+            .find_map(|t| VarType::from_attribute(&t.value))
+            .ok_or_else(|| format!("Missing type attribute for {}", names.join(", ")))?;
+        variables.extend(names.into_iter().map(|name| (name, var_type)));
+    }
+
+    Ok(variables)
+}
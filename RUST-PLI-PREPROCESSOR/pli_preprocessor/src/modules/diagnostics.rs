@@ -0,0 +1,674 @@
+#![allow(dead_code)] // Suppress warnings for unused functions in this module.
+
+////////////////////////////////////////////////////////////////////////////////
+// MODULE NAME: Diagnostics
+// ----------------------------------------------------------------------------
+// DESCRIPTION:
+// This module provides structured diagnostics for the PL/I preprocessor, so a
+// single run yields a list of errors, warnings, and notes that IDEs and other
+// tools can consume.
+//
+// FUNCTIONALITY:
+// - Defines `Diagnostic`, a message tied to a source line with a `Severity`.
+// - Provides `DiagnosticSink`, the collector the tokenizer, validator, and
+//   conditional modules push into during processing.
+// - Renders a source line with a `^` under the offending column, in the style
+//   of compiler error output.
+// - Optionally collapses repeated diagnostics with the same severity and
+//   message into one, counting the repeats.
+// - Renders diagnostic lists for humans, as JSON lines, or in the GCC
+//   `file:line:col: severity: message` style with `format_diagnostics`.
+// - Assigns each kind of diagnostic a stable code (such as `V002` for an
+//   unmatched `%ENDIF`) from the `DIAGNOSTIC_CODES` table, which also holds
+//   the longer help text printed by `--explain`.
+//
+// USAGE:
+// - Create a `DiagnosticSink`, pass it to the checking functions, then call
+//   `into_diagnostics` to retrieve the collected list.
+// - Pass the list to `format_diagnostics` to hand it to editors or CI tools.
+// - Read a diagnostic's `code`, and call `explain_code` for the help text of
+//   a code.
+//
+// AUTHOR: FirstLink Consulting Services (FLCS)
+// LICENSE: MIT License
+// DATE: 10/16/2026
+// VERSION: 1.0.0
+////////////////////////////////////////////////////////////////////////////////
+
+////////////////////////////////////////////////////////////////////////////////
+// IMPORTS
+////////////////////////////////////////////////////////////////////////////////
+
+use crate::modules::include_handler::json_string;
+use std::fmt;
+use std::str::FromStr;
+
+////////////////////////////////////////////////////////////////////////////////
+// CONSTANTS
+////////////////////////////////////////////////////////////////////////////////
+
+/// Tab stops are expanded to this width before a caret is placed, so the caret
+/// lines up with the source however the terminal renders tabs.
+pub const TAB_WIDTH: usize = 8;
+
+/// The stable diagnostic codes, in code order.
+///
+/// A code's letter names the phase that reports it: `T` the tokenizer, `V`
+/// the validator, `P` the parser, `I` the include handler, `X` expression
+/// evaluation, `D` directive processing, and `A` the optional analyses. Each
+/// code is attached where its diagnostic or error is raised.
+///
+/// Codes are never reused. An entry's `former` codes are those it had before
+/// codes were grouped by phase; `explain_code` still accepts them, and no
+/// other entry takes them.
+pub const DIAGNOSTIC_CODES: &[DiagnosticCode] = &[
+    DiagnosticCode {
+        code: "T001",
+        former: &["E004"],
+        summary: "Unterminated string literal",
+        explanation: "A string literal was opened with `'` but not closed on the same \
+            line. A quote inside a string is written twice.",
+        example: "X = 'IT''S DONE;  ->  X = 'IT''S DONE';",
+    },
+    DiagnosticCode {
+        code: "T002",
+        former: &[],
+        summary: "Empty expression",
+        explanation: "An expression handed to `evaluator::evaluate_condition` or \
+            `evaluator::tokenize_expression` was empty or only whitespace.",
+        example: "evaluate_condition(\"\")  ->  evaluate_condition(\"N > 0\")",
+    },
+    DiagnosticCode {
+        code: "V001",
+        former: &["W001"],
+        summary: "Invalid directive",
+        explanation: "A `%` word is not a known preprocessor directive. It is usually \
+            misspelled, or the name after a lone `%` is missing; the statement is \
+            passed through unchanged.",
+        example: "%INCLUD 'DEFS.PLI';  ->  %INCLUDE 'DEFS.PLI';",
+    },
+    DiagnosticCode {
+        code: "V002",
+        former: &["E001"],
+        summary: "Unmatched %ENDIF directive",
+        explanation: "An `%ENDIF` closes the innermost open `%IF ... %THEN;` block, \
+            but no block was open. It is usually left over after a `%IF` was removed, \
+            or one block was closed twice.",
+        example:
+            "%IF DEBUG = 1 %THEN;\n  PUT SKIP LIST('DEBUG');\n%ENDIF;\n%ENDIF;  <- remove this line",
+    },
+    DiagnosticCode {
+        code: "V003",
+        former: &["E002"],
+        summary: "Unmatched %IF directive",
+        explanation: "A `%IF condition %THEN;` opens a block that must be closed by \
+            `%ENDIF;`, but the source ended with the block still open.",
+        example:
+            "%IF DEBUG = 1 %THEN;\n  PUT SKIP LIST('DEBUG');\n%ENDIF;  <- add the missing %ENDIF",
+    },
+    DiagnosticCode {
+        code: "V004",
+        former: &["E003"],
+        summary: "%IF blocks nested too deeply",
+        explanation: "`%IF` blocks may nest at most `conditional::MAX_IF_DEPTH` (256) \
+            levels deep, or the limit set with `ProcessOptions::max_if_depth`. \
+            Deeper nesting is usually generated code gone wrong.",
+        example: "Combine nested conditions into one:\n\
+            %IF A = 1 %THEN;\n  %IF B = 1 %THEN;  ->  %IF A = 1 AND B = 1 %THEN;",
+    },
+    DiagnosticCode {
+        code: "V005",
+        former: &["W002"],
+        summary: "Missing semicolon",
+        explanation: "A statement ended without its terminating `;` before the next \
+            statement or the end of the source.",
+        example: "A = 1  ->  A = 1;",
+    },
+    DiagnosticCode {
+        code: "V006",
+        former: &[],
+        summary: "%THEN without matching %IF",
+        explanation: "A `%THEN` must follow the condition of a `%IF` on the same \
+            line, but no `%IF` was open.",
+        example: "DEBUG = 1 %THEN;  ->  %IF DEBUG = 1 %THEN;",
+    },
+    DiagnosticCode {
+        code: "V007",
+        former: &[],
+        summary: "%ELSE without matching %IF",
+        explanation: "A `%ELSE` starts the other branch of the innermost open \
+            `%IF ... %THEN;` block, but no block was open. Its code is treated as \
+            the code around it.",
+        example: "A = 1;\n%ELSE;  <- remove this line, or add the missing %IF",
+    },
+    DiagnosticCode {
+        code: "V008",
+        former: &[],
+        summary: "Duplicate %ELSE in %IF block",
+        explanation: "A `%IF ... %THEN;` block has at most one `%ELSE`. The code \
+            after a second one is never active.",
+        example:
+            "%IF A = 1 %THEN;\n  X = 1;\n%ELSE;\n  X = 2;\n%ELSE;  <- use %ENDIF; and a new %IF",
+    },
+    DiagnosticCode {
+        code: "P001",
+        former: &[],
+        summary: "END without an open block",
+        explanation: "An `END;` closes the innermost open `DO`, `BEGIN`, `SELECT`, \
+            or `PROCEDURE` block, but no block was open.",
+        example: "DO I = 1 TO 3;\n  X = X + I;\nEND;\nEND;  <- remove this line",
+    },
+    DiagnosticCode {
+        code: "P002",
+        former: &[],
+        summary: "Unterminated block",
+        explanation: "A `DO`, `BEGIN`, `SELECT`, or `PROCEDURE` block must be closed \
+            by `END;`, but the source ended with the block still open.",
+        example: "DO I = 1 TO 3;\n  X = X + I;\nEND;  <- add the missing END",
+    },
+    DiagnosticCode {
+        code: "I001",
+        former: &[],
+        summary: "Invalid include directive",
+        explanation: "A `%INCLUDE` must name the file to include.",
+        example: "%INCLUDE;  ->  %INCLUDE 'DEFS.PLI';",
+    },
+    DiagnosticCode {
+        code: "I002",
+        former: &[],
+        summary: "Include not found",
+        explanation: "An included file could not be read. It is looked for next to \
+            the including file, then in each `--include-path` directory in order.",
+        example: "pli_preprocessor main.pli out.pli run.log -I copybooks",
+    },
+    DiagnosticCode {
+        code: "I003",
+        former: &[],
+        summary: "Recursive include",
+        explanation: "A file includes itself, directly or through other includes, \
+            so expanding it would never end.",
+        example: "Move the shared declarations to a file that both include.",
+    },
+    DiagnosticCode {
+        code: "I004",
+        former: &[],
+        summary: "Include not expanded",
+        explanation: "Includes are expanded before preprocessing, which needs a way \
+            to read the included files. In-memory source processed without an \
+            `IncludeResolver` cannot expand its `%INCLUDE` or `%XINCLUDE` \
+            directives, and they are never copied to the output.",
+        example:
+            "preprocess_string(src, ..)  ->  preprocess_string_with_resolver(src, .., &resolver)",
+    },
+    DiagnosticCode {
+        code: "X001",
+        former: &["E011"],
+        summary: "Expression cannot be evaluated",
+        explanation: "A `%IF` condition or `%SET` expression failed to evaluate. The \
+            message names the cause: most often a variable that was never set \
+            (choose another policy with `--undefined-vars=zero|empty`), a `/` or \
+            `MOD` by zero, or an operator without an operand. `AND` skips its \
+            right side when the left is false, which can guard a division.",
+        example: "%SET LEVEL = 1;  <- add before the first use\n%IF LEVEL > 0 %THEN;",
+    },
+    DiagnosticCode {
+        code: "D001",
+        former: &["E005"],
+        summary: "Unterminated %COMMENT",
+        explanation: "The text of a `%COMMENT` runs to the next `;`, but the source \
+            ended before one was found.",
+        example: "%COMMENT Explain the next block  ->  %COMMENT Explain the next block;",
+    },
+    DiagnosticCode {
+        code: "D002",
+        former: &["E006"],
+        summary: "Unterminated %MACRO",
+        explanation: "A `%MACRO NAME;` definition collects body lines until \
+            `%ENDMACRO;`, but the source ended before one was found. The error is \
+            reported at the line of the `%MACRO` header.",
+        example:
+            "%MACRO GREET;\n  PUT SKIP LIST('HELLO');\n%ENDMACRO;  <- add the missing %ENDMACRO",
+    },
+    DiagnosticCode {
+        code: "D003",
+        former: &["E007"],
+        summary: "Unterminated %PROCEDURE",
+        explanation: "A preprocessor procedure runs from its `%NAME: PROCEDURE` header \
+            to `%END;`, but the source ended before one was found.",
+        example: "%TWICE: PROCEDURE(N);\n  %RETURN(N * 2);\n%END;  <- add the missing %END",
+    },
+    DiagnosticCode {
+        code: "D004",
+        former: &["E008"],
+        summary: "Undefined label",
+        explanation: "A `%GOTO` names a label that no `%label:` statement defines, \
+            so the jump is skipped.",
+        example: "%GOTO DONE;\n...\n%DONE: ;  <- define the label",
+    },
+    DiagnosticCode {
+        code: "D005",
+        former: &["E009"],
+        summary: "Duplicate label",
+        explanation: "Two `%label:` statements define the same label, so a `%GOTO` \
+            to it would be ambiguous. The first definition is used.",
+        example: "%AGAIN: ;\n...\n%AGAIN: ;  ->  %AGAIN2: ;",
+    },
+    DiagnosticCode {
+        code: "D006",
+        former: &["E010"],
+        summary: "Too many %GOTO jumps",
+        explanation: "A run may follow at most `labels::MAX_GOTO_JUMPS` (10000) \
+            `%GOTO` jumps, which stops loops whose exit condition never holds. \
+            Processing stops at the jump that exceeded the limit.",
+        example: "%SET N = 0;\n%AGAIN: ;\n%SET N = N + 1;  <- make the loop progress\n\
+            %IF N < 3 %THEN %GOTO AGAIN;",
+    },
+    DiagnosticCode {
+        code: "D007",
+        former: &[],
+        summary: "Unsupported %DO group",
+        explanation: "`%DO` groups are not supported, so a `%DO`, and an `%END` that \
+            does not close a `%PROCEDURE`, are reported and left out of the output. \
+            The lines between them are processed as if the group were not there. \
+            Guard several lines with the block form of `%IF` instead.",
+        example: "%IF A = 1 %THEN %DO;  ->  %IF A = 1 %THEN;\n  X = 1;\n%END;  ->  %ENDIF;",
+    },
+    DiagnosticCode {
+        code: "A001",
+        former: &["N001"],
+        summary: "Block is never active",
+        explanation: "With `--analyze`, a `%IF` whose condition is a constant false \
+            is reported, since the code it guards can never be emitted.",
+        example: "%IF 0 = 1 %THEN;  ->  remove the block, or test a variable",
+    },
+    DiagnosticCode {
+        code: "A002",
+        former: &["N002"],
+        summary: "Indentation mixes tabs and spaces",
+        explanation: "With `--warn-indentation`, a line indented with both tabs and \
+            spaces is reported, since it lines up differently in different editors.",
+        example: "Indent with spaces only, or run `--format`.",
+    },
+];
+
+////////////////////////////////////////////////////////////////////////////////
+// PUBLIC STRUCTURES
+////////////////////////////////////////////////////////////////////////////////
+
+/// A stable diagnostic code and its help text.
+///
+/// # Fields
+/// - `code`: The code, such as `V002`.
+/// - `former`: Retired codes that meant the same thing, such as `E001`.
+/// - `summary`: A one-line description.
+/// - `explanation`: What causes the diagnostic.
+/// - `example`: How to fix it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiagnosticCode {
+    pub code: &'static str,
+    pub former: &'static [&'static str],
+    pub summary: &'static str,
+    pub explanation: &'static str,
+    pub example: &'static str,
+}
+
+/// The severity of a `Diagnostic`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+/// A single message produced while processing a source.
+///
+/// # Fields
+/// - `severity`: How serious the problem is.
+/// - `line`: The 1-based source line the message refers to.
+/// - `column`: The 1-based character column, when the problem has one.
+/// - `message`: A human-readable description.
+/// - `code`: The stable code of the message (see `DIAGNOSTIC_CODES`), if the
+///   place that reported it assigned one.
+/// - `count`: How many identical diagnostics this one stands for; above 1 only
+///   when a deduplicating `DiagnosticSink` collapsed repeats into it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub line: usize,
+    pub column: Option<usize>,
+    pub message: String,
+    pub code: Option<&'static str>,
+    pub count: usize,
+}
+
+impl Diagnostic {
+    /// Creates a new `Diagnostic` with no column and no code.
+    pub fn new(severity: Severity, line: usize, message: &str) -> Self {
+        Self {
+            severity,
+            line,
+            column: None,
+            message: message.to_string(),
+            code: None,
+            count: 1,
+        }
+    }
+
+    /// Sets how many identical diagnostics this one stands for.
+    pub fn with_count(mut self, count: usize) -> Self {
+        self.count = count;
+        self
+    }
+
+    /// Attaches the 1-based column the diagnostic points at.
+    pub fn with_column(mut self, column: usize) -> Self {
+        self.column = Some(column);
+        self
+    }
+
+    /// Sets the stable code (see `DIAGNOSTIC_CODES`).
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}: {}: {}",
+            self.line,
+            self.severity,
+            counted_message(self)
+        )
+    }
+}
+
+/// How `format_diagnostics` renders a diagnostic list.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DiagnosticFormat {
+    /// `line 3: warning: message`, as the log shows it.
+    #[default]
+    Human,
+    /// One JSON object per line.
+    Json,
+    /// `file:line:col: severity: message`, as GCC prints it.
+    Gcc,
+}
+
+/// Parses `human`, `json`, or `gcc`, ignoring ASCII case.
+impl FromStr for DiagnosticFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "human" => Ok(DiagnosticFormat::Human),
+            "json" => Ok(DiagnosticFormat::Json),
+            "gcc" => Ok(DiagnosticFormat::Gcc),
+            _ => Err(format!("Unknown diagnostic format: {}", s)),
+        }
+    }
+}
+
+/// Collects diagnostics pushed by the processing modules.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::diagnostics::DiagnosticSink;
+/// let mut sink = DiagnosticSink::new();
+/// sink.warning(3, "Invalid directive: %FOO");
+/// assert_eq!(sink.warning_count(), 1);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DiagnosticSink {
+    diagnostics: Vec<Diagnostic>,
+    dedup: bool,
+}
+
+impl DiagnosticSink {
+    /// Creates an empty sink.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enables or disables collapsing of repeated diagnostics.
+    ///
+    /// While enabled, a diagnostic whose severity and message match one
+    /// already recorded only increments that one's `count`; the first
+    /// occurrence keeps its line and column.
+    pub fn set_dedup(&mut self, dedup: bool) {
+        self.dedup = dedup;
+    }
+
+    /// Records a diagnostic.
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        if self.dedup {
+            let existing = self
+                .diagnostics
+                .iter_mut()
+                .find(|d| d.severity == diagnostic.severity && d.message == diagnostic.message);
+            if let Some(existing) = existing {
+                existing.count += diagnostic.count;
+                return;
+            }
+        }
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// Records an error on the given line.
+    pub fn error(&mut self, line: usize, message: &str) {
+        self.push(Diagnostic::new(Severity::Error, line, message));
+    }
+
+    /// Records a warning on the given line.
+    pub fn warning(&mut self, line: usize, message: &str) {
+        self.push(Diagnostic::new(Severity::Warning, line, message));
+    }
+
+    /// Records a note on the given line.
+    pub fn note(&mut self, line: usize, message: &str) {
+        self.push(Diagnostic::new(Severity::Note, line, message));
+    }
+
+    /// Records an error with a stable code on the given line.
+    pub fn error_with_code(&mut self, line: usize, code: &'static str, message: &str) {
+        self.push(Diagnostic::new(Severity::Error, line, message).with_code(code));
+    }
+
+    /// Returns the diagnostics collected so far.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Returns the number of collected errors, counting collapsed repeats.
+    pub fn error_count(&self) -> usize {
+        self.count(Severity::Error)
+    }
+
+    /// Returns the number of collected warnings, counting collapsed repeats.
+    pub fn warning_count(&self) -> usize {
+        self.count(Severity::Warning)
+    }
+
+    /// Consumes the sink and returns the collected diagnostics in push order.
+    pub fn into_diagnostics(self) -> Vec<Diagnostic> {
+        self.diagnostics
+    }
+
+    fn count(&self, severity: Severity) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == severity)
+            .map(|d| d.count)
+            .sum()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// PUBLIC FUNCTIONS
+////////////////////////////////////////////////////////////////////////////////
+
+/// Renders a message followed by the source line and a `^` under `column`.
+///
+/// Tabs in `source_line` are expanded to `TAB_WIDTH` stops so the caret sits
+/// under the right character. A column of 0 or past the end of the line puts
+/// the caret just after the last character.
+///
+/// # Arguments
+/// - `source_line`: The line the message refers to.
+/// - `column`: The 1-based character column to underline.
+/// - `message`: The text printed above the source line.
+///
+/// # Returns
+/// - `String`: Three lines: the message, the expanded source, and the caret.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::diagnostics::format_diagnostic_with_caret;
+/// let text = format_diagnostic_with_caret("X = 'oops;", 5, "Unmatched string literal");
+/// assert_eq!(text, "Unmatched string literal\nX = 'oops;\n    ^");
+/// ```
+pub fn format_diagnostic_with_caret(source_line: &str, column: usize, message: &str) -> String {
+    let mut expanded = String::new();
+    let mut caret_offset = None;
+    for (index, c) in source_line.chars().enumerate() {
+        if index + 1 == column {
+            caret_offset = Some(expanded.chars().count());
+        }
+        if c == '\t' {
+            let width = TAB_WIDTH - expanded.chars().count() % TAB_WIDTH;
+            expanded.push_str(&" ".repeat(width));
+        } else {
+            expanded.push(c);
+        }
+    }
+    let caret_offset = caret_offset.unwrap_or_else(|| expanded.chars().count());
+    format!("{}\n{}\n{}^", message, expanded, " ".repeat(caret_offset))
+}
+
+/// Renders diagnostics one per line in the given format.
+///
+/// The GCC format omits the column when a diagnostic has none; the JSON format
+/// writes it, and a diagnostic without a stable code, as `null`. The human
+/// format does not name the file.
+///
+/// # Arguments
+/// - `diagnostics`: The diagnostics to render, in order.
+/// - `format`: The output format.
+/// - `file`: The source file the diagnostics refer to.
+///
+/// # Returns
+/// - `String`: One line per diagnostic, each ending in a newline.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::diagnostics::{format_diagnostics, Diagnostic, DiagnosticFormat, Severity};
+/// let list = vec![Diagnostic::new(Severity::Error, 4, "Unmatched string literal").with_column(9)];
+/// assert_eq!(
+///     format_diagnostics(&list, DiagnosticFormat::Gcc, "main.pli"),
+///     "main.pli:4:9: error: Unmatched string literal\n"
+/// );
+/// ```
+pub fn format_diagnostics(
+    diagnostics: &[Diagnostic],
+    format: DiagnosticFormat,
+    file: &str,
+) -> String {
+    let mut text = String::new();
+    for diagnostic in diagnostics {
+        let line = match format {
+            DiagnosticFormat::Human => diagnostic.to_string(),
+            DiagnosticFormat::Json => format!(
+                "{{\"file\": {}, \"line\": {}, \"column\": {}, \"severity\": \"{}\", \"code\": {}, \"message\": {}}}",
+                json_string(file),
+                diagnostic.line,
+                diagnostic.column.map_or("null".to_string(), |c| c.to_string()),
+                diagnostic.severity,
+                diagnostic.code.map_or("null".to_string(), json_string),
+                json_string(&counted_message(diagnostic))
+            ),
+            DiagnosticFormat::Gcc => match diagnostic.column {
+                Some(column) => format!(
+                    "{}:{}:{}: {}: {}",
+                    file, diagnostic.line, column, diagnostic.severity, counted_message(diagnostic)
+                ),
+                None => format!(
+                    "{}:{}: {}: {}",
+                    file, diagnostic.line, diagnostic.severity, counted_message(diagnostic)
+                ),
+            },
+        };
+        text.push_str(&line);
+        text.push('\n');
+    }
+    text
+}
+
+/// Returns the help text for a diagnostic code.
+///
+/// A former code is explained under the code that replaced it, after a line
+/// naming the replacement.
+///
+/// # Arguments
+/// - `code`: The code to explain, such as `V002`; case is ignored.
+///
+/// # Returns
+/// - `Result<String, String>`: The code's summary, explanation, and example
+///   fix, or an error message if the code is unknown.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::diagnostics::explain_code;
+/// let help = explain_code("v002").unwrap();
+/// assert!(help.starts_with("V002: Unmatched %ENDIF directive\n"));
+/// assert!(explain_code("E001").unwrap().starts_with("E001 is now V002.\n"));
+/// assert!(explain_code("E999").is_err());
+/// ```
+pub fn explain_code(code: &str) -> Result<String, String> {
+    let (entry, former) = DIAGNOSTIC_CODES
+        .iter()
+        .find_map(|entry| {
+            if entry.code.eq_ignore_ascii_case(code) {
+                Some((entry, None))
+            } else {
+                entry
+                    .former
+                    .iter()
+                    .find(|former| former.eq_ignore_ascii_case(code))
+                    .map(|former| (entry, Some(former)))
+            }
+        })
+        .ok_or_else(|| format!("Unknown diagnostic code: {}", code))?;
+    let renamed = former.map_or(String::new(), |former| {
+        format!("{} is now {}.\n\n", former, entry.code)
+    });
+    Ok(format!(
+        "{}{}: {}\n\n{}\n\nExample fix:\n{}\n",
+        renamed, entry.code, entry.summary, entry.explanation, entry.example
+    ))
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// PRIVATE FUNCTIONS
+////////////////////////////////////////////////////////////////////////////////
+
+/// Returns the message with `(and N more)` appended for collapsed repeats.
+fn counted_message(diagnostic: &Diagnostic) -> String {
+    if diagnostic.count > 1 {
+        format!("{} (and {} more)", diagnostic.message, diagnostic.count - 1)
+    } else {
+        diagnostic.message.clone()
+    }
+}
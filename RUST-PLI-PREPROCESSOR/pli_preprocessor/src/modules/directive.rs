@@ -0,0 +1,184 @@
+#![allow(dead_code)] // Suppress warnings for unused functions in this module.
+
+////////////////////////////////////////////////////////////////////////////////
+// MODULE NAME: Directive Registry
+// ----------------------------------------------------------------------------
+// DESCRIPTION:
+// This module is the single source of truth for which preprocessor directives
+// exist and which `DirectiveCategory` each belongs to. The tokenizer and the
+// validator both consult it, so the two can no longer drift apart.
+//
+// FUNCTIONALITY:
+// - Lists the built-in directives and their categories in `BUILTIN_DIRECTIVES`.
+// - Normalizes directive spellings with `canonicalize`.
+// - Provides `DirectiveRegistry`, which layers directives registered at runtime
+//   on top of the built-in table.
+//
+// USAGE:
+// - Call `builtin_category` to look up a standard directive.
+// - Call `canonicalize` to turn a directive as written (any case, stray
+//   spacing) into its canonical spelling.
+// - Create a `DirectiveRegistry`, `register` custom directives, and store it in
+//   a `TokenizerConfig` to have the tokenizer categorize them.
+//
+// AUTHOR: FirstLink Consulting Services (FLCS)
+// LICENSE: MIT License
+// DATE: 10/16/2026
+// VERSION: 1.0.0
+////////////////////////////////////////////////////////////////////////////////
+
+////////////////////////////////////////////////////////////////////////////////
+// IMPORTS
+////////////////////////////////////////////////////////////////////////////////
+
+use crate::modules::tokenizer::DirectiveCategory;
+use std::collections::HashMap;
+
+////////////////////////////////////////////////////////////////////////////////
+// CONSTANTS
+////////////////////////////////////////////////////////////////////////////////
+
+/// The standard directives and their categories.
+///
+/// Directives whose category is `Other` are still recognized as valid.
+pub const BUILTIN_DIRECTIVES: &[(&str, DirectiveCategory)] = &[
+    ("%IF", DirectiveCategory::ControlFlow),
+    ("%THEN", DirectiveCategory::ControlFlow),
+    ("%ELSE", DirectiveCategory::ControlFlow),
+    ("%ENDIF", DirectiveCategory::ControlFlow),
+    ("%GOTO", DirectiveCategory::ControlFlow),
+    ("%GO", DirectiveCategory::ControlFlow),
+    ("%MACRO", DirectiveCategory::MacroHandling),
+    ("%ENDMACRO", DirectiveCategory::MacroHandling),
+    ("%INCLUDE", DirectiveCategory::MacroHandling),
+    ("%XINCLUDE", DirectiveCategory::MacroHandling),
+    ("%ACTIVATE", DirectiveCategory::MacroHandling),
+    ("%DEACTIVATE", DirectiveCategory::MacroHandling),
+    ("%DECLARE", DirectiveCategory::MacroHandling),
+    ("%DCL", DirectiveCategory::MacroHandling),
+    ("%SET", DirectiveCategory::MacroHandling),
+    ("%RETURN", DirectiveCategory::MacroHandling),
+    ("%SWITCH", DirectiveCategory::Conditional),
+    ("%CASE", DirectiveCategory::Conditional),
+    ("%EVALUATE", DirectiveCategory::Conditional),
+    ("%DEFAULT", DirectiveCategory::Other),
+    ("%COMMENT", DirectiveCategory::Comment),
+    ("%DO", DirectiveCategory::Other),
+    ("%END", DirectiveCategory::Other),
+    ("%PRINT", DirectiveCategory::Other),
+    ("%NOPRINT", DirectiveCategory::Other),
+];
+
+////////////////////////////////////////////////////////////////////////////////
+// PUBLIC STRUCTURES
+////////////////////////////////////////////////////////////////////////////////
+
+/// The built-in directives plus any registered at runtime.
+///
+/// Lookups ignore case. A registered directive overrides a built-in one of the
+/// same name.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::directive::DirectiveRegistry;
+/// # use pli_preprocessor::modules::tokenizer::DirectiveCategory;
+/// let mut registry = DirectiveRegistry::new();
+/// registry.register("%MYDIR", DirectiveCategory::ControlFlow);
+/// assert_eq!(registry.category("%mydir"), DirectiveCategory::ControlFlow);
+/// assert_eq!(registry.category("%IF"), DirectiveCategory::ControlFlow);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DirectiveRegistry {
+    custom: HashMap<String, DirectiveCategory>,
+}
+
+impl DirectiveRegistry {
+    /// Creates a registry holding only the built-in directives.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a custom directive, adding the leading `%` if it is missing.
+    pub fn register(&mut self, directive: &str, category: DirectiveCategory) {
+        let mut name = directive.to_uppercase();
+        if !name.starts_with('%') {
+            name.insert(0, '%');
+        }
+        self.custom.insert(name, category);
+    }
+
+    /// Returns the category of a directive, or `Other` if it is unknown.
+    pub fn category(&self, directive: &str) -> DirectiveCategory {
+        self.lookup(directive).unwrap_or(DirectiveCategory::Other)
+    }
+
+    /// Returns `true` if the directive is built in or registered.
+    pub fn is_known(&self, directive: &str) -> bool {
+        self.lookup(directive).is_some()
+    }
+
+    fn lookup(&self, directive: &str) -> Option<DirectiveCategory> {
+        let name = directive.to_uppercase();
+        self.custom
+            .get(&name)
+            .cloned()
+            .or_else(|| builtin_category(&name))
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// PUBLIC FUNCTIONS
+////////////////////////////////////////////////////////////////////////////////
+
+/// Looks up a standard directive in `BUILTIN_DIRECTIVES`, ignoring case.
+///
+/// # Arguments
+/// - `directive`: The directive token, including the leading `%`.
+///
+/// # Returns
+/// - `Option<DirectiveCategory>`: The category, or `None` if the directive is
+///   not a standard one.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::directive::builtin_category;
+/// # use pli_preprocessor::modules::tokenizer::DirectiveCategory;
+/// assert_eq!(builtin_category("%endif"), Some(DirectiveCategory::ControlFlow));
+/// assert_eq!(builtin_category("%MYDIR"), None);
+/// ```
+pub fn builtin_category(directive: &str) -> Option<DirectiveCategory> {
+    BUILTIN_DIRECTIVES
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(directive))
+        .map(|(_, category)| category.clone())
+}
+
+/// Returns the canonical spelling of a standard directive.
+///
+/// Case is ignored, as is whitespace anywhere in the token, so `%if `,
+/// `% If`, and `%IF` all map to `%IF`.
+///
+/// # Arguments
+/// - `token`: The directive as written, including the leading `%`.
+///
+/// # Returns
+/// - `Option<String>`: The uppercase directive from `BUILTIN_DIRECTIVES`, or
+///   `None` if the token is not a standard directive.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::directive::canonicalize;
+/// assert_eq!(canonicalize(" %EndIf "), Some("%ENDIF".to_string()));
+/// assert_eq!(canonicalize("%MYDIR"), None);
+/// ```
+pub fn canonicalize(token: &str) -> Option<String> {
+    let name: String = token
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_uppercase();
+    BUILTIN_DIRECTIVES
+        .iter()
+        .find(|(directive, _)| *directive == name)
+        .map(|(directive, _)| directive.to_string())
+}
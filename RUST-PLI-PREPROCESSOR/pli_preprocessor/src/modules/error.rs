@@ -0,0 +1,176 @@
+#![allow(dead_code)] // Suppress warnings for unused functions in this module.
+
+////////////////////////////////////////////////////////////////////////////////
+// MODULE NAME: Errors
+// ----------------------------------------------------------------------------
+// DESCRIPTION:
+// This module defines `PreprocessorError`, the error type returned by the
+// crate's entry points, so failures from different phases compose with `?`
+// and implement `std::error::Error` for callers using `Box<dyn Error>` or
+// error-handling crates.
+//
+// FUNCTIONALITY:
+// - Defines `PreprocessorError`, with one variant per phase that can fail:
+//   tokenizing, parsing, evaluating, including, and I/O.
+// - Converts from `io::Error` into the `Io` variant, and from the `String`
+//   errors of the lower-level helpers into the `Other` variant.
+// - Carries the stable diagnostic code of each tokenizer, parser, and include
+//   error, set where the error is raised; `code` reports it.
+//
+// USAGE:
+// - Propagate errors from `pipeline::process_reader`, `parser::parse_program`,
+//   `evaluator::evaluate_value`, and the `include_handler` file functions with
+//   `?` into any function returning `Result<_, PreprocessorError>`.
+// - Helpers that still return `String` errors convert with `?` too; wrap them
+//   in a specific variant with `map_err(PreprocessorError::Evaluator)` when the
+//   phase is known.
+//
+// AUTHOR: FirstLink Consulting Services (FLCS)
+// LICENSE: MIT License
+// DATE: 10/16/2026
+// VERSION: 1.0.0
+////////////////////////////////////////////////////////////////////////////////
+
+////////////////////////////////////////////////////////////////////////////////
+// IMPORTS
+////////////////////////////////////////////////////////////////////////////////
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+////////////////////////////////////////////////////////////////////////////////
+// PUBLIC STRUCTURES
+////////////////////////////////////////////////////////////////////////////////
+
+/// An error raised by the preprocessor, tagged with the phase that failed.
+///
+/// `Display` shows the message alone, exactly as the phase reported it.
+/// Two `Io` errors are equal when their kinds and messages are.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::error::PreprocessorError;
+/// # use pli_preprocessor::modules::evaluator::evaluate_value;
+/// # use pli_preprocessor::modules::parser::parse_program;
+/// fn run(source: &str) -> Result<usize, PreprocessorError> {
+///     let nodes = parse_program(source)?;
+///     let total = evaluate_value("1 + 2")?;
+///     Ok(nodes.len() + total.to_string().len())
+/// }
+/// assert_eq!(
+///     run("END;").unwrap_err().to_string(),
+///     "END without an open block at line 1"
+/// );
+/// ```
+#[derive(Debug)]
+pub enum PreprocessorError {
+    /// An expression or source line could not be tokenized.
+    Tokenizer { code: &'static str, message: String },
+    /// The statement structure is malformed, e.g. an unbalanced `END`.
+    Parser { code: &'static str, message: String },
+    /// An expression could not be evaluated; always code `X001`.
+    Evaluator(String),
+    /// An `%INCLUDE` could not be resolved or forms a cycle.
+    Include { code: &'static str, message: String },
+    /// Reading the input or writing the output failed.
+    Io(io::Error),
+    /// An error from a helper that reports plain `String` errors.
+    Other(String),
+}
+
+impl PreprocessorError {
+    /// Returns the stable diagnostic code of the error, such as `I002` for a
+    /// missing include (see `diagnostics::DIAGNOSTIC_CODES`), or `None` for
+    /// `Io` and `Other` errors.
+    pub fn code(&self) -> Option<&'static str> {
+        match self {
+            PreprocessorError::Tokenizer { code, .. }
+            | PreprocessorError::Parser { code, .. }
+            | PreprocessorError::Include { code, .. } => Some(code),
+            PreprocessorError::Evaluator(_) => Some("X001"),
+            PreprocessorError::Io(_) | PreprocessorError::Other(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for PreprocessorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PreprocessorError::Tokenizer { message, .. }
+            | PreprocessorError::Parser { message, .. }
+            | PreprocessorError::Include { message, .. }
+            | PreprocessorError::Evaluator(message)
+            | PreprocessorError::Other(message) => write!(f, "{}", message),
+            PreprocessorError::Io(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for PreprocessorError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PreprocessorError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl PartialEq for PreprocessorError {
+    fn eq(&self, other: &Self) -> bool {
+        use PreprocessorError::*;
+        match (self, other) {
+            (
+                Tokenizer {
+                    code: a_code,
+                    message: a,
+                },
+                Tokenizer {
+                    code: b_code,
+                    message: b,
+                },
+            )
+            | (
+                Parser {
+                    code: a_code,
+                    message: a,
+                },
+                Parser {
+                    code: b_code,
+                    message: b,
+                },
+            )
+            | (
+                Include {
+                    code: a_code,
+                    message: a,
+                },
+                Include {
+                    code: b_code,
+                    message: b,
+                },
+            ) => a_code == b_code && a == b,
+            (Evaluator(a), Evaluator(b)) | (Other(a), Other(b)) => a == b,
+            (Io(a), Io(b)) => a.kind() == b.kind() && a.to_string() == b.to_string(),
+            _ => false,
+        }
+    }
+}
+
+impl From<io::Error> for PreprocessorError {
+    fn from(error: io::Error) -> Self {
+        PreprocessorError::Io(error)
+    }
+}
+
+impl From<String> for PreprocessorError {
+    fn from(message: String) -> Self {
+        PreprocessorError::Other(message)
+    }
+}
+
+impl From<&str> for PreprocessorError {
+    fn from(message: &str) -> Self {
+        PreprocessorError::Other(message.to_string())
+    }
+}
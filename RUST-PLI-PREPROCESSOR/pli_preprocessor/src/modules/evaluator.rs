@@ -10,12 +10,33 @@
 // FUNCTIONALITY:
 // - Parses and evaluates expressions used in PL/I directives.
 // - Supports precedence and associativity for operators.
-// - Handles variables with values from a predefined context.
+// - Handles variables with values from a shared `Context`.
 // - Converts infix expressions to postfix notation for correct evaluation.
 //
 // USAGE:
 // - Use `evaluate_expression` to compute the result of an expression.
+// - Use `evaluate_condition` to compare two expressions (e.g. `0 = 1`).
+// - Use `evaluate_value` for typed expressions over numbers and strings,
+//   including `||` concatenation, comparisons, `AND`/`OR`, and `NOT`/`^`
+//   negation, which yield a `Value::Boolean`.
+// - Use `parse_expression` to get an expression's postfix (RPN) form, or
+//   `parse_expression_with_positions` to also map each RPN term back to its
+//   input token for caret diagnostics.
+// - Use `resolve_variables` to replace variables in tokenized expressions with
+//   their values from a `Context`.
+// - Use `evaluate_tokens` to evaluate tokenized expressions against a
+//   `Context`. `AND`/`OR` short-circuit: the right operand of `false AND x` or
+//   `true OR x` may fail (e.g. name an undefined variable) without error.
 // - Extend the `evaluate_operator` function to support more operators.
+// - Use the `MOD` keyword for remainders; `%` is reserved for directives.
+// - `AND`, `OR`, `MOD`, and `NOT` are operators only where an operator is
+//   expected. Where an operand is expected, `evaluate_tokens` and
+//   `parse_expression` read them as variable names (`AND = 1`).
+// - Write hexadecimal and binary integer constants in PL/I form: `'FF'X` and
+//   `'1010'B`. A plain quoted string is a string operand.
+// - Write decimal constants with a point, as in `3.5` or `.25`. They evaluate
+//   to a `Value::Decimal`, and arithmetic with a decimal operand yields a
+//   decimal. Decimal comparisons allow for rounding (see `DECIMAL_TOLERANCE`).
 //
 // AUTHOR: FirstLink Consulting Services (FLCS)
 // LICENSE: MIT License
@@ -23,6 +44,86 @@
 // VERSION: 2.0.1
 ////////////////////////////////////////////////////////////////////////////////
 
+////////////////////////////////////////////////////////////////////////////////
+// IMPORTS
+////////////////////////////////////////////////////////////////////////////////
+
+use crate::modules::context::{Context, Value};
+use crate::modules::error::PreprocessorError;
+use crate::modules::tokenizer::{CharClassConfig, Token, TokenCategory};
+use log::debug;
+use std::cmp::Ordering;
+
+////////////////////////////////////////////////////////////////////////////////
+// CONSTANTS
+////////////////////////////////////////////////////////////////////////////////
+
+/// The relative tolerance of decimal comparisons.
+///
+/// Decimals are held as `f64`, which cannot represent most decimal fractions
+/// exactly, so `0.1 + 0.2 = 0.3` would be false under exact equality. When
+/// either operand of a comparison is a decimal, two values are therefore equal
+/// if they differ by at most `DECIMAL_TOLERANCE` times the larger magnitude
+/// (or times 1 for magnitudes below 1), and `<`, `>`, `<=`, and `>=` agree
+/// with that equality. Integers always compare exactly.
+pub const DECIMAL_TOLERANCE: f64 = 1e-9;
+
+////////////////////////////////////////////////////////////////////////////////
+// PRIVATE STRUCTURES
+////////////////////////////////////////////////////////////////////////////////
+
+/// A postfix (RPN) term with the index of the input token it came from.
+///
+/// `operand` tells a variable operand from an operator, since a variable may
+/// share a keyword operator's name (`AND`, `OR`, `MOD`, `NOT`). `sign` marks a
+/// prefix `+` or `-`, which shares its text with the binary operator.
+#[derive(Debug)]
+struct PostfixTerm {
+    text: String,
+    index: usize,
+    operand: bool,
+    sign: bool,
+}
+
+impl PostfixTerm {
+    fn operand(text: &str, index: usize) -> Self {
+        PostfixTerm {
+            text: text.to_string(),
+            index,
+            operand: true,
+            sign: false,
+        }
+    }
+
+    fn operator(text: &str, index: usize) -> Self {
+        PostfixTerm {
+            text: text.to_string(),
+            index,
+            operand: false,
+            sign: false,
+        }
+    }
+
+    fn sign(text: &str, index: usize) -> Self {
+        PostfixTerm {
+            text: text.to_string(),
+            index,
+            operand: false,
+            sign: true,
+        }
+    }
+
+    /// A prefix sign binds tighter than any binary operator, so `-2 * 3`
+    /// negates 2 before multiplying.
+    fn precedence(&self) -> u8 {
+        if self.sign {
+            8
+        } else {
+            precedence(&self.text)
+        }
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // PUBLIC FUNCTIONS
 ////////////////////////////////////////////////////////////////////////////////
@@ -33,21 +134,68 @@
 /// - `expression`: A `&str` containing the expression to evaluate (e.g., `"3 + 5"`).
 ///
 /// # Returns
-/// - `Result<i32, String>`: Returns `Ok(result)` with the computed value, or an
-///   `Err(String)` with an error message if the expression is invalid.
+/// - `Result<i64, PreprocessorError>`: Returns `Ok(result)` with the computed
+///   value, or a `Tokenizer` or `Evaluator` error if the expression is invalid.
 ///
 /// # Example
 /// ```rust
+/// # use pli_preprocessor::modules::evaluator::evaluate_expression;
 /// let result = evaluate_expression("3 + 5");
 /// assert_eq!(result, Ok(8));
 /// ```
-pub fn evaluate_expression(expression: &str) -> Result<i32, String> {
+pub fn evaluate_expression(expression: &str) -> Result<i64, PreprocessorError> {
     if expression.trim().is_empty() {
-        return Err("Expression is empty".to_string());
+        return Err(PreprocessorError::Evaluator(
+            "Expression is empty".to_string(),
+        ));
     }
 
     let tokens = tokenize_expression(expression)?;
-    parse_and_evaluate(&tokens)
+    parse_and_evaluate(&tokens).map_err(PreprocessorError::Evaluator)
+}
+
+/// Evaluates a condition, comparing the expressions on either side of a single
+/// comparison operator (`=`, `^=`, `!=`, `<>`, `<`, `>`, `<=`, `>=`).
+///
+/// A condition without a comparison operator is true when its value is nonzero.
+/// Either side may be a decimal, compared within `DECIMAL_TOLERANCE`.
+///
+/// # Arguments
+/// - `condition`: A `&str` containing whitespace-separated tokens (e.g., `"2 * 3 = 6"`).
+///
+/// # Returns
+/// - `Result<bool, PreprocessorError>`: Returns the truth value of the
+///   condition, or a `Tokenizer` or `Evaluator` error if either side cannot be
+///   evaluated.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::evaluator::evaluate_condition;
+/// assert_eq!(evaluate_condition("0 = 1"), Ok(false));
+/// assert_eq!(evaluate_condition("2 + 2 >= 4"), Ok(true));
+/// assert_eq!(evaluate_condition("3.5 > 3.4"), Ok(true));
+/// ```
+pub fn evaluate_condition(condition: &str) -> Result<bool, PreprocessorError> {
+    let tokens = tokenize_expression(condition)?;
+    let comparison = tokens
+        .iter()
+        .position(|t| ["=", "^=", "!=", "<>", "<", ">", "<=", ">="].contains(&t.as_str()));
+    let evaluate = |terms: &[String]| {
+        parse_and_evaluate_value(terms)
+            .and_then(|value| value_to_decimal(&value).map(|_| value))
+            .map_err(PreprocessorError::Evaluator)
+    };
+
+    let index = match comparison {
+        Some(index) => index,
+        None => {
+            return truth_value(&evaluate(&tokens)?).map_err(PreprocessorError::Evaluator);
+        }
+    };
+    let left = evaluate(&tokens[..index])?;
+    let right = evaluate(&tokens[index + 1..])?;
+
+    compare_values(&left, &right, &tokens[index]).map_err(PreprocessorError::Evaluator)
 }
 
 /// Tokenizes an expression into a list of operators and operands.
@@ -56,22 +204,77 @@ pub fn evaluate_expression(expression: &str) -> Result<i32, String> {
 /// - `expression`: A `&str` containing the expression to tokenize.
 ///
 /// # Returns
-/// - `Result<Vec<String>, String>`: Returns a vector of tokens or an error message.
+/// - `Result<Vec<String>, PreprocessorError>`: Returns a vector of tokens, or a
+///   `Tokenizer` error for an empty expression or unterminated string.
 ///
 /// # Example
 /// ```rust
+/// # use pli_preprocessor::modules::evaluator::tokenize_expression;
 /// let tokens = tokenize_expression("3 + 5");
-/// assert_eq!(tokens, Ok(vec!["3", "+", "5"]));
+/// assert_eq!(tokens.unwrap(), vec!["3", "+", "5"]);
 /// ```
-pub fn tokenize_expression(expression: &str) -> Result<Vec<String>, String> {
+pub fn tokenize_expression(expression: &str) -> Result<Vec<String>, PreprocessorError> {
     if expression.trim().is_empty() {
-        return Err("Expression is empty".to_string());
+        return Err(PreprocessorError::Tokenizer {
+            code: "T002",
+            message: "Expression is empty".to_string(),
+        });
     }
 
-    let tokens: Vec<String> = expression
-        .split_whitespace()
-        .map(|s| s.to_string())
-        .collect();
+    let mut tokens: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let mut chars = expression.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            if !current.is_empty() {
+                tokens.push(std::mem::take(&mut current));
+            }
+        } else if c == '\'' {
+            // A quoted string is one token, even if it contains spaces.
+            if !current.is_empty() {
+                tokens.push(std::mem::take(&mut current));
+            }
+            current.push(c);
+            let mut closed = false;
+            while let Some(next) = chars.next() {
+                current.push(next);
+                if next == '\'' {
+                    if chars.peek() == Some(&'\'') {
+                        current.push(chars.next().unwrap_or('\''));
+                    } else {
+                        closed = true;
+                        break;
+                    }
+                }
+            }
+            if !closed {
+                return Err(PreprocessorError::Tokenizer {
+                    code: "T001",
+                    message: format!("Unterminated string literal: {}", current),
+                });
+            }
+            // A trailing `X` or `B` makes the literal a hex or binary constant.
+            if let Some(suffix) = chars.next_if(|n| matches!(n, 'X' | 'x' | 'B' | 'b')) {
+                current.push(suffix);
+            }
+            tokens.push(std::mem::take(&mut current));
+        } else if c == '('
+            || c == ')'
+            || (c == '^' && current.is_empty() && chars.peek() != Some(&'='))
+        {
+            // Parentheses and prefix negation stand alone, as in `^(A = B)`.
+            if !current.is_empty() {
+                tokens.push(std::mem::take(&mut current));
+            }
+            tokens.push(c.to_string());
+        } else {
+            current.push(c);
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
 
     Ok(tokens)
 }
@@ -82,62 +285,245 @@ pub fn tokenize_expression(expression: &str) -> Result<Vec<String>, String> {
 /// - `tokens`: A `&[String]` slice containing the tokenized expression.
 ///
 /// # Returns
-/// - `Result<i32, String>`: Returns the computed result or an error message.
+/// - `Result<i64, String>`: Returns the computed result or an error message.
 ///
 /// # Example
 /// ```rust
+/// # use pli_preprocessor::modules::evaluator::parse_and_evaluate;
 /// let tokens = vec!["3".to_string(), "+".to_string(), "5".to_string()];
 /// let result = parse_and_evaluate(&tokens);
 /// assert_eq!(result, Ok(8));
 /// ```
-pub fn parse_and_evaluate(tokens: &[String]) -> Result<i32, String> {
+pub fn parse_and_evaluate(tokens: &[String]) -> Result<i64, String> {
+    parse_and_evaluate_value(tokens).and_then(|value| value_to_int(&value))
+}
+
+/// Evaluates an expression whose operands may be numbers or quoted strings.
+///
+/// `||` concatenates, converting numbers to their decimal text; arithmetic
+/// operators require numeric operands. Comparisons, `AND`/`&`, `OR`/`|`, and
+/// the prefix negation `NOT`/`^` yield a `Value::Boolean`.
+///
+/// # Arguments
+/// - `expression`: A `&str` containing the expression (e.g., `"'AB' || 'CD'"`).
+///
+/// # Returns
+/// - `Result<Value, PreprocessorError>`: Returns the typed result, or a
+///   `Tokenizer` or `Evaluator` error.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::evaluator::evaluate_value;
+/// # use pli_preprocessor::modules::context::Value;
+/// assert_eq!(evaluate_value("'AB' || 'CD'"), Ok(Value::Str("ABCD".to_string())));
+/// assert_eq!(evaluate_value("'N' || 1 + 2"), Ok(Value::Str("N3".to_string())));
+/// assert_eq!(evaluate_value("NOT 1 = 0"), Ok(Value::Boolean(true)));
+/// ```
+pub fn evaluate_value(expression: &str) -> Result<Value, PreprocessorError> {
+    let tokens = tokenize_expression(expression)?;
+    parse_and_evaluate_value(&tokens).map_err(PreprocessorError::Evaluator)
+}
+
+/// Parses and evaluates a list of tokens into a typed `Value`.
+///
+/// # Arguments
+/// - `tokens`: A `&[String]` slice containing the tokenized expression.
+///
+/// # Returns
+/// - `Result<Value, String>`: Returns the computed result or an error message.
+pub fn parse_and_evaluate_value(tokens: &[String]) -> Result<Value, String> {
     if tokens.is_empty() {
         return Err("No tokens to evaluate".to_string());
     }
 
     // Convert infix expression to postfix (Reverse Polish Notation)
-    let postfix_tokens = infix_to_postfix(tokens)?;
-    println!("Postfix Tokens: {:?}", postfix_tokens); // Debug: Postfix representation
+    let postfix_tokens = to_postfix(tokens, false)?;
+    debug!("Postfix Tokens: {:?}", postfix_tokens);
 
-    let mut stack: Vec<i32> = Vec::new();
-
-    // Evaluate the postfix expression
-    for token in postfix_tokens {
-        if let Ok(num) = token.parse::<i32>() {
-            // If the token is a number, push it onto the stack
-            stack.push(num);
-        } else {
-            // If the token is an operator, ensure there are enough operands
-            if stack.len() < 2 {
-                println!(
-                    "Malformed Expression: Stack: {:?}, Operator: {}",
-                    stack, token
-                ); // Debug: Stack state
-                return Err("Malformed expression".to_string());
-            }
-
-            let b = stack.pop().unwrap();
-            let a = stack.pop().unwrap();
+    evaluate_postfix(&postfix_tokens, |name| {
+        Err(format!("Unsupported token: {}", name))
+    })
+}
 
-            println!(
-                "Stack Before: {:?}, Operator: {}, Operands: ({}, {})",
-                stack, token, a, b
-            ); // Debug: Before operation
+/// Evaluates tokenizer output for an expression against `context`.
+///
+/// Unlike `resolve_variables`, a variable is only looked up when its operand
+/// is evaluated, and `AND`/`&` and `OR`/`|` short-circuit: when the left
+/// operand is false (for `AND`) or true (for `OR`), the right operand's
+/// errors, such as an undefined variable, are ignored. Otherwise any error is
+/// reported.
+///
+/// # Arguments
+/// - `tokens`: The expression's tokens.
+/// - `context`: The variables the expression may refer to.
+///
+/// # Returns
+/// - `Result<Value, String>`: The typed result, or an error message.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::evaluator::evaluate_tokens;
+/// # use pli_preprocessor::modules::context::{Context, Value};
+/// # use pli_preprocessor::modules::tokenizer::tokenize_pli;
+/// let tokens = tokenize_pli("0 = 1 AND UNDEFINED > 0");
+/// let value = evaluate_tokens(&tokens.iter().collect::<Vec<_>>(), &Context::new());
+/// assert_eq!(value, Ok(Value::Boolean(false)));
+/// ```
+pub fn evaluate_tokens(tokens: &[&Token], context: &Context) -> Result<Value, String> {
+    let terms: Vec<String> = merge_operator_tokens(tokens)
+        .into_iter()
+        .map(|token| token.value)
+        .collect();
+    if terms.is_empty() {
+        return Err("No tokens to evaluate".to_string());
+    }
+    let postfix_tokens = to_postfix(&terms, true)?;
+    debug!("Postfix Tokens: {:?}", postfix_tokens);
 
-            // Perform the operation and push the result onto the stack
-            let result = evaluate_operator(a, b, &token)?;
-            stack.push(result);
+    evaluate_postfix(&postfix_tokens, |name| context.lookup(name))
+}
 
-            println!("Stack After: {:?}", stack); // Debug: After operation
+/// Re-forms two-character operators (`^=`, `<=`, `<>`, `||`, ...) and decimal
+/// constants (`3.5`, `.5`) that the tokenizer emits as separate adjacent
+/// pieces.
+///
+/// # Arguments
+/// - `tokens`: Tokenizer output for an expression, with columns set.
+///
+/// # Returns
+/// - `Vec<Token>`: The tokens with adjacent operator pairs and decimal pieces
+///   merged into one.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::evaluator::merge_operator_tokens;
+/// # use pli_preprocessor::modules::tokenizer::tokenize_pli;
+/// let tokens = tokenize_pli("A ^= B");
+/// let merged = merge_operator_tokens(&tokens.iter().collect::<Vec<_>>());
+/// assert_eq!(merged[1].value, "^=");
+/// ```
+pub fn merge_operator_tokens(tokens: &[&Token]) -> Vec<Token> {
+    let mut merged: Vec<Token> = Vec::new();
+    let mut previous_end = 0;
+    for &token in tokens {
+        let adjacent = token.column > 0 && token.column == previous_end;
+        previous_end = token.column + token.original.chars().count();
+        if let Some(last) = merged.last_mut() {
+            let pair = matches!(
+                (last.value.as_str(), token.value.as_str()),
+                ("^" | "!" | "<" | ">", "=") | ("<", ">") | ("|", "|")
+            );
+            if adjacent && pair {
+                last.value.push_str(&token.value);
+                last.original.push_str(&token.original);
+                last.category = TokenCategory::Operator;
+                continue;
+            }
+            if adjacent && is_decimal_piece(&last.value, &token.value) {
+                last.value.push_str(&token.value);
+                last.original.push_str(&token.original);
+                last.category = TokenCategory::Identifier;
+                continue;
+            }
         }
+        merged.push(token.clone());
     }
+    merged
+}
 
-    if stack.len() != 1 {
-        println!("Final Stack State: {:?}", stack); // Debug: Final stack state
-        return Err("Malformed expression".to_string());
+/// Turns tokenizer output for an expression into evaluator terms, replacing
+/// each variable with its current value from `context`.
+///
+/// Adjacent operator characters are merged first (see `merge_operator_tokens`).
+/// `Str` values are re-quoted so they evaluate as string literals. Undefined
+/// variables follow the context's `UndefinedPolicy`.
+///
+/// # Arguments
+/// - `tokens`: The expression's tokens.
+/// - `context`: The variables the expression may refer to.
+///
+/// # Returns
+/// - `Result<Vec<String>, String>`: The terms, ready for
+///   `parse_and_evaluate_value`, or an error naming an undefined variable.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::evaluator::resolve_variables;
+/// # use pli_preprocessor::modules::context::{Context, Value};
+/// # use pli_preprocessor::modules::tokenizer::tokenize_pli;
+/// let mut context = Context::new();
+/// context.set("N", Value::Int(2));
+/// let tokens = tokenize_pli("N + 1");
+/// let terms = resolve_variables(&tokens.iter().collect::<Vec<_>>(), &context).unwrap();
+/// assert_eq!(terms, vec!["2", "+", "1"]);
+/// ```
+pub fn resolve_variables(tokens: &[&Token], context: &Context) -> Result<Vec<String>, String> {
+    let mut terms = Vec::new();
+    for token in merge_operator_tokens(tokens) {
+        let is_variable = token.category == TokenCategory::Identifier
+            && !is_integer_literal(&token.value)
+            && parse_decimal(&token.value).is_none();
+        if !is_variable {
+            terms.push(token.value);
+            continue;
+        }
+        match context.lookup(&token.value)? {
+            value @ (Value::Int(_) | Value::Decimal(_) | Value::Boolean(_)) => {
+                terms.push(value.to_string())
+            }
+            Value::Str(text) => terms.push(format!("'{}'", text.replace('\'', "''"))),
+        }
     }
+    Ok(terms)
+}
+
+/// Converts an expression to postfix (RPN) without evaluating it.
+///
+/// Operands may be numbers, quoted strings, or variable names.
+///
+/// # Arguments
+/// - `tokens`: A slice of infix tokens, as produced by `tokenize_expression`.
+///
+/// # Returns
+/// - `Result<Vec<String>, String>`: The postfix tokens, or an error message.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::evaluator::{parse_expression, tokenize_expression};
+/// let rpn = parse_expression(&tokenize_expression("A + B * C").unwrap());
+/// assert_eq!(rpn.unwrap(), vec!["A", "B", "C", "*", "+"]);
+/// ```
+pub fn parse_expression(tokens: &[String]) -> Result<Vec<String>, String> {
+    parse_expression_with_positions(tokens)
+        .map(|terms| terms.into_iter().map(|(term, _)| term).collect())
+}
 
-    Ok(stack[0])
+/// Converts an expression to postfix (RPN), pairing each term with the index
+/// of the input token it came from.
+///
+/// The index lets a later evaluation error point at the offending operator in
+/// the original text.
+///
+/// # Arguments
+/// - `tokens`: A slice of infix tokens, as produced by `tokenize_expression`.
+///
+/// # Returns
+/// - `Result<Vec<(String, usize)>, String>`: The postfix terms with their input
+///   indices, or an error message.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::evaluator::{parse_expression_with_positions, tokenize_expression};
+/// let rpn = parse_expression_with_positions(&tokenize_expression("A + B * C").unwrap());
+/// assert_eq!(rpn.unwrap()[3], ("*".to_string(), 3));
+/// ```
+pub fn parse_expression_with_positions(tokens: &[String]) -> Result<Vec<(String, usize)>, String> {
+    to_postfix(tokens, true).map(|terms| {
+        terms
+            .into_iter()
+            .map(|term| (term.text, term.index))
+            .collect()
+    })
 }
 
 /// Converts an infix expression to postfix (RPN).
@@ -149,40 +535,86 @@ pub fn parse_and_evaluate(tokens: &[String]) -> Result<i32, String> {
 /// - `Result<Vec<String>, String>`: Returns a vector of postfix tokens or an error.
 ///
 /// # Example
-/// ```rust
+/// ```ignore
 /// let tokens = vec!["3".to_string(), "+".to_string(), "5".to_string()];
 /// let result = infix_to_postfix(&tokens);
 /// assert_eq!(result, Ok(vec!["3".to_string(), "5".to_string(), "+".to_string()]));
 /// ```
 fn infix_to_postfix(tokens: &[String]) -> Result<Vec<String>, String> {
-    let mut output: Vec<String> = Vec::new();
-    let mut operators: Vec<String> = Vec::new();
+    to_postfix(tokens, false).map(|terms| terms.into_iter().map(|term| term.text).collect())
+}
 
-    let precedence = |op: &str| match op {
-        "+" | "-" => 1,
-        "*" | "/" => 2,
-        _ => 0,
-    };
+/// Shunting-yard conversion shared by `infix_to_postfix` and
+/// `parse_expression_with_positions`; `names` allows variable names as operands.
+///
+/// With `names`, the keyword operators `AND`, `OR`, `MOD`, and `NOT` are
+/// operators only where one is expected. Where an operand is expected they
+/// name a variable, so `AND = 1` compares the variable `AND` with 1, while
+/// `A AND B` is a conjunction. `NOT` stays a prefix negation unless it is
+/// followed by the end of the expression, `)`, or a symbolic operator.
+/// A `+` or `-` where an operand is expected is a prefix sign, as in `X > -1`.
+fn to_postfix(tokens: &[String], names: bool) -> Result<Vec<PostfixTerm>, String> {
+    let mut output: Vec<PostfixTerm> = Vec::new();
+    let mut operators: Vec<PostfixTerm> = Vec::new();
 
     let mut expect_operand = true;
 
-    for token in tokens {
-        if let Ok(_) = token.parse::<i32>() {
-            output.push(token.clone());
+    for (index, token) in tokens.iter().enumerate() {
+        let upper = token.to_uppercase();
+        let is_operator = precedence(&upper) > 0;
+        let keyword_operand = names
+            && expect_operand
+            && is_variable_name(token)
+            && (!is_negation(&upper) || follows_operand(tokens.get(index + 1)));
+        if parse_operand(token).is_some()
+            || (names && !is_operator && is_variable_name(token))
+            || keyword_operand
+        {
+            output.push(PostfixTerm::operand(token, index));
             expect_operand = false;
-        } else if ["+", "-", "*", "/"].contains(&token.as_str()) {
+        } else if is_negation(&upper) {
+            // A prefix operator pops nothing; it applies to what follows.
+            if !expect_operand {
+                return Err(format!("Unexpected operator '{}'", upper));
+            }
+            operators.push(PostfixTerm::operator(&upper, index));
+        } else if expect_operand && (token == "-" || token == "+") {
+            operators.push(PostfixTerm::sign(token, index));
+        } else if token == "(" {
+            if !expect_operand {
+                return Err("Unexpected '('".to_string());
+            }
+            operators.push(PostfixTerm::operator(token, index));
+        } else if token == ")" {
+            if expect_operand {
+                return Err("Missing operand before ')'".to_string());
+            }
+            loop {
+                match operators.pop() {
+                    Some(op) if op.text == "(" => break,
+                    Some(op) => output.push(op),
+                    None => return Err("Unbalanced parentheses".to_string()),
+                }
+            }
+        } else if is_operator {
+            let token = &token.to_uppercase();
             if expect_operand {
                 return Err(format!("Operator '{}' without operand", token));
             }
             while let Some(op) = operators.last() {
-                if precedence(op) >= precedence(token) {
+                if op.precedence() >= precedence(token) {
                     output.push(operators.pop().unwrap());
                 } else {
                     break;
                 }
             }
-            operators.push(token.clone());
+            operators.push(PostfixTerm::operator(token, index));
             expect_operand = true;
+        } else if let Some(Err(message)) = parse_based_constant(token) {
+            return Err(message);
+        } else if is_integer_literal(token) {
+            // Only an integer too large for an `i64` fails to parse.
+            return Err("Arithmetic overflow".to_string());
         } else {
             return Err(format!("Unsupported token: {}", token));
         }
@@ -193,39 +625,390 @@ fn infix_to_postfix(tokens: &[String]) -> Result<Vec<String>, String> {
     }
 
     while let Some(op) = operators.pop() {
+        if op.text == "(" {
+            return Err("Unbalanced parentheses".to_string());
+        }
         output.push(op);
     }
 
     Ok(output)
 }
 
+/// Evaluates a postfix expression, calling `lookup` for variable operands.
+///
+/// Each stack entry holds a value or the error that computing it raised, so
+/// that `AND` and `OR` can discard the error of a right operand they do not
+/// need. A malformed expression is reported at once.
+fn evaluate_postfix<F>(postfix_tokens: &[PostfixTerm], lookup: F) -> Result<Value, String>
+where
+    F: Fn(&str) -> Result<Value, String>,
+{
+    let mut stack: Vec<Result<Value, String>> = Vec::new();
+
+    for term in postfix_tokens {
+        let token = &term.text;
+        if let Some(value) = parse_operand(token) {
+            // If the token is an operand, push it onto the stack
+            stack.push(Ok(value));
+        } else if term.operand {
+            stack.push(lookup(token));
+        } else if is_negation(token) {
+            let operand = stack
+                .pop()
+                .ok_or_else(|| "Malformed expression".to_string())?;
+            stack.push(operand.and_then(|value| Ok(Value::Boolean(!truth_value(&value)?))));
+        } else if term.sign {
+            // `-X` is `0 - X`, which also rejects a non-numeric operand.
+            let operand = stack
+                .pop()
+                .ok_or_else(|| "Malformed expression".to_string())?;
+            stack.push(apply_binary_operator(Ok(Value::Int(0)), operand, token));
+        } else {
+            // If the token is an operator, ensure there are enough operands
+            let (b, a) = match (stack.pop(), stack.pop()) {
+                (Some(b), Some(a)) => (b, a),
+                _ => return Err("Malformed expression".to_string()),
+            };
+            let result = apply_binary_operator(a, b, token);
+            debug!("Operator: {}, Result: {:?}", token, result);
+            stack.push(result);
+        }
+    }
+
+    match (stack.pop(), stack.is_empty()) {
+        (Some(value), true) => value,
+        _ => Err("Malformed expression".to_string()),
+    }
+}
+
+/// Applies a binary operator to two evaluated operands, short-circuiting
+/// `AND` on a false left operand and `OR` on a true one.
+fn apply_binary_operator(
+    a: Result<Value, String>,
+    b: Result<Value, String>,
+    operator: &str,
+) -> Result<Value, String> {
+    let a = a?;
+    match operator {
+        "AND" | "&" if !truth_value(&a)? => return Ok(Value::Boolean(false)),
+        "OR" | "|" if truth_value(&a)? => return Ok(Value::Boolean(true)),
+        _ => {}
+    }
+    let b = b?;
+    let decimal = matches!(a, Value::Decimal(_)) || matches!(b, Value::Decimal(_));
+
+    Ok(match operator {
+        "||" => Value::Str(format!("{}{}", a, b)),
+        "AND" | "&" | "OR" | "|" => Value::Boolean(truth_value(&b)?),
+        "=" | "^=" | "!=" | "<>" | "<" | ">" | "<=" | ">=" => {
+            Value::Boolean(compare_values(&a, &b, operator)?)
+        }
+        _ if decimal => Value::Decimal(evaluate_decimal_operator(
+            value_to_decimal(&a)?,
+            value_to_decimal(&b)?,
+            operator,
+        )?),
+        _ => Value::Int(evaluate_operator(
+            value_to_int(&a)?,
+            value_to_int(&b)?,
+            operator,
+        )?),
+    })
+}
+
+/// Returns the binding strength of an operator, or 0 for anything else.
+///
+/// As in PL/I, `||` binds looser than arithmetic and comparisons looser than
+/// `||`. Negation (`NOT`/`^`) applies to a whole comparison, and `AND`/`&`
+/// binds tighter than `OR`/`|`.
+fn precedence(op: &str) -> u8 {
+    match op {
+        "OR" | "|" => 1,
+        "AND" | "&" => 2,
+        "NOT" | "^" => 3,
+        "=" | "^=" | "!=" | "<>" | "<" | ">" | "<=" | ">=" => 4,
+        "||" => 5,
+        "+" | "-" => 6,
+        "*" | "/" | "MOD" => 7,
+        _ => 0,
+    }
+}
+
+/// Returns `true` for the unary logical negation operators `NOT` and `^`.
+fn is_negation(op: &str) -> bool {
+    op == "NOT" || op == "^"
+}
+
 /// Evaluates a binary operation.
 ///
 /// # Arguments
 /// - `a`: The left operand.
 /// - `b`: The right operand.
-/// - `operator`: A `&str` representing the operator (e.g., `+`, `-`, `*`, `/`).
+/// - `operator`: A `&str` representing the operator (e.g., `+`, `-`, `*`, `/`, `MOD`).
 ///
 /// # Returns
-/// - `Result<i32, String>`: Returns the result of the operation or an error message.
+/// - `Result<i64, String>`: Returns the result of the operation, or an error message
+///   for division by zero or a result that does not fit in an `i64`.
 ///
 /// # Example
 /// ```rust
+/// # use pli_preprocessor::modules::evaluator::evaluate_operator;
 /// let result = evaluate_operator(3, 5, "+");
 /// assert_eq!(result, Ok(8));
 /// ```
-pub fn evaluate_operator(a: i32, b: i32, operator: &str) -> Result<i32, String> {
-    match operator {
-        "+" => Ok(a + b),
-        "-" => Ok(a - b),
-        "*" => Ok(a * b),
+pub fn evaluate_operator(a: i64, b: i64, operator: &str) -> Result<i64, String> {
+    let result = match operator {
+        "+" => a.checked_add(b),
+        "-" => a.checked_sub(b),
+        "*" => a.checked_mul(b),
         "/" => {
             if b == 0 {
-                Err("Division by zero".to_string())
+                return Err("Division by zero".to_string());
+            }
+            a.checked_div(b)
+        }
+        "MOD" => {
+            if b == 0 {
+                return Err("Division by zero".to_string());
+            }
+            // PL/I's MOD takes the sign of the divisor, unlike Rust's `%`.
+            a.checked_rem(b).map(|r| {
+                if r != 0 && (r < 0) != (b < 0) {
+                    r + b
+                } else {
+                    r
+                }
+            })
+        }
+        _ => return Err(format!("Unsupported operator: {}", operator)),
+    };
+    result.ok_or_else(|| "Arithmetic overflow".to_string())
+}
+
+/// Evaluates a binary operation on decimals.
+///
+/// `MOD` takes the sign of the divisor, as it does for integers.
+///
+/// # Arguments
+/// - `a`: The left operand.
+/// - `b`: The right operand.
+/// - `operator`: A `&str` representing the operator (e.g., `+`, `-`, `*`, `/`, `MOD`).
+///
+/// # Returns
+/// - `Result<f64, String>`: Returns the result of the operation, or an error
+///   message for division by zero or a result too large for an `f64`.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::evaluator::evaluate_decimal_operator;
+/// assert_eq!(evaluate_decimal_operator(1.5, 2.0, "*"), Ok(3.0));
+/// assert_eq!(evaluate_decimal_operator(-1.5, 1.0, "MOD"), Ok(0.5));
+/// ```
+pub fn evaluate_decimal_operator(a: f64, b: f64, operator: &str) -> Result<f64, String> {
+    let result = match operator {
+        "+" => a + b,
+        "-" => a - b,
+        "*" => a * b,
+        "/" | "MOD" if b == 0.0 => return Err("Division by zero".to_string()),
+        "/" => a / b,
+        "MOD" => {
+            let r = a % b;
+            if r != 0.0 && (r < 0.0) != (b < 0.0) {
+                r + b
             } else {
-                Ok(a / b)
+                r
             }
         }
-        _ => Err(format!("Unsupported operator: {}", operator)),
+        _ => return Err(format!("Unsupported operator: {}", operator)),
+    };
+    if result.is_finite() {
+        Ok(result)
+    } else {
+        Err("Arithmetic overflow".to_string())
+    }
+}
+
+/// Parses an operand token: an integer, a decimal, a hex or binary constant,
+/// or a quoted string (with `''` escapes).
+fn parse_operand(token: &str) -> Option<Value> {
+    if let Ok(number) = token.parse::<i64>() {
+        return Some(Value::Int(number));
+    }
+    if let Some(number) = parse_decimal(token) {
+        return Some(Value::Decimal(number));
+    }
+    if let Some(constant) = parse_based_constant(token) {
+        return constant.ok().map(Value::Int);
+    }
+    if token.len() >= 2 && token.starts_with('\'') && token.ends_with('\'') {
+        return Some(Value::Str(token[1..token.len() - 1].replace("''", "'")));
+    }
+    None
+}
+
+/// Parses a decimal constant such as `3.5`, `-0.25`, `.5`, or `2.`: digits
+/// with exactly one decimal point. Exponents, `inf`, and `NaN` are rejected.
+fn parse_decimal(token: &str) -> Option<f64> {
+    let digits = token.strip_prefix(['+', '-']).unwrap_or(token);
+    let (whole, fraction) = digits.split_once('.')?;
+    let valid = !(whole.is_empty() && fraction.is_empty())
+        && whole
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit());
+    if valid {
+        token.parse::<f64>().ok()
+    } else {
+        None
+    }
+}
+
+/// Parses a hexadecimal (`'FF'X`) or binary (`'1010'B`) integer constant.
+///
+/// Returns `None` if the token is not written in either form, and an error if
+/// it is but its digits are invalid or the value does not fit in an `i64`.
+fn parse_based_constant(token: &str) -> Option<Result<i64, String>> {
+    let (radix, kind) = match token.chars().last()? {
+        'X' | 'x' => (16, "hexadecimal"),
+        'B' | 'b' => (2, "binary"),
+        _ => return None,
+    };
+    let digits = token[..token.len() - 1]
+        .strip_prefix('\'')?
+        .strip_suffix('\'')?;
+    Some(
+        i64::from_str_radix(digits, radix)
+            .ok()
+            .filter(|_| !digits.starts_with(['+', '-']))
+            .ok_or_else(|| format!("Invalid {} constant: {}", kind, token)),
+    )
+}
+
+/// Returns `true` if `token` is written as an integer: digits with an optional
+/// sign, however many there are.
+fn is_integer_literal(token: &str) -> bool {
+    let digits = token.strip_prefix(['+', '-']).unwrap_or(token);
+    !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Returns `true` if `next` continues the decimal constant `last`: a point
+/// after digits, or digits after a point.
+fn is_decimal_piece(last: &str, next: &str) -> bool {
+    let digits = |text: &str| !text.is_empty() && text.chars().all(|c| c.is_ascii_digit());
+    match last.strip_suffix('.') {
+        Some(whole) => (whole.is_empty() || digits(whole)) && digits(next),
+        None => digits(last) && next == ".",
+    }
+}
+
+/// Returns `true` if `token` can only follow a complete operand: the end of
+/// the expression, `)`, or a symbolic binary operator such as `=` or `*`.
+fn follows_operand(token: Option<&String>) -> bool {
+    token.is_none_or(|token| {
+        token == ")" || (precedence(token) > 0 && !is_negation(token) && !is_variable_name(token))
+    })
+}
+
+/// Returns `true` if the token is a PL/I identifier such as `COUNT`, `X_1`, or
+/// `MY$VAR`, made of the tokenizer's default identifier characters.
+fn is_variable_name(token: &str) -> bool {
+    let classes = CharClassConfig::default();
+    let mut chars = token.chars();
+    chars
+        .next()
+        .is_some_and(|c| !c.is_numeric() && classes.is_identifier_start(c))
+        && chars.all(|c| classes.is_identifier_continue(c))
+}
+
+/// Compares two values numerically when both hold numbers, or as text when
+/// both are strings.
+///
+/// Integers compare exactly; when either side is a decimal, both compare as
+/// decimals within `DECIMAL_TOLERANCE`. A string that does not hold a number
+/// cannot be compared with a number, so `'ABC' > 1` is an error.
+fn compare_values(a: &Value, b: &Value, operator: &str) -> Result<bool, String> {
+    let ordering = match (value_to_int(a), value_to_int(b)) {
+        (Ok(left), Ok(right)) => left.cmp(&right),
+        _ => match (value_to_decimal(a), value_to_decimal(b), a, b) {
+            (Ok(left), Ok(right), _, _) => compare_decimals(left, right),
+            (_, _, Value::Str(left), Value::Str(right)) => left.cmp(right),
+            _ => {
+                return Err(format!(
+                    "Type mismatch: cannot compare {} with {}",
+                    quote_value(a),
+                    quote_value(b)
+                ))
+            }
+        },
+    };
+    Ok(match operator {
+        "=" => ordering.is_eq(),
+        "^=" | "!=" | "<>" => ordering.is_ne(),
+        "<" => ordering.is_lt(),
+        ">" => ordering.is_gt(),
+        "<=" => ordering.is_le(),
+        _ => ordering.is_ge(),
+    })
+}
+
+/// Renders a value for an error message, quoting strings so that `'1'` and
+/// `1` read differently.
+fn quote_value(value: &Value) -> String {
+    match value {
+        Value::Str(text) => format!("'{}'", text),
+        _ => value.to_string(),
+    }
+}
+
+/// Orders two decimals, treating values within `DECIMAL_TOLERANCE` as equal.
+fn compare_decimals(left: f64, right: f64) -> Ordering {
+    let scale = left.abs().max(right.abs()).max(1.0);
+    if (left - right).abs() <= DECIMAL_TOLERANCE * scale {
+        Ordering::Equal
+    } else {
+        left.total_cmp(&right)
+    }
+}
+
+/// Returns the truth of a value: a boolean as is, a number when nonzero.
+fn truth_value(value: &Value) -> Result<bool, String> {
+    match value {
+        Value::Boolean(flag) => Ok(*flag),
+        Value::Decimal(number) => Ok(*number != 0.0),
+        _ => value_to_int(value).map(|number| number != 0),
+    }
+}
+
+/// Converts a value to an integer, accepting strings that hold a number and
+/// decimals without a fractional part.
+fn value_to_int(value: &Value) -> Result<i64, String> {
+    match value {
+        Value::Int(number) => Ok(*number),
+        Value::Decimal(number) if number.fract() == 0.0 && number.abs() < i64::MAX as f64 => {
+            Ok(*number as i64)
+        }
+        Value::Decimal(number) => Err(format!("Expected an integer, found {}", number)),
+        Value::Boolean(flag) => Ok(i64::from(*flag)),
+        Value::Str(text) => text
+            .trim()
+            .parse::<i64>()
+            .map_err(|_| format!("Expected a number, found '{}'", text)),
+    }
+}
+
+/// Converts a value to a decimal, accepting strings that hold a number.
+fn value_to_decimal(value: &Value) -> Result<f64, String> {
+    match value {
+        Value::Decimal(number) => Ok(*number),
+        Value::Int(number) => Ok(*number as f64),
+        Value::Boolean(flag) => Ok(f64::from(u8::from(*flag))),
+        Value::Str(text) => {
+            let text = text.trim();
+            text.parse::<i64>()
+                .ok()
+                .map(|number| number as f64)
+                .or_else(|| parse_decimal(text))
+                .ok_or_else(|| format!("Expected a number, found '{}'", text))
+        }
     }
 }
@@ -0,0 +1,205 @@
+#![allow(dead_code)] // Suppress warnings for unused functions in this module.
+
+////////////////////////////////////////////////////////////////////////////////
+// MODULE NAME: Source Formatter
+// ----------------------------------------------------------------------------
+// DESCRIPTION:
+// This module re-emits PL/I source in a normalized layout without
+// preprocessing it: conditionals are not evaluated, macros are not expanded,
+// and includes are not inlined.
+//
+// FUNCTIONALITY:
+// - Collapses each run of whitespace between tokens to a single space, keeping
+//   adjacent tokens such as `B(1);` together.
+// - Spells directives in uppercase, in their canonical form (`% if` becomes
+//   `%IF`).
+// - Indents each line by the number of open `%DO` and `%IF` ... `%ENDIF`
+//   blocks, replacing its original indentation.
+// - Keeps string literals, `/* ... */` comments (including ones spanning
+//   several lines), and `%COMMENT` text exactly as written.
+//
+// USAGE:
+// - Call `format_source` on a whole source; block depth carries across lines.
+// - Call `format_line` to normalize a single line at a given depth.
+//
+// AUTHOR: FirstLink Consulting Services (FLCS)
+// LICENSE: MIT License
+// DATE: 10/16/2026
+// VERSION: 1.0.0
+////////////////////////////////////////////////////////////////////////////////
+
+////////////////////////////////////////////////////////////////////////////////
+// IMPORTS
+////////////////////////////////////////////////////////////////////////////////
+
+use crate::modules::conditional::split_inline_if;
+use crate::modules::directive::canonicalize;
+use crate::modules::parser::split_statements;
+use crate::modules::tokenizer::{
+    has_tokenizer_error, strip_comments, tokenize_pli, tokenize_pli_preserve_ws, DirectiveCategory,
+    Token, TokenCategory,
+};
+
+////////////////////////////////////////////////////////////////////////////////
+// CONSTANTS
+////////////////////////////////////////////////////////////////////////////////
+
+/// The indentation added for each open `%DO` or `%IF` block.
+pub const FORMAT_INDENT: &str = "  ";
+
+////////////////////////////////////////////////////////////////////////////////
+// PUBLIC FUNCTIONS
+////////////////////////////////////////////////////////////////////////////////
+
+/// Formats a whole source.
+///
+/// A `%DO` (alone, or after `%THEN` or `%ELSE`) opens a block closed by `%END`,
+/// and a `%IF condition %THEN;` opens one closed by `%ENDIF`. The closing line
+/// sits at the depth of its opening line, as does a `%ELSE` inside an `%ENDIF`
+/// block. Blank lines stay blank, lines continuing a comment are kept as
+/// written, and a line the tokenizer rejects (such as an unterminated string)
+/// only loses its trailing whitespace.
+///
+/// # Arguments
+/// - `source`: The source text.
+///
+/// # Returns
+/// - `String`: The formatted source, each line ending in a newline.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::formatter::format_source;
+/// let text = format_source("%if   DEBUG  %then;\nA   =  B(1);\n   %endif;\n");
+/// assert_eq!(text, "%IF DEBUG %THEN;\n  A = B(1);\n%ENDIF;\n");
+/// ```
+pub fn format_source(source: &str) -> String {
+    let mut text = String::new();
+    // The closing directive of each open block, innermost last.
+    let mut blocks: Vec<&str> = Vec::new();
+    let mut in_comment = false;
+
+    for line in source.lines() {
+        if in_comment {
+            in_comment = !line.contains("*/");
+            text.push_str(line.trim_end());
+            text.push('\n');
+            continue;
+        }
+
+        let tokens = tokenize_pli(line);
+        if tokens.is_empty() || has_tokenizer_error(&tokens) {
+            text.push_str(line.trim_end());
+            text.push('\n');
+            continue;
+        }
+        in_comment = tokens
+            .last()
+            .is_some_and(|t| t.category == TokenCategory::Comment && !closes_comment(&t.value));
+
+        let statements = split_statements(&strip_comments(tokens));
+        let outdent = match statements.first().and_then(|s| s.first()) {
+            Some(first) => match canonicalize(&first.value).as_deref() {
+                Some("%END") | Some("%ENDIF") => true,
+                Some("%ELSE") => blocks.last() == Some(&"%ENDIF"),
+                _ => false,
+            },
+            None => false,
+        };
+        let depth = if outdent {
+            blocks.len().saturating_sub(1)
+        } else {
+            blocks.len()
+        };
+        text.push_str(&format_line(line, depth));
+        text.push('\n');
+
+        for statement in &statements {
+            update_blocks(statement, &mut blocks);
+        }
+    }
+    text
+}
+
+/// Formats a single line at the given block depth.
+///
+/// # Arguments
+/// - `line`: The source line.
+/// - `depth`: How many `FORMAT_INDENT`s to indent the line by.
+///
+/// # Returns
+/// - `String`: The line with normalized spacing and directives, or an empty
+///   string for a blank line.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::formatter::format_line;
+/// assert_eq!(format_line("  %set  X =  'A  B' ;  ", 1), "  %SET X = 'A  B' ;");
+/// ```
+pub fn format_line(line: &str, depth: usize) -> String {
+    let tokens = tokenize_pli_preserve_ws(line);
+    let first = tokens
+        .iter()
+        .position(|t| t.category != TokenCategory::Whitespace);
+    let last = tokens
+        .iter()
+        .rposition(|t| t.category != TokenCategory::Whitespace);
+    let (first, last) = match (first, last) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return String::new(),
+    };
+
+    let mut text = FORMAT_INDENT.repeat(depth);
+    let mut in_comment_directive = false;
+    for token in &tokens[first..=last] {
+        match token.category {
+            TokenCategory::Whitespace if in_comment_directive => text.push_str(&token.original),
+            TokenCategory::Whitespace => text.push(' '),
+            TokenCategory::Directive => {
+                in_comment_directive = token.directive_category == Some(DirectiveCategory::Comment);
+                text.push_str(&directive_text(token));
+            }
+            _ => {
+                if token.category == TokenCategory::Separator && token.value == ";" {
+                    in_comment_directive = false;
+                }
+                text.push_str(&token.original);
+            }
+        }
+    }
+    text
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// PRIVATE FUNCTIONS
+////////////////////////////////////////////////////////////////////////////////
+
+/// Returns the canonical uppercase spelling of a directive token.
+fn directive_text(token: &Token) -> String {
+    canonicalize(&token.original).unwrap_or_else(|| token.original.to_uppercase())
+}
+
+/// Returns `true` if a comment token ends with its closing `*/`.
+fn closes_comment(comment: &str) -> bool {
+    comment.len() >= 4 && comment.ends_with("*/")
+}
+
+/// Opens or closes blocks for one statement, in source order.
+fn update_blocks(statement: &[Token], blocks: &mut Vec<&str>) {
+    let first = match statement.first().and_then(|t| canonicalize(&t.value)) {
+        Some(first) => first,
+        None => return,
+    };
+    match first.as_str() {
+        "%END" | "%ENDIF" => {
+            blocks.pop();
+        }
+        "%IF" if split_inline_if(statement).is_none() => blocks.push("%ENDIF"),
+        _ => {}
+    }
+    if statement
+        .iter()
+        .any(|t| canonicalize(&t.value).as_deref() == Some("%DO"))
+    {
+        blocks.push("%END");
+    }
+}
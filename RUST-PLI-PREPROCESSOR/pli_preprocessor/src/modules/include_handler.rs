@@ -9,13 +9,36 @@
 // of specified files.
 //
 // FUNCTIONALITY:
-// - Processes `%INCLUDE` directives in PL/I source code.
+// - Processes `%INCLUDE` directives in PL/I source code, including ones that
+//   name several members (`%INCLUDE A, B, C;`), which are read in order.
+// - Copies `%XINCLUDE`d files verbatim, leaving the includes they contain
+//   unexpanded, and can bracket the copies with `VERBATIM_BEGIN` and
+//   `VERBATIM_END` lines so the pipeline leaves them unprocessed too.
 // - Validates the existence and readability of included files.
-// - Supports relative and absolute paths.
+// - Supports relative and absolute paths, and looks relative names up in a
+//   list of search directories when the including file's directory lacks them.
+// - Reads included content through the `IncludeResolver` trait, so includes
+//   can come from the filesystem (`FsIncludeResolver`) or from memory
+//   (`MapIncludeResolver`).
+// - Walks nested includes to build the include dependency graph, rendered as
+//   an indented tree, JSON, or Graphviz DOT.
+// - Flattens a source by inlining its includes recursively, optionally
+//   bracketing each included file with `BEGIN`/`END INCLUDE` comment lines
+//   or keeping each include directive as a comment, and counts the includes
+//   inlined.
+// - Decides from modification times whether an output is out of date with
+//   respect to its input and includes.
+// - Reads gzip-compressed sources (a `.gz` extension) transparently.
 //
 // USAGE:
-// - Use `process_include` to handle `%INCLUDE` directives.
-// - Extend `resolve_include_path` to customize file path resolution.
+// - Use `process_include` to handle `%INCLUDE` directives, or
+//   `process_include_with` to read them through a resolver.
+// - Use `expand_includes` to inline the includes of in-memory source.
+// - Extend `resolve_include_path` to customize file path resolution, or use
+//   `find_include_path` and the `*_with_paths` functions to search further
+//   directories in order.
+// - Use `collect_include_edges` and the `format_include_*` functions to list
+//   what a source pulls in without processing it.
 //
 // AUTHOR: FirstLink Consulting Services (FLCS)
 // LICENSE: MIT License
@@ -27,8 +50,173 @@
 // IMPORTS
 ////////////////////////////////////////////////////////////////////////////////
 
+use crate::modules::error::PreprocessorError;
+use crate::modules::pipeline::trace_event;
+use flate2::read::GzDecoder;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+
+////////////////////////////////////////////////////////////////////////////////
+// CONSTANTS
+////////////////////////////////////////////////////////////////////////////////
+
+/// The line opening the content of an `%XINCLUDE` when
+/// `InlineOptions::mark_verbatim` is set. The pipeline writes the lines up to
+/// the matching `VERBATIM_END` as they are, without preprocessing them.
+pub const VERBATIM_BEGIN: &str = "/* %XINCLUDE VERBATIM BEGIN */";
+
+/// The line closing the content opened by `VERBATIM_BEGIN`.
+pub const VERBATIM_END: &str = "/* %XINCLUDE VERBATIM END */";
+
+////////////////////////////////////////////////////////////////////////////////
+// PUBLIC STRUCTURES
+////////////////////////////////////////////////////////////////////////////////
+
+/// One `%INCLUDE` relationship: `from` includes `to` on the given line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncludeEdge {
+    pub from: PathBuf,
+    pub to: PathBuf,
+    pub line: usize,
+}
+
+/// How inlined includes are laid out in a flattened source.
+///
+/// # Fields
+/// - `markers`: Brackets each included file with `/* BEGIN INCLUDE name */` and
+///   `/* END INCLUDE name */` lines.
+/// - `keep_directives`: Keeps each `%INCLUDE` or `%XINCLUDE` line as a comment
+///   immediately before the content it inserts (and before its `BEGIN` marker).
+/// - `mark_verbatim`: Brackets the content of each `%XINCLUDE`, inside any
+///   markers, with `VERBATIM_BEGIN` and `VERBATIM_END` lines for the pipeline.
+/// - `trace`: Logs an `include` event (see `pipeline::trace_event`) for each
+///   include inlined, numbered by the line of the flattened source where its
+///   content (or its `BEGIN` marker) starts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InlineOptions {
+    pub markers: bool,
+    pub keep_directives: bool,
+    pub mark_verbatim: bool,
+    pub trace: bool,
+}
+
+/// Supplies the content of included files by name.
+///
+/// The name is the path exactly as written in the `%INCLUDE` directive. The
+/// functions that read through a resolver report its error messages as
+/// `PreprocessorError::Include`.
+pub trait IncludeResolver {
+    /// Returns the content of the include `name`, or an error message.
+    fn resolve(&self, name: &str) -> Result<String, String>;
+
+    /// Returns the include `name` as written in the include `parent`, or in
+    /// the source being expanded when `parent` is `None`.
+    ///
+    /// The result pairs a key identifying the include with its content. Keys
+    /// detect include cycles, and each include's key is passed back as the
+    /// `parent` of the includes it contains. By default names resolve the same
+    /// at any depth through `resolve`, and are their own keys.
+    fn resolve_from(&self, name: &str, parent: Option<&str>) -> Result<(String, String), String> {
+        let _ = parent;
+        Ok((name.to_string(), self.resolve(name)?))
+    }
+}
+
+/// Reads includes from disk, resolving relative names against the directory
+/// of the file containing them (`base_dir` for the source being expanded),
+/// then against each of `search_paths` in order (see `find_include_path`).
+/// Each include is keyed by its normalized path.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::include_handler::{FsIncludeResolver, IncludeResolver};
+/// # use std::path::PathBuf;
+/// # let dir = std::env::temp_dir().join("pli_doc_fs_resolver");
+/// # std::fs::create_dir_all(&dir).unwrap();
+/// # std::fs::create_dir_all(dir.join("copybooks")).unwrap();
+/// # std::fs::write(dir.join("copybooks/defs.pli"), "DCL X FIXED;").unwrap();
+/// # std::env::set_current_dir(&dir).unwrap();
+/// # std::fs::create_dir_all("src").unwrap();
+/// let resolver = FsIncludeResolver::new("src")
+///     .with_search_paths(vec![PathBuf::from("copybooks"), PathBuf::from("shared")]);
+/// let content = resolver.resolve("defs.pli");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FsIncludeResolver {
+    pub base_dir: PathBuf,
+    pub search_paths: Vec<PathBuf>,
+}
+
+impl FsIncludeResolver {
+    /// Creates a resolver for includes relative to `base_dir`.
+    pub fn new(base_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            base_dir: base_dir.into(),
+            search_paths: Vec::new(),
+        }
+    }
+
+    /// Sets the directories searched, in order, for includes missing from
+    /// `base_dir`.
+    pub fn with_search_paths(mut self, search_paths: Vec<PathBuf>) -> Self {
+        self.search_paths = search_paths;
+        self
+    }
+}
+
+impl IncludeResolver for FsIncludeResolver {
+    fn resolve(&self, name: &str) -> Result<String, String> {
+        self.resolve_from(name, None).map(|(_, content)| content)
+    }
+
+    fn resolve_from(&self, name: &str, parent: Option<&str>) -> Result<(String, String), String> {
+        let current_dir = parent
+            .and_then(|parent| Path::new(parent).parent())
+            .unwrap_or(&self.base_dir);
+        let path = find_include_path(name, current_dir, &self.search_paths)
+            .map(|path| normalize_path(&path))
+            .map_err(|e| e.to_string())?;
+        let content = read_file(&path).map_err(|e| e.to_string())?;
+        Ok((path.display().to_string(), content))
+    }
+}
+
+/// Serves includes from an in-memory map, for tests and virtual filesystems.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::include_handler::{IncludeResolver, MapIncludeResolver};
+/// let mut resolver = MapIncludeResolver::new();
+/// resolver.insert("defs.pli", "DCL X FIXED;");
+/// assert_eq!(resolver.resolve("defs.pli"), Ok("DCL X FIXED;".to_string()));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MapIncludeResolver {
+    files: HashMap<String, String>,
+}
+
+impl MapIncludeResolver {
+    /// Creates an empty resolver.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the content of the include `name`.
+    pub fn insert(&mut self, name: &str, content: &str) {
+        self.files.insert(name.to_string(), content.to_string());
+    }
+}
+
+impl IncludeResolver for MapIncludeResolver {
+    fn resolve(&self, name: &str) -> Result<String, String> {
+        self.files
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("Include not found: {}", name))
+    }
+}
 
 ////////////////////////////////////////////////////////////////////////////////
 // PUBLIC FUNCTIONS
@@ -41,24 +229,61 @@ use std::path::{Path, PathBuf};
 /// - `current_dir`: A `&Path` representing the current working directory for relative paths.
 ///
 /// # Returns
-/// - `Result<String, String>`: Returns the file content as a string, or an error message.
+/// - `Result<String, PreprocessorError>`: Returns the file content as a string,
+///   or an `Include` error.
 ///
 /// # Example
 /// ```rust
-/// let content = process_include("%INCLUDE 'example.pli';", Path::new("/path/to/current"));
+/// # use pli_preprocessor::modules::include_handler::process_include;
+/// # let dir = std::env::temp_dir().join("pli_doc_process_include");
+/// # std::fs::create_dir_all(&dir).unwrap();
+/// # std::fs::write(dir.join("example.pli"), "DCL X FIXED;").unwrap();
+/// let content = process_include("%INCLUDE 'example.pli';", &dir);
 /// assert!(content.is_ok());
 /// ```
-pub fn process_include(directive: &str, current_dir: &Path) -> Result<String, String> {
-    let file_path = extract_file_path(directive)
-        .ok_or_else(|| format!("Invalid include directive: {}", directive))?;
+pub fn process_include(directive: &str, current_dir: &Path) -> Result<String, PreprocessorError> {
+    process_include_with(directive, &FsIncludeResolver::new(current_dir))
+}
 
-    let resolved_path = resolve_include_path(&file_path, current_dir)?;
+/// Processes an `%INCLUDE` directive, reading the content through `resolver`.
+///
+/// A directive naming several members returns their contents concatenated in
+/// order, each starting on a new line.
+///
+/// # Arguments
+/// - `directive`: The `%INCLUDE` directive (e.g., `%INCLUDE 'file.pli';`).
+/// - `resolver`: The `IncludeResolver` supplying the content.
+///
+/// # Returns
+/// - `Result<String, PreprocessorError>`: The included content, or an
+///   `Include` error if the directive names no file or the resolver fails.
+pub fn process_include_with(
+    directive: &str,
+    resolver: &dyn IncludeResolver,
+) -> Result<String, PreprocessorError> {
+    let file_paths = extract_file_paths(directive);
+    if file_paths.is_empty() {
+        return Err(PreprocessorError::Include {
+            code: "I001",
+            message: format!("Invalid include directive: {}", directive),
+        });
+    }
 
-    read_file(&resolved_path)
+    let mut text = String::new();
+    for file_path in &file_paths {
+        if !text.is_empty() && !text.ends_with('\n') {
+            text.push('\n');
+        }
+        let content = resolver.resolve(file_path).map_err(include_not_found)?;
+        text.push_str(&content);
+    }
+    Ok(text)
 }
 
 /// Extracts the file path from an `%INCLUDE` directive.
 ///
+/// For a directive naming several members, this is the first of them.
+///
 /// # Arguments
 /// - `directive`: A `&str` containing the `%INCLUDE` directive.
 ///
@@ -67,30 +292,111 @@ pub fn process_include(directive: &str, current_dir: &Path) -> Result<String, St
 ///
 /// # Example
 /// ```rust
+/// # use pli_preprocessor::modules::include_handler::extract_file_path;
 /// let path = extract_file_path("%INCLUDE 'example.pli';");
 /// assert_eq!(path, Some("example.pli".to_string()));
 /// ```
 pub fn extract_file_path(directive: &str) -> Option<String> {
-    let parts: Vec<&str> = directive.split_whitespace().collect();
+    extract_file_paths(directive).into_iter().next()
+}
 
-    // Ensure the directive starts with "%INCLUDE" and has at least two parts
-    if parts.len() < 2 || parts[0] != "%INCLUDE" {
-        return None;
+/// Extracts every member named by an `%INCLUDE` directive, in order.
+///
+/// Members are separated by commas and may be quoted or bare; the directive
+/// ends at the first `;` outside quotes. Empty members are skipped.
+///
+/// # Arguments
+/// - `directive`: A `&str` containing the `%INCLUDE` directive.
+///
+/// # Returns
+/// - `Vec<String>`: The member names, empty if the directive is invalid.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::include_handler::extract_file_paths;
+/// let paths = extract_file_paths("%INCLUDE 'a.pli', B, 'c.pli';");
+/// assert_eq!(paths, vec!["a.pli", "B", "c.pli"]);
+/// ```
+pub fn extract_file_paths(directive: &str) -> Vec<String> {
+    let rest = match directive.trim_start().strip_prefix("%INCLUDE") {
+        Some(rest) if rest.starts_with(char::is_whitespace) => rest,
+        _ => return Vec::new(),
+    };
+
+    let mut members = Vec::new();
+    let mut member = String::new();
+    let mut in_quote = false;
+    for c in rest.chars() {
+        match c {
+            '\'' => in_quote = !in_quote,
+            ',' if !in_quote => {
+                members.push(std::mem::take(&mut member));
+                continue;
+            }
+            ';' if !in_quote => break,
+            _ => {}
+        }
+        member.push(c);
     }
+    members.push(member);
 
-    // Trim leading/trailing quotes and semicolon
-    let path = parts[1].trim_matches(&['\'', ';'][..]);
+    members.iter().filter_map(|m| member_path(m)).collect()
+}
 
-    // Return None if the path is empty after trimming
-    if path.is_empty() {
-        return None;
-    }
+/// Returns the file named by a source line's `%INCLUDE` directive, if it has one.
+///
+/// Unlike `extract_file_path`, the directive keyword may be in any case and the
+/// line may be indented. For a directive naming several members, this is the
+/// first of them; `include_targets` returns them all.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::include_handler::include_target;
+/// assert_eq!(include_target("  %include 'defs.pli';"), Some("defs.pli".to_string()));
+/// assert_eq!(include_target("X = 1;"), None);
+/// ```
+pub fn include_target(line: &str) -> Option<String> {
+    include_targets(line).into_iter().next()
+}
 
-    Some(path.to_string())
+/// Returns every file named by a source line's `%INCLUDE` directive, in order.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::include_handler::include_targets;
+/// assert_eq!(include_targets("%include 'a.pli', 'b.pli';"), vec!["a.pli", "b.pli"]);
+/// assert!(include_targets("X = 1;").is_empty());
+/// ```
+pub fn include_targets(line: &str) -> Vec<String> {
+    directive_targets(line, "%INCLUDE")
+}
+
+/// Returns the file named by a source line's `%XINCLUDE` directive, if it has one.
+///
+/// `%XINCLUDE` copies a file verbatim: unlike an `%INCLUDE`d file, its own
+/// `%INCLUDE`s and `%XINCLUDE`s are not expanded. As with `include_target`, the
+/// keyword may be in any case and the line may be indented.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::include_handler::xinclude_target;
+/// assert_eq!(xinclude_target("%xinclude 'defs.pli';"), Some("defs.pli".to_string()));
+/// assert_eq!(xinclude_target("%INCLUDE 'defs.pli';"), None);
+/// ```
+pub fn xinclude_target(line: &str) -> Option<String> {
+    xinclude_targets(line).into_iter().next()
+}
+
+/// Returns every file named by a source line's `%XINCLUDE` directive, in order.
+pub fn xinclude_targets(line: &str) -> Vec<String> {
+    directive_targets(line, "%XINCLUDE")
 }
 
 /// Resolves the full path of an included file.
-pub fn resolve_include_path(file_path: &str, current_dir: &Path) -> Result<PathBuf, String> {
+pub fn resolve_include_path(
+    file_path: &str,
+    current_dir: &Path,
+) -> Result<PathBuf, PreprocessorError> {
     let path = Path::new(file_path);
     if path.is_absolute() {
         Ok(path.to_path_buf())
@@ -99,8 +405,656 @@ pub fn resolve_include_path(file_path: &str, current_dir: &Path) -> Result<PathB
     }
 }
 
+/// Resolves the full path of an included file, searching further directories.
+///
+/// A relative name is looked up in `current_dir` first, then in each of
+/// `search_paths` in order, and the first existing file wins. If none of them
+/// holds the file, the path under `current_dir` is returned so that reading it
+/// reports the missing file. Absolute names are returned as is.
+///
+/// # Arguments
+/// - `file_path`: The path as written in the `%INCLUDE` directive.
+/// - `current_dir`: The directory of the including file.
+/// - `search_paths`: The directories to search next, earliest first.
+///
+/// # Returns
+/// - `Result<PathBuf, PreprocessorError>`: The path to read the include from.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::include_handler::find_include_path;
+/// # use std::path::{Path, PathBuf};
+/// # let dir = std::env::temp_dir().join("pli_doc_find_include_path");
+/// # std::fs::create_dir_all(&dir).unwrap();
+/// # std::fs::create_dir_all(dir.join("copybooks")).unwrap();
+/// # std::fs::create_dir_all(dir.join("shared")).unwrap();
+/// # std::fs::write(dir.join("copybooks/defs.pli"), "").unwrap();
+/// # std::fs::write(dir.join("shared/defs.pli"), "").unwrap();
+/// # std::env::set_current_dir(&dir).unwrap();
+/// # std::fs::create_dir_all("src").unwrap();
+/// // Only copybooks/defs.pli and shared/defs.pli exist.
+/// let dirs = vec![PathBuf::from("copybooks"), PathBuf::from("shared")];
+/// let path = find_include_path("defs.pli", Path::new("src"), &dirs).unwrap();
+/// assert_eq!(path, PathBuf::from("copybooks/defs.pli"));
+/// ```
+pub fn find_include_path(
+    file_path: &str,
+    current_dir: &Path,
+    search_paths: &[PathBuf],
+) -> Result<PathBuf, PreprocessorError> {
+    let local = resolve_include_path(file_path, current_dir)?;
+    if Path::new(file_path).is_absolute() || local.is_file() {
+        return Ok(local);
+    }
+    for dir in search_paths {
+        let candidate = resolve_include_path(file_path, dir)?;
+        if candidate.is_file() {
+            return Ok(candidate);
+        }
+    }
+    Ok(local)
+}
+
 /// Reads the content of a file.
-pub fn read_file(path: &Path) -> Result<String, String> {
-    fs::read_to_string(path)
-        .map_err(|err| format!("Failed to read file {}: {}", path.display(), err))
+///
+/// A file whose extension is `.gz` (in any case) is decompressed first, so
+/// `deck.pli.gz` reads as the `deck.pli` it holds. Both failures are `Io`
+/// errors: one that cannot be read or decompressed keeps the kind of the
+/// underlying error, and one that is not valid UTF-8 text is rejected as by
+/// `decode_source_text`.
+pub fn read_file(path: &Path) -> Result<String, PreprocessorError> {
+    let mut bytes = fs::read(path).map_err(|err| {
+        io::Error::new(
+            err.kind(),
+            format!("Failed to read file {}: {}", path.display(), err),
+        )
+    })?;
+    if is_gzip_path(path) {
+        let mut decompressed = Vec::new();
+        GzDecoder::new(bytes.as_slice())
+            .read_to_end(&mut decompressed)
+            .map_err(|err| {
+                io::Error::new(
+                    err.kind(),
+                    format!("Failed to decompress file {}: {}", path.display(), err),
+                )
+            })?;
+        bytes = decompressed;
+    }
+    decode_source_text(bytes, &path.display().to_string())
+}
+
+/// Returns `true` if `path` has a `.gz` extension, ignoring ASCII case.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::include_handler::is_gzip_path;
+/// # use std::path::Path;
+/// assert!(is_gzip_path(Path::new("deck.pli.GZ")));
+/// assert!(!is_gzip_path(Path::new("deck.pli")));
+/// ```
+pub fn is_gzip_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("gz"))
+}
+
+/// Decodes source bytes as UTF-8 text.
+///
+/// # Arguments
+/// - `bytes`: The raw content of the source.
+/// - `name`: How to refer to the source in the error message, e.g. its path.
+///
+/// # Returns
+/// - `Result<String, PreprocessorError>`: The text, or an `Io` error of kind
+///   `InvalidData` locating the first invalid byte by line and column.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::include_handler::decode_source_text;
+/// let error = decode_source_text(b"A = 1;\nB\xFF".to_vec(), "main.pli").unwrap_err();
+/// assert_eq!(
+///     error.to_string(),
+///     "Input file is not valid UTF-8 text: main.pli has invalid byte 0xFF at line 2, column 2"
+/// );
+/// ```
+pub fn decode_source_text(bytes: Vec<u8>, name: &str) -> Result<String, PreprocessorError> {
+    String::from_utf8(bytes).map_err(|err| {
+        let bytes = err.as_bytes();
+        let valid = &bytes[..err.utf8_error().valid_up_to()];
+        let line = valid.iter().filter(|&&b| b == b'\n').count() + 1;
+        let line_start = valid.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+        let column = String::from_utf8_lossy(&valid[line_start..]).chars().count() + 1;
+        let message = format!(
+            "Input file is not valid UTF-8 text: {} has invalid byte 0x{:02X} at line {}, column {}",
+            name,
+            bytes[valid.len()],
+            line,
+            column
+        );
+        PreprocessorError::Io(io::Error::new(io::ErrorKind::InvalidData, message))
+    })
+}
+
+/// Walks the includes of `root` recursively and returns every include edge.
+///
+/// Each include is resolved relative to the directory of the file containing
+/// it. A file is only scanned once, so include cycles terminate; the edge that
+/// closes a cycle is still reported.
+///
+/// # Arguments
+/// - `root`: The source file to start from.
+///
+/// # Returns
+/// - `Result<Vec<IncludeEdge>, PreprocessorError>`: The edges in depth-first
+///   order, an `Io` error if `root` cannot be read, or an `Include` error if
+///   an included file cannot be read.
+pub fn collect_include_edges(root: &Path) -> Result<Vec<IncludeEdge>, PreprocessorError> {
+    collect_include_edges_with_paths(root, &[])
+}
+
+/// Walks the includes of `root` like `collect_include_edges`, looking up each
+/// include that is not next to the file containing it in `search_paths`, in
+/// order (see `find_include_path`).
+///
+/// # Arguments
+/// - `root`: The source file to start from.
+/// - `search_paths`: The include search directories, earliest first.
+///
+/// # Returns
+/// - `Result<Vec<IncludeEdge>, PreprocessorError>`: The edges in depth-first
+///   order, or the errors of `collect_include_edges`.
+pub fn collect_include_edges_with_paths(
+    root: &Path,
+    search_paths: &[PathBuf],
+) -> Result<Vec<IncludeEdge>, PreprocessorError> {
+    let content = read_file(root)?;
+    let mut edges = Vec::new();
+    let mut visited = HashSet::from([root.to_path_buf()]);
+    walk_includes(root, &content, search_paths, &mut visited, &mut edges)?;
+    Ok(edges)
+}
+
+/// Renders include edges as an indented tree rooted at `root`.
+///
+/// Children are indented two spaces per level. A file that includes one of its
+/// own ancestors is marked `(cycle)` and not expanded again.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::include_handler::{format_include_tree, IncludeEdge};
+/// # use std::path::{Path, PathBuf};
+/// # let edge = |from: &str, to: &str| IncludeEdge { from: PathBuf::from(from), to: PathBuf::from(to), line: 1 };
+/// # let edges = vec![edge("main.pli", "defs.pli"), edge("defs.pli", "types.pli")];
+/// let text = format_include_tree(Path::new("main.pli"), &edges);
+/// assert_eq!(text, "main.pli\n  defs.pli\n    types.pli\n");
+/// ```
+pub fn format_include_tree(root: &Path, edges: &[IncludeEdge]) -> String {
+    let mut text = format!("{}\n", root.display());
+    let mut ancestors = vec![root.to_path_buf()];
+    render_tree(root, edges, &mut ancestors, &mut text);
+    text
+}
+
+/// Renders include edges as a JSON array of `{"from", "to", "line"}` objects.
+pub fn format_include_json(edges: &[IncludeEdge]) -> String {
+    let entries: Vec<String> = edges
+        .iter()
+        .map(|edge| {
+            format!(
+                "  {{\"from\": {}, \"to\": {}, \"line\": {}}}",
+                json_string(&edge.from.display().to_string()),
+                json_string(&edge.to.display().to_string()),
+                edge.line
+            )
+        })
+        .collect();
+    if entries.is_empty() {
+        "[]\n".to_string()
+    } else {
+        format!("[\n{}\n]\n", entries.join(",\n"))
+    }
+}
+
+/// Renders include edges as a Graphviz DOT digraph.
+pub fn format_include_dot(edges: &[IncludeEdge]) -> String {
+    let mut text = String::from("digraph includes {\n");
+    for edge in edges {
+        text.push_str(&format!(
+            "  {} -> {};\n",
+            json_string(&edge.from.display().to_string()),
+            json_string(&edge.to.display().to_string())
+        ));
+    }
+    text.push_str("}\n");
+    text
+}
+
+/// Returns the content of `root` with every `%INCLUDE` line replaced, recursively,
+/// by the content of the file it names. An `%XINCLUDE` line is replaced by the
+/// content of its file as written, without expanding the includes in it.
+///
+/// With `markers`, each included file is bracketed by `/* BEGIN INCLUDE name */`
+/// and `/* END INCLUDE name */` lines, where `name` is the path as written in
+/// the directive.
+///
+/// # Arguments
+/// - `root`: The source file to flatten.
+/// - `markers`: Whether to emit the begin/end comment lines.
+///
+/// # Returns
+/// - `Result<String, PreprocessorError>`: The flattened source, an `Io` error
+///   if `root` cannot be read, or an `Include` error if an included file cannot
+///   be read or a file includes itself, directly or indirectly.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::include_handler::flatten_includes;
+/// # use std::path::Path;
+/// # let dir = std::env::temp_dir().join("pli_doc_flatten_includes");
+/// # std::fs::create_dir_all(&dir).unwrap();
+/// # std::fs::write(dir.join("main.pli"), "%INCLUDE 'defs.pli';\nX = 1;").unwrap();
+/// # std::fs::write(dir.join("defs.pli"), "DCL A FIXED;").unwrap();
+/// # std::env::set_current_dir(&dir).unwrap();
+/// // main.pli: "%INCLUDE 'defs.pli';\nX = 1;"    defs.pli: "DCL A FIXED;"
+/// let text = flatten_includes(Path::new("main.pli"), true).unwrap();
+/// assert_eq!(text, "/* BEGIN INCLUDE defs.pli */\nDCL A FIXED;\n/* END INCLUDE defs.pli */\nX = 1;\n");
+/// ```
+pub fn flatten_includes(root: &Path, markers: bool) -> Result<String, PreprocessorError> {
+    flatten_includes_with_paths(root, markers, &[])
+}
+
+/// Flattens `root` like `flatten_includes`, looking up each include that is
+/// not next to the file containing it in `search_paths`, in order (see
+/// `find_include_path`).
+///
+/// # Arguments
+/// - `root`: The source file to flatten.
+/// - `markers`: Whether to emit the begin/end comment lines.
+/// - `search_paths`: The include search directories, earliest first.
+///
+/// # Returns
+/// - `Result<String, PreprocessorError>`: The flattened source, or the errors
+///   of `flatten_includes`.
+pub fn flatten_includes_with_paths(
+    root: &Path,
+    markers: bool,
+    search_paths: &[PathBuf],
+) -> Result<String, PreprocessorError> {
+    let options = InlineOptions {
+        markers,
+        ..Default::default()
+    };
+    flatten_includes_counted(root, options, search_paths).map(|(text, _)| text)
+}
+
+/// Flattens `root` like `flatten_includes_with_paths`, also returning how
+/// many `%INCLUDE` and `%XINCLUDE` directives were inlined, at any depth.
+///
+/// The includes are read through an `FsIncludeResolver`, so nested includes
+/// resolve relative to the file containing them.
+///
+/// # Arguments
+/// - `root`: The source file to flatten.
+/// - `options`: How to lay out the inlined includes.
+/// - `search_paths`: The include search directories, earliest first.
+///
+/// # Returns
+/// - `Result<(String, usize), PreprocessorError>`: The flattened source and
+///   the number of includes inlined, or the errors of `flatten_includes`.
+pub fn flatten_includes_counted(
+    root: &Path,
+    options: InlineOptions,
+    search_paths: &[PathBuf],
+) -> Result<(String, usize), PreprocessorError> {
+    let source = read_file(root)?;
+    let resolver = FsIncludeResolver::new(root.parent().unwrap_or_else(|| Path::new("")))
+        .with_search_paths(search_paths.to_vec());
+    let root = normalize_path(root).display().to_string();
+    let mut text = String::new();
+    let mut stack = vec![root.clone()];
+    let mut count = 0;
+    expand_into(
+        &source,
+        &resolver,
+        options,
+        Some(&root),
+        &mut stack,
+        &mut text,
+        &mut count,
+    )?;
+    Ok((text, count))
+}
+
+/// Inlines the includes of in-memory source, recursively, reading each one
+/// through `resolver`.
+///
+/// `flatten_includes` is this function reading through an
+/// `FsIncludeResolver`; includes are identified by the keys of `resolver`.
+///
+/// # Arguments
+/// - `source`: The source text.
+/// - `resolver`: The `IncludeResolver` supplying included content.
+/// - `markers`: Whether to bracket each include with `BEGIN`/`END INCLUDE` lines.
+///
+/// # Returns
+/// - `Result<String, PreprocessorError>`: The expanded source, or an `Include`
+///   error if an include cannot be resolved or includes itself.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::include_handler::{expand_includes, MapIncludeResolver};
+/// let mut resolver = MapIncludeResolver::new();
+/// resolver.insert("defs.pli", "DCL X FIXED;");
+/// let text = expand_includes("%INCLUDE 'defs.pli';\nX = 1;", &resolver, false).unwrap();
+/// assert_eq!(text, "DCL X FIXED;\nX = 1;\n");
+/// ```
+pub fn expand_includes(
+    source: &str,
+    resolver: &dyn IncludeResolver,
+    markers: bool,
+) -> Result<String, PreprocessorError> {
+    let options = InlineOptions {
+        markers,
+        ..Default::default()
+    };
+    expand_includes_counted(source, resolver, options).map(|(text, _)| text)
+}
+
+/// Inlines the includes of in-memory source like `expand_includes`, also
+/// returning how many `%INCLUDE` and `%XINCLUDE` directives were inlined, at
+/// any depth.
+///
+/// # Arguments
+/// - `source`: The source text.
+/// - `resolver`: The `IncludeResolver` supplying included content.
+/// - `options`: How to lay out the inlined includes.
+///
+/// # Returns
+/// - `Result<(String, usize), PreprocessorError>`: The expanded source and the
+///   number of includes inlined, or the errors of `expand_includes`.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::include_handler::{expand_includes_counted, InlineOptions, MapIncludeResolver};
+/// let mut resolver = MapIncludeResolver::new();
+/// resolver.insert("defs.pli", "DCL X FIXED;");
+/// let options = InlineOptions { keep_directives: true, ..Default::default() };
+/// let (text, count) = expand_includes_counted("%INCLUDE 'defs.pli';", &resolver, options).unwrap();
+/// assert_eq!(text, "/* %INCLUDE 'defs.pli'; */\nDCL X FIXED;\n");
+/// assert_eq!(count, 1);
+/// ```
+pub fn expand_includes_counted(
+    source: &str,
+    resolver: &dyn IncludeResolver,
+    options: InlineOptions,
+) -> Result<(String, usize), PreprocessorError> {
+    let mut text = String::new();
+    let mut count = 0;
+    expand_into(
+        source,
+        resolver,
+        options,
+        None,
+        &mut Vec::new(),
+        &mut text,
+        &mut count,
+    )?;
+    Ok((text, count))
+}
+
+/// Returns `true` if `output` must be regenerated from `input` and `includes`.
+///
+/// The output is up to date only if it exists and its modification time is
+/// strictly newer than that of the input and of every include. Any file whose
+/// modification time cannot be read forces a rebuild.
+///
+/// # Arguments
+/// - `input`: The source file.
+/// - `includes`: Every file the source includes, directly or indirectly.
+/// - `output`: The generated file.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::include_handler::{collect_include_edges, needs_rebuild};
+/// # use std::path::{Path, PathBuf};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let dir = std::env::temp_dir().join("pli_doc_needs_rebuild");
+/// # std::fs::create_dir_all(&dir).unwrap();
+/// # std::fs::write(dir.join("main.pli"), "X = 1;").unwrap();
+/// # std::fs::write(dir.join("main.out"), "X = 1;").unwrap();
+/// # std::env::set_current_dir(&dir).unwrap();
+/// # let (input, output) = (Path::new("main.pli"), Path::new("main.out"));
+/// let includes: Vec<PathBuf> = collect_include_edges(input)?.into_iter().map(|e| e.to).collect();
+/// if !needs_rebuild(input, &includes, output) {
+///     return Ok(()); // nothing changed since the last run
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn needs_rebuild(input: &Path, includes: &[PathBuf], output: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+
+    let output_time = match modified(output) {
+        Some(time) => time,
+        None => return true,
+    };
+    std::iter::once(input)
+        .chain(includes.iter().map(PathBuf::as_path))
+        .any(|source| modified(source).is_none_or(|time| time >= output_time))
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// PRIVATE FUNCTIONS
+////////////////////////////////////////////////////////////////////////////////
+
+/// Scans `content`, the text of `file`, for includes, recording edges and
+/// recursing into files not `visited` yet.
+fn walk_includes(
+    file: &Path,
+    content: &str,
+    search_paths: &[PathBuf],
+    visited: &mut HashSet<PathBuf>,
+    edges: &mut Vec<IncludeEdge>,
+) -> Result<(), PreprocessorError> {
+    let current_dir = file.parent().unwrap_or_else(|| Path::new(""));
+
+    for (index, line) in content.lines().enumerate() {
+        for target in include_targets(line) {
+            let resolved = normalize_path(&find_include_path(&target, current_dir, search_paths)?);
+            edges.push(IncludeEdge {
+                from: file.to_path_buf(),
+                to: resolved.clone(),
+                line: index + 1,
+            });
+            if visited.insert(resolved.clone()) {
+                let content = read_file(&resolved).map_err(|e| include_not_found(e.to_string()))?;
+                walk_includes(&resolved, &content, search_paths, visited, edges)?;
+            }
+        }
+        // The includes of a verbatim copy are never expanded.
+        for target in xinclude_targets(line) {
+            edges.push(IncludeEdge {
+                from: file.to_path_buf(),
+                to: normalize_path(&find_include_path(&target, current_dir, search_paths)?),
+                line: index + 1,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Removes `.` and `..` components lexically so each file has one spelling.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push("..");
+                }
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    normalized
+}
+
+/// Wraps a resolver's failure to supply an include as an `I002` error.
+fn include_not_found(message: String) -> PreprocessorError {
+    PreprocessorError::Include {
+        code: "I002",
+        message,
+    }
+}
+
+/// Appends `source`, the content of the include keyed `parent`, to `text` with
+/// its includes expanded through `resolver`, adding the includes inlined to
+/// `count`.
+///
+/// `stack` holds the keys of the includes currently being expanded.
+fn expand_into(
+    source: &str,
+    resolver: &dyn IncludeResolver,
+    options: InlineOptions,
+    parent: Option<&str>,
+    stack: &mut Vec<String>,
+    text: &mut String,
+    count: &mut usize,
+) -> Result<(), PreprocessorError> {
+    for line in source.lines() {
+        let targets = xinclude_targets(line);
+        if !targets.is_empty() {
+            push_directive_comment(line, options, text);
+            for target in &targets {
+                let (_, content) = resolver
+                    .resolve_from(target, parent)
+                    .map_err(include_not_found)?;
+                push_include_start(target, options, text);
+                if options.mark_verbatim {
+                    text.push_str(VERBATIM_BEGIN);
+                    text.push('\n');
+                }
+                push_lines(&content, text);
+                if options.mark_verbatim {
+                    text.push_str(VERBATIM_END);
+                    text.push('\n');
+                }
+                push_include_end(target, options, text);
+                *count += 1;
+            }
+            continue;
+        }
+        let targets = include_targets(line);
+        if targets.is_empty() {
+            text.push_str(line);
+            text.push('\n');
+            continue;
+        }
+
+        push_directive_comment(line, options, text);
+        for target in &targets {
+            let (key, content) = resolver
+                .resolve_from(target, parent)
+                .map_err(include_not_found)?;
+            if stack.contains(&key) {
+                return Err(PreprocessorError::Include {
+                    code: "I003",
+                    message: format!("Recursive include of {}", key),
+                });
+            }
+
+            push_include_start(target, options, text);
+            *count += 1;
+            stack.push(key.clone());
+            expand_into(&content, resolver, options, Some(&key), stack, text, count)?;
+            stack.pop();
+            push_include_end(target, options, text);
+        }
+    }
+    Ok(())
+}
+
+/// Returns the members named by `line` if it is a `keyword` directive, which
+/// may be in any case and indented.
+fn directive_targets(line: &str, keyword: &str) -> Vec<String> {
+    let trimmed = line.trim();
+    match trimmed.get(..keyword.len()) {
+        Some(found) if found.eq_ignore_ascii_case(keyword) => {
+            extract_file_paths(&format!("%INCLUDE{}", &trimmed[keyword.len()..]))
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Returns the file named by one member of an `%INCLUDE` directive: the text
+/// between its quotes, or its first word if it is bare.
+fn member_path(member: &str) -> Option<String> {
+    let member = member.trim();
+    let path = match member.strip_prefix('\'') {
+        Some(quoted) => quoted.split('\'').next().unwrap_or(""),
+        None => member.split_whitespace().next().unwrap_or(""),
+    };
+    (!path.is_empty()).then(|| path.to_string())
+}
+
+/// Appends an include directive `line` as a comment, if `options` ask for it.
+fn push_directive_comment(line: &str, options: InlineOptions, text: &mut String) {
+    if options.keep_directives {
+        // A `*/` in the line would end the comment early.
+        text.push_str(&format!("/* {} */\n", line.trim().replace("*/", "* /")));
+    }
+}
+
+/// Appends the `BEGIN` marker of an inlined include, if `options` ask for one,
+/// tracing the include at the line it starts on if they ask for that.
+fn push_include_start(target: &str, options: InlineOptions, text: &mut String) {
+    if options.trace {
+        let line = text.matches('\n').count() + 1;
+        trace_event(line, "include", &format!("target={}", target));
+    }
+    if options.markers {
+        text.push_str(&format!("/* BEGIN INCLUDE {} */\n", target));
+    }
+}
+
+/// Appends the `END` marker of an inlined include, if `options` ask for one.
+fn push_include_end(target: &str, options: InlineOptions, text: &mut String) {
+    if options.markers {
+        text.push_str(&format!("/* END INCLUDE {} */\n", target));
+    }
+}
+
+/// Appends each line of `content` to `text` as written.
+fn push_lines(content: &str, text: &mut String) {
+    for line in content.lines() {
+        text.push_str(line);
+        text.push('\n');
+    }
+}
+
+/// Appends the children of `file` to `text`, indented by nesting depth.
+fn render_tree(
+    file: &Path,
+    edges: &[IncludeEdge],
+    ancestors: &mut Vec<PathBuf>,
+    text: &mut String,
+) {
+    for edge in edges.iter().filter(|e| e.from == file) {
+        let indent = "  ".repeat(ancestors.len());
+        if ancestors.contains(&edge.to) {
+            text.push_str(&format!("{}{} (cycle)\n", indent, edge.to.display()));
+            continue;
+        }
+        text.push_str(&format!("{}{}\n", indent, edge.to.display()));
+        ancestors.push(edge.to.clone());
+        render_tree(&edge.to, edges, ancestors, text);
+        ancestors.pop();
+    }
+}
+
+/// Quotes a string for JSON and DOT output, escaping it as `serde_json` does.
+pub(crate) fn json_string(value: &str) -> String {
+    serde_json::Value::from(value).to_string()
 }
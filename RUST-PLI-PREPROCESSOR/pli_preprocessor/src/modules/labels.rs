@@ -0,0 +1,175 @@
+#![allow(dead_code)] // Suppress warnings for unused functions in this module.
+
+////////////////////////////////////////////////////////////////////////////////
+// MODULE NAME: Preprocessor Labels
+// ----------------------------------------------------------------------------
+// DESCRIPTION:
+// This module handles preprocessor control flow by label, written as
+//
+//     %GOTO SKIP;          (or %GO TO SKIP;)
+//     ...
+//     %SKIP: ;
+//
+// where processing continues at the line holding the `%SKIP:` label.
+//
+// FUNCTIONALITY:
+// - Splits a `%LABEL:` prefix off a statement with `split_label`.
+// - Parses `%GOTO label;` and `%GO TO label;` statements with `parse_goto`.
+// - Maps every label of a source to its line with `collect_labels`, reporting
+//   duplicates.
+//
+// USAGE:
+// - Call `collect_labels` on the source lines before processing them, then
+//   look up the target of each `%GOTO` in the returned map.
+// - Stop jumping after `MAX_GOTO_JUMPS` jumps, which ends `%GOTO` loops that
+//   never exit.
+//
+// AUTHOR: FirstLink Consulting Services (FLCS)
+// LICENSE: MIT License
+// DATE: 10/16/2026
+// VERSION: 1.0.0
+////////////////////////////////////////////////////////////////////////////////
+
+////////////////////////////////////////////////////////////////////////////////
+// IMPORTS
+////////////////////////////////////////////////////////////////////////////////
+
+use crate::modules::diagnostics::DiagnosticSink;
+use crate::modules::directive::canonicalize;
+use crate::modules::parser::split_statements;
+use crate::modules::tokenizer::{tokenize_pli, Token, TokenCategory};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+////////////////////////////////////////////////////////////////////////////////
+// CONSTANTS
+////////////////////////////////////////////////////////////////////////////////
+
+/// The most `%GOTO` jumps taken in one run before processing stops, which
+/// ends loops that never exit.
+pub const MAX_GOTO_JUMPS: usize = 10_000;
+
+////////////////////////////////////////////////////////////////////////////////
+// PUBLIC FUNCTIONS
+////////////////////////////////////////////////////////////////////////////////
+
+/// Splits a `%LABEL:` prefix off a statement.
+///
+/// The label is a `%` name that is not a standard directive, followed by `:`.
+/// A `%NAME: PROCEDURE` header is not a label (see
+/// `procedures::parse_proc_header`).
+///
+/// # Arguments
+/// - `tokens`: The tokens of one statement.
+///
+/// # Returns
+/// - `Option<(String, &[Token])>`: The uppercase label without its `%` and the
+///   statement after the `:`, or `None` if the statement has no label.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::labels::split_label;
+/// # use pli_preprocessor::modules::tokenizer::tokenize_pli;
+/// let tokens = tokenize_pli("%AGAIN: %SET N = N + 1;");
+/// let (label, rest) = split_label(&tokens).unwrap();
+/// assert_eq!(label, "AGAIN");
+/// assert_eq!(rest[0].value, "%SET");
+/// ```
+pub fn split_label(tokens: &[Token]) -> Option<(String, &[Token])> {
+    match tokens {
+        [name, colon, rest @ ..]
+            if name.category == TokenCategory::Directive
+                && canonicalize(&name.value).is_none()
+                && colon.category == TokenCategory::Separator
+                && colon.value == ":"
+                && !rest
+                    .first()
+                    .is_some_and(|t| matches!(t.value.as_str(), "PROCEDURE" | "PROC")) =>
+        {
+            Some((name.value.trim_start_matches('%').to_uppercase(), rest))
+        }
+        _ => None,
+    }
+}
+
+/// Parses a `%GOTO label;` or `%GO TO label;` statement.
+///
+/// # Arguments
+/// - `tokens`: The tokens of one statement.
+///
+/// # Returns
+/// - `Option<Result<String, String>>`: `None` if the statement is not a
+///   `%GOTO`; otherwise the uppercase target label, or an error message if the
+///   label is missing.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::labels::parse_goto;
+/// # use pli_preprocessor::modules::tokenizer::tokenize_pli;
+/// assert_eq!(parse_goto(&tokenize_pli("%GO TO done;")), Some(Ok("DONE".to_string())));
+/// assert_eq!(parse_goto(&tokenize_pli("%SET X = 1;")), None);
+/// ```
+pub fn parse_goto(tokens: &[Token]) -> Option<Result<String, String>> {
+    let rest = match tokens
+        .first()
+        .and_then(|t| canonicalize(&t.value))
+        .as_deref()
+    {
+        Some("%GOTO") => &tokens[1..],
+        Some("%GO") => match &tokens[1..] {
+            [to, rest @ ..] if to.value.eq_ignore_ascii_case("TO") => rest,
+            _ => return Some(Err("%GO must be followed by TO".to_string())),
+        },
+        _ => return None,
+    };
+    let rest = match rest {
+        [head @ .., last] if last.value == ";" => head,
+        _ => rest,
+    };
+    Some(match rest {
+        [label] if label.category == TokenCategory::Identifier => Ok(label.value.to_uppercase()),
+        [] => Err("%GOTO requires a label".to_string()),
+        _ => Err("%GOTO takes a single label".to_string()),
+    })
+}
+
+/// Maps each label of a source to the 1-based line it appears on.
+///
+/// A label defined twice keeps its first line, and the second definition is
+/// reported as an error.
+///
+/// # Arguments
+/// - `lines`: The source lines.
+/// - `sink`: The `DiagnosticSink` receiving duplicate-label errors.
+///
+/// # Returns
+/// - `HashMap<String, usize>`: The uppercase labels and their lines.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::diagnostics::DiagnosticSink;
+/// # use pli_preprocessor::modules::labels::collect_labels;
+/// let lines = vec!["%GOTO DONE;".to_string(), "A = 1;".to_string(), "%DONE: ;".to_string()];
+/// let labels = collect_labels(&lines, &mut DiagnosticSink::new());
+/// assert_eq!(labels.get("DONE"), Some(&3));
+/// ```
+pub fn collect_labels(lines: &[String], sink: &mut DiagnosticSink) -> HashMap<String, usize> {
+    let mut labels = HashMap::new();
+    for (index, line) in lines.iter().enumerate() {
+        for statement in split_statements(&tokenize_pli(line)) {
+            if let Some((label, _)) = split_label(&statement) {
+                match labels.entry(label) {
+                    Entry::Occupied(entry) => sink.error_with_code(
+                        index + 1,
+                        "D005",
+                        &format!("Duplicate label %{}", entry.key()),
+                    ),
+                    Entry::Vacant(entry) => {
+                        entry.insert(index + 1);
+                    }
+                }
+            }
+        }
+    }
+    labels
+}
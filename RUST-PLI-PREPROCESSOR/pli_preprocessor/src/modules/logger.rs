@@ -8,7 +8,8 @@
                           // with configurable verbosity levels, timestamped entries, and multiple log levels.
                           //
                           // Features:
-                          // - Logs messages to a specified log file and optionally to the console.
+                          // - Logs messages to a specified log file and optionally to the console, where
+                          //   `init_logger_with_console` sets a separate, typically coarser, level.
                           // - Configurable verbosity levels to control the granularity of log output:
                           //     - `0`: Logs only errors (`ERROR`).
                           //     - `1`: Logs warnings and errors (`WARN` and `ERROR`).
@@ -18,6 +19,10 @@
                           // - Timestamped log entries in the format `YYYY-MM-DD HH:MM:SS.mmmµs`.
                           // - Easy integration with the `log` crate macros: `log::info!`, `log::debug!`, etc.
                           // - Flexible configuration for dynamic logging needs.
+                          // - `init_logger_with_stderr_fallback` logs to standard error instead when
+                          //   the log file cannot be opened; otherwise the error names the path.
+                          // - With the `capture-logger` feature, `init_capture_logger` installs a logger
+                          //   that keeps every record in memory so tests can assert on log output.
                           //
                           // Purpose:
                           // Centralized logging is essential for debugging, monitoring, and maintaining the
@@ -72,7 +77,40 @@ use chrono::Local; // For timestamped logs.
 use fern::Dispatch;
 use log::LevelFilter; // For setting log level filtering.
 use log::{debug, error, info, warn};
+use std::fs::{File, OpenOptions};
 use std::io; // For potential I/O errors in logger initialization.
+#[cfg(feature = "capture-logger")]
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// The records kept by `CaptureLogger`: each record's level and message.
+#[cfg(feature = "capture-logger")]
+pub type CapturedRecords = Arc<Mutex<Vec<(log::Level, String)>>>;
+
+/// A logger that stores every record in memory instead of writing it out.
+///
+/// Tests install it with `init_capture_logger` and inspect the returned
+/// records. It is only built with the `capture-logger` feature, which the
+/// crate's own tests enable.
+#[cfg(feature = "capture-logger")]
+#[derive(Debug, Clone, Default)]
+pub struct CaptureLogger {
+    records: CapturedRecords,
+}
+
+#[cfg(feature = "capture-logger")]
+impl log::Log for CaptureLogger {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        if let Ok(mut records) = self.records.lock() {
+            records.push((record.level(), record.args().to_string()));
+        }
+    }
+
+    fn flush(&self) {}
+}
 
 /// Initializes the logging system for the PL/I Preprocessor application.
 ///
@@ -104,6 +142,8 @@ use std::io; // For potential I/O errors in logger initialization.
 ///
 /// # Example
 /// ```rust
+/// # use pli_preprocessor::modules::logger::init_logger;
+/// # std::env::set_current_dir(std::env::temp_dir()).unwrap();
 /// if let Err(e) = init_logger("application.log", true, 3) {
 ///     eprintln!("Failed to initialize logger: {}", e);
 ///     std::process::exit(1);
@@ -113,7 +153,8 @@ use std::io; // For potential I/O errors in logger initialization.
 ///
 /// # Notes
 /// - Call this function once at the start of the application, before generating any log messages.
-/// - If the log file cannot be created or accessed, the function returns an error.
+/// - If the log file cannot be created or accessed, the function returns an error
+///   naming the path (see `open_log_file`).
 /// - This function utilizes the `fern` crate for log configuration and the `log` crate for logging macros.
 ///
 /// # Dependencies
@@ -129,6 +170,120 @@ pub fn init_logger(
     log_file: &str,
     verbose: bool,
     verbosity_level: u8,
+) -> Result<(), fern::InitError> {
+    init_logger_with_console(log_file, verbose, verbosity_level, LevelFilter::Off)
+}
+
+/// Initializes logging like `init_logger`, and also echoes records at or above
+/// `console_level` to standard error.
+///
+/// The log file still receives every record allowed by `verbosity_level`, so a
+/// quiet console (`LevelFilter::Error`) does not thin out the log.
+///
+/// # Arguments
+/// - `log_file`: The path of the log file.
+/// - `verbose`: Prints a confirmation message once the logger is ready.
+/// - `verbosity_level`: The log file's verbosity, as for `init_logger`.
+/// - `console_level`: The most detailed level echoed to the console;
+///   `LevelFilter::Off` disables console logging.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::logger::init_logger_with_console;
+/// # use log::LevelFilter;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # std::env::set_current_dir(std::env::temp_dir())?;
+/// // Errors only on the console, INFO and above in the file.
+/// init_logger_with_console("application.log", false, 2, LevelFilter::Error)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn init_logger_with_console(
+    log_file: &str,
+    verbose: bool,
+    verbosity_level: u8,
+    console_level: LevelFilter,
+) -> Result<(), fern::InitError> {
+    let file = open_log_file(log_file)?;
+    apply_logger(file.into(), verbose, verbosity_level, console_level)
+}
+
+/// Initializes logging like `init_logger_with_console`, but falls back to
+/// logging to standard error when the log file cannot be opened.
+///
+/// The fallback sends every record allowed by `verbosity_level` to standard
+/// error in place of the file, starting with a warning that names the log
+/// file; the separate console echo is dropped so that records are not printed
+/// twice.
+///
+/// # Arguments
+/// - `log_file`: The path of the log file.
+/// - `verbose`: Prints a confirmation message once the logger is ready.
+/// - `verbosity_level`: The log verbosity, as for `init_logger`.
+/// - `console_level`: The most detailed level echoed to the console while the
+///   log file is in use.
+///
+/// # Returns
+/// - `Result<(), fern::InitError>`: An error only if a logger is already set.
+pub fn init_logger_with_stderr_fallback(
+    log_file: &str,
+    verbose: bool,
+    verbosity_level: u8,
+    console_level: LevelFilter,
+) -> Result<(), fern::InitError> {
+    match open_log_file(log_file) {
+        Ok(file) => apply_logger(file.into(), verbose, verbosity_level, console_level),
+        Err(e) => {
+            apply_logger(
+                io::stderr().into(),
+                verbose,
+                verbosity_level,
+                LevelFilter::Off,
+            )?;
+            warn!("{}. Logging to standard error instead.", e);
+            Ok(())
+        }
+    }
+}
+
+/// Opens the log file for appending, creating it if needed.
+///
+/// # Arguments
+/// - `log_file`: The path of the log file.
+///
+/// # Returns
+/// - `Result<File, io::Error>`: The open file, or an error of the same kind
+///   that names the path and hints at the usual causes.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::logger::open_log_file;
+/// let error = open_log_file("/no/such/dir/run.log").unwrap_err();
+/// assert!(error.to_string().starts_with("Cannot open log file '/no/such/dir/run.log'"));
+/// ```
+pub fn open_log_file(log_file: &str) -> Result<File, io::Error> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_file)
+        .map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!(
+                    "Cannot open log file '{}': {}. Check that its directory exists and is writable",
+                    log_file, e
+                ),
+            )
+        })
+}
+
+/// Installs the global logger, writing records allowed by `verbosity_level`
+/// to `output` and echoing those at or above `console_level` to standard error.
+fn apply_logger(
+    output: fern::Output,
+    verbose: bool,
+    verbosity_level: u8,
+    console_level: LevelFilter,
 ) -> Result<(), fern::InitError> {
     let log_level = match verbosity_level {
         0 => log::LevelFilter::Error,
@@ -149,9 +304,17 @@ pub fn init_logger(
                 message
             ))
         })
-        .level(log::LevelFilter::Error) // Default log level for all modules.
-        .level_for("pli_tokenizer", log_level) // Specific log level for the application.
-        .chain(fern::log_file(log_file)?) // Log to the specified file.
+        .chain(
+            fern::Dispatch::new()
+                .level(log::LevelFilter::Error) // Default log level for all modules.
+                .level_for("pli_preprocessor", log_level) // Specific log level for the application.
+                .chain(output), // Log to the log file, or its fallback.
+        )
+        .chain(
+            fern::Dispatch::new()
+                .level(console_level)
+                .chain(io::stderr()),
+        )
         .apply()?;
 
     if verbose {
@@ -168,3 +331,32 @@ pub fn init_logger(
 
     Ok(())
 }
+
+/// Installs a `CaptureLogger` as the global logger, capturing every level.
+///
+/// A process has a single global logger, so the first call installs it and
+/// later calls return the same records. Tests running in parallel share them
+/// and should look for their own messages rather than assume an exact list.
+///
+/// # Returns
+/// - `CapturedRecords`: The shared list of captured records.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::logger::init_capture_logger;
+/// let records = init_capture_logger();
+/// log::warn!("disk almost full");
+/// assert!(records.lock().unwrap().contains(&(log::Level::Warn, "disk almost full".to_string())));
+/// ```
+#[cfg(feature = "capture-logger")]
+pub fn init_capture_logger() -> CapturedRecords {
+    static LOGGER: OnceLock<CaptureLogger> = OnceLock::new();
+    let logger = LOGGER.get_or_init(|| {
+        let logger = CaptureLogger::default();
+        if log::set_boxed_logger(Box::new(logger.clone())).is_ok() {
+            log::set_max_level(LevelFilter::Trace);
+        }
+        logger
+    });
+    Arc::clone(&logger.records)
+}
@@ -20,6 +20,12 @@
                           // Usage:
                           // 1. Use `expand_macro` to expand a macro definition or usage.
                           // 2. Integrate with the tokenizer to handle macros inline.
+                          // 3. Register definitions in a `MacroTable` and call `expand_line`
+                          //    to substitute active macro names in a source line.
+                          //    `%DEACTIVATE name;` / `%ACTIVATE name;` toggle substitution.
+                          // 4. Replacement text is rescanned for further macros unless
+                          //    rescanning is disabled for the macro (`set_macro_rescan`)
+                          //    or for the whole table (`set_rescan`).
                           //
                           // Example:
                           // ```rust
@@ -50,6 +56,287 @@
 
 use log::{debug, error, info, warn}; // For logging macro expansion process.
 use regex::Regex; // For future implementation of macro parsing (not yet in use).
+use serde::{Deserialize, Serialize}; // Lets `context::save_state` persist the table.
+use std::collections::HashMap;
+
+/// A macro registered in a `MacroTable`.
+///
+/// # Fields
+/// - `name`: The uppercase macro name.
+/// - `body`: The replacement text substituted for the name.
+/// - `active`: Whether the name is currently replaced (see `%ACTIVATE`/`%DEACTIVATE`).
+/// - `rescan`: Whether the body is rescanned for further macro names once
+///   substituted (`%ACTIVATE name RESCAN`/`NORESCAN`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MacroDefinition {
+    pub name: String,
+    pub body: String,
+    pub active: bool,
+    pub rescan: bool,
+}
+
+/// The set of macros defined so far in a source.
+///
+/// Names are case-insensitive and stored in uppercase, matching the tokenizer.
+///
+/// Substituted bodies are rescanned for further macro names by default. As in
+/// PL/I, a macro is never replaced inside its own expansion, so rescanning
+/// always terminates.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::macro_expander::MacroTable;
+/// let mut table = MacroTable::new();
+/// table.define("GREETING", "'HELLO'");
+/// assert_eq!(table.expand_line("X = GREETING;"), "X = 'HELLO';");
+/// table.deactivate("GREETING").unwrap();
+/// assert_eq!(table.expand_line("X = GREETING;"), "X = GREETING;");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MacroTable {
+    macros: HashMap<String, MacroDefinition>,
+    rescan: bool,
+}
+
+impl Default for MacroTable {
+    fn default() -> Self {
+        Self {
+            macros: HashMap::new(),
+            rescan: true,
+        }
+    }
+}
+
+impl MacroTable {
+    /// Creates an empty macro table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enables or disables rescanning for every macro in the table.
+    ///
+    /// When disabled, bodies are substituted as is, whatever each macro's own
+    /// `rescan` setting.
+    pub fn set_rescan(&mut self, rescan: bool) {
+        self.rescan = rescan;
+    }
+
+    /// Enables or disables rescanning of one macro's body.
+    ///
+    /// # Returns
+    /// - `Result<(), String>`: An error if the macro is not defined.
+    pub fn set_macro_rescan(&mut self, name: &str, rescan: bool) -> Result<(), String> {
+        match self.macros.get_mut(&name.to_uppercase()) {
+            Some(definition) => {
+                definition.rescan = rescan;
+                Ok(())
+            }
+            None => Err(format!("Undefined macro: {}", name.to_uppercase())),
+        }
+    }
+
+    /// Defines (or redefines) a macro. New definitions start out active and
+    /// rescanned.
+    pub fn define(&mut self, name: &str, body: &str) {
+        let name = name.to_uppercase();
+        debug!("MacroTable: defining macro {} as {:?}", name, body);
+        self.macros.insert(
+            name.clone(),
+            MacroDefinition {
+                name,
+                body: body.to_string(),
+                active: true,
+                rescan: true,
+            },
+        );
+    }
+
+    /// Returns the definition of a macro, if any.
+    pub fn get(&self, name: &str) -> Option<&MacroDefinition> {
+        self.macros.get(&name.to_uppercase())
+    }
+
+    /// Returns `true` if a macro with the given name is defined.
+    pub fn is_defined(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Re-enables substitution of a macro (`%ACTIVATE name;`).
+    ///
+    /// # Returns
+    /// - `Result<(), String>`: An error if the macro is not defined.
+    pub fn activate(&mut self, name: &str) -> Result<(), String> {
+        self.set_active(name, true)
+    }
+
+    /// Stops substitution of a macro until reactivated (`%DEACTIVATE name;`).
+    ///
+    /// # Returns
+    /// - `Result<(), String>`: An error if the macro is not defined.
+    pub fn deactivate(&mut self, name: &str) -> Result<(), String> {
+        self.set_active(name, false)
+    }
+
+    /// Replaces every active macro name in `line` with the macro body.
+    ///
+    /// Identifiers inside string literals are left untouched. Each substituted
+    /// body is rescanned for further macro names when rescanning is enabled,
+    /// skipping the macros already being expanded.
+    ///
+    /// # Arguments
+    /// - `line`: The source line to expand.
+    ///
+    /// # Returns
+    /// - `String`: The expanded line.
+    pub fn expand_line(&self, line: &str) -> String {
+        self.expand_line_with_names(line).0
+    }
+
+    /// Expands `line` like `expand_line`, also returning the names of the
+    /// macros substituted, in the order they were expanded.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use pli_preprocessor::modules::macro_expander::MacroTable;
+    /// let mut table = MacroTable::new();
+    /// table.define("GREETING", "'HELLO'");
+    /// let (line, names) = table.expand_line_with_names("X = GREETING;");
+    /// assert_eq!(line, "X = 'HELLO';");
+    /// assert_eq!(names, vec!["GREETING"]);
+    /// ```
+    pub fn expand_line_with_names(&self, line: &str) -> (String, Vec<String>) {
+        self.expand_line_within(line, usize::MAX)
+            .unwrap_or_else(|| (line.to_string(), Vec::new()))
+    }
+
+    /// Expands `line` like `expand_line_with_names`, giving up as soon as the
+    /// expansion grows past `budget` bytes.
+    ///
+    /// Nested expansions can grow exponentially, so the budget is checked
+    /// while the text is built rather than once it is complete.
+    ///
+    /// # Arguments
+    /// - `line`: The source line to expand.
+    /// - `budget`: The most bytes the expanded line may hold.
+    ///
+    /// # Returns
+    /// - `Option<(String, Vec<String>)>`: The expanded line and the macros
+    ///   substituted, or `None` if the expansion exceeded `budget`.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use pli_preprocessor::modules::macro_expander::MacroTable;
+    /// let mut table = MacroTable::new();
+    /// table.define("GREETING", "'HELLO'");
+    /// assert!(table.expand_line_within("X = GREETING;", 12).is_some());
+    /// assert_eq!(table.expand_line_within("X = GREETING;", 11), None);
+    /// ```
+    pub fn expand_line_within(&self, line: &str, budget: usize) -> Option<(String, Vec<String>)> {
+        let mut used = Vec::new();
+        let expanded = self.expand_text(line, budget, &mut Vec::new(), &mut used)?;
+        Some((expanded, used))
+    }
+
+    /// Expands `line`, leaving the macros named in `expanding` unreplaced and
+    /// recording each substituted macro in `used`. Returns `None` once the
+    /// text exceeds `budget` bytes.
+    fn expand_text(
+        &self,
+        line: &str,
+        budget: usize,
+        expanding: &mut Vec<String>,
+        used: &mut Vec<String>,
+    ) -> Option<String> {
+        let mut expanded = String::with_capacity(line.len().min(budget));
+        let mut word = String::new();
+        let mut in_string = false;
+
+        for c in line.chars() {
+            if !in_string && is_macro_name_char(c) {
+                word.push(c);
+                continue;
+            }
+            self.flush_word(&mut word, &mut expanded, budget, expanding, used)?;
+            if c == '\'' {
+                in_string = !in_string;
+            }
+            expanded.push(c);
+            if expanded.len() > budget {
+                return None;
+            }
+        }
+        self.flush_word(&mut word, &mut expanded, budget, expanding, used)?;
+
+        Some(expanded)
+    }
+
+    fn flush_word(
+        &self,
+        word: &mut String,
+        expanded: &mut String,
+        budget: usize,
+        expanding: &mut Vec<String>,
+        used: &mut Vec<String>,
+    ) -> Option<()> {
+        if word.is_empty() {
+            return Some(());
+        }
+        match self.get(word) {
+            Some(definition) if definition.active && !expanding.contains(&definition.name) => {
+                debug!("MacroTable: expanding {}", definition.name);
+                used.push(definition.name.clone());
+                if self.rescan && definition.rescan {
+                    expanding.push(definition.name.clone());
+                    let remaining = budget.saturating_sub(expanded.len());
+                    let body = self.expand_text(&definition.body, remaining, expanding, used)?;
+                    expanded.push_str(&body);
+                    expanding.pop();
+                } else {
+                    expanded.push_str(&definition.body);
+                }
+            }
+            _ => expanded.push_str(word),
+        }
+        word.clear();
+        (expanded.len() <= budget).then_some(())
+    }
+
+    fn set_active(&mut self, name: &str, active: bool) -> Result<(), String> {
+        match self.macros.get_mut(&name.to_uppercase()) {
+            Some(definition) => {
+                definition.active = active;
+                Ok(())
+            }
+            None => Err(format!("Undefined macro: {}", name.to_uppercase())),
+        }
+    }
+}
+
+/// Returns `true` if `c` may appear in a macro name.
+fn is_macro_name_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '$' | '@' | '#')
+}
+
+/// Splits a macro body fragment at the `%ENDMACRO` directive, if present.
+///
+/// # Arguments
+/// - `text`: A fragment of a macro body (a whole line or the rest of one).
+///
+/// # Returns
+/// - `(String, bool)`: The body text before `%ENDMACRO` (trimmed), and whether
+///   `%ENDMACRO` was found.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::macro_expander::split_macro_body;
+/// assert_eq!(split_macro_body("'HELLO' %ENDMACRO;"), ("'HELLO'".to_string(), true));
+/// ```
+pub fn split_macro_body(text: &str) -> (String, bool) {
+    match text.to_ascii_uppercase().find("%ENDMACRO") {
+        Some(index) => (text[..index].trim().to_string(), true),
+        None => (text.trim().to_string(), false),
+    }
+}
 
 /// Expands a macro definition or usage within a given PL/I line or block of code.
 ///
@@ -62,9 +349,11 @@ use regex::Regex; // For future implementation of macro parsing (not yet in use)
 ///
 /// # Example
 /// ```rust
+/// # use pli_preprocessor::modules::macro_expander::expand_macro;
 /// let input = "%MACRO TEST; VALUE = 1; %ENDMACRO;";
 /// let result = expand_macro(input);
-/// assert_eq!(result, Some("Expanded macro output"));
+/// // Not implemented yet: nothing is expanded.
+/// assert_eq!(result, None);
 /// ```
 ///
 /// # Notes
@@ -93,8 +382,10 @@ pub fn expand_macro(input: &str) -> Option<String> {
 ///
 /// # Example
 /// ```rust
+/// # use pli_preprocessor::modules::macro_expander::validate_macro;
 /// let macro_def = "%MACRO TEST; VALUE = 1; %ENDMACRO;";
-/// assert!(validate_macro(macro_def));
+/// // Not implemented yet: every definition is rejected.
+/// assert!(!validate_macro(macro_def));
 /// ```
 pub fn validate_macro(macro_definition: &str) -> bool {
     // Placeholder: Log the validation attempt.
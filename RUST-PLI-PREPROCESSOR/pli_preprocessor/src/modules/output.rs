@@ -13,10 +13,24 @@
 // - Appends logs or debug messages to a designated log file.
 // - Ensures proper handling of file creation, opening, and closing.
 // - Handles errors gracefully during file operations.
+// - Abstracts the processed-output destination behind the `OutputSink` trait,
+//   so processing can target files, memory, or callbacks.
 //
 // USAGE:
 // - Use `write_line_to_file` to write a single line to an output file.
 // - Use `append_log_message` to add a log entry to a log file.
+// - Use `reconstruct_line` to rebuild source text from tokens.
+// - Use `apply_keyword_case` to normalize the case of PL/I keywords and
+//   directives in an output line, leaving identifiers as written.
+// - Use `format_listing` to produce a numbered source listing, which
+//   `%NOPRINT`/`%PRINT` switch off and on.
+// - Use `tokens_to_csv` to export tokenized source for spreadsheet analysis.
+// - Use `tokens_to_html` to highlight one line's tokens by category, and
+//   `source_to_html` for a standalone highlighted page of a whole source.
+// - Use `unified_diff` to preview how fresh output differs from an existing
+//   output file.
+// - Pass a `&mut dyn OutputSink` (a `File`, a `Vec<String>`, or a
+//   `CallbackSink`) to the pipeline to choose where output goes.
 //
 // AUTHOR: FirstLink Consulting Services (FLCS)
 // LICENSE: MIT License
@@ -28,9 +42,207 @@
 // IMPORTS
 ////////////////////////////////////////////////////////////////////////////////
 
+use crate::modules::directive::canonicalize;
+use crate::modules::include_handler::{VERBATIM_BEGIN, VERBATIM_END};
+use crate::modules::parser::split_statements;
+use crate::modules::tokenizer::{
+    strip_comments, tokenize_pli, tokenize_pli_preserve_ws, Token, TokenCategory,
+};
+use similar::{capture_diff_slices, Algorithm, DiffTag};
 use std::fs::{File, OpenOptions};
 use std::io::{self, Write};
 use std::path::Path;
+use std::str::FromStr;
+
+////////////////////////////////////////////////////////////////////////////////
+// CONSTANTS
+////////////////////////////////////////////////////////////////////////////////
+
+/// The header row written by `tokens_to_csv`.
+pub const TOKEN_CSV_HEADER: &str = "line,column,value,category,directive_category";
+
+/// The stylesheet of the page written by `source_to_html`.
+pub const HTML_STYLE: &str = ".tok-comment { color: #6a737d; font-style: italic; }
+.tok-directive { color: #d73a49; font-weight: bold; }
+.tok-literal { color: #032f62; }
+.tok-operator, .tok-separator { color: #005cc5; }
+.tok-unknown { color: #b31d28; text-decoration: underline wavy; }";
+
+/// The unchanged lines `unified_diff` shows around each change.
+pub const DIFF_CONTEXT_LINES: usize = 3;
+
+/// The PL/I statement keywords and attributes affected by `KeywordCase`.
+pub const PLI_KEYWORDS: &[&str] = &[
+    "ALLOCATE",
+    "AUTOMATIC",
+    "AUTO",
+    "BASED",
+    "BEGIN",
+    "BINARY",
+    "BIN",
+    "BIT",
+    "BY",
+    "CALL",
+    "CHARACTER",
+    "CHAR",
+    "CLOSE",
+    "DATA",
+    "DECIMAL",
+    "DEC",
+    "DECLARE",
+    "DCL",
+    "DO",
+    "EDIT",
+    "ELSE",
+    "END",
+    "ENTRY",
+    "EXTERNAL",
+    "EXT",
+    "FILE",
+    "FIXED",
+    "FLOAT",
+    "FREE",
+    "GET",
+    "GO",
+    "GOTO",
+    "IF",
+    "INITIAL",
+    "INIT",
+    "INTERNAL",
+    "ITERATE",
+    "LABEL",
+    "LEAVE",
+    "LIST",
+    "MAIN",
+    "ON",
+    "OPEN",
+    "OPTIONS",
+    "OTHERWISE",
+    "POINTER",
+    "PTR",
+    "PROCEDURE",
+    "PROC",
+    "PUT",
+    "READ",
+    "RETURN",
+    "RETURNS",
+    "SELECT",
+    "SIGNAL",
+    "SKIP",
+    "STATIC",
+    "STOP",
+    "THEN",
+    "TO",
+    "UNTIL",
+    "VARYING",
+    "VAR",
+    "WHEN",
+    "WHILE",
+    "WRITE",
+];
+
+////////////////////////////////////////////////////////////////////////////////
+// PUBLIC STRUCTURES
+////////////////////////////////////////////////////////////////////////////////
+
+/// How the output spells PL/I keywords and preprocessor directives.
+///
+/// Identifiers and string literals always keep the author's spelling.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeywordCase {
+    Upper,
+    Lower,
+    #[default]
+    Preserve,
+}
+
+/// Parses `upper`, `lower`, or `preserve`, ignoring ASCII case.
+impl FromStr for KeywordCase {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "upper" => Ok(KeywordCase::Upper),
+            "lower" => Ok(KeywordCase::Lower),
+            "preserve" => Ok(KeywordCase::Preserve),
+            _ => Err(format!("Unknown keyword case: {}", s)),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// PRIVATE STRUCTURES
+////////////////////////////////////////////////////////////////////////////////
+
+/// One step of a line diff: a line both texts share, or one only the old or
+/// only the new text has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DiffEdit<'a> {
+    Keep(&'a str),
+    Delete(&'a str),
+    Insert(&'a str),
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// OUTPUT SINKS
+////////////////////////////////////////////////////////////////////////////////
+
+/// A destination for processed output lines.
+///
+/// Implementations receive one line at a time, without a trailing newline.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::output::OutputSink;
+/// let mut lines: Vec<String> = Vec::new();
+/// lines.write_line("DECLARE X FIXED;").unwrap();
+/// assert_eq!(lines, vec!["DECLARE X FIXED;"]);
+/// ```
+pub trait OutputSink {
+    /// Writes a single processed line to the sink.
+    fn write_line(&mut self, line: &str) -> io::Result<()>;
+}
+
+/// Writes each line to the file followed by a newline.
+impl OutputSink for File {
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        writeln!(self, "{}", line)
+    }
+}
+
+/// Collects lines in memory, which is convenient for tests and embedding.
+impl OutputSink for Vec<String> {
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        self.push(line.to_string());
+        Ok(())
+    }
+}
+
+/// Wraps a closure so it can be used as an `OutputSink`, e.g. to stream
+/// processed lines to a GUI.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::output::{CallbackSink, OutputSink};
+/// let mut count = 0;
+/// let mut sink = CallbackSink(|_line: &str| {
+///     count += 1;
+///     Ok(())
+/// });
+/// sink.write_line("A = 1;").unwrap();
+/// ```
+pub struct CallbackSink<F>(pub F)
+where
+    F: FnMut(&str) -> io::Result<()>;
+
+impl<F> OutputSink for CallbackSink<F>
+where
+    F: FnMut(&str) -> io::Result<()>,
+{
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        (self.0)(line)
+    }
+}
 
 ////////////////////////////////////////////////////////////////////////////////
 // PUBLIC FUNCTIONS
@@ -47,7 +259,9 @@ use std::path::Path;
 ///
 /// # Example
 /// ```rust
-/// write_line_to_file("/tmp/output.txt", "Processed line").unwrap();
+/// # use pli_preprocessor::modules::output::write_line_to_file;
+/// let path = std::env::temp_dir().join("output.txt");
+/// write_line_to_file(&path, "Processed line").unwrap();
 /// ```
 pub fn write_line_to_file(file_path: &Path, line: &str) -> Result<(), String> {
     let mut file = File::create(file_path)
@@ -67,7 +281,9 @@ pub fn write_line_to_file(file_path: &Path, line: &str) -> Result<(), String> {
 ///
 /// # Example
 /// ```rust
-/// append_log_message("/tmp/preprocessor.log", "Log entry").unwrap();
+/// # use pli_preprocessor::modules::output::append_log_message;
+/// let path = std::env::temp_dir().join("preprocessor.log");
+/// append_log_message(&path, "Log entry").unwrap();
 /// ```
 pub fn append_log_message(log_path: &Path, message: &str) -> Result<(), String> {
     let mut file = OpenOptions::new()
@@ -83,3 +299,370 @@ pub fn append_log_message(log_path: &Path, message: &str) -> Result<(), String>
         )
     })
 }
+
+/// Rebuilds a source line from its tokens.
+///
+/// Tokens contribute their `original` text, so the output of
+/// `tokenize_pli_preserve_ws` reconstructs the input exactly. For tokens from
+/// `tokenize_pli`, which drops whitespace, gaps are padded with spaces up to
+/// each token's recorded column.
+///
+/// # Arguments
+/// - `tokens`: The tokens of a single line, in source order.
+///
+/// # Returns
+/// - `String`: The reconstructed line.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::output::reconstruct_line;
+/// # use pli_preprocessor::modules::tokenizer::tokenize_pli_preserve_ws;
+/// let line = "%IF  DEBUG %THEN;";
+/// assert_eq!(reconstruct_line(&tokenize_pli_preserve_ws(line)), line);
+/// ```
+pub fn reconstruct_line(tokens: &[Token]) -> String {
+    let mut line = String::new();
+    let mut width = 0;
+    for token in tokens {
+        if token.column > width + 1 {
+            line.push_str(&" ".repeat(token.column - 1 - width));
+            width = token.column - 1;
+        }
+        line.push_str(&token.original);
+        width += token.original.chars().count();
+    }
+    line
+}
+
+/// Rewrites the PL/I keywords and directives of a line in the requested case.
+///
+/// A keyword is an identifier listed in `PLI_KEYWORDS`; every `%` directive
+/// counts too. All other text, including spacing, is left unchanged.
+///
+/// # Arguments
+/// - `line`: The output line.
+/// - `case`: The policy to apply; `Preserve` returns the line as is.
+///
+/// # Returns
+/// - `String`: The rewritten line.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::output::{apply_keyword_case, KeywordCase};
+/// let line = apply_keyword_case("dcl MyVar fixed;", KeywordCase::Upper);
+/// assert_eq!(line, "DCL MyVar FIXED;");
+/// ```
+pub fn apply_keyword_case(line: &str, case: KeywordCase) -> String {
+    if case == KeywordCase::Preserve {
+        return line.to_string();
+    }
+    let mut tokens = tokenize_pli_preserve_ws(line);
+    for token in &mut tokens {
+        let keyword = token.category == TokenCategory::Directive
+            || (token.category == TokenCategory::Identifier
+                && PLI_KEYWORDS.contains(&token.value.as_str()));
+        if keyword {
+            token.original = match case {
+                KeywordCase::Upper => token.original.to_uppercase(),
+                _ => token.original.to_lowercase(),
+            };
+        }
+    }
+    reconstruct_line(&tokens)
+}
+
+/// Renders a numbered listing of a source, honoring `%NOPRINT` and `%PRINT`.
+///
+/// Each listed line is its 1-based line number, right-aligned in five columns,
+/// a space, and the line as written. A line holding `%NOPRINT` stops the
+/// listing and one holding `%PRINT` resumes it; those lines themselves are
+/// never listed. Only the listing is affected: the processed output keeps
+/// every line. The `VERBATIM_BEGIN` and `VERBATIM_END` lines bracketing
+/// `%XINCLUDE`d content are not listed either, but keep their numbers.
+///
+/// # Arguments
+/// - `source`: The source text, with includes already inlined if they should
+///   be listed too.
+///
+/// # Returns
+/// - `String`: The listing, each line ending in a newline.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::output::format_listing;
+/// let listing = format_listing("A = 1;\n%NOPRINT;\nB = 2;\n%PRINT;\nC = 3;\n");
+/// assert_eq!(listing, "    1 A = 1;\n    5 C = 3;\n");
+/// ```
+pub fn format_listing(source: &str) -> String {
+    let mut listing = String::new();
+    let mut printing = true;
+    for (index, line) in source.lines().enumerate() {
+        if line == VERBATIM_BEGIN || line == VERBATIM_END {
+            continue;
+        }
+        let mut control = false;
+        for statement in split_statements(&strip_comments(tokenize_pli(line))) {
+            match statement
+                .first()
+                .and_then(|t| canonicalize(&t.value))
+                .as_deref()
+            {
+                Some("%PRINT") => printing = true,
+                Some("%NOPRINT") => printing = false,
+                _ => continue,
+            }
+            control = true;
+        }
+        if printing && !control {
+            listing.push_str(&format!("{:>5} {}\n", index + 1, line));
+        }
+    }
+    listing
+}
+
+/// Renders tokenized source as CSV, one row per token.
+///
+/// Tokens do not record their line, so the tokens are given line by line, as
+/// `tokenize_pli` produces them. The first row is `TOKEN_CSV_HEADER`. The
+/// category columns hold the names from `TokenCategory::as_str` and
+/// `DirectiveCategory::as_str`; `directive_category` is empty for tokens that
+/// are not directives. A value containing a comma, a double quote, or a line
+/// break is enclosed in double quotes, with its own double quotes doubled.
+///
+/// # Arguments
+/// - `lines`: The tokens of each source line; the first is line 1.
+///
+/// # Returns
+/// - `String`: The CSV text, each row ending in a newline.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::output::tokens_to_csv;
+/// # use pli_preprocessor::modules::tokenizer::tokenize_pli;
+/// let csv = tokens_to_csv(&[tokenize_pli("%IF A %THEN;")]);
+/// assert!(csv.contains("1,1,%IF,Directive,ControlFlow\n"));
+/// ```
+pub fn tokens_to_csv(lines: &[Vec<Token>]) -> String {
+    let mut csv = format!("{}\n", TOKEN_CSV_HEADER);
+    for (index, tokens) in lines.iter().enumerate() {
+        for token in tokens {
+            csv.push_str(&format!(
+                "{},{},{},{},{}\n",
+                index + 1,
+                token.column,
+                csv_field(&token.value),
+                token.category.as_str(),
+                token.directive_category.as_ref().map_or("", |c| c.as_str())
+            ));
+        }
+    }
+    csv
+}
+
+/// Renders one line's tokens as HTML, each in a span classed by category.
+///
+/// Each token becomes `<span class="tok-<category>">text</span>`, where
+/// `<category>` is the lowercase `TokenCategory::as_str` name (so directives
+/// get `tok-directive`). The text is the token's `original` spelling with
+/// `&`, `<`, `>`, and `"` escaped. Whitespace tokens, as produced by
+/// `tokenize_pli_preserve_ws`, are copied without a span.
+///
+/// # Arguments
+/// - `tokens`: The tokens of a single line, in source order.
+///
+/// # Returns
+/// - `String`: The HTML fragment, without a trailing newline.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::output::tokens_to_html;
+/// # use pli_preprocessor::modules::tokenizer::tokenize_pli_preserve_ws;
+/// let html = tokens_to_html(&tokenize_pli_preserve_ws("%IF A<B"));
+/// assert!(html.starts_with("<span class=\"tok-directive\">%IF</span> "));
+/// assert!(html.contains("&lt;"));
+/// ```
+pub fn tokens_to_html(tokens: &[Token]) -> String {
+    let mut html = String::new();
+    for token in tokens {
+        let text = html_escape(&token.original);
+        if token.category == TokenCategory::Whitespace {
+            html.push_str(&text);
+        } else {
+            html.push_str(&format!(
+                "<span class=\"tok-{}\">{}</span>",
+                token.category.as_str().to_lowercase(),
+                text
+            ));
+        }
+    }
+    html
+}
+
+/// Renders a whole source as a standalone HTML page with highlighted tokens.
+///
+/// Each line is tokenized with `tokenize_pli_preserve_ws`, so spacing is kept,
+/// and rendered with `tokens_to_html` inside a `<pre class="pli">` block. The
+/// page styles the token classes with `HTML_STYLE`.
+///
+/// # Arguments
+/// - `source`: The source text.
+///
+/// # Returns
+/// - `String`: The HTML page, ending in a newline.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::output::source_to_html;
+/// let page = source_to_html("%SET X = 1;\n");
+/// assert!(page.contains("<span class=\"tok-directive\">%SET</span>"));
+/// ```
+pub fn source_to_html(source: &str) -> String {
+    let mut html = format!(
+        concat!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n",
+            "<style>\n{}\n</style>\n</head>\n<body>\n<pre class=\"pli\">"
+        ),
+        HTML_STYLE
+    );
+    for line in source.lines() {
+        html.push_str(&tokens_to_html(&tokenize_pli_preserve_ws(line)));
+        html.push('\n');
+    }
+    html.push_str("</pre>\n</body>\n</html>\n");
+    html
+}
+
+/// Renders the line differences between `old` and `new` as a unified diff.
+///
+/// Lines are matched with Myers' diff algorithm, which keeps large, mostly
+/// unchanged texts cheap to compare, and each hunk
+/// shows up to `DIFF_CONTEXT_LINES` unchanged lines around its changes; hunks
+/// whose context would overlap are merged. Line endings are not compared.
+///
+/// # Arguments
+/// - `old`: The current text, e.g. the existing output file.
+/// - `new`: The replacement text, e.g. the freshly processed output.
+/// - `old_name`: The label of the `---` header line.
+/// - `new_name`: The label of the `+++` header line.
+///
+/// # Returns
+/// - `String`: The diff, or an empty string if the texts have the same lines.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::output::unified_diff;
+/// let diff = unified_diff("A;\nB;\n", "A;\nC;\n", "old.pli", "new.pli");
+/// assert_eq!(diff, "--- old.pli\n+++ new.pli\n@@ -1,2 +1,2 @@\n A;\n-B;\n+C;\n");
+/// ```
+pub fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str) -> String {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let edits = diff_lines(&old_lines, &new_lines);
+    let changes: Vec<usize> = edits
+        .iter()
+        .enumerate()
+        .filter(|(_, edit)| !matches!(edit, DiffEdit::Keep(_)))
+        .map(|(index, _)| index)
+        .collect();
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    // The old and new line numbers (0-based) at which each edit applies.
+    let mut positions = Vec::with_capacity(edits.len());
+    let (mut old_line, mut new_line) = (0, 0);
+    for edit in &edits {
+        positions.push((old_line, new_line));
+        match edit {
+            DiffEdit::Keep(_) => {
+                old_line += 1;
+                new_line += 1;
+            }
+            DiffEdit::Delete(_) => old_line += 1,
+            DiffEdit::Insert(_) => new_line += 1,
+        }
+    }
+
+    let mut text = format!("--- {}\n+++ {}\n", old_name, new_name);
+    let mut first = 0;
+    while first < changes.len() {
+        // Extend the hunk while the next change is close enough to share context.
+        let mut last = first;
+        while last + 1 < changes.len()
+            && changes[last + 1] - changes[last] <= 2 * DIFF_CONTEXT_LINES + 1
+        {
+            last += 1;
+        }
+        let start = changes[first].saturating_sub(DIFF_CONTEXT_LINES);
+        let end = (changes[last] + 1 + DIFF_CONTEXT_LINES).min(edits.len());
+        let hunk = &edits[start..end];
+
+        let old_count = hunk
+            .iter()
+            .filter(|edit| !matches!(edit, DiffEdit::Insert(_)))
+            .count();
+        let new_count = hunk
+            .iter()
+            .filter(|edit| !matches!(edit, DiffEdit::Delete(_)))
+            .count();
+        // An empty range is numbered after the line it follows.
+        let (old_start, new_start) = positions[start];
+        text.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            old_start + usize::from(old_count > 0),
+            old_count,
+            new_start + usize::from(new_count > 0),
+            new_count
+        ));
+        for edit in hunk {
+            let (marker, line) = match edit {
+                DiffEdit::Keep(line) => (' ', line),
+                DiffEdit::Delete(line) => ('-', line),
+                DiffEdit::Insert(line) => ('+', line),
+            };
+            text.push(marker);
+            text.push_str(line);
+            text.push('\n');
+        }
+        first = last + 1;
+    }
+    text
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// PRIVATE FUNCTIONS
+////////////////////////////////////////////////////////////////////////////////
+
+/// Lists the edits turning `old` into `new` with Myers' algorithm, which
+/// skips their common prefix and suffix and runs in linear space; a replaced
+/// run is deleted before its replacement is inserted.
+fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<DiffEdit<'a>> {
+    let mut edits = Vec::with_capacity(old.len().max(new.len()));
+    for op in capture_diff_slices(Algorithm::Myers, old, new) {
+        let (_, old_range, new_range) = op.as_tag_tuple();
+        if op.tag() == DiffTag::Equal {
+            edits.extend(old[old_range].iter().map(|line| DiffEdit::Keep(line)));
+            continue;
+        }
+        edits.extend(old[old_range].iter().map(|line| DiffEdit::Delete(line)));
+        edits.extend(new[new_range].iter().map(|line| DiffEdit::Insert(line)));
+    }
+    edits
+}
+
+/// Escapes the characters HTML gives a meaning in text and attributes.
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Quotes a CSV field when it contains a comma, a double quote, or a line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -16,6 +16,14 @@
 //
 // USAGE:
 // - Use `parse_line` to tokenize and categorize a single line of code.
+// - Use `classify_equals` to tell assignment `=` from comparison `=`.
+// - Use `split_statements` to split a tokenized line into its statements.
+// - Use `parse_statement` to separate a statement's `LABEL:` prefix from its
+//   body.
+// - Use `parse_program` to group a source's statements into `DO`/`END` blocks;
+//   every statement and block carries the `Span` of source it covers.
+// - Use `format_ast_tree` or `format_ast_json` to print that tree, as
+//   `--dump-ast` does.
 // - Extend `parse_source` for processing entire files.
 //
 // AUTHOR: FirstLink Consulting Services (FLCS)
@@ -28,7 +36,135 @@
 // IMPORTS
 ////////////////////////////////////////////////////////////////////////////////
 
+use crate::modules::error::PreprocessorError;
+use crate::modules::evaluator::merge_operator_tokens;
+use crate::modules::tokenizer::{tokenize_pli, Token, TokenCategory};
+use serde::Serialize;
 use std::collections::HashMap;
+use std::fmt;
+
+////////////////////////////////////////////////////////////////////////////////
+// PUBLIC STRUCTURES
+////////////////////////////////////////////////////////////////////////////////
+
+/// A 1-based line and column in the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    /// Creates a position from a 1-based line and column.
+    pub fn new(line: usize, column: usize) -> Self {
+        Self { line, column }
+    }
+}
+
+/// Displays as `line:column`.
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// A range of source text.
+///
+/// `start` is the position of the first character and `end` the position just
+/// past the last one, so a one-character token at column 5 spans 5 to 6.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    /// Creates a span from its start and end positions.
+    pub fn new(start: Position, end: Position) -> Self {
+        Self { start, end }
+    }
+
+    /// Returns the span of a token found on the given line.
+    pub fn of_token(token: &Token, line: usize) -> Self {
+        let width = token.original.chars().count();
+        Self::new(
+            Position::new(line, token.column),
+            Position::new(line, token.column + width),
+        )
+    }
+
+    /// Returns the smallest span covering both `self` and `other`.
+    pub fn to(self, other: Span) -> Self {
+        Self::new(self.start.min(other.start), self.end.max(other.end))
+    }
+}
+
+/// Displays as `start-end`, for example `1:1-3:5`.
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
+/// A statement with its optional label prefix, as in `LOOP: DO I = 1 TO 10;`.
+///
+/// # Fields
+/// - `label`: The uppercase label name, if the statement starts with `IDENT :`.
+/// - `tokens`: The statement's tokens after the label and its `:`.
+/// - `token_spans`: The span of each entry of `tokens`.
+/// - `span`: The span of the whole statement, label included.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Statement {
+    pub label: Option<String>,
+    pub tokens: Vec<Token>,
+    pub token_spans: Vec<Span>,
+    pub span: Span,
+}
+
+/// A block opened by `DO`, `BEGIN`, `SELECT`, or `PROCEDURE` and closed by the
+/// matching `END`. A `DO` or `BEGIN` after `THEN`, `ELSE`, `OTHERWISE`, or a
+/// `WHEN (...)` list also opens a block.
+///
+/// # Fields
+/// - `header`: The statement that opens the block.
+/// - `body`: The statements and nested blocks between the header and `END`.
+/// - `end`: The closing `END` statement.
+/// - `span`: The span from the start of `header` to the end of `end`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Block {
+    pub header: Statement,
+    pub body: Vec<AstNode>,
+    pub end: Statement,
+    pub span: Span,
+}
+
+/// A node of the tree built by `parse_program`.
+///
+/// Nodes serialize with a `kind` field of `Statement` or `Block` next to the
+/// node's own fields, which is the shape `--dump-ast=json` prints.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind")]
+pub enum AstNode {
+    Statement(Statement),
+    Block(Block),
+}
+
+impl AstNode {
+    /// Returns the span the node covers.
+    pub fn span(&self) -> Span {
+        match self {
+            AstNode::Statement(statement) => statement.span,
+            AstNode::Block(block) => block.span,
+        }
+    }
+}
+
+/// The meaning of an `=` token, which PL/I uses for both assignment and equality.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EqualsRole {
+    Assignment,
+    Comparison,
+}
 
 ////////////////////////////////////////////////////////////////////////////////
 // PUBLIC FUNCTIONS
@@ -36,6 +172,10 @@ use std::collections::HashMap;
 
 /// Parses a single line of PL/I source code into tokens.
 ///
+/// This delegates to `tokenizer::tokenize_pli` so both entry points split
+/// lines identically; tokens keep their original spelling (identifiers are not
+/// uppercased).
+///
 /// # Arguments
 /// - `line`: A `&str` representing the source code line.
 ///
@@ -44,75 +184,15 @@ use std::collections::HashMap;
 ///
 /// # Example
 /// ```rust
+/// # use pli_preprocessor::modules::parser::parse_line;
 /// let tokens = parse_line("DECLARE X FIXED;");
 /// assert_eq!(tokens, vec!["DECLARE", "X", "FIXED", ";"]);
 /// ```
 pub fn parse_line(line: &str) -> Vec<String> {
-    let mut tokens = Vec::new();
-    let mut buffer = String::new();
-    let mut inside_quotes = false;
-
-    println!("Parsing line: {:?}", line); // Debug: Show the input line
-
-    for ch in line.chars() {
-        println!("Processing character: {:?}", ch); // Debug: Show each character
-
-        if ch == '\'' {
-            println!("Quote encountered. Inside quotes: {}", inside_quotes); // Debug: Quote state
-            if inside_quotes {
-                buffer.push(ch); // Add the closing quote
-                tokens.push(buffer.clone());
-                println!("Token added (quoted): {:?}", buffer); // Debug: Quoted token
-                buffer.clear();
-            } else {
-                if !buffer.is_empty() {
-                    tokens.push(buffer.clone());
-                    println!("Token added (before quote): {:?}", buffer); // Debug: Token before quote
-                    buffer.clear();
-                }
-                buffer.push(ch); // Start a new quoted token
-            }
-            inside_quotes = !inside_quotes;
-        } else if inside_quotes {
-            buffer.push(ch);
-        } else if ch.is_whitespace() {
-            println!("Whitespace encountered. Current buffer: {:?}", buffer); // Debug: Whitespace
-            if !buffer.is_empty() {
-                tokens.push(buffer.clone());
-                println!("Token added (whitespace): {:?}", buffer); // Debug: Token after whitespace
-                buffer.clear();
-            }
-        } else if ch == '%' && buffer.is_empty() {
-            buffer.push(ch); // Start a directive token
-        } else if buffer.starts_with('%') {
-            buffer.push(ch);
-            if ch.is_whitespace() || ch.is_ascii_punctuation() {
-                tokens.push(buffer.trim().to_string());
-                println!("Token added (directive): {:?}", buffer.trim()); // Debug: Directive token
-                buffer.clear();
-            }
-        } else if ch.is_ascii_punctuation() {
-            println!("Punctuation encountered: {:?}", ch); // Debug: Punctuation
-            if !buffer.is_empty() {
-                tokens.push(buffer.clone());
-                println!("Token added (before punctuation): {:?}", buffer); // Debug: Token before punctuation
-                buffer.clear();
-            }
-            tokens.push(ch.to_string());
-            println!("Token added (punctuation): {:?}", ch); // Debug: Punctuation token
-        } else {
-            buffer.push(ch);
-        }
-    }
-
-    if !buffer.is_empty() {
-        println!("Final token added: {:?}", buffer); // Debug: Final token
-        tokens.push(buffer.clone());
-        buffer.clear(); // Clear the buffer
-    }
-
-    println!("Tokens generated: {:?}", tokens); // Debug: Final token list
-    tokens
+    tokenize_pli(line)
+        .into_iter()
+        .map(|token| token.original)
+        .collect()
 }
 
 /// Parses the entire PL/I source code into structured tokens.
@@ -127,6 +207,8 @@ pub fn parse_line(line: &str) -> Vec<String> {
 ///
 /// # Example
 /// ```rust
+/// # use pli_preprocessor::modules::parser::parse_source;
+/// # use std::collections::HashMap;
 /// let mut directives = HashMap::new();
 /// let result = parse_source("DECLARE X FIXED;\n%INCLUDE 'example.pli';", &mut directives);
 /// assert!(result.is_ok());
@@ -148,3 +230,400 @@ pub fn parse_source(
 
     Ok(tokenized_lines)
 }
+
+/// Determines whether each `=` in a statement assigns or compares.
+///
+/// The first top-level `=` of a `%SET`, `%DECLARE`/`%DCL`, or plain assignment
+/// statement (one starting with an identifier) is an assignment; every other
+/// `=`, including all of those in a `%IF` condition, is a comparison. The `=`
+/// of two-character operators such as `<=` and `^=` is not reported. Token
+/// categories are left unchanged.
+///
+/// # Arguments
+/// - `tokens`: The tokens of one statement, as produced by the tokenizer.
+///
+/// # Returns
+/// - `Vec<(usize, EqualsRole)>`: The column of each `=` with its role.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::parser::{classify_equals, EqualsRole};
+/// # use pli_preprocessor::modules::tokenizer::tokenize_pli;
+/// let roles = classify_equals(&tokenize_pli("%SET X = 1;"));
+/// assert_eq!(roles, vec![(8, EqualsRole::Assignment)]);
+/// ```
+pub fn classify_equals(tokens: &[Token]) -> Vec<(usize, EqualsRole)> {
+    let merged = merge_operator_tokens(&tokens.iter().collect::<Vec<_>>());
+    let assigns = match merged.first() {
+        Some(first) => match first.value.as_str() {
+            "%SET" | "%DECLARE" | "%DCL" => true,
+            _ => first.category == TokenCategory::Identifier,
+        },
+        None => false,
+    };
+
+    let mut roles = Vec::new();
+    let mut assignment_seen = !assigns;
+    let mut depth = 0;
+    for token in &merged {
+        match token.value.as_str() {
+            "(" => depth += 1,
+            ")" => depth -= 1,
+            "=" => {
+                let role = if !assignment_seen && depth == 0 {
+                    assignment_seen = true;
+                    EqualsRole::Assignment
+                } else {
+                    EqualsRole::Comparison
+                };
+                roles.push((token.column, role));
+            }
+            _ => {}
+        }
+    }
+    roles
+}
+
+/// Splits a tokenized line into statements at top-level `;` separators.
+///
+/// Each statement keeps its terminating `;`; trailing tokens without one form
+/// a final, unterminated statement. A `;` inside a string literal is part of
+/// the literal token, and one inside parentheses does not end the statement.
+///
+/// The `;` after `%ENDIF` and `%END` is optional: when either directive starts
+/// a statement and is not followed by a `;` (comments aside), it forms a
+/// statement of its own, so `%ENDIF B = 2;` splits like `%ENDIF; B = 2;`.
+/// `%END label;` names the block it closes and stays one statement.
+///
+/// # Arguments
+/// - `tokens`: The tokens of one line, as produced by the tokenizer.
+///
+/// # Returns
+/// - `Vec<Vec<Token>>`: The statements in source order.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::parser::split_statements;
+/// # use pli_preprocessor::modules::tokenizer::tokenize_pli;
+/// let statements = split_statements(&tokenize_pli("A = 1; B = 2;"));
+/// assert_eq!(statements.len(), 2);
+/// ```
+pub fn split_statements(tokens: &[Token]) -> Vec<Vec<Token>> {
+    let mut statements = Vec::new();
+    let mut current = Vec::new();
+    let mut depth = 0;
+    for (index, token) in tokens.iter().enumerate() {
+        match token.value.as_str() {
+            "(" => depth += 1,
+            ")" => depth -= 1,
+            _ => {}
+        }
+        current.push(token.clone());
+        if depth <= 0 && token.category == TokenCategory::Separator && token.value == ";" {
+            statements.push(std::mem::take(&mut current));
+        } else if current.len() == 1 && token.is_one_of(&["%ENDIF", "%END"]) {
+            let mut rest = tokens[index + 1..]
+                .iter()
+                .filter(|t| t.category != TokenCategory::Comment);
+            let next = rest.next();
+            let labelled = token.value == "%END"
+                && next.is_some_and(|t| t.category == TokenCategory::Identifier)
+                && rest.next().is_some_and(|t| t.value == ";");
+            if next.is_some_and(|t| t.value != ";") && !labelled {
+                statements.push(std::mem::take(&mut current));
+            }
+        }
+    }
+    if !current.is_empty() {
+        statements.push(current);
+    }
+    statements
+}
+
+/// Separates a leading `LABEL:` from the rest of a statement.
+///
+/// Only an identifier immediately followed by a `:` separator is a label; a
+/// statement without one is returned whole with `label` set to `None`. Spans
+/// are placed on line 1; use `parse_statement_at` for a statement elsewhere.
+///
+/// # Arguments
+/// - `tokens`: The tokens of one statement, as split by `split_statements`.
+///
+/// # Returns
+/// - `Statement`: The label, if any, and the remaining tokens.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::parser::parse_statement;
+/// # use pli_preprocessor::modules::tokenizer::tokenize_pli;
+/// let statement = parse_statement(&tokenize_pli("LOOP: DO I = 1 TO 10;"));
+/// assert_eq!(statement.label.as_deref(), Some("LOOP"));
+/// assert_eq!(statement.tokens[0].value, "DO");
+/// ```
+pub fn parse_statement(tokens: &[Token]) -> Statement {
+    parse_statement_at(tokens, 1)
+}
+
+/// Like `parse_statement`, for a statement found on the given 1-based line.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::parser::{parse_statement_at, Position};
+/// # use pli_preprocessor::modules::tokenizer::tokenize_pli;
+/// let statement = parse_statement_at(&tokenize_pli("  X = 1;"), 4);
+/// assert_eq!(statement.span.start, Position::new(4, 3));
+/// assert_eq!(statement.span.end, Position::new(4, 9));
+/// ```
+pub fn parse_statement_at(tokens: &[Token], line: usize) -> Statement {
+    let located: Vec<(usize, Token)> = tokens.iter().map(|t| (line, t.clone())).collect();
+    build_statement(located)
+}
+
+/// Parses a source into statements, grouping `DO`/`END` blocks into a tree.
+///
+/// Statements may span lines; each ends at a top-level `;`. `DO`, `BEGIN`,
+/// `SELECT`, and `PROCEDURE`/`PROC` statements open a block that the next
+/// unmatched `END` closes. Preprocessor directives such as `%DO` are ordinary
+/// statements here.
+///
+/// # Arguments
+/// - `source`: The source text.
+///
+/// # Returns
+/// - `Result<Vec<AstNode>, PreprocessorError>`: The top-level nodes, or a
+///   `Parser` error for an `END` without a block or a block without an `END`.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::parser::{parse_program, Position};
+/// let nodes = parse_program("DO I = 1 TO 2;\n  X = I;\nEND;").unwrap();
+/// assert_eq!(nodes[0].span().start, Position::new(1, 1));
+/// assert_eq!(nodes[0].span().end, Position::new(3, 5));
+/// ```
+pub fn parse_program(source: &str) -> Result<Vec<AstNode>, PreprocessorError> {
+    let mut statements = Vec::new();
+    let mut current: Vec<(usize, Token)> = Vec::new();
+    let mut depth = 0;
+    for (index, line) in source.lines().enumerate() {
+        for token in tokenize_pli(line) {
+            match token.value.as_str() {
+                "(" => depth += 1,
+                ")" => depth -= 1,
+                _ => {}
+            }
+            let ends =
+                depth <= 0 && token.category == TokenCategory::Separator && token.value == ";";
+            current.push((index + 1, token));
+            if ends {
+                statements.push(build_statement(std::mem::take(&mut current)));
+                depth = 0;
+            }
+        }
+    }
+    if !current.is_empty() {
+        statements.push(build_statement(current));
+    }
+
+    // Each open block collects its header and the nodes parsed so far.
+    let mut stack: Vec<(Statement, Vec<AstNode>)> = Vec::new();
+    let mut nodes = Vec::new();
+    for statement in statements {
+        if block_keyword(&statement).is_some() {
+            stack.push((statement, Vec::new()));
+            continue;
+        }
+        match statement.tokens.first().map(|t| t.value.as_str()) {
+            Some("END") => {
+                let (header, body) = stack.pop().ok_or_else(|| PreprocessorError::Parser {
+                    code: "P001",
+                    message: format!(
+                        "END without an open block at line {}",
+                        statement.span.start.line
+                    ),
+                })?;
+                let block = Block {
+                    span: header.span.to(statement.span),
+                    header,
+                    body,
+                    end: statement,
+                };
+                match stack.last_mut() {
+                    Some((_, body)) => body.push(AstNode::Block(block)),
+                    None => nodes.push(AstNode::Block(block)),
+                }
+            }
+            _ => match stack.last_mut() {
+                Some((_, body)) => body.push(AstNode::Statement(statement)),
+                None => nodes.push(AstNode::Statement(statement)),
+            },
+        }
+    }
+    if let Some((header, _)) = stack.last() {
+        return Err(PreprocessorError::Parser {
+            code: "P002",
+            message: format!(
+                "Unterminated {} block at line {}",
+                block_keyword(header).unwrap_or_default(),
+                header.span.start.line
+            ),
+        });
+    }
+    Ok(nodes)
+}
+
+/// Renders a tree from `parse_program` as indented text, one line per node.
+///
+/// Each line shows the node kind, its span, and the statement text; a block
+/// shows its header, then its body and `END` indented beneath it.
+///
+/// # Arguments
+/// - `nodes`: The nodes returned by `parse_program`.
+///
+/// # Returns
+/// - `String`: The rendered tree, ending with a newline.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::parser::{format_ast_tree, parse_program};
+/// let nodes = parse_program("DO;\n  X = 1;\nEND;").unwrap();
+/// assert_eq!(
+///     format_ast_tree(&nodes),
+///     "Block 1:1-3:5 DO;\n  Statement 2:3-2:9 X = 1;\n  Statement 3:1-3:5 END;\n"
+/// );
+/// ```
+pub fn format_ast_tree(nodes: &[AstNode]) -> String {
+    let mut out = String::new();
+    for node in nodes {
+        write_ast_node(node, 0, &mut out);
+    }
+    out
+}
+
+/// Renders a tree from `parse_program` as a JSON array of nodes.
+///
+/// Each node is an object with a `kind` of `Statement` or `Block`; see
+/// `AstNode` for the fields.
+///
+/// # Arguments
+/// - `nodes`: The nodes returned by `parse_program`.
+///
+/// # Returns
+/// - `Result<String, String>`: The pretty-printed JSON, or an error message if
+///   serialization fails.
+pub fn format_ast_json(nodes: &[AstNode]) -> Result<String, String> {
+    serde_json::to_string_pretty(nodes).map_err(|e| e.to_string())
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// PRIVATE FUNCTIONS
+////////////////////////////////////////////////////////////////////////////////
+
+/// Returns the keyword that makes a statement open a block, if any.
+///
+/// `DO`, `BEGIN`, `SELECT`, and `PROCEDURE`/`PROC` open a block at the start
+/// of a statement; `DO` and `BEGIN` also do after `THEN`, `ELSE`, `OTHERWISE`,
+/// or the closing `)` of a `WHEN` list, and `BEGIN` ends an `ON` statement
+/// whose on-unit is a block (`ON ENDFILE(SYSIN) BEGIN;`).
+fn block_keyword(statement: &Statement) -> Option<&str> {
+    let values: Vec<&str> = statement.tokens.iter().map(|t| t.value.as_str()).collect();
+    match values.first() {
+        Some(&keyword @ ("DO" | "BEGIN" | "SELECT" | "PROCEDURE" | "PROC")) => {
+            return Some(keyword)
+        }
+        Some(_) => {}
+        None => return None,
+    }
+    let body = match values.last() {
+        Some(&";") => &values[..values.len() - 1],
+        _ => &values[..],
+    };
+    if values[0] == "ON" && body.last() == Some(&"BEGIN") {
+        return Some("BEGIN");
+    }
+    let is_when = values[0] == "WHEN";
+    values.windows(2).find_map(|pair| match pair {
+        [before, keyword @ ("DO" | "BEGIN")]
+            if matches!(*before, "THEN" | "ELSE" | "OTHERWISE") || (is_when && *before == ")") =>
+        {
+            Some(*keyword)
+        }
+        _ => None,
+    })
+}
+
+/// Appends `node` to `out` as indented tree lines for `format_ast_tree`.
+fn write_ast_node(node: &AstNode, depth: usize, out: &mut String) {
+    let indent = "  ".repeat(depth);
+    match node {
+        AstNode::Statement(statement) => {
+            out.push_str(&format!(
+                "{}Statement {} {}\n",
+                indent,
+                statement.span,
+                statement_text(statement)
+            ));
+        }
+        AstNode::Block(block) => {
+            out.push_str(&format!(
+                "{}Block {} {}\n",
+                indent,
+                block.span,
+                statement_text(&block.header)
+            ));
+            for child in &block.body {
+                write_ast_node(child, depth + 1, out);
+            }
+            write_ast_node(&AstNode::Statement(block.end.clone()), depth + 1, out);
+        }
+    }
+}
+
+/// Joins a statement's source text with single spaces, label first.
+fn statement_text(statement: &Statement) -> String {
+    let mut text = statement
+        .label
+        .as_ref()
+        .map(|label| format!("{}: ", label))
+        .unwrap_or_default();
+    for (index, token) in statement.tokens.iter().enumerate() {
+        if index > 0 && token.value != ";" {
+            text.push(' ');
+        }
+        text.push_str(&token.original);
+    }
+    text
+}
+
+/// Builds a `Statement` from tokens tagged with the line they came from.
+fn build_statement(located: Vec<(usize, Token)>) -> Statement {
+    let span = match (located.first(), located.last()) {
+        (Some((first_line, first)), Some((last_line, last))) => {
+            Span::of_token(first, *first_line).to(Span::of_token(last, *last_line))
+        }
+        _ => Span::default(),
+    };
+
+    let is_label = matches!(
+        located.as_slice(),
+        [(_, name), (_, colon), ..]
+            if name.category == TokenCategory::Identifier
+                && name.value.parse::<i64>().is_err()
+                && colon.category == TokenCategory::Separator
+                && colon.value == ":"
+    );
+    let (label, body) = if is_label {
+        (Some(located[0].1.value.clone()), &located[2..])
+    } else {
+        (None, &located[..])
+    };
+
+    Statement {
+        label,
+        token_spans: body
+            .iter()
+            .map(|(line, t)| Span::of_token(t, *line))
+            .collect(),
+        tokens: body.iter().map(|(_, t)| t.clone()).collect(),
+        span,
+    }
+}
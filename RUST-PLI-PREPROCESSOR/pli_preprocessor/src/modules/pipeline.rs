@@ -0,0 +1,1702 @@
+#![allow(dead_code)] // Suppress warnings for unused functions in this module.
+
+////////////////////////////////////////////////////////////////////////////////
+// MODULE NAME: Pipeline
+// ----------------------------------------------------------------------------
+// DESCRIPTION:
+// This module drives the PL/I preprocessing workflow (tokenization,
+// validation, expansion, and output generation) and holds the configuration
+// that controls it.
+//
+// FUNCTIONALITY:
+// - Defines `ProcessOptions`, the set of switches controlling a processing run.
+// - Provides `ProcessOptionsBuilder` for ergonomic construction in tests and
+//   embedding scenarios.
+// - Processes source lines from any `BufRead` and writes the results through
+//   an `OutputSink`, returning `ProcessStats` and the collected `Diagnostic`s.
+// - Validates and applies each `;`-terminated statement of a line separately,
+//   so a line may mix directives such as `%SET` with ordinary statements.
+// - Drops `%COMMENT` text up to its terminating `;` without tokenizing it.
+// - Warns about code statements missing their terminating `;`.
+// - With `trace`, logs each line's phase decisions as `trace!` events under
+//   `TRACE_TARGET`.
+// - With `continuation_char`, stitches tokens continued across lines back
+//   together before any line is processed.
+// - Writes `%XINCLUDE`d content bracketed by `include_handler::VERBATIM_BEGIN`
+//   and `VERBATIM_END` lines as it is, without preprocessing it.
+// - Writes only the code in taken `%IF ... %THEN;` / `%ELSE;` / `%ENDIF;`
+//   branches, and applies only the directives there.
+// - Follows `%GOTO label;` to the line of its `%label:`, up to
+//   `labels::MAX_GOTO_JUMPS` jumps per run. A line run again reports nothing
+//   new.
+// - Collects `%PROCEDURE` definitions into a `ProcTable` so `%SET` expressions
+//   can call them.
+// - Reports `%DO` groups, which are not supported, instead of writing their
+//   `%DO` and `%END` lines as code.
+//
+// USAGE:
+// - Build options with `ProcessOptions::builder().verbose(true).build()`.
+// - Clone and tweak existing options to reuse them across runs.
+// - Call `process_reader` with a reader, a sink, and the options, or
+//   `process_reader_with_context` to share preprocessor variables with the caller.
+// - Call `process_reader_with_line_hook` to observe each line's tokens and
+//   emit decision, e.g. to count active lines from a linter.
+// - Call `preprocess_string` to preprocess in-memory source into a `String`.
+// - Call `format_expansion_report` to compare a run's input and output sizes.
+// - Call `would_emit` to ask whether a line lies in active `%IF` code, e.g.
+//   to gray out inactive code in an editor.
+// - Pass an `IncludeResolver` to `process_reader_with_resolver` or
+//   `preprocess_string_with_resolver` to have `%INCLUDE`s expanded first.
+//
+// AUTHOR: FirstLink Consulting Services (FLCS)
+// LICENSE: MIT License
+// DATE: 10/16/2026
+// VERSION: 1.0.0
+////////////////////////////////////////////////////////////////////////////////
+
+////////////////////////////////////////////////////////////////////////////////
+// IMPORTS
+////////////////////////////////////////////////////////////////////////////////
+
+use crate::modules::analysis::{check_constant_condition, check_mixed_indentation};
+use crate::modules::conditional::{
+    check_conditional_structure_with_limit, depth_error, evaluate_condition_tokens,
+    split_inline_if, MAX_IF_DEPTH,
+};
+use crate::modules::context::{parse_declaration, Context, UndefinedPolicy};
+use crate::modules::diagnostics::{
+    format_diagnostic_with_caret, Diagnostic, DiagnosticSink, Severity,
+};
+use crate::modules::error::PreprocessorError;
+use crate::modules::include_handler::{
+    decode_source_text, expand_includes_counted, IncludeResolver, InlineOptions, VERBATIM_BEGIN,
+    VERBATIM_END,
+};
+use crate::modules::labels::{collect_labels, parse_goto, split_label, MAX_GOTO_JUMPS};
+use crate::modules::macro_expander::{split_macro_body, MacroTable};
+use crate::modules::output::{apply_keyword_case, reconstruct_line, KeywordCase, OutputSink};
+use crate::modules::parser::split_statements;
+use crate::modules::procedures::{parse_proc_header, ProcBuilder, ProcTable};
+use crate::modules::tokenizer::{
+    directive_histogram, filter_by_category, has_tokenizer_error, join_continued_tokens,
+    report_tokenizer_errors, tokenize_pli, DirectiveCategory, Token, TokenCategory,
+};
+use crate::modules::validator::{check_directive_tokens, SemicolonCheck};
+use log::{debug, error, info, trace, warn};
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::path::PathBuf;
+use std::time::Instant;
+
+////////////////////////////////////////////////////////////////////////////////
+// CONSTANTS
+////////////////////////////////////////////////////////////////////////////////
+
+/// The log target of the events written when `ProcessOptions::trace` is set.
+pub const TRACE_TARGET: &str = "pli_preprocessor::trace";
+
+////////////////////////////////////////////////////////////////////////////////
+// PUBLIC STRUCTURES
+////////////////////////////////////////////////////////////////////////////////
+
+/// A per-line callback: the 1-based line number, the line's tokens, and
+/// whether the line is emitted.
+pub type LineHook<'a> = dyn FnMut(usize, &[Token], bool) + 'a;
+
+/// Options controlling a single preprocessing run.
+///
+/// # Fields
+/// - `verbose`: Enables additional console output.
+/// - `dry_run`: Simulates processing without writing output.
+/// - `stats`: Reports directive category counts once processing completes.
+/// - `strict`: Treats questionable constructs as errors instead of warnings.
+/// - `analyze`: Runs the optional analysis passes, which report notes such as
+///   `%IF` blocks that can never be active.
+/// - `include_markers`: Brackets inlined include content with
+///   `/* BEGIN INCLUDE ... */` and `/* END INCLUDE ... */` lines. Only the
+///   `*_with_resolver` entry points inline includes, so `process_reader` and
+///   the other entry points ignore it; callers inlining includes themselves
+///   pass `inline_options()` on (see `include_handler::flatten_includes_counted`).
+/// - `keep_include_directives`: Keeps each inlined `%INCLUDE` line as a comment
+///   immediately before the included content. Ignored like `include_markers`.
+/// - `no_rescan`: Substitutes macro bodies without rescanning them for further
+///   macro names, overriding `%ACTIVATE name RESCAN`.
+/// - `keyword_case`: How PL/I keywords and directives are spelled in the
+///   output (see `output::apply_keyword_case`).
+/// - `warn_indentation`: Reports a note for lines whose indentation mixes
+///   tabs and spaces.
+/// - `dedup_diagnostics`: Collapses diagnostics with the same severity and
+///   message into the first one, which counts the repeats. The diagnostics
+///   are then logged when the run ends rather than as they are found, so the
+///   log shows the counts.
+/// - `undefined_vars`: What an undefined variable in a `%IF` condition or
+///   `%SET` expression yields. When set, it replaces the run's `Context`
+///   policy; when `None`, the context keeps its own.
+/// - `trace`: Logs a `trace!` event for every phase decision (see
+///   `trace_event`).
+/// - `continuation_char`: When set, a line ending in this character inside an
+///   open string literal continues the literal on the next line (see
+///   `tokenizer::join_continued_tokens`).
+/// - `include_paths`: The directories searched, in order, for includes not
+///   found next to the including file (see
+///   `include_handler::flatten_includes_with_paths`).
+/// - `max_if_depth`: The deepest `%IF` nesting allowed; `None` uses
+///   `conditional::MAX_IF_DEPTH`.
+/// - `max_output_bytes`: The most output, in bytes counting one newline per
+///   line, a run may produce before it is aborted; `None` (the default) sets
+///   no limit. Guards against macro or include expansions that blow up; a
+///   macro expansion is abandoned as soon as it passes the limit.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::pipeline::ProcessOptions;
+/// let options = ProcessOptions::builder().verbose(true).strict(true).build();
+/// assert!(options.verbose && options.strict);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProcessOptions {
+    pub verbose: bool,
+    pub dry_run: bool,
+    pub stats: bool,
+    pub strict: bool,
+    pub analyze: bool,
+    pub include_markers: bool,
+    pub keep_include_directives: bool,
+    pub no_rescan: bool,
+    pub keyword_case: KeywordCase,
+    pub warn_indentation: bool,
+    pub dedup_diagnostics: bool,
+    pub trace: bool,
+    pub undefined_vars: Option<UndefinedPolicy>,
+    pub continuation_char: Option<char>,
+    pub include_paths: Vec<PathBuf>,
+    pub max_if_depth: Option<usize>,
+    pub max_output_bytes: Option<usize>,
+}
+
+impl ProcessOptions {
+    /// Returns a builder starting from the default options.
+    pub fn builder() -> ProcessOptionsBuilder {
+        ProcessOptionsBuilder::default()
+    }
+
+    /// Returns the `InlineOptions` for inlining includes in this run. The
+    /// content of each `%XINCLUDE` is marked so the pipeline copies it as is,
+    /// and includes are traced with `trace`.
+    pub fn inline_options(&self) -> InlineOptions {
+        InlineOptions {
+            markers: self.include_markers,
+            keep_directives: self.keep_include_directives,
+            mark_verbatim: true,
+            trace: self.trace,
+        }
+    }
+}
+
+/// Builder for `ProcessOptions`.
+///
+/// Each setter consumes and returns the builder so calls can be chained.
+#[derive(Debug, Clone, Default)]
+pub struct ProcessOptionsBuilder {
+    options: ProcessOptions,
+}
+
+impl ProcessOptionsBuilder {
+    /// Sets the `verbose` flag.
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.options.verbose = verbose;
+        self
+    }
+
+    /// Sets the `dry_run` flag.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.options.dry_run = dry_run;
+        self
+    }
+
+    /// Sets the `stats` flag.
+    pub fn stats(mut self, stats: bool) -> Self {
+        self.options.stats = stats;
+        self
+    }
+
+    /// Sets the `strict` flag.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.options.strict = strict;
+        self
+    }
+
+    /// Sets the `analyze` flag.
+    pub fn analyze(mut self, analyze: bool) -> Self {
+        self.options.analyze = analyze;
+        self
+    }
+
+    /// Sets the `include_markers` flag.
+    pub fn include_markers(mut self, include_markers: bool) -> Self {
+        self.options.include_markers = include_markers;
+        self
+    }
+
+    /// Sets the `keep_include_directives` flag.
+    pub fn keep_include_directives(mut self, keep_include_directives: bool) -> Self {
+        self.options.keep_include_directives = keep_include_directives;
+        self
+    }
+
+    /// Sets the `no_rescan` flag.
+    pub fn no_rescan(mut self, no_rescan: bool) -> Self {
+        self.options.no_rescan = no_rescan;
+        self
+    }
+
+    /// Sets the `keyword_case` policy.
+    pub fn keyword_case(mut self, keyword_case: KeywordCase) -> Self {
+        self.options.keyword_case = keyword_case;
+        self
+    }
+
+    /// Sets the `warn_indentation` flag.
+    pub fn warn_indentation(mut self, warn_indentation: bool) -> Self {
+        self.options.warn_indentation = warn_indentation;
+        self
+    }
+
+    /// Sets the `dedup_diagnostics` flag.
+    pub fn dedup_diagnostics(mut self, dedup_diagnostics: bool) -> Self {
+        self.options.dedup_diagnostics = dedup_diagnostics;
+        self
+    }
+
+    /// Sets the `undefined_vars` policy.
+    pub fn undefined_vars(mut self, undefined_vars: Option<UndefinedPolicy>) -> Self {
+        self.options.undefined_vars = undefined_vars;
+        self
+    }
+
+    /// Sets the `trace` flag.
+    pub fn trace(mut self, trace: bool) -> Self {
+        self.options.trace = trace;
+        self
+    }
+
+    /// Sets the `continuation_char` indicator.
+    pub fn continuation_char(mut self, continuation_char: Option<char>) -> Self {
+        self.options.continuation_char = continuation_char;
+        self
+    }
+
+    /// Appends a directory to the `include_paths` search list.
+    pub fn include_path(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.options.include_paths.push(dir.into());
+        self
+    }
+
+    /// Sets the `max_if_depth` limit.
+    pub fn max_if_depth(mut self, max_if_depth: Option<usize>) -> Self {
+        self.options.max_if_depth = max_if_depth;
+        self
+    }
+
+    /// Sets the `max_output_bytes` limit, e.g. `Some(100 * 1024 * 1024)`.
+    pub fn max_output_bytes(mut self, max_output_bytes: Option<usize>) -> Self {
+        self.options.max_output_bytes = max_output_bytes;
+        self
+    }
+
+    /// Returns the configured `ProcessOptions`.
+    pub fn build(self) -> ProcessOptions {
+        self.options
+    }
+}
+
+/// Counters gathered while processing a source.
+///
+/// # Fields
+/// - `lines_read`: The number of physical lines read from the input.
+/// - `lines_written`: The number of lines written to the output sink.
+/// - `directive_counts`: The number of directives per `DirectiveCategory`.
+/// - `macros_expanded`: The number of macro substitutions in emitted lines,
+///   counting those made while rescanning a macro body.
+/// - `includes_resolved`: The number of includes inlined before processing;
+///   only the `*_with_resolver` entry points inline includes themselves, so
+///   callers that flatten the source first fill it in.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProcessStats {
+    pub lines_read: usize,
+    pub lines_written: usize,
+    pub directive_counts: HashMap<DirectiveCategory, usize>,
+    pub macros_expanded: usize,
+    pub includes_resolved: usize,
+}
+
+/// The outcome of a processing run.
+///
+/// # Fields
+/// - `stats`: The counters gathered during the run.
+/// - `diagnostics`: The errors, warnings, and notes reported, in source order
+///   except for end-of-file checks which come last.
+#[derive(Debug, Clone, Default)]
+pub struct ProcessResult {
+    pub stats: ProcessStats,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// PUBLIC FUNCTIONS
+////////////////////////////////////////////////////////////////////////////////
+
+/// Processes source lines and applies the preprocessor workflow.
+/// This includes tokenization, validation, macro expansion, conditional evaluation, and more.
+///
+/// # Arguments
+/// - `reader`: The source to read PL/I lines from.
+/// - `sink`: The `OutputSink` receiving processed lines. Nothing is written when
+///   `options.dry_run` is set.
+/// - `options`: The `ProcessOptions` controlling the run.
+///
+/// # Returns
+/// - `Result<ProcessResult, PreprocessorError>`: The counters and diagnostics
+///   gathered during the run, or an `Io` error raised by the reader or the
+///   sink. Input that is not valid UTF-8 text is rejected up front with an
+///   `Io` error of kind `InvalidData`.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::pipeline::{process_reader, ProcessOptions};
+/// # use std::io;
+/// let mut output: Vec<String> = Vec::new();
+/// let source = io::Cursor::new("A = 1;\n\nB = 2;\n");
+/// let result = process_reader(source, &mut output, &ProcessOptions::default()).unwrap();
+/// assert_eq!(output, vec!["A = 1;", "B = 2;"]);
+/// assert_eq!(result.stats.lines_written, 2);
+/// ```
+pub fn process_reader<R: BufRead>(
+    reader: R,
+    sink: &mut dyn OutputSink,
+    options: &ProcessOptions,
+) -> Result<ProcessResult, PreprocessorError> {
+    process_reader_with_context(reader, sink, options, &mut Context::new())
+}
+
+/// Preprocesses in-memory source and returns the transformed text.
+///
+/// This is the library entry point for embedding: it runs the same pipeline as
+/// `process_reader_with_context` without touching the filesystem. Each output
+/// line is terminated by `\n`. Includes are not expanded: a `%INCLUDE` or
+/// `%XINCLUDE` is an `I004` error, so source that includes other files goes
+/// through `preprocess_string_with_resolver`.
+///
+/// # Arguments
+/// - `source`: The PL/I source text.
+/// - `options`: The `ProcessOptions` controlling the run. With `dry_run` set,
+///   the returned text is empty.
+/// - `context`: The preprocessor variables, which persist after the call.
+///
+/// # Returns
+/// - `Result<String, Vec<Diagnostic>>`: The transformed text if no errors were
+///   reported, or every diagnostic of the run (errors, warnings, and notes) if
+///   at least one error was.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::context::Context;
+/// # use pli_preprocessor::modules::pipeline::{preprocess_string, ProcessOptions};
+/// let text = preprocess_string("%SET N = 1;\nA = N;\n", &ProcessOptions::default(), &mut Context::new());
+/// assert_eq!(text, Ok("A = N;\n".to_string()));
+/// ```
+pub fn preprocess_string(
+    source: &str,
+    options: &ProcessOptions,
+    context: &mut Context,
+) -> Result<String, Vec<Diagnostic>> {
+    let mut output: Vec<String> = Vec::new();
+    let result = process_reader_with_context(source.as_bytes(), &mut output, options, context)
+        .map_err(|e| vec![error_diagnostic(&e)])?;
+
+    if result
+        .diagnostics
+        .iter()
+        .any(|d| d.severity == Severity::Error)
+    {
+        return Err(result.diagnostics);
+    }
+    Ok(output.iter().map(|line| format!("{}\n", line)).collect())
+}
+
+/// Preprocesses in-memory source like `preprocess_string`, first expanding its
+/// `%INCLUDE`s through `resolver`.
+///
+/// Includes are expanded before any `%IF` is evaluated, so every include must
+/// resolve, even one in code that turns out to be inactive.
+///
+/// # Arguments
+/// - `source`: The PL/I source text.
+/// - `options`: The `ProcessOptions` controlling the run; `include_markers`
+///   and `keep_include_directives` lay out each expanded include.
+/// - `context`: The preprocessor variables, which persist after the call.
+/// - `resolver`: The `IncludeResolver` supplying included content.
+///
+/// # Returns
+/// - `Result<String, Vec<Diagnostic>>`: As for `preprocess_string`; an include
+///   that cannot be resolved is reported as a single error.
+pub fn preprocess_string_with_resolver(
+    source: &str,
+    options: &ProcessOptions,
+    context: &mut Context,
+    resolver: &dyn IncludeResolver,
+) -> Result<String, Vec<Diagnostic>> {
+    let (expanded, _) = expand_includes_counted(source, resolver, options.inline_options())
+        .map_err(|e| vec![error_diagnostic(&e)])?;
+    preprocess_string(&expanded, options, context)
+}
+
+/// Processes source lines like `process_reader_with_context`, first expanding
+/// their `%INCLUDE`s through `resolver`.
+///
+/// Diagnostics refer to lines of the expanded source. As the includes are
+/// expanded before any `%IF` is evaluated, every include must resolve, even
+/// one in code that turns out to be inactive.
+///
+/// # Arguments
+/// - `reader`: The source to read PL/I lines from.
+/// - `sink`: The `OutputSink` receiving processed lines.
+/// - `options`: The `ProcessOptions` controlling the run; `include_markers`
+///   and `keep_include_directives` lay out each expanded include.
+/// - `context`: The preprocessor variables, which persist after the call.
+/// - `resolver`: The `IncludeResolver` supplying included content.
+///
+/// # Returns
+/// - `Result<ProcessResult, PreprocessorError>`: As for
+///   `process_reader_with_context`; an include that cannot be resolved is an
+///   `Include` error.
+pub fn process_reader_with_resolver<R: BufRead>(
+    mut reader: R,
+    sink: &mut dyn OutputSink,
+    options: &ProcessOptions,
+    context: &mut Context,
+    resolver: &dyn IncludeResolver,
+) -> Result<ProcessResult, PreprocessorError> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    let source = decode_source_text(bytes, "input")?;
+    let (expanded, includes) =
+        expand_includes_counted(&source, resolver, options.inline_options())?;
+    let mut result = process_reader_with_context(expanded.as_bytes(), sink, options, context)?;
+    result.stats.includes_resolved = includes;
+    Ok(result)
+}
+
+/// Processes source lines like `process_reader`, reading and updating the
+/// preprocessor variables in `context`.
+///
+/// `%DECLARE`/`%DCL` statements register variables in the context and `%SET`
+/// assigns them. In strict mode, a `%SET` to an undeclared variable is an error.
+/// The context's `UndefinedPolicy` is replaced by `options.undefined_vars`
+/// when that is set.
+///
+/// The whole input is read before processing starts, so input that is not
+/// valid UTF-8 text fails with an `Io` error of kind `InvalidData` naming the
+/// first bad byte, and nothing is written.
+///
+/// # Arguments
+/// - `reader`: The source to read PL/I lines from.
+/// - `sink`: The `OutputSink` receiving processed lines.
+/// - `options`: The `ProcessOptions` controlling the run.
+/// - `context`: The preprocessor variables, which persist after the call.
+///
+/// # Returns
+/// - `Result<ProcessResult, PreprocessorError>`: The counters and diagnostics
+///   gathered during the run, or an `Io` error raised by the reader or the
+///   sink.
+pub fn process_reader_with_context<R: BufRead>(
+    reader: R,
+    sink: &mut dyn OutputSink,
+    options: &ProcessOptions,
+    context: &mut Context,
+) -> Result<ProcessResult, PreprocessorError> {
+    process_reader_with_line_hook(reader, sink, options, context, None)
+}
+
+/// Processes source lines like `process_reader_with_context`, calling `on_line`
+/// for each non-blank line once the pipeline has decided its fate.
+///
+/// The callback receives the 1-based line number, the line's tokens, and
+/// whether the line is emitted. Lines that only feed a `%MACRO` body or a
+/// `%COMMENT`, and `%XINCLUDE`d lines copied as they are, are reported with no
+/// tokens. The emit decision ignores
+/// `options.dry_run`.
+///
+/// # Arguments
+/// - `reader`: The source to read PL/I lines from.
+/// - `sink`: The `OutputSink` receiving processed lines.
+/// - `options`: The `ProcessOptions` controlling the run.
+/// - `context`: The preprocessor variables, which persist after the call.
+/// - `on_line`: The optional per-line callback.
+///
+/// # Returns
+/// - `Result<ProcessResult, PreprocessorError>`: As for
+///   `process_reader_with_context`.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::context::Context;
+/// # use pli_preprocessor::modules::pipeline::{process_reader_with_line_hook, ProcessOptions};
+/// # use pli_preprocessor::modules::tokenizer::Token;
+/// # use std::io;
+/// let mut active = 0;
+/// let mut count = |_: usize, _: &[Token], emitted: bool| active += emitted as usize;
+/// let source = io::Cursor::new("%SET N = 1;\nA = N;\n");
+/// let mut output: Vec<String> = Vec::new();
+/// let options = ProcessOptions::default();
+/// process_reader_with_line_hook(source, &mut output, &options, &mut Context::new(), Some(&mut count)).unwrap();
+/// assert_eq!(active, 1);
+/// ```
+pub fn process_reader_with_line_hook<R: BufRead>(
+    mut reader: R,
+    sink: &mut dyn OutputSink,
+    options: &ProcessOptions,
+    context: &mut Context,
+    mut on_line: Option<&mut LineHook>,
+) -> Result<ProcessResult, PreprocessorError> {
+    let mut report = |line: usize, tokens: &[Token], emitted: bool| {
+        if options.trace {
+            trace_event(line, "emit", &format!("emitted={}", emitted));
+        }
+        if let Some(hook) = on_line.as_mut() {
+            hook(line, tokens, emitted);
+        }
+    };
+    if let Some(policy) = options.undefined_vars {
+        context.set_undefined_policy(policy);
+    }
+    let mut stats = ProcessStats::default();
+    let mut diagnostics = DiagnosticSink::new();
+    diagnostics.set_dedup(options.dedup_diagnostics);
+    let mut conditional_directives: Vec<(usize, String)> = Vec::new();
+    let mut semicolons = SemicolonCheck::new();
+    let mut macros = MacroTable::new();
+    macros.set_rescan(!options.no_rescan);
+    // The macro currently being defined: its name and the body lines so far.
+    let mut open_macro: Option<OpenMacro> = None;
+    let mut procs = ProcTable::new();
+    // The preprocessor procedure currently being defined.
+    let mut open_proc: Option<ProcBuilder> = None;
+    // Whether a `%COMMENT` is still looking for its terminating `;`.
+    let mut in_comment = false;
+    // Whether a `/* ... */` comment is still looking for its `*/`.
+    let mut in_block_comment = false;
+    // Whether the lines are `%XINCLUDE`d content to copy as they are.
+    let mut verbatim = false;
+    // The `%IF` blocks open at the current line.
+    let mut blocks = OpenBlocks::new(options.max_if_depth.unwrap_or(MAX_IF_DEPTH));
+    // Diagnostics are logged as they are found, unless repeats are collapsed
+    // into them later; `logged` counts those already written.
+    let mut logged = 0;
+    // The output produced so far, checked against `options.max_output_bytes`.
+    let mut output_bytes = 0;
+
+    // Reject input that is not text before producing any output.
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    let source = decode_source_text(bytes, "input")?;
+    let source = match options.continuation_char {
+        Some(indicator) => join_continued_tokens(&source, indicator),
+        None => source,
+    };
+
+    // Iterate through the lines by index, so a `%GOTO` can move to any line.
+    let lines: Vec<String> = source.lines().map(str::to_string).collect();
+    stats.lines_read = lines
+        .iter()
+        .filter(|line| *line != VERBATIM_BEGIN && *line != VERBATIM_END)
+        .count();
+    let labels = collect_labels(&lines, &mut diagnostics);
+    let mut next_line = 0;
+    let mut jumps = 0;
+    // The number of open `%IF` blocks when each line run so far was first
+    // reached, and the error ending a run of too many jumps.
+    let mut visited: Vec<Option<usize>> = vec![None; lines.len()];
+    let mut jump_limit: Option<Diagnostic> = None;
+    while let Some(content) = lines.get(next_line).cloned() {
+        let line_number = next_line;
+        next_line += 1;
+        if !options.dedup_diagnostics {
+            log_new_diagnostics(&diagnostics, &mut logged, &lines);
+        }
+        // A line revisited through a `%GOTO` runs again, but what it reports
+        // was reported the first time, so it goes to a sink that is dropped.
+        let revisited = visited[line_number].is_some();
+        visited[line_number].get_or_insert(blocks.depth());
+        let mut repeated = DiagnosticSink::new();
+        let diagnostics = if revisited {
+            &mut repeated
+        } else {
+            &mut diagnostics
+        };
+        let reported_before = diagnostics.diagnostics().len();
+        // `%XINCLUDE`d content, blank lines included, is written as it is.
+        if content == VERBATIM_BEGIN || content == VERBATIM_END {
+            verbatim = content == VERBATIM_BEGIN;
+            continue;
+        }
+        if verbatim && !blocks.active() {
+            report(line_number + 1, &[], false);
+            continue;
+        }
+        if verbatim {
+            write_unprocessed(
+                sink,
+                &content,
+                line_number + 1,
+                options,
+                &mut output_bytes,
+                &mut stats,
+            )?;
+            report(line_number + 1, &[], true);
+            continue;
+        }
+        if content.trim().is_empty() {
+            continue; // Skip blank lines.
+        }
+
+        // The text of a `/* ... */` comment left open on an earlier line is
+        // written as it is, without running directives or recasing keywords;
+        // code after its `*/` is processed with the comment blanked out.
+        let mut comment_head: Option<String> = None;
+        let mut content = content;
+        if in_block_comment {
+            let active = blocks.active();
+            match content.find("*/") {
+                Some(end) if !content[end + 2..].trim().is_empty() => {
+                    in_block_comment = false;
+                    let head = content[..end + 2].to_string();
+                    let blank = " ".repeat(head.chars().count());
+                    content = format!("{}{}", blank, &content[end + 2..]);
+                    comment_head = Some(head).filter(|_| active);
+                }
+                end => {
+                    in_block_comment = end.is_none();
+                    if active {
+                        write_unprocessed(
+                            sink,
+                            &content,
+                            line_number + 1,
+                            options,
+                            &mut output_bytes,
+                            &mut stats,
+                        )?;
+                    }
+                    report(line_number + 1, &[], active);
+                    continue;
+                }
+            }
+        }
+
+        if options.verbose {
+            info!("Processing line {}: {}", line_number + 1, content);
+        }
+        if options.warn_indentation {
+            check_mixed_indentation(&content, line_number + 1, diagnostics);
+        }
+
+        // Lines inside a `%MACRO` definition only contribute to its body.
+        if let Some(mut definition) = open_macro.take() {
+            let (fragment, closed) = split_macro_body(&content);
+            if !fragment.is_empty() {
+                definition.body.push(fragment);
+            }
+            if closed && blocks.active() {
+                macros.define(&definition.name, &definition.body.join("\n"));
+            } else {
+                open_macro = Some(definition);
+            }
+            report(line_number + 1, &[], false);
+            continue;
+        }
+
+        // `%COMMENT` text runs to the next `;`, possibly on a later line.
+        // It is blanked out rather than removed, so that the columns of
+        // the code after it stay correct, and it is never written.
+        let indent = content[..content.len() - content.trim_start().len()].to_string();
+        let mut commented = false;
+        if in_comment || starts_with_comment_directive(&content) {
+            if !in_comment && !revisited {
+                *stats
+                    .directive_counts
+                    .entry(DirectiveCategory::Comment)
+                    .or_insert(0) += 1;
+            }
+            match content.find(';') {
+                Some(end) => {
+                    in_comment = false;
+                    commented = true;
+                    let blank = " ".repeat(content[..=end].chars().count());
+                    content = format!("{}{}", blank, &content[end + 1..]);
+                    if content.trim().is_empty() {
+                        report(line_number + 1, &[], false);
+                        continue;
+                    }
+                }
+                None => {
+                    in_comment = true;
+                    report(line_number + 1, &[], false);
+                    continue;
+                }
+            }
+        }
+
+        // Phase 1: Tokenization
+        let tokenize_start = Instant::now();
+        let tokens = tokenize_pli(&content);
+        in_block_comment = leaves_comment_open(&tokens);
+        let tokenize_elapsed = tokenize_start.elapsed();
+        debug!(
+            "Line {} Tokenization took: {:.2?} - Tokens: {:?}",
+            line_number + 1,
+            tokenize_elapsed,
+            tokens
+        );
+        info!("Line {} Tokens: {:?}", line_number + 1, tokens);
+        if options.trace {
+            let values: Vec<&str> = tokens.iter().map(|t| t.value.as_str()).collect();
+            trace_event(line_number + 1, "tokenize", &format!("tokens={:?}", values));
+        }
+
+        if !revisited {
+            for (category, count) in directive_histogram(&tokens) {
+                *stats.directive_counts.entry(category).or_insert(0) += count;
+            }
+        }
+
+        // Phase 2: Validation
+        // An inline `%IF ... %THEN statement;` needs no `%ENDIF`, so its
+        // directives stay out of the block structure check.
+        report_tokenizer_errors(&tokens, line_number + 1, diagnostics);
+        // A `;` swallowed by a broken literal is already reported. The block
+        // structure and `;` checks see each line once, however often it runs.
+        if !has_tokenizer_error(&tokens) && !revisited {
+            semicolons.check_line(line_number + 1, &tokens, diagnostics);
+        }
+        let statements = split_statements(&tokens);
+        for statement in &statements {
+            if split_inline_if(statement).is_none() && !revisited {
+                conditional_directives.extend(
+                    filter_by_category(statement, TokenCategory::Directive)
+                        .into_iter()
+                        .map(|t| (line_number + 1, t.value.clone())),
+                );
+            }
+        }
+
+        // Phase 3: Macro Expansion
+        // A `%MACRO` header owns the rest of its line as the body. In
+        // inactive code the definition is read but not kept.
+        if tokens.first().map(|t| t.value.as_str()) == Some("%MACRO") {
+            check_directive_tokens(&tokens, line_number + 1, diagnostics);
+            let mut discarded = MacroTable::new();
+            open_macro = start_macro_definition(
+                &content,
+                &tokens,
+                line_number + 1,
+                if blocks.active() {
+                    &mut macros
+                } else {
+                    &mut discarded
+                },
+                diagnostics,
+            );
+            if options.trace {
+                let reported = diagnostics.diagnostics().len() - reported_before;
+                trace_event(
+                    line_number + 1,
+                    "validate",
+                    &format!("diagnostics={}", reported),
+                );
+            }
+            report(line_number + 1, &tokens, false);
+            continue;
+        }
+
+        // Every other statement is validated and applied on its own;
+        // those consumed by a directive are dropped from the output.
+        let mut kept: Vec<Token> = Vec::new();
+        let mut consumed = commented;
+        for statement in statements {
+            // Preprocessor procedure definitions are consumed whole.
+            if let Some(builder) = open_proc.as_mut() {
+                consumed = true;
+                match builder.add_statement(&statement) {
+                    Ok(true) => {
+                        if let Some(Err(e)) = open_proc.take().map(|b| b.finish(&mut procs)) {
+                            diagnostics.error(line_number + 1, &e);
+                        }
+                    }
+                    Ok(false) => {}
+                    Err(e) => diagnostics.error(line_number + 1, &e),
+                }
+                continue;
+            }
+            // Phase 6 (block form): `%IF condition %THEN;` ... `%ELSE;` ...
+            // `%ENDIF;` decide which code is active. Inactive code is still
+            // validated, but never applied or written.
+            let directive = block_directive(&statement);
+            if directive.is_some() || !blocks.active() {
+                consumed = true;
+                let body = split_label(&statement).map_or(&statement[..], |(_, rest)| rest);
+                check_directive_tokens(body, line_number + 1, diagnostics);
+                if options.analyze {
+                    check_constant_condition(body, line_number + 1, diagnostics);
+                }
+                if let Some(message) = body
+                    .get(1)
+                    .filter(|_| directive.is_some_and(|(kind, _)| kind == BlockDirective::Else))
+                    .and_then(|t| unsupported_group(&t.value))
+                {
+                    diagnostics.error_with_code(line_number + 1, "D007", message);
+                }
+                match directive {
+                    Some((BlockDirective::If, condition)) => {
+                        let holds = blocks.active()
+                            && evaluate_condition_tokens(condition, context).unwrap_or_else(|e| {
+                                diagnostics.error_with_code(line_number + 1, "X001", &e);
+                                false
+                            });
+                        if let Err(e) = blocks.open(holds) {
+                            diagnostics.error_with_code(line_number + 1, "V004", &e);
+                        }
+                    }
+                    Some((BlockDirective::Else, _)) => blocks.switch(),
+                    Some((BlockDirective::EndIf, _)) => blocks.close(),
+                    None => {}
+                }
+                continue;
+            }
+            if let Some(header) = parse_proc_header(&statement) {
+                consumed = true;
+                match header {
+                    Ok((name, params)) => open_proc = Some(ProcBuilder::new(&name, params)),
+                    Err(e) => diagnostics.error(line_number + 1, &e),
+                }
+                continue;
+            }
+            // A `%LABEL:` prefix only marks a `%GOTO` target.
+            let statement = match split_label(&statement) {
+                Some((_, rest)) => {
+                    consumed = true;
+                    if rest.iter().all(|t| t.value == ";") {
+                        continue;
+                    }
+                    rest.to_vec()
+                }
+                None => statement,
+            };
+            check_directive_tokens(&statement, line_number + 1, diagnostics);
+            if options.analyze {
+                check_constant_condition(&statement, line_number + 1, diagnostics);
+            }
+            // `%DO` groups are not supported. Their `%DO` and `%END` are
+            // reported, even after a `%THEN` whose condition is false, and
+            // never written.
+            let group = split_inline_if(&statement).map_or(&statement[..], |(_, code)| code);
+            if let Some(message) = group.first().and_then(|t| unsupported_group(&t.value)) {
+                consumed = true;
+                diagnostics.error_with_code(line_number + 1, "D007", message);
+                continue;
+            }
+            // Phase 6 (inline form): run the statement after `%THEN` only
+            // when the condition holds. A directive there is applied like a
+            // statement of its own; any other statement is kept.
+            let mut goto = parse_goto(&statement);
+            if let Some((condition, code)) = split_inline_if(&statement) {
+                consumed = true;
+                match evaluate_condition_tokens(condition, context) {
+                    Ok(true) => match parse_goto(code) {
+                        Some(target) => goto = Some(target),
+                        None => {
+                            let applied = apply_statement(
+                                code,
+                                line_number + 1,
+                                context,
+                                &mut macros,
+                                &procs,
+                                options.strict,
+                                diagnostics,
+                            );
+                            if !applied {
+                                kept.extend(code.iter().cloned());
+                            }
+                        }
+                    },
+                    Ok(false) => {}
+                    Err(e) => diagnostics.error_with_code(line_number + 1, "X001", &e),
+                }
+                if goto.is_none() {
+                    continue;
+                }
+            }
+            // A `%GOTO` resumes at its label's line; the rest of this line
+            // is skipped.
+            if let Some(goto) = goto {
+                consumed = true;
+                match goto {
+                    Ok(label) => match labels.get(&label) {
+                        Some(_) if jumps == MAX_GOTO_JUMPS => {
+                            let message = format!(
+                                "%GOTO {} exceeded the limit of {} jumps",
+                                label, MAX_GOTO_JUMPS
+                            );
+                            jump_limit = Some(
+                                Diagnostic::new(Severity::Error, line_number + 1, &message)
+                                    .with_code("D006"),
+                            );
+                            next_line = lines.len();
+                            break;
+                        }
+                        Some(&target) => {
+                            jumps += 1;
+                            next_line = target - 1;
+                            // Blocks opened since the label's line was last
+                            // reached are left by jumping back to it.
+                            if let Some(depth) = visited[next_line] {
+                                blocks.truncate(depth);
+                            }
+                            break;
+                        }
+                        None => {
+                            diagnostics.error_with_code(
+                                line_number + 1,
+                                "D004",
+                                &format!("Undefined label: %{}", label),
+                            );
+                        }
+                    },
+                    Err(e) => diagnostics.error(line_number + 1, &e),
+                }
+                continue;
+            }
+            let applied = apply_statement(
+                &statement,
+                line_number + 1,
+                context,
+                &mut macros,
+                &procs,
+                options.strict,
+                diagnostics,
+            );
+            if applied {
+                consumed = true;
+            } else {
+                kept.extend(statement);
+            }
+        }
+        if options.trace {
+            let reported = diagnostics.diagnostics().len() - reported_before;
+            trace_event(
+                line_number + 1,
+                "validate",
+                &format!("diagnostics={}", reported),
+            );
+        }
+        if kept.is_empty() {
+            // The end of a comment is written even when no code follows it.
+            if let Some(head) = comment_head {
+                write_unprocessed(
+                    sink,
+                    &head,
+                    line_number + 1,
+                    options,
+                    &mut output_bytes,
+                    &mut stats,
+                )?;
+            }
+            report(line_number + 1, &tokens, false);
+            continue;
+        }
+        let content = if consumed {
+            format!("{}{}", indent, reconstruct_line(&kept).trim_start())
+        } else {
+            content
+        };
+        // Expansion stops as soon as the line would take the output past
+        // `max_output_bytes`, before an exploding macro is fully built.
+        let (content, expanded) = match options.max_output_bytes {
+            Some(limit) => macros
+                .expand_line_within(&content, limit.saturating_sub(output_bytes))
+                .ok_or_else(|| output_limit_error(limit, line_number + 1))?,
+            None => macros.expand_line_with_names(&content),
+        };
+        stats.macros_expanded += expanded.len();
+        if options.trace {
+            trace_event(
+                line_number + 1,
+                "macro",
+                &format!("expanded={:?}", expanded),
+            );
+        }
+        report(line_number + 1, &tokens, true);
+
+        // Phase 4: Expression Evaluation
+        // TODO: Evaluate conditional expressions in the line.
+
+        // Phase 5: Include Resolution
+        // TODO: Resolve includes to replace lines dynamically.
+
+        // Phase 7: Output Generation
+        output_bytes += content.lines().map(|line| line.len() + 1).sum::<usize>();
+        // The blanked comment was counted as one byte per character.
+        if let Some(head) = &comment_head {
+            output_bytes += head.len() - head.chars().count();
+        }
+        if let Some(limit) = options
+            .max_output_bytes
+            .filter(|&limit| output_bytes > limit)
+        {
+            return Err(output_limit_error(limit, line_number + 1));
+        }
+        if !options.dry_run {
+            for (index, output_line) in content.lines().enumerate() {
+                let mut output_line = apply_keyword_case(output_line, options.keyword_case);
+                // The end of a comment goes back in front of the code after it.
+                if let Some(head) = comment_head.as_ref().filter(|_| index == 0) {
+                    let blank = " ".repeat(head.chars().count());
+                    output_line = match output_line.strip_prefix(&blank) {
+                        Some(code) => format!("{}{}", head, code),
+                        None => format!("{} {}", head, output_line.trim_start()),
+                    };
+                }
+                sink.write_line(&output_line)?; // Write processed line to the sink.
+                stats.lines_written += 1;
+            }
+        }
+    }
+
+    if let Some(error) = jump_limit {
+        diagnostics.push(error);
+    }
+    if let Some(definition) = open_macro {
+        diagnostics.error_with_code(
+            definition.line,
+            "D002",
+            &format!("Unterminated %MACRO '{}' at end of file", definition.name),
+        );
+    }
+    if in_comment {
+        diagnostics.error_with_code(stats.lines_read, "D001", "Unterminated %COMMENT");
+    }
+    if let Some(builder) = open_proc {
+        diagnostics.error_with_code(
+            stats.lines_read,
+            "D003",
+            &format!("Unterminated %PROCEDURE {}", builder.name),
+        );
+    }
+
+    // A `%IF` past the depth limit was reported when it was reached.
+    let mut structure = DiagnosticSink::new();
+    check_conditional_structure_with_limit(
+        &conditional_directives,
+        options.max_if_depth.unwrap_or(MAX_IF_DEPTH),
+        &mut structure,
+    );
+    for diagnostic in structure.into_diagnostics() {
+        if diagnostic.code != Some("V004") || !diagnostics.diagnostics().contains(&diagnostic) {
+            diagnostics.push(diagnostic);
+        }
+    }
+    semicolons.finish(&mut diagnostics);
+    log_new_diagnostics(&diagnostics, &mut logged, &lines);
+
+    Ok(ProcessResult {
+        stats,
+        diagnostics: diagnostics.into_diagnostics(),
+    })
+}
+
+/// Renders the expansion report of one input file: its input and output line
+/// counts, the macros expanded and includes resolved, and the expansion ratio
+/// (output lines per input line).
+///
+/// # Arguments
+/// - `input_file`: The name of the input, as it should appear in the report.
+/// - `input_lines`: The number of lines of the input itself, before its
+///   includes are inlined.
+/// - `stats`: The counters of the run that processed it.
+///
+/// # Returns
+/// - `String`: The report, each line ending in a newline. The ratio is `-`
+///   for an empty input.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::pipeline::{format_expansion_report, ProcessStats};
+/// let stats = ProcessStats { lines_read: 3, lines_written: 6, macros_expanded: 2, ..Default::default() };
+/// let report = format_expansion_report("main.pli", 3, &stats);
+/// assert!(report.ends_with("  Expansion ratio:   2.00\n"));
+/// ```
+pub fn format_expansion_report(
+    input_file: &str,
+    input_lines: usize,
+    stats: &ProcessStats,
+) -> String {
+    let ratio = if input_lines == 0 {
+        "-".to_string()
+    } else {
+        format!("{:.2}", stats.lines_written as f64 / input_lines as f64)
+    };
+    format!(
+        "{}\n  Input lines:       {}\n  Output lines:      {}\n  Macros expanded:   {}\n  Includes resolved: {}\n  Expansion ratio:   {}\n",
+        input_file,
+        input_lines,
+        stats.lines_written,
+        stats.macros_expanded,
+        stats.includes_resolved,
+        ratio
+    )
+}
+
+/// Logs one trace event at `trace!` level under `TRACE_TARGET`.
+///
+/// Every event reads `line=<n> phase=<phase> <detail>`, where `n` is a line of
+/// the source with its includes inlined, as in diagnostics. The pipeline emits,
+/// per line and in this order, `tokenize` (`tokens=[...]`), `validate`
+/// (`diagnostics=<new count>`), `macro` (`expanded=[...]`, emitted lines only),
+/// and `emit` (`emitted=true|false`). Blank lines get no event, and lines that
+/// are not tokenized (`%MACRO` body and `%COMMENT` lines, and `%XINCLUDE`d
+/// content) only get `emit`.
+/// `include` events (`target=<name>`) are logged while the includes are
+/// inlined, before the first line, at the line where each include's content
+/// starts; nested includes are traced too.
+///
+/// # Arguments
+/// - `line`: The 1-based source line the event concerns.
+/// - `phase`: The pipeline phase that made the decision.
+/// - `detail`: The decision, as `key=value` text.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::pipeline::trace_event;
+/// trace_event(3, "emit", "emitted=false");
+/// // logs "line=3 phase=emit emitted=false"
+/// ```
+pub fn trace_event(line: usize, phase: &str, detail: &str) {
+    trace!(target: TRACE_TARGET, "line={} phase={} {}", line, phase, detail);
+}
+
+/// Reports whether a line lies in active code, given the variables in `ctx`.
+///
+/// The source is scanned up to the line: `%IF condition %THEN;` ... `%ELSE;`
+/// ... `%ENDIF;` blocks are evaluated by the same rules `process_reader`
+/// follows, and statements in active code (such as
+/// `%SET`, or the statement after a true inline `%IF`) are applied to a copy
+/// of `ctx`. A condition that cannot be evaluated counts as false. Nothing is
+/// written, and `%GOTO`s are not followed. A `%IF`, `%ELSE`, or `%ENDIF` line
+/// is active when the code around its block is.
+///
+/// # Arguments
+/// - `source`: The source text.
+/// - `line_number`: The 1-based line to ask about.
+/// - `ctx`: The preprocessor variables at the start of the source.
+///
+/// # Returns
+/// - `bool`: `true` if every enclosing `%IF` branch is taken, or `false` if
+///   one is not or the line is out of range.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::context::{Context, Value};
+/// # use pli_preprocessor::modules::pipeline::would_emit;
+/// let source = "%IF DEBUG = 1 %THEN;\nA = 1;\n%ELSE;\nB = 2;\n%ENDIF;\n";
+/// let mut ctx = Context::new();
+/// ctx.set("DEBUG", Value::Int(1));
+/// assert!(would_emit(source, 2, &ctx));
+/// assert!(!would_emit(source, 4, &ctx));
+/// ```
+pub fn would_emit(source: &str, line_number: usize, ctx: &Context) -> bool {
+    let mut context = ctx.clone();
+    let mut macros = MacroTable::new();
+    let procs = ProcTable::new();
+    let mut diagnostics = DiagnosticSink::new();
+    let mut blocks = OpenBlocks::new(MAX_IF_DEPTH);
+
+    for (index, line) in source.lines().enumerate().take(line_number) {
+        let statements = split_statements(&tokenize_pli(line));
+        if index + 1 == line_number {
+            return match statements.first().and_then(|s| block_directive(s)) {
+                Some((BlockDirective::Else | BlockDirective::EndIf, _)) => {
+                    blocks.enclosing_active()
+                }
+                _ => blocks.active(),
+            };
+        }
+
+        for statement in &statements {
+            match block_directive(statement) {
+                Some((BlockDirective::If, condition)) => {
+                    let holds = blocks.active()
+                        && evaluate_condition_tokens(condition, &context) == Ok(true);
+                    let _ = blocks.open(holds);
+                }
+                Some((BlockDirective::Else, _)) => blocks.switch(),
+                Some((BlockDirective::EndIf, _)) => blocks.close(),
+                None if blocks.active() => {
+                    let code = match split_inline_if(statement) {
+                        Some((condition, code)) => {
+                            if evaluate_condition_tokens(condition, &context) != Ok(true) {
+                                continue;
+                            }
+                            code
+                        }
+                        None => statement,
+                    };
+                    apply_statement(
+                        code,
+                        index + 1,
+                        &mut context,
+                        &mut macros,
+                        &procs,
+                        false,
+                        &mut diagnostics,
+                    );
+                }
+                None => {}
+            }
+        }
+    }
+    false
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// PRIVATE STRUCTURES
+////////////////////////////////////////////////////////////////////////////////
+
+/// The `%IF ... %ELSE ... %ENDIF` blocks open at a point in the source.
+///
+/// Each open block records whether the code around it is active, whether one
+/// of its branches has been taken, and whether the current branch is active.
+/// An `%ELSE` or `%ENDIF` outside any block is ignored here, and a second
+/// `%ELSE` makes the rest of the block inactive; the block structure check
+/// reports them (`V007`, `V002`, `V008`).
+///
+/// At most `limit` blocks are kept. Blocks past the limit are only counted in
+/// `overflow`, and the code inside them is inactive.
+#[derive(Debug)]
+struct OpenBlocks {
+    blocks: Vec<(bool, bool, bool)>,
+    limit: usize,
+    overflow: usize,
+}
+
+impl OpenBlocks {
+    /// Creates an empty set of blocks that nest at most `limit` deep.
+    fn new(limit: usize) -> Self {
+        OpenBlocks {
+            blocks: Vec::new(),
+            limit,
+            overflow: 0,
+        }
+    }
+
+    /// Whether code at this point is active.
+    fn active(&self) -> bool {
+        self.overflow == 0 && self.blocks.last().is_none_or(|block| block.2)
+    }
+
+    /// Whether the code around the innermost block is active.
+    fn enclosing_active(&self) -> bool {
+        match self.overflow {
+            0 => self.blocks.last().is_none_or(|block| block.0),
+            1 => self.blocks.last().is_none_or(|block| block.2),
+            _ => false,
+        }
+    }
+
+    /// Opens a block whose `%THEN` branch is taken when `holds` is true and
+    /// the code around it is active.
+    ///
+    /// Returns an error for the first block past the depth limit.
+    fn open(&mut self, holds: bool) -> Result<(), String> {
+        if self.blocks.len() >= self.limit {
+            self.overflow += 1;
+            return match self.overflow {
+                1 => Err(depth_error(self.limit)),
+                _ => Ok(()),
+            };
+        }
+        let taken = holds && self.active();
+        self.blocks.push((self.active(), taken, taken));
+        Ok(())
+    }
+
+    /// Moves the innermost block to its `%ELSE` branch.
+    fn switch(&mut self) {
+        if self.overflow > 0 {
+            return;
+        }
+        if let Some(block) = self.blocks.last_mut() {
+            block.2 = block.0 && !block.1;
+            block.1 = true;
+        }
+    }
+
+    /// Closes the innermost block.
+    fn close(&mut self) {
+        if self.overflow > 0 {
+            self.overflow -= 1;
+        } else {
+            self.blocks.pop();
+        }
+    }
+
+    /// The number of open blocks.
+    fn depth(&self) -> usize {
+        self.blocks.len() + self.overflow
+    }
+
+    /// Closes every block opened after there were `depth` of them.
+    fn truncate(&mut self, depth: usize) {
+        self.overflow = depth.saturating_sub(self.blocks.len()).min(self.overflow);
+        self.blocks.truncate(depth);
+    }
+}
+
+/// A directive of the block form of `%IF`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BlockDirective {
+    If,
+    Else,
+    EndIf,
+}
+
+/// A `%MACRO` definition still waiting for its `%ENDMACRO`.
+///
+/// # Fields
+/// - `name`: The macro's name.
+/// - `line`: The 1-based line of the `%MACRO` header, where an unterminated
+///   definition is reported.
+/// - `body`: The body lines collected so far.
+struct OpenMacro {
+    name: String,
+    line: usize,
+    body: Vec<String>,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// PRIVATE FUNCTIONS
+////////////////////////////////////////////////////////////////////////////////
+
+/// Starts a `%MACRO NAME; body %ENDMACRO;` definition.
+///
+/// The body may follow the header on the same line and may be closed on the
+/// same line, in which case the macro is defined immediately.
+///
+/// # Arguments
+/// - `content`: The raw source line holding the `%MACRO` header.
+/// - `tokens`: The tokens of that line.
+/// - `line`: The 1-based line number, for diagnostics.
+/// - `macros`: The table receiving completed definitions.
+/// - `diagnostics`: The sink receiving malformed-header errors.
+///
+/// # Returns
+/// - `Option<OpenMacro>`: The still-open definition, or `None` if it was
+///   completed or rejected.
+fn start_macro_definition(
+    content: &str,
+    tokens: &[Token],
+    line: usize,
+    macros: &mut MacroTable,
+    diagnostics: &mut DiagnosticSink,
+) -> Option<OpenMacro> {
+    let name = match tokens.get(1) {
+        Some(token) if token.category == TokenCategory::Identifier => token.value.clone(),
+        _ => {
+            diagnostics.error(line, "%MACRO requires a name");
+            return None;
+        }
+    };
+
+    // The body starts after the `;` terminating the header.
+    let remainder = content.split_once(';').map_or("", |(_, rest)| rest);
+    let (fragment, closed) = split_macro_body(remainder);
+    let body: Vec<String> = if fragment.is_empty() {
+        Vec::new()
+    } else {
+        vec![fragment]
+    };
+
+    if closed {
+        macros.define(&name, &body.join("\n"));
+        None
+    } else {
+        Some(OpenMacro { name, line, body })
+    }
+}
+
+/// Applies a statement that is a `%DECLARE`/`%DCL`, `%SET`, `%ACTIVATE`,
+/// `%DEACTIVATE`, `%PRINT`, or `%NOPRINT` directive.
+///
+/// `%PRINT` and `%NOPRINT` only control the source listing (see
+/// `output::format_listing`), so they are consumed without effect here.
+/// A `%INCLUDE` or `%XINCLUDE` that reaches this point was not expanded, as
+/// no `IncludeResolver` was given; it is reported (`I004`) and consumed.
+///
+/// In `%ACTIVATE A NORESCAN, B;`, a `RESCAN` or `NORESCAN` keyword sets
+/// whether the macro named before it is rescanned.
+///
+/// # Arguments
+/// - `statement`: The statement's tokens, as split by `split_statements`.
+/// - `line`: The 1-based line number, for diagnostics.
+/// - `context`: The preprocessor variables to update.
+/// - `macros`: The macro table whose activation state may change.
+/// - `procs`: The preprocessor procedures `%SET` expressions may call.
+/// - `strict`: Whether `%SET` targets must have been declared.
+/// - `diagnostics`: The sink receiving errors and warnings.
+///
+/// # Returns
+/// - `bool`: `true` if the statement was one of these directives and is
+///   therefore consumed, `false` if it belongs in the output.
+fn apply_statement(
+    statement: &[Token],
+    line: usize,
+    context: &mut Context,
+    macros: &mut MacroTable,
+    procs: &ProcTable,
+    strict: bool,
+    diagnostics: &mut DiagnosticSink,
+) -> bool {
+    match statement.first().map(|t| t.value.as_str()) {
+        Some("%DECLARE" | "%DCL") => match parse_declaration(statement) {
+            Ok(variables) => {
+                for (name, var_type) in variables {
+                    context.declare(&name, var_type);
+                }
+            }
+            Err(e) => diagnostics.error(line, &e),
+        },
+        Some("%SET") => {
+            if let Err(diagnostic) = apply_set(statement, line, context, procs, strict) {
+                diagnostics.push(diagnostic);
+            }
+        }
+        Some(directive @ ("%ACTIVATE" | "%DEACTIVATE")) => {
+            let activate = directive == "%ACTIVATE";
+            let mut previous: Option<&str> = None;
+            for name in statement[1..]
+                .iter()
+                .filter(|t| t.category == TokenCategory::Identifier)
+            {
+                let result = match (name.value.as_str(), previous) {
+                    ("RESCAN" | "NORESCAN", Some(target)) if activate => {
+                        macros.set_macro_rescan(target, name.value == "RESCAN")
+                    }
+                    _ if activate => macros.activate(&name.value),
+                    _ => macros.deactivate(&name.value),
+                };
+                previous = Some(&name.value);
+                if let Err(e) = result {
+                    diagnostics.warning(line, &e);
+                }
+            }
+        }
+        Some("%PRINT" | "%NOPRINT") => {}
+        Some(directive @ ("%INCLUDE" | "%XINCLUDE")) => diagnostics.error_with_code(
+            line,
+            "I004",
+            &format!("{} was not expanded: no include resolver", directive),
+        ),
+        _ => return false,
+    }
+    true
+}
+
+/// Recognizes a statement of the block form of `%IF`, after any `%LABEL:`
+/// prefix: `%IF condition %THEN;` (with nothing after `%THEN`), `%ELSE`, or
+/// `%ENDIF`.
+///
+/// # Arguments
+/// - `statement`: The statement's tokens.
+///
+/// # Returns
+/// - `Option<(BlockDirective, &[Token])>`: The directive and, for `%IF`, the
+///   condition tokens between `%IF` and `%THEN`; `None` for any other
+///   statement, including the inline `%IF ... %THEN statement;`.
+fn block_directive(statement: &[Token]) -> Option<(BlockDirective, &[Token])> {
+    let statement = split_label(statement).map_or(statement, |(_, rest)| rest);
+    match statement.first()?.value.as_str() {
+        "%IF" if split_inline_if(statement).is_none() => {
+            let then = statement.iter().position(|t| t.value == "%THEN");
+            Some((
+                BlockDirective::If,
+                &statement[1..then.unwrap_or(statement.len())],
+            ))
+        }
+        "%ELSE" => Some((BlockDirective::Else, &[])),
+        "%ENDIF" => Some((BlockDirective::EndIf, &[])),
+        _ => None,
+    }
+}
+
+/// Applies a tokenized `%SET name = expression;` statement to the context.
+///
+/// The expression is evaluated with the typed evaluator, so it may combine
+/// numbers, quoted strings, and variables (replaced by their current values).
+///
+/// # Arguments
+/// - `tokens`: The statement's tokens, starting with `%SET`.
+/// - `line`: The 1-based line number, for the error.
+/// - `context`: The preprocessor variables to update.
+/// - `procs`: The preprocessor procedures the expression may call.
+/// - `strict`: Whether the target must have been declared.
+///
+/// # Returns
+/// - `Result<(), Diagnostic>`: An error if the statement is malformed, the
+///   target is undeclared in strict mode, or the expression cannot be
+///   evaluated; only the last carries a code, `X001`.
+fn apply_set(
+    tokens: &[Token],
+    line: usize,
+    context: &mut Context,
+    procs: &ProcTable,
+    strict: bool,
+) -> Result<(), Diagnostic> {
+    let error = |message: &str| Diagnostic::new(Severity::Error, line, message);
+    let name = match tokens.get(1) {
+        Some(token) if token.category == TokenCategory::Identifier => &token.value,
+        _ => return Err(error("%SET requires a variable name")),
+    };
+    if tokens.get(2).map(|t| t.value.as_str()) != Some("=") {
+        return Err(error(&format!("%SET {} requires '='", name)));
+    }
+    if strict && !context.is_declared(name) {
+        return Err(error(&format!("%SET target {} is not declared", name)));
+    }
+
+    let expression: Vec<Token> = tokens[3..]
+        .iter()
+        .take_while(|t| t.value != ";")
+        .cloned()
+        .collect();
+    let value = procs
+        .evaluate(&expression, context)
+        .map_err(|e| error(&e).with_code("X001"))?;
+
+    context.set(name, value);
+    Ok(())
+}
+
+/// Writes a line that is not preprocessed, such as `%XINCLUDE`d content or
+/// comment text, exactly as it is.
+///
+/// # Arguments
+/// - `sink`: The output receiving the line; nothing is written in a dry run.
+/// - `content`: The line to write.
+/// - `line`: The 1-based line number, for the output limit error.
+/// - `options`: The run's options.
+/// - `output_bytes`: The output produced so far, updated with this line.
+/// - `stats`: The run's counters, updated with this line.
+///
+/// # Returns
+/// - `Result<(), PreprocessorError>`: An error if the line takes the output
+///   past `max_output_bytes` or cannot be written.
+fn write_unprocessed(
+    sink: &mut dyn OutputSink,
+    content: &str,
+    line: usize,
+    options: &ProcessOptions,
+    output_bytes: &mut usize,
+    stats: &mut ProcessStats,
+) -> Result<(), PreprocessorError> {
+    *output_bytes += content.len() + 1;
+    if let Some(limit) = options
+        .max_output_bytes
+        .filter(|&limit| *output_bytes > limit)
+    {
+        return Err(output_limit_error(limit, line));
+    }
+    if !options.dry_run {
+        sink.write_line(content)?;
+        stats.lines_written += 1;
+    }
+    Ok(())
+}
+
+/// Reports whether a line's tokens end inside a `/* ... */` comment, which
+/// then continues on the next line.
+fn leaves_comment_open(tokens: &[Token]) -> bool {
+    tokens.last().is_some_and(|token| {
+        token.category == TokenCategory::Comment
+            && (token.value.len() < 4 || !token.value.ends_with("*/"))
+    })
+}
+
+/// Builds the error that ends a run whose output passed `max_output_bytes`.
+fn output_limit_error(limit: usize, line: usize) -> PreprocessorError {
+    PreprocessorError::Other(format!(
+        "Output exceeds the limit of {} bytes at line {}",
+        limit, line
+    ))
+}
+
+/// Converts an error that ended a run into a diagnostic on line 0, keeping its
+/// stable code.
+fn error_diagnostic(error: &PreprocessorError) -> Diagnostic {
+    let diagnostic = Diagnostic::new(Severity::Error, 0, &error.to_string());
+    match error.code() {
+        Some(code) => diagnostic.with_code(code),
+        None => diagnostic,
+    }
+}
+
+/// Returns the error for a statement that starts with `directive`, if it opens
+/// or closes a `%DO` group. An `%END` that closes a `%PROCEDURE` never gets
+/// here, as the procedure definition consumes it.
+fn unsupported_group(directive: &str) -> Option<&'static str> {
+    match directive {
+        "%DO" => Some("%DO groups are not supported"),
+        "%END" => Some("%END without %PROCEDURE; %DO groups are not supported"),
+        _ => None,
+    }
+}
+
+/// Returns `true` if the line's first token is the `%COMMENT` directive.
+fn starts_with_comment_directive(content: &str) -> bool {
+    let trimmed = content.trim_start();
+    trimmed
+        .get(..8)
+        .is_some_and(|keyword| keyword.eq_ignore_ascii_case("%COMMENT"))
+        && !trimmed[8..]
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Logs the diagnostics pushed since the last call, by severity.
+///
+/// Diagnostics that carry a column are rendered with
+/// `format_diagnostic_with_caret` against their line; all others are logged on
+/// a single line.
+///
+/// # Arguments
+/// - `diagnostics`: The sink holding every diagnostic reported so far.
+/// - `logged`: How many diagnostics have already been logged; advanced here.
+/// - `lines`: The source lines the diagnostics are numbered against.
+fn log_new_diagnostics(diagnostics: &DiagnosticSink, logged: &mut usize, lines: &[String]) {
+    for diagnostic in &diagnostics.diagnostics()[*logged..] {
+        let source = diagnostic
+            .line
+            .checked_sub(1)
+            .and_then(|index| lines.get(index));
+        let text = match (diagnostic.column, source) {
+            (Some(column), Some(source)) => {
+                format_diagnostic_with_caret(source, column, &diagnostic.to_string())
+            }
+            _ => diagnostic.to_string(),
+        };
+        match diagnostic.severity {
+            Severity::Error => error!("{}", text),
+            Severity::Warning => warn!("{}", text),
+            Severity::Note => info!("{}", text),
+        }
+    }
+    *logged = diagnostics.diagnostics().len();
+}
@@ -0,0 +1,420 @@
+#![allow(dead_code)] // Suppress warnings for unused functions in this module.
+
+////////////////////////////////////////////////////////////////////////////////
+// MODULE NAME: Preprocessor Procedures
+// ----------------------------------------------------------------------------
+// DESCRIPTION:
+// This module holds user-defined preprocessor functions, written as
+//
+//     %NAME: PROCEDURE(PARAM);
+//       %RETURN(expression);
+//     %END;
+//
+// and evaluates references to them inside preprocessor expressions.
+//
+// FUNCTIONALITY:
+// - Provides `ProcTable`, which registers procedures by name, analogous to
+//   `MacroTable` for macros.
+// - Parses procedure headers and `%RETURN` statements from tokens.
+// - Collects a definition statement by statement with `ProcBuilder`, so it
+//   may span several lines.
+// - Evaluates expressions in which procedure calls are replaced by their
+//   `%RETURN` values.
+//
+// USAGE:
+// - Feed the statements that follow a header (see `parse_proc_header`) to a
+//   `ProcBuilder` until it reports the closing `%END`.
+// - Call `ProcTable::evaluate` instead of the plain evaluator wherever an
+//   expression may call a procedure.
+//
+// AUTHOR: FirstLink Consulting Services (FLCS)
+// LICENSE: MIT License
+// DATE: 10/16/2026
+// VERSION: 1.0.0
+////////////////////////////////////////////////////////////////////////////////
+
+////////////////////////////////////////////////////////////////////////////////
+// IMPORTS
+////////////////////////////////////////////////////////////////////////////////
+
+use crate::modules::context::{Context, Value};
+use crate::modules::evaluator::evaluate_tokens;
+use crate::modules::tokenizer::{Token, TokenCategory};
+use std::collections::HashMap;
+
+////////////////////////////////////////////////////////////////////////////////
+// CONSTANTS
+////////////////////////////////////////////////////////////////////////////////
+
+/// The deepest chain of nested procedure calls evaluated before giving up,
+/// which stops runaway recursion.
+pub const MAX_CALL_DEPTH: usize = 64;
+
+////////////////////////////////////////////////////////////////////////////////
+// PUBLIC STRUCTURES
+////////////////////////////////////////////////////////////////////////////////
+
+/// A procedure registered in a `ProcTable`.
+///
+/// # Fields
+/// - `name`: The uppercase procedure name, without the `%`.
+/// - `params`: The uppercase parameter names, in order.
+/// - `body`: The tokens of the `%RETURN` expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcDefinition {
+    pub name: String,
+    pub params: Vec<String>,
+    pub body: Vec<Token>,
+}
+
+/// The preprocessor procedures defined so far in a source.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::context::{Context, Value};
+/// # use pli_preprocessor::modules::procedures::ProcTable;
+/// # use pli_preprocessor::modules::tokenizer::tokenize_pli;
+/// let mut procs = ProcTable::new();
+/// procs.define("TWENTY", Vec::new(), tokenize_pli("10 * 2"));
+/// let value = procs.evaluate(&tokenize_pli("TWENTY + 1"), &Context::new());
+/// assert_eq!(value, Ok(Value::Int(21)));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProcTable {
+    procs: HashMap<String, ProcDefinition>,
+}
+
+impl ProcTable {
+    /// Creates an empty procedure table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Defines (or redefines) a procedure.
+    pub fn define(&mut self, name: &str, params: Vec<String>, body: Vec<Token>) {
+        let name = name.to_uppercase();
+        self.procs.insert(
+            name.clone(),
+            ProcDefinition {
+                name,
+                params: params.iter().map(|p| p.to_uppercase()).collect(),
+                body,
+            },
+        );
+    }
+
+    /// Returns the definition of a procedure, if any.
+    pub fn get(&self, name: &str) -> Option<&ProcDefinition> {
+        self.procs.get(&name.to_uppercase())
+    }
+
+    /// Returns `true` if a procedure with the given name is defined.
+    pub fn is_defined(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Calls a procedure with already evaluated arguments.
+    ///
+    /// The arguments are bound to the parameters in a copy of `context`, so
+    /// the caller's variables are visible but never modified.
+    ///
+    /// # Returns
+    /// - `Result<Value, String>`: The `%RETURN` value, or an error message if
+    ///   the procedure is undefined, the argument count is wrong, or its
+    ///   expression fails.
+    pub fn call(&self, name: &str, args: &[Value], context: &Context) -> Result<Value, String> {
+        self.call_at_depth(name, args, context, 0)
+    }
+
+    /// Evaluates an expression, replacing each procedure call with its value.
+    ///
+    /// A call is a procedure name, followed by a parenthesized, comma-separated
+    /// argument list unless the procedure takes no parameters.
+    ///
+    /// # Arguments
+    /// - `tokens`: The expression's tokens.
+    /// - `context`: The variables the expression may refer to.
+    ///
+    /// # Returns
+    /// - `Result<Value, String>`: The value, or an error message.
+    pub fn evaluate(&self, tokens: &[Token], context: &Context) -> Result<Value, String> {
+        self.evaluate_at_depth(tokens, context, 0)
+    }
+
+    fn evaluate_at_depth(
+        &self,
+        tokens: &[Token],
+        context: &Context,
+        depth: usize,
+    ) -> Result<Value, String> {
+        let tokens = self.substitute_calls(tokens, context, depth)?;
+        evaluate_tokens(&tokens.iter().collect::<Vec<_>>(), context)
+    }
+
+    fn call_at_depth(
+        &self,
+        name: &str,
+        args: &[Value],
+        context: &Context,
+        depth: usize,
+    ) -> Result<Value, String> {
+        let definition = self
+            .get(name)
+            .ok_or_else(|| format!("Undefined procedure: {}", name.to_uppercase()))?;
+        if depth >= MAX_CALL_DEPTH {
+            return Err(format!(
+                "Procedure calls nested too deeply in {}",
+                definition.name
+            ));
+        }
+        if args.len() != definition.params.len() {
+            return Err(format!(
+                "Procedure {} expects {} argument(s), got {}",
+                definition.name,
+                definition.params.len(),
+                args.len()
+            ));
+        }
+
+        let mut local = context.clone();
+        for (param, value) in definition.params.iter().zip(args) {
+            local.set(param, value.clone());
+        }
+        self.evaluate_at_depth(&definition.body, &local, depth + 1)
+    }
+
+    /// Replaces every procedure call in `tokens` by a token holding its value.
+    fn substitute_calls(
+        &self,
+        tokens: &[Token],
+        context: &Context,
+        depth: usize,
+    ) -> Result<Vec<Token>, String> {
+        let mut substituted = Vec::with_capacity(tokens.len());
+        let mut index = 0;
+        while index < tokens.len() {
+            let token = &tokens[index];
+            index += 1;
+            let definition = match self.get(&token.value) {
+                Some(definition) if token.category == TokenCategory::Identifier => definition,
+                _ => {
+                    substituted.push(token.clone());
+                    continue;
+                }
+            };
+
+            let mut args = Vec::new();
+            if tokens.get(index).map(|t| t.value.as_str()) == Some("(") {
+                let close = closing_paren(tokens, index).ok_or_else(|| {
+                    format!("Unclosed argument list for procedure {}", definition.name)
+                })?;
+                for arg in split_arguments(&tokens[index + 1..close]) {
+                    args.push(self.evaluate_at_depth(arg, context, depth)?);
+                }
+                index = close + 1;
+            }
+            let value = self.call_at_depth(&definition.name, &args, context, depth)?;
+            substituted.push(value_token(&value).with_column(token.column));
+        }
+        Ok(substituted)
+    }
+}
+
+/// Collects a procedure definition statement by statement.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::procedures::{ProcBuilder, ProcTable};
+/// # use pli_preprocessor::modules::tokenizer::tokenize_pli;
+/// let mut builder = ProcBuilder::new("TWENTY", Vec::new());
+/// assert_eq!(builder.add_statement(&tokenize_pli("%RETURN(10 * 2);")), Ok(false));
+/// assert_eq!(builder.add_statement(&tokenize_pli("%END;")), Ok(true));
+/// let mut procs = ProcTable::new();
+/// builder.finish(&mut procs).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcBuilder {
+    pub name: String,
+    params: Vec<String>,
+    body: Option<Vec<Token>>,
+}
+
+impl ProcBuilder {
+    /// Starts the definition of procedure `name`.
+    pub fn new(name: &str, params: Vec<String>) -> Self {
+        Self {
+            name: name.to_uppercase(),
+            params,
+            body: None,
+        }
+    }
+
+    /// Adds one statement of the procedure body.
+    ///
+    /// # Returns
+    /// - `Result<bool, String>`: `true` once the closing `%END` is reached, or
+    ///   an error message for a malformed or unsupported statement.
+    pub fn add_statement(&mut self, statement: &[Token]) -> Result<bool, String> {
+        match statement.first().map(|t| t.value.as_str()) {
+            Some("%END") => Ok(true),
+            Some("%RETURN") => {
+                self.body = Some(parse_return(statement)?);
+                Ok(false)
+            }
+            _ => Err(format!(
+                "Unsupported statement in procedure {}; only %RETURN is allowed",
+                self.name
+            )),
+        }
+    }
+
+    /// Registers the completed procedure in `procs`.
+    ///
+    /// # Returns
+    /// - `Result<(), String>`: An error message if no `%RETURN` was seen.
+    pub fn finish(self, procs: &mut ProcTable) -> Result<(), String> {
+        match self.body {
+            Some(body) => {
+                procs.define(&self.name, self.params, body);
+                Ok(())
+            }
+            None => Err(format!("Procedure {} has no %RETURN", self.name)),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// PUBLIC FUNCTIONS
+////////////////////////////////////////////////////////////////////////////////
+
+/// Parses a `%NAME: PROCEDURE(P1, P2);` header (`PROC` is accepted too).
+///
+/// # Arguments
+/// - `tokens`: The tokens of one statement.
+///
+/// # Returns
+/// - `Option<Result<(String, Vec<String>), String>>`: `None` if the statement
+///   is not a procedure header; otherwise the name and parameter names, or an
+///   error message for a malformed parameter list.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::procedures::parse_proc_header;
+/// # use pli_preprocessor::modules::tokenizer::tokenize_pli;
+/// let header = parse_proc_header(&tokenize_pli("%DOUBLE: PROCEDURE(N);"));
+/// assert_eq!(header, Some(Ok(("DOUBLE".to_string(), vec!["N".to_string()]))));
+/// ```
+pub fn parse_proc_header(tokens: &[Token]) -> Option<Result<(String, Vec<String>), String>> {
+    let (name, rest) = match tokens {
+        [name, colon, keyword, rest @ ..]
+            if name.category == TokenCategory::Directive
+                && colon.value == ":"
+                && matches!(keyword.value.as_str(), "PROCEDURE" | "PROC") =>
+        {
+            (name.value.trim_start_matches('%').to_string(), rest)
+        }
+        _ => return None,
+    };
+
+    if rest.first().map(|t| t.value.as_str()) != Some("(") {
+        return Some(Ok((name, Vec::new())));
+    }
+    let close = match closing_paren(rest, 0) {
+        Some(close) => close,
+        None => {
+            return Some(Err(format!(
+                "Unclosed parameter list for procedure {}",
+                name
+            )))
+        }
+    };
+    let mut params = Vec::new();
+    for param in split_arguments(&rest[1..close]) {
+        match param {
+            [token] if token.category == TokenCategory::Identifier => {
+                params.push(token.value.clone())
+            }
+            _ => {
+                return Some(Err(format!(
+                    "Invalid parameter list for procedure {}",
+                    name
+                )))
+            }
+        }
+    }
+    Some(Ok((name, params)))
+}
+
+/// Extracts the expression of a `%RETURN(expression);` statement.
+///
+/// # Returns
+/// - `Result<Vec<Token>, String>`: The expression's tokens, or an error message
+///   if the expression is missing or not parenthesized.
+pub fn parse_return(tokens: &[Token]) -> Result<Vec<Token>, String> {
+    let close = match tokens.get(1) {
+        Some(open) if open.value == "(" => closing_paren(tokens, 1),
+        _ => None,
+    };
+    match close {
+        Some(close) if close > 2 => Ok(tokens[2..close].to_vec()),
+        _ => Err("%RETURN requires a parenthesized expression".to_string()),
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// PRIVATE FUNCTIONS
+////////////////////////////////////////////////////////////////////////////////
+
+/// Returns the index of the `)` matching the `(` at `open`.
+fn closing_paren(tokens: &[Token], open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (index, token) in tokens.iter().enumerate().skip(open) {
+        match token.value.as_str() {
+            "(" => depth += 1,
+            ")" => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(index);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Splits an argument list at its top-level commas; an empty list has no
+/// arguments.
+fn split_arguments(tokens: &[Token]) -> Vec<&[Token]> {
+    if tokens.is_empty() {
+        return Vec::new();
+    }
+    let mut args = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (index, token) in tokens.iter().enumerate() {
+        match token.value.as_str() {
+            "(" => depth += 1,
+            ")" => depth -= 1,
+            "," if depth == 0 => {
+                args.push(&tokens[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    args.push(&tokens[start..]);
+    args
+}
+
+/// Builds a token that evaluates to `value`.
+fn value_token(value: &Value) -> Token {
+    match value {
+        Value::Str(text) => Token::new(
+            &format!("'{}'", text.replace('\'', "''")),
+            TokenCategory::Literal,
+            None,
+        ),
+        _ => Token::new(&value.to_string(), TokenCategory::Identifier, None),
+    }
+}
@@ -0,0 +1,221 @@
+#![allow(dead_code)] // Suppress warnings for unused functions in this module.
+
+////////////////////////////////////////////////////////////////////////////////
+// MODULE NAME: Document Symbols
+// ----------------------------------------------------------------------------
+// DESCRIPTION:
+// This module extracts the top-level constructs of a PL/I source file, in the
+// shape editors expect for an LSP "document symbols" outline.
+//
+// FUNCTIONALITY:
+// - Reports `%MACRO` definitions with the lines they span.
+// - Reports `%INCLUDE` and `%XINCLUDE` directives with the file they name.
+// - Reports every variable named by a `DECLARE`/`DCL` statement (including the
+//   `%DECLARE`/`%DCL` preprocessor forms), spanning up to the closing `;`.
+//
+// USAGE:
+// - Call `document_symbols` with the full source text.
+// - Use `declared_names` to list the variables of one tokenized declaration.
+//
+// AUTHOR: FirstLink Consulting Services (FLCS)
+// LICENSE: MIT License
+// DATE: 10/16/2026
+// VERSION: 1.0.0
+////////////////////////////////////////////////////////////////////////////////
+
+////////////////////////////////////////////////////////////////////////////////
+// IMPORTS
+////////////////////////////////////////////////////////////////////////////////
+
+use crate::modules::include_handler::{include_targets, xinclude_targets};
+use crate::modules::macro_expander::split_macro_body;
+use crate::modules::tokenizer::{tokenize_pli, Token, TokenCategory};
+
+////////////////////////////////////////////////////////////////////////////////
+// PUBLIC STRUCTURES
+////////////////////////////////////////////////////////////////////////////////
+
+/// The kind of construct a `DocumentSymbol` describes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SymbolKind {
+    Macro,
+    Include,
+    Declare,
+}
+
+/// A named top-level construct and the lines it covers.
+///
+/// # Fields
+/// - `name`: The macro name, included file, or declared variable.
+/// - `kind`: What sort of construct this is.
+/// - `start_line`: The 1-based line where the construct begins.
+/// - `end_line`: The 1-based line where it ends (inclusive).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocumentSymbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+impl DocumentSymbol {
+    /// Creates a new `DocumentSymbol`.
+    pub fn new(name: &str, kind: SymbolKind, start_line: usize, end_line: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            kind,
+            start_line,
+            end_line,
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// PUBLIC FUNCTIONS
+////////////////////////////////////////////////////////////////////////////////
+
+/// Extracts macro definitions, includes, and declared variables from a source.
+///
+/// # Arguments
+/// - `source`: The full text of a PL/I source file.
+///
+/// # Returns
+/// - `Vec<DocumentSymbol>`: The symbols in the order they start.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::symbols::{document_symbols, DocumentSymbol, SymbolKind};
+/// let symbols = document_symbols("%INCLUDE 'defs.pli';\nDCL X FIXED;");
+/// assert_eq!(symbols[0], DocumentSymbol::new("defs.pli", SymbolKind::Include, 1, 1));
+/// assert_eq!(symbols[1], DocumentSymbol::new("X", SymbolKind::Declare, 2, 2));
+/// ```
+pub fn document_symbols(source: &str) -> Vec<DocumentSymbol> {
+    let mut symbols = Vec::new();
+    let mut lines = source.lines().enumerate();
+
+    while let Some((index, content)) = lines.next() {
+        let start_line = index + 1;
+        let tokens = tokenize_pli(content);
+        let keyword = match tokens.first() {
+            Some(token) => token.value.as_str(),
+            None => continue,
+        };
+
+        match keyword {
+            "%MACRO" => {
+                let name = match tokens.get(1) {
+                    Some(token) if token.category == TokenCategory::Identifier => &token.value,
+                    _ => continue,
+                };
+                let header_rest = content.split_once(';').map_or("", |(_, rest)| rest);
+                let mut end_line = start_line;
+                if !split_macro_body(header_rest).1 {
+                    // An unterminated definition runs to the end of the source.
+                    for (body_index, body) in lines.by_ref() {
+                        end_line = body_index + 1;
+                        if split_macro_body(body).1 {
+                            break;
+                        }
+                    }
+                }
+                symbols.push(DocumentSymbol::new(
+                    name,
+                    SymbolKind::Macro,
+                    start_line,
+                    end_line,
+                ));
+            }
+            "%INCLUDE" | "%XINCLUDE" => {
+                let mut paths = include_targets(content);
+                paths.extend(xinclude_targets(content));
+                for path in paths {
+                    symbols.push(DocumentSymbol::new(
+                        &path,
+                        SymbolKind::Include,
+                        start_line,
+                        start_line,
+                    ));
+                }
+            }
+            "DECLARE" | "DCL" | "%DECLARE" | "%DCL" => {
+                let mut statement = tokens.clone();
+                let mut end_line = start_line;
+                while !statement.iter().any(|t| t.value == ";") {
+                    match lines.next() {
+                        Some((next_index, next)) => {
+                            end_line = next_index + 1;
+                            statement.extend(tokenize_pli(next));
+                        }
+                        None => break,
+                    }
+                }
+                for name in declared_names(&statement) {
+                    symbols.push(DocumentSymbol::new(
+                        &name,
+                        SymbolKind::Declare,
+                        start_line,
+                        end_line,
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    symbols
+}
+
+/// Lists the variables named by a tokenized `DECLARE` statement.
+///
+/// Handles comma-separated declarations (`DCL A FIXED, B CHAR;`) and factored
+/// name lists (`DCL (A, B) FIXED;`). Attribute parentheses such as `CHAR(10)`
+/// are skipped.
+///
+/// # Arguments
+/// - `tokens`: The statement's tokens, starting with the `DECLARE` keyword.
+///
+/// # Returns
+/// - `Vec<String>`: The declared names, uppercased, in source order.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::symbols::declared_names;
+/// # use pli_preprocessor::modules::tokenizer::tokenize_pli;
+/// let names = declared_names(&tokenize_pli("DCL (A, B) FIXED, C CHAR(8);"));
+/// assert_eq!(names, vec!["A", "B", "C"]);
+/// ```
+pub fn declared_names(tokens: &[Token]) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut depth = 0;
+    // Whether the next identifier names a variable rather than an attribute.
+    let mut expect_name = true;
+    // Whether we are inside a factored `(A, B)` name list.
+    let mut in_name_list = false;
+
+    for token in tokens.iter().skip(1) {
+        match token.value.as_str() {
+            ";" => break,
+            "(" => {
+                if expect_name && depth == 0 {
+                    in_name_list = true;
+                }
+                depth += 1;
+            }
+            ")" => {
+                depth -= 1;
+                if depth == 0 {
+                    in_name_list = false;
+                }
+                expect_name = false;
+            }
+            "," => expect_name = depth == 0 || in_name_list,
+            _ if token.category == TokenCategory::Identifier && expect_name => {
+                names.push(token.value.clone());
+                expect_name = false;
+            }
+            _ => expect_name = false,
+        }
+    }
+
+    names
+}
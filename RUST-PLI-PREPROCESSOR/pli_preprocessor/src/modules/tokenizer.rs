@@ -16,13 +16,28 @@
 // FUNCTION INVENTORY:
 // -----------------------------------------------------------------------------
 // - tokenize_pli: Tokenizes PL/I input into tokens.
+// - tokenize_pli_with_config: Tokenizes PL/I input using a `TokenizerConfig`.
+// - tokenize_pli_preserve_ws: Tokenizes PL/I input, keeping whitespace tokens.
+// - TokenIter: Lazily yields the tokens of PL/I input one at a time.
+// - CharClassConfig: Defines a dialect's identifier and operator characters.
 // - get_directive_category: Retrieves the directive category.
 // - handle_directive: Processes directives starting with `%`.
 // - handle_string_literal: Handles string literals enclosed in quotes.
-// - handle_special_characters: Tokenizes special characters like `;` and `=`.
+// - take_literal_suffix: Folds `B`/`X`/`B4`/`GX` literal suffixes into literals.
+// - handle_special_characters: Tokenizes special characters like `;`, `=`, and `->`.
+// - handle_block_comment: Reads a `/* ... */` comment into a single token.
 // - finalize_token: Finalizes the current token being constructed.
 // - has_tokenizer_error: Detects errors like unmatched string literals.
 // - is_valid_preprocessor_directive: Validates the presence of valid directives.
+// - directive_histogram: Counts directive tokens per directive category.
+// - token_stats: Summarizes token, identifier, and directive counts.
+// - format_token_stats_table / format_token_stats_json: Render `TokenStats`.
+// - filter_by_category: Selects the tokens of one token category.
+// - filter_directives: Selects the directive tokens of one directive category.
+// - strip_comments: Drops `/* */` comments and `%COMMENT` statements.
+// - classify_line: Classifies a whole line as blank, directive, comment, or code.
+// - join_continued_tokens: Stitches tokens continued across lines back together.
+// - report_tokenizer_errors: Pushes tokenizer errors into a `DiagnosticSink`.
 //
 // -----------------------------------------------------------------------------
 // AUTHOR:
@@ -40,21 +55,45 @@
 // - FirstLink Consulting Services (FLCS)
 // -----------------------------------------------------------------------------
 ////////////////////////////////////////////////////////////////////////////////
-use log::debug;
-use std::collections::HashMap;
-use std::iter::Peekable;
-use std::str::Chars;
+use crate::modules::diagnostics::{Diagnostic, DiagnosticSink, Severity};
+use crate::modules::directive::{builtin_category, DirectiveRegistry};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::iter::{Enumerate, Peekable};
+use std::str::{Chars, FromStr};
+
+/// The character stream consumed by the tokenizer helpers. Each character is
+/// paired with its 0-based character index in the line, from which token
+/// columns are derived.
+pub type CharStream<'a> = Peekable<Enumerate<Chars<'a>>>;
 
 ////////////////////////////////////////////////////////////////////////////////
 // FUNCTION INVENTORY
 // -----------------------------------------------------------------------------
 // - tokenize_pli: Splits input strings into tokens.
+// - tokenize_pli_with_config: Splits input strings using a `TokenizerConfig`.
+// - tokenize_pli_preserve_ws: Tokenizes PL/I input, keeping whitespace tokens.
+// - TokenIter: Lazily yields the tokens of PL/I input one at a time.
+// - CharClassConfig: Defines a dialect's identifier and operator characters.
 // - handle_directive: Processes directives starting with `%`.
 // - handle_string_literal: Handles string literals enclosed in quotes.
-// - handle_special_characters: Tokenizes special characters like `;` and `=`.
+// - take_literal_suffix: Folds `B`/`X`/`B4`/`GX` literal suffixes into literals.
+// - handle_special_characters: Tokenizes special characters like `;`, `=`, and `->`.
+// - handle_block_comment: Reads a `/* ... */` comment into a single token.
 // - finalize_token: Finalizes the current token being constructed.
 // - has_tokenizer_error: Checks for tokenizer errors like unmatched strings.
 // - is_valid_preprocessor_directive: Validates the presence of a valid directive.
+// - directive_histogram: Counts directive tokens per directive category.
+// - token_stats: Summarizes token, identifier, and directive counts.
+// - format_token_stats_table / format_token_stats_json: Render `TokenStats`.
+// - filter_by_category: Selects the tokens of one token category.
+// - filter_directives: Selects the directive tokens of one directive category.
+// - strip_comments: Drops `/* */` comments and `%COMMENT` statements.
+// - classify_line: Classifies a whole line as blank, directive, comment, or code.
+// - join_continued_tokens: Stitches tokens continued across lines back together.
+// - report_tokenizer_errors: Pushes tokenizer errors into a `DiagnosticSink`.
 ////////////////////////////////////////////////////////////////////////////////
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -62,17 +101,32 @@ use std::str::Chars;
 // -----------------------------------------------------------------------------
 // Represents a token in the PL/I tokenizer. Each token consists of its raw text
 // value, a general category, and an optional specific category if it is a directive.
+// Literal tokens additionally record their `LiteralKind`. `column` is the
+// 1-based character column where the token starts in its line, or 0 when the
+// token was not produced from source text. `original` is the exact source text
+// of the token before normalization (e.g. before uppercasing identifiers).
+// `unterminated` marks a string literal that reached the end of the line
+// without its closing quote.
+// Tokens serialize with serde, which the regression corpus uses to store
+// expected tokenizer output as JSON.
 // -----------------------------------------------------------------------------
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Token {
     pub value: String,
     pub category: TokenCategory,
     pub directive_category: Option<DirectiveCategory>,
+    pub literal_kind: Option<LiteralKind>,
+    pub column: usize,
+    pub original: String,
+    #[serde(default)]
+    pub unterminated: bool,
 }
 
 impl Token {
     /// Creates a new `Token` instance.
     ///
+    /// The token's `original` text defaults to `value`.
+    ///
     /// # Parameters:
     /// - `value`: The raw text of the token.
     /// - `category`: The general category of the token.
@@ -89,8 +143,74 @@ impl Token {
             value: value.to_string(),
             category,
             directive_category,
+            literal_kind: None,
+            column: 0,
+            original: value.to_string(),
+            unterminated: false,
         }
     }
+
+    /// Returns the token with the given source text recorded as `original`.
+    ///
+    /// # Parameters:
+    /// - `original`: The token's text exactly as it appeared in the source.
+    ///
+    /// # Returns:
+    /// - `Token`: The same token with `original` set.
+    pub fn with_original(mut self, original: &str) -> Self {
+        self.original = original.to_string();
+        self
+    }
+
+    /// Returns the token positioned at the given 1-based column.
+    ///
+    /// # Parameters:
+    /// - `column`: The column where the token starts in its line.
+    ///
+    /// # Returns:
+    /// - `Token`: The same token with `column` set.
+    pub fn with_column(mut self, column: usize) -> Self {
+        self.column = column;
+        self
+    }
+
+    /// Returns the token tagged with the given literal sub-kind.
+    ///
+    /// # Parameters:
+    /// - `kind`: The kind of literal the token represents.
+    ///
+    /// # Returns:
+    /// - `Token`: The same token with `literal_kind` set.
+    pub fn with_literal_kind(mut self, kind: LiteralKind) -> Self {
+        self.literal_kind = Some(kind);
+        self
+    }
+
+    /// Returns the token marked as a string literal missing its closing quote.
+    ///
+    /// # Parameters:
+    /// - `unterminated`: Whether the literal ended without a closing quote.
+    ///
+    /// # Returns:
+    /// - `Token`: The same token with `unterminated` set.
+    pub fn with_unterminated(mut self, unterminated: bool) -> Self {
+        self.unterminated = unterminated;
+        self
+    }
+
+    /// Returns `true` if the token's value is one of `directives`.
+    ///
+    /// Directive values are already uppercased by the tokenizer, so the
+    /// comparison is exact.
+    ///
+    /// # Parameters:
+    /// - `directives`: The directive spellings to test against, e.g. `&["%IF", "%ELSE"]`.
+    ///
+    /// # Returns:
+    /// - `bool`: Whether the token's value is in `directives`.
+    pub fn is_one_of(&self, directives: &[&str]) -> bool {
+        directives.contains(&self.value.as_str())
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -98,14 +218,84 @@ impl Token {
 // -----------------------------------------------------------------------------
 // Enumerates general categories for tokens.
 // -----------------------------------------------------------------------------
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum TokenCategory {
+    Comment,
     Directive,
     Identifier,
     Literal,
     Operator,
     Separator,
     Unknown,
+    Whitespace,
+}
+
+impl TokenCategory {
+    /// All categories, in declaration order.
+    pub const ALL: [TokenCategory; 8] = [
+        TokenCategory::Comment,
+        TokenCategory::Directive,
+        TokenCategory::Identifier,
+        TokenCategory::Literal,
+        TokenCategory::Operator,
+        TokenCategory::Separator,
+        TokenCategory::Unknown,
+        TokenCategory::Whitespace,
+    ];
+
+    /// Returns the variant name, e.g. `"Identifier"`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenCategory::Comment => "Comment",
+            TokenCategory::Directive => "Directive",
+            TokenCategory::Identifier => "Identifier",
+            TokenCategory::Literal => "Literal",
+            TokenCategory::Operator => "Operator",
+            TokenCategory::Separator => "Separator",
+            TokenCategory::Unknown => "Unknown",
+            TokenCategory::Whitespace => "Whitespace",
+        }
+    }
+}
+
+/// Parses a variant name, ignoring ASCII case.
+impl FromStr for TokenCategory {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TokenCategory::ALL
+            .into_iter()
+            .find(|category| category.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("Unknown token category: {}", s))
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// ENUM: LiteralKind
+// -----------------------------------------------------------------------------
+// Enumerates the sub-kinds of quoted literals, determined by the suffix that
+// immediately follows the closing quote (e.g. `'1010'B`, `'FF'X`).
+// -----------------------------------------------------------------------------
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LiteralKind {
+    Character,
+    Bit,
+    BitHex,
+    Hex,
+    GraphicHex,
+}
+
+impl LiteralKind {
+    /// Returns the literal suffix for this kind (empty for character strings).
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            LiteralKind::Character => "",
+            LiteralKind::Bit => "B",
+            LiteralKind::BitHex => "B4",
+            LiteralKind::Hex => "X",
+            LiteralKind::GraphicHex => "GX",
+        }
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -113,7 +303,7 @@ pub enum TokenCategory {
 // -----------------------------------------------------------------------------
 // Enumerates specific categories for preprocessor directives.
 // -----------------------------------------------------------------------------
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Serialize, Deserialize)]
 pub enum DirectiveCategory {
     ControlFlow,
     MacroHandling,
@@ -122,10 +312,192 @@ pub enum DirectiveCategory {
     Other,
 }
 
+impl DirectiveCategory {
+    /// All categories, in declaration order.
+    pub const ALL: [DirectiveCategory; 5] = [
+        DirectiveCategory::ControlFlow,
+        DirectiveCategory::MacroHandling,
+        DirectiveCategory::Conditional,
+        DirectiveCategory::Comment,
+        DirectiveCategory::Other,
+    ];
+
+    /// Returns the variant name, e.g. `"ControlFlow"`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DirectiveCategory::ControlFlow => "ControlFlow",
+            DirectiveCategory::MacroHandling => "MacroHandling",
+            DirectiveCategory::Conditional => "Conditional",
+            DirectiveCategory::Comment => "Comment",
+            DirectiveCategory::Other => "Other",
+        }
+    }
+}
+
+/// Parses a variant name, ignoring ASCII case.
+impl FromStr for DirectiveCategory {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        DirectiveCategory::ALL
+            .into_iter()
+            .find(|category| category.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("Unknown directive category: {}", s))
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// ENUM: LineClass
+// -----------------------------------------------------------------------------
+// Classifies a whole source line by its first meaningful token. `%COMMENT`
+// directives and lines starting with `/*` are both reported as `Comment`.
+// -----------------------------------------------------------------------------
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LineClass {
+    Blank,
+    Directive(DirectiveCategory),
+    Comment,
+    Code,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// STRUCT: TokenStats
+// -----------------------------------------------------------------------------
+// Summarizes the tokens of a source, for profiling its complexity.
+//
+// `category_counts` and `directive_counts` only hold the categories that
+// occur. `unique_identifiers` counts distinct identifier values, leaving out
+// numbers (which the tokenizer also reports as identifiers); identifiers are
+// compared as normalized by the tokenizer, so `Total` and `TOTAL` are one.
+// -----------------------------------------------------------------------------
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TokenStats {
+    pub total_tokens: usize,
+    pub category_counts: HashMap<TokenCategory, usize>,
+    pub unique_identifiers: usize,
+    pub directives: usize,
+    pub directive_counts: HashMap<DirectiveCategory, usize>,
+}
+
+/// The directives `is_valid_preprocessor_directive` accepts at the start of a line.
+pub const PREPROCESSOR_DIRECTIVES: &[&str] = &[
+    "%IF", "%THEN", "%ELSE", "%ENDIF", "%MACRO", "%INCLUDE", "%COMMENT",
+];
+
+/// The default `TokenizerConfig::long_line_threshold`, in characters.
+pub const DEFAULT_LONG_LINE_THRESHOLD: usize = 100_000;
+
+/// The extended alphabetic characters PL/I allows in identifiers by default.
+pub const DEFAULT_EXTENDED_IDENTIFIER_CHARS: &[char] = &['$', '@', '#'];
+
+/// The characters tokenized as operators by default.
+pub const DEFAULT_OPERATOR_CHARS: &[char] = &['=', '#', '*', '+', '-', '/', '<', '>', '&', '|'];
+
+////////////////////////////////////////////////////////////////////////////////
+// STRUCT: CharClassConfig
+// -----------------------------------------------------------------------------
+// Defines which characters make up identifiers and which are operators, so
+// dialects that disagree can be tokenized without forking the tokenizer.
+//
+// Letters, digits, and `_` always start and continue identifiers (numbers are
+// identifier tokens too). `identifier_start` lists further characters that
+// may start an identifier, and `identifier_continue` further characters that
+// may only appear after its first character; a start character may also
+// continue one. The default allows `$`, `@`, and `#` anywhere, as many PL/I
+// shops do; empty lists make them split identifiers as special characters.
+//
+// `operator_chars` lists the single characters categorized as `Operator`; a
+// character that is neither an identifier character, an operator, nor one of
+// the fixed constructs (quotes, `%`, `;`, `:`, `->`, and `/*`) is `Unknown`.
+// A character in both an identifier list and `operator_chars` is an
+// identifier character, so the default `#` is never an operator.
+//
+// # Example:
+// ```rust
+// let classes = CharClassConfig {
+//     identifier_continue: vec!['.'],
+//     ..CharClassConfig::default()
+// };
+// assert!(classes.is_identifier_continue('.') && !classes.is_identifier_start('.'));
+// ```
+// -----------------------------------------------------------------------------
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CharClassConfig {
+    pub identifier_start: Vec<char>,
+    pub identifier_continue: Vec<char>,
+    pub operator_chars: Vec<char>,
+}
+
+impl Default for CharClassConfig {
+    fn default() -> Self {
+        Self {
+            identifier_start: DEFAULT_EXTENDED_IDENTIFIER_CHARS.to_vec(),
+            identifier_continue: DEFAULT_EXTENDED_IDENTIFIER_CHARS.to_vec(),
+            operator_chars: DEFAULT_OPERATOR_CHARS.to_vec(),
+        }
+    }
+}
+
+impl CharClassConfig {
+    /// Returns `true` if `c` may start an identifier.
+    pub fn is_identifier_start(&self, c: char) -> bool {
+        c.is_alphanumeric() || c == '_' || self.identifier_start.contains(&c)
+    }
+
+    /// Returns `true` if `c` may follow the first character of an identifier.
+    pub fn is_identifier_continue(&self, c: char) -> bool {
+        self.is_identifier_start(c) || self.identifier_continue.contains(&c)
+    }
+
+    /// Returns `true` if `c` is tokenized as an operator on its own.
+    pub fn is_operator(&self, c: char) -> bool {
+        self.operator_chars.contains(&c)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// STRUCT: TokenizerConfig
+// -----------------------------------------------------------------------------
+// Configures dialect-dependent tokenizer behavior.
+//
+// `char_classes` defines the identifier and operator characters (see
+// `CharClassConfig`).
+//
+// `directives` categorizes `%` directives; register custom directives in it to
+// have them categorized like the built-in ones.
+//
+// `allow_spaced_directives` accepts whitespace between `%` and the directive
+// keyword, so `% IF` tokenizes as `%IF`. It is off by default because the
+// spacing is nonstandard.
+//
+// `long_line_threshold` is the line length, in characters, above which the
+// tokenizer logs a warning; such lines usually come from generated code.
+// `None` disables the warning. Long lines are still tokenized in full.
+// -----------------------------------------------------------------------------
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenizerConfig {
+    pub char_classes: CharClassConfig,
+    pub directives: DirectiveRegistry,
+    pub allow_spaced_directives: bool,
+    pub long_line_threshold: Option<usize>,
+}
+
+impl Default for TokenizerConfig {
+    fn default() -> Self {
+        Self {
+            char_classes: CharClassConfig::default(),
+            directives: DirectiveRegistry::new(),
+            allow_spaced_directives: false,
+            long_line_threshold: Some(DEFAULT_LONG_LINE_THRESHOLD),
+        }
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // FUNCTION: get_directive_category
 // -----------------------------------------------------------------------------
-// Retrieves the category of a given PL/I preprocessor directive.
+// Retrieves the category of a given PL/I preprocessor directive from the
+// built-in table in the `directive` module.
 //
 // # Parameters:
 // - `directive` (`&str`): The directive token.
@@ -134,13 +506,7 @@ pub enum DirectiveCategory {
 // - `DirectiveCategory`: The category of the directive.
 ////////////////////////////////////////////////////////////////////////////////
 pub fn get_directive_category(directive: &str) -> DirectiveCategory {
-    match directive {
-        "%IF" | "%THEN" | "%ELSE" | "%ENDIF" => DirectiveCategory::ControlFlow,
-        "%MACRO" | "%INCLUDE" => DirectiveCategory::MacroHandling,
-        "%SWITCH" | "%CASE" | "%EVALUATE" => DirectiveCategory::Conditional,
-        "%COMMENT" => DirectiveCategory::Comment,
-        _ => DirectiveCategory::Other,
-    }
+    builtin_category(directive).unwrap_or(DirectiveCategory::Other)
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -159,38 +525,179 @@ pub fn get_directive_category(directive: &str) -> DirectiveCategory {
 //
 // # Returns:
 // - `Vec<Token>`: A vector of tokens parsed from the input.
+//
+// # See Also:
+// - `tokenize_pli_with_config`: Tokenizes with a non-default configuration.
 ////////////////////////////////////////////////////////////////////////////////
 pub fn tokenize_pli(input: &str) -> Vec<Token> {
-    let mut chars = input.chars().peekable();
+    TokenIter::new(input).collect()
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// FUNCTION: tokenize_pli_preserve_ws
+// -----------------------------------------------------------------------------
+// Tokenizes a given PL/I input string like `tokenize_pli`, but also emits a
+// `TokenCategory::Whitespace` token for every run of whitespace between tokens.
+// Concatenating the `original` text of the returned tokens reproduces `input`
+// exactly, which formatters rely on for round-tripping.
+//
+// # Parameters:
+// - `input` (`&str`): The PL/I input line to be tokenized.
+//
+// # Returns:
+// - `Vec<Token>`: The tokens of `input`, whitespace included, in source order.
+////////////////////////////////////////////////////////////////////////////////
+pub fn tokenize_pli_preserve_ws(input: &str) -> Vec<Token> {
+    let chars: Vec<char> = input.chars().collect();
     let mut tokens = Vec::new();
-    let mut current_token = String::new();
-    let mut in_string = false;
+    // The 1-based column of the first character not yet covered by a token.
+    let mut column = 1;
 
-    while let Some(c) = chars.next() {
-        if c.is_whitespace() && !in_string {
-            finalize_token(&mut current_token, &mut tokens);
-            continue;
+    for token in tokenize_pli(input) {
+        if token.column > column {
+            let run: String = chars[column - 1..token.column - 1].iter().collect();
+            tokens.push(Token::new(&run, TokenCategory::Whitespace, None).with_column(column));
+        }
+        column = token.column + token.original.chars().count();
+        tokens.push(token);
+    }
+
+    if column <= chars.len() {
+        let run: String = chars[column - 1..].iter().collect();
+        tokens.push(Token::new(&run, TokenCategory::Whitespace, None).with_column(column));
+    }
+    tokens
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// FUNCTION: tokenize_pli_with_config
+// -----------------------------------------------------------------------------
+// Tokenizes a given PL/I input string using the supplied `TokenizerConfig`.
+// Logs a warning when `input` is longer than `config.long_line_threshold`.
+//
+// # Parameters:
+// - `input` (`&str`): The PL/I input line to be tokenized.
+// - `config` (`&TokenizerConfig`): The dialect configuration to apply.
+//
+// # Returns:
+// - `Vec<Token>`: A vector of tokens parsed from the input.
+////////////////////////////////////////////////////////////////////////////////
+pub fn tokenize_pli_with_config(input: &str, config: &TokenizerConfig) -> Vec<Token> {
+    TokenIter::with_config(input, config).collect()
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// STRUCT: TokenIter
+// -----------------------------------------------------------------------------
+// Tokenizes a line lazily, yielding one `Token` at a time, so consumers that
+// process tokens in order never materialize the whole vector. It produces the
+// same tokens as `tokenize_pli_with_config`, which simply collects it.
+//
+// Each step consumes one construct (a character, literal, or directive) and
+// may complete at most two tokens; they wait in `pending` until requested.
+//
+// # Example:
+// ```rust
+// for token in TokenIter::new("A = 1;") {
+//     println!("{:?}", token);
+// }
+// ```
+// -----------------------------------------------------------------------------
+pub struct TokenIter<'a> {
+    chars: CharStream<'a>,
+    config: Cow<'a, TokenizerConfig>,
+    pending: Vec<Token>,
+    current_token: String,
+    in_string: bool,
+    end_column: usize,
+    finished: bool,
+}
+
+impl<'a> TokenIter<'a> {
+    /// Creates an iterator over the tokens of `input` with the default config.
+    pub fn new(input: &'a str) -> Self {
+        Self::start(input, Cow::Owned(TokenizerConfig::default()))
+    }
+
+    /// Creates an iterator over the tokens of `input` using `config`.
+    pub fn with_config(input: &'a str, config: &'a TokenizerConfig) -> Self {
+        Self::start(input, Cow::Borrowed(config))
+    }
+
+    /// Logs a warning when `input` is longer than `config.long_line_threshold`.
+    fn start(input: &'a str, config: Cow<'a, TokenizerConfig>) -> Self {
+        if let Some(threshold) = config.long_line_threshold {
+            // The byte length bounds the character count, so most lines skip the count.
+            if input.len() > threshold {
+                let length = input.chars().count();
+                if length > threshold {
+                    warn!(
+                        "Tokenizing a {}-character line (threshold {})",
+                        length, threshold
+                    );
+                }
+            }
+        }
+
+        Self {
+            chars: input.chars().enumerate().peekable(),
+            config,
+            pending: Vec::new(),
+            current_token: String::new(),
+            in_string: false,
+            end_column: 1,
+            finished: false,
+        }
+    }
+
+    /// Consumes the next construct of the input into `pending`.
+    fn step(&mut self) {
+        let (index, c) = match self.chars.next() {
+            Some(next) => next,
+            None => {
+                finalize_token(&mut self.current_token, self.end_column, &mut self.pending);
+                self.finished = true;
+                return;
+            }
+        };
+        let column = index + 1;
+        self.end_column = column + 1;
+        let tokens = &mut self.pending;
+        let current_token = &mut self.current_token;
+        let chars = &mut self.chars;
+        let classes = &self.config.char_classes;
+        if c.is_whitespace() && !self.in_string {
+            finalize_token(current_token, column, tokens);
+            return;
         }
 
         match c {
-            '\'' => handle_string_literal(
-                c,
-                &mut chars,
-                &mut in_string,
-                &mut current_token,
-                &mut tokens,
-            ),
-            '%' => handle_directive(c, &mut chars, &mut current_token, &mut tokens),
-            '=' | '#' | '*' | ';' => {
-                handle_special_characters(c, &mut chars, &mut current_token, &mut tokens)
+            '\'' => {
+                handle_string_literal(c, column, chars, &mut self.in_string, current_token, tokens)
+            }
+            '%' => handle_directive(c, column, chars, &self.config, current_token, tokens),
+            _ if !current_token.is_empty() && classes.is_identifier_continue(c) => {
+                current_token.push(c)
             }
-            _ if c.is_alphanumeric() || c == '_' => current_token.push(c),
-            _ => handle_special_characters(c, &mut chars, &mut current_token, &mut tokens),
+            _ if classes.is_identifier_start(c) => current_token.push(c),
+            _ => handle_special_characters(c, column, chars, classes, current_token, tokens),
         }
     }
+}
 
-    finalize_token(&mut current_token, &mut tokens);
-    tokens
+impl Iterator for TokenIter<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        while self.pending.is_empty() && !self.finished {
+            self.step();
+        }
+        if self.pending.is_empty() {
+            None
+        } else {
+            Some(self.pending.remove(0))
+        }
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -198,40 +705,79 @@ pub fn tokenize_pli(input: &str) -> Vec<Token> {
 // -----------------------------------------------------------------------------
 // Finalizes the current token and adds it to the token list.
 //
+// The token text is moved out of `current_token` rather than copied, and only
+// uppercased when it holds lowercase or non-ASCII characters, so long lines of
+// short tokens do not pay for several allocations per token.
+//
 // # Parameters:
 // - `current_token` (`&mut String`): The token string to finalize.
+// - `end_column` (`usize`): The column just past the token's last character.
 // - `tokens` (`&mut Vec<Token>`): The list of tokens to add the finalized token.
 ////////////////////////////////////////////////////////////////////////////////
-fn finalize_token(current_token: &mut String, tokens: &mut Vec<Token>) {
-    if !current_token.is_empty() {
-        tokens.push(Token::new(
-            &current_token.to_uppercase(),
-            TokenCategory::Identifier,
-            None,
-        ));
-        current_token.clear();
+fn finalize_token(current_token: &mut String, end_column: usize, tokens: &mut Vec<Token>) {
+    if current_token.is_empty() {
+        return;
     }
+    let original = std::mem::take(current_token);
+    let column = end_column - original.chars().count();
+    let value = if original
+        .bytes()
+        .any(|b| !b.is_ascii() || b.is_ascii_lowercase())
+    {
+        original.to_uppercase()
+    } else {
+        original.clone()
+    };
+    tokens.push(Token {
+        value,
+        category: TokenCategory::Identifier,
+        directive_category: None,
+        literal_kind: None,
+        column,
+        original,
+        unterminated: false,
+    });
 }
 
 ////////////////////////////////////////////////////////////////////////////////
 // FUNCTION: handle_directive
 // -----------------------------------------------------------------------------
-// Processes directives in the input and categorizes them.
+// Processes directives in the input and categorizes them. When the config
+// allows spaced directives, whitespace between `%` and a keyword is skipped;
+// it is kept in the token's `original` text. A `%` not followed by a name
+// (as in `%;`, `% `, or a `%` ending the input) is not a directive: it becomes
+// an `Unknown` token, which the validator reports as an empty directive.
 //
 // # Parameters:
 // - `current_char`: The current character, typically `%`.
+// - `column`: The 1-based column of `current_char`.
 // - `chars`: The character iterator for processing the input.
+// - `config`: The `TokenizerConfig` whose registry categorizes the directive.
 // - `current_token`: A mutable reference to the current token string.
 // - `tokens`: A mutable reference to the list of generated tokens.
 ////////////////////////////////////////////////////////////////////////////////
 pub fn handle_directive(
     current_char: char,
-    chars: &mut Peekable<Chars>,
+    column: usize,
+    chars: &mut CharStream,
+    config: &TokenizerConfig,
     current_token: &mut String,
     tokens: &mut Vec<Token>,
 ) {
+    finalize_token(current_token, column, tokens);
     current_token.push(current_char);
-    while let Some(&next_char) = chars.peek() {
+    if config.allow_spaced_directives {
+        let mut lookahead = chars.clone();
+        let mut spacing = String::new();
+        while let Some((_, c)) = lookahead.next_if(|&(_, c)| c.is_whitespace()) {
+            spacing.push(c);
+        }
+        if !spacing.is_empty() && matches!(lookahead.peek(), Some(&(_, c)) if c.is_alphabetic()) {
+            *chars = lookahead;
+            current_token.push_str(&spacing);
+        }
+    }
+    while let Some(&(_, next_char)) = chars.peek() {
         if next_char.is_alphanumeric() || next_char == '_' {
             current_token.push(next_char);
             chars.next();
@@ -240,20 +786,36 @@ pub fn handle_directive(
         }
     }
 
-    let directive = current_token.to_uppercase();
-    let directive_category = get_directive_category(&directive);
-    tokens.push(Token::new(
-        &directive,
-        TokenCategory::Directive,
-        Some(directive_category),
-    ));
+    if current_token == "%" {
+        tokens.push(Token::new("%", TokenCategory::Unknown, None).with_column(column));
+        current_token.clear();
+        return;
+    }
+
+    let directive: String = current_token
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_uppercase();
+    let directive_category = config.directives.category(&directive);
+    tokens.push(
+        Token::new(
+            &directive,
+            TokenCategory::Directive,
+            Some(directive_category),
+        )
+        .with_column(column)
+        .with_original(current_token),
+    );
     current_token.clear();
 }
 
 ////////////////////////////////////////////////////////////////////////////////
 // FUNCTION: has_tokenizer_error
 // -----------------------------------------------------------------------------
-// Checks for tokenizer errors such as unmatched string literals.
+// Checks for tokenizer errors such as unmatched string literals. The check
+// relies on the flag set by `handle_string_literal`, since a literal such as
+// `'abc''` ends in a quote yet is still open.
 //
 // # Parameters:
 // - `tokens` (`&[Token]`): The list of tokens to validate.
@@ -262,9 +824,31 @@ pub fn handle_directive(
 // - `bool`: `true` if any errors are found, `false` otherwise.
 ////////////////////////////////////////////////////////////////////////////////
 pub fn has_tokenizer_error(tokens: &[Token]) -> bool {
-    tokens
-        .iter()
-        .any(|token| token.value.starts_with("'") && !token.value.ends_with("'"))
+    tokens.iter().any(|token| token.unterminated)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// FUNCTION: report_tokenizer_errors
+// -----------------------------------------------------------------------------
+// Pushes an error into the sink for each malformed token, such as an unmatched
+// string literal.
+//
+// # Parameters:
+// - `tokens` (`&[Token]`): The tokens of a single line.
+// - `line` (`usize`): The 1-based line number the tokens came from.
+// - `sink` (`&mut DiagnosticSink`): The collector receiving the errors.
+////////////////////////////////////////////////////////////////////////////////
+pub fn report_tokenizer_errors(tokens: &[Token], line: usize, sink: &mut DiagnosticSink) {
+    for token in tokens {
+        if has_tokenizer_error(std::slice::from_ref(token)) {
+            let message = format!("Unmatched string literal: {}", token.value);
+            let mut diagnostic = Diagnostic::new(Severity::Error, line, &message).with_code("T001");
+            if token.column > 0 {
+                diagnostic = diagnostic.with_column(token.column);
+            }
+            sink.push(diagnostic);
+        }
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -279,21 +863,334 @@ pub fn has_tokenizer_error(tokens: &[Token]) -> bool {
 // - `bool`: `true` if the first token is a valid directive, `false` otherwise.
 ////////////////////////////////////////////////////////////////////////////////
 pub fn is_valid_preprocessor_directive(tokens: &[Token]) -> bool {
-    tokens.get(0).map_or(false, |token| {
-        matches!(
-            token.value.as_str(),
-            "%IF" | "%THEN" | "%ELSE" | "%ENDIF" | "%MACRO" | "%INCLUDE" | "%COMMENT"
-        )
-    })
+    tokens
+        .first()
+        .is_some_and(|token| token.is_one_of(PREPROCESSOR_DIRECTIVES))
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// FUNCTION: directive_histogram
+// -----------------------------------------------------------------------------
+// Counts how many directive tokens fall into each `DirectiveCategory`.
+//
+// Only tokens carrying a `directive_category` are counted; categories that do
+// not appear in the input are absent from the resulting map.
+//
+// # Parameters:
+// - `tokens` (`&[Token]`): The tokens to summarize, typically for a whole file.
+//
+// # Returns:
+// - `HashMap<DirectiveCategory, usize>`: The number of directives per category.
+////////////////////////////////////////////////////////////////////////////////
+pub fn directive_histogram(tokens: &[Token]) -> HashMap<DirectiveCategory, usize> {
+    let mut histogram = HashMap::new();
+    for category in tokens
+        .iter()
+        .filter_map(|token| token.directive_category.clone())
+    {
+        *histogram.entry(category).or_insert(0) += 1;
+    }
+    histogram
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// FUNCTION: token_stats
+// -----------------------------------------------------------------------------
+// Counts the tokens of a source: in total, per `TokenCategory`, its distinct
+// identifiers, and its directives, per `DirectiveCategory` as reported by
+// `directive_histogram`.
+//
+// # Parameters:
+// - `tokens` (`&[Token]`): The tokens to summarize, typically for a whole file.
+//
+// # Returns:
+// - `TokenStats`: The counts.
+////////////////////////////////////////////////////////////////////////////////
+pub fn token_stats(tokens: &[Token]) -> TokenStats {
+    let mut category_counts = HashMap::new();
+    let mut identifiers = HashSet::new();
+    for token in tokens {
+        *category_counts.entry(token.category.clone()).or_insert(0) += 1;
+        let numeric = token
+            .value
+            .starts_with(|c: char| c.is_ascii_digit() || c == '.');
+        if token.category == TokenCategory::Identifier && !numeric {
+            identifiers.insert(token.value.as_str());
+        }
+    }
+    let directive_counts = directive_histogram(tokens);
+
+    TokenStats {
+        total_tokens: tokens.len(),
+        category_counts,
+        unique_identifiers: identifiers.len(),
+        directives: directive_counts.values().sum(),
+        directive_counts,
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// FUNCTION: format_token_stats_table
+// -----------------------------------------------------------------------------
+// Renders `TokenStats` as an aligned table. Every token and directive category
+// is listed, in declaration order, so reports line up between files.
+//
+// # Parameters:
+// - `stats` (`&TokenStats`): The counts to render.
+//
+// # Returns:
+// - `String`: The table, each line ending in a newline.
+////////////////////////////////////////////////////////////////////////////////
+pub fn format_token_stats_table(stats: &TokenStats) -> String {
+    let mut text = format!("{:<20} {}\n", "Tokens", stats.total_tokens);
+    for category in TokenCategory::ALL {
+        let count = stats.category_counts.get(&category).copied().unwrap_or(0);
+        text.push_str(&format!("  {:<18} {}\n", category.as_str(), count));
+    }
+    text.push_str(&format!(
+        "{:<20} {}\n",
+        "Unique identifiers", stats.unique_identifiers
+    ));
+    text.push_str(&format!("{:<20} {}\n", "Directives", stats.directives));
+    for category in DirectiveCategory::ALL {
+        let count = stats.directive_counts.get(&category).copied().unwrap_or(0);
+        text.push_str(&format!("  {:<18} {}\n", category.as_str(), count));
+    }
+    text
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// FUNCTION: format_token_stats_json
+// -----------------------------------------------------------------------------
+// Renders `TokenStats` as a JSON object, listing every category like
+// `format_token_stats_table`.
+//
+// # Parameters:
+// - `stats` (`&TokenStats`): The counts to render.
+//
+// # Returns:
+// - `String`: The JSON object, ending in a newline.
+////////////////////////////////////////////////////////////////////////////////
+pub fn format_token_stats_json(stats: &TokenStats) -> String {
+    let categories: Vec<String> = TokenCategory::ALL
+        .iter()
+        .map(|category| {
+            let count = stats.category_counts.get(category).copied().unwrap_or(0);
+            format!("\"{}\": {}", category.as_str(), count)
+        })
+        .collect();
+    let directives: Vec<String> = DirectiveCategory::ALL
+        .iter()
+        .map(|category| {
+            let count = stats.directive_counts.get(category).copied().unwrap_or(0);
+            format!("\"{}\": {}", category.as_str(), count)
+        })
+        .collect();
+    format!(
+        "{{\"total_tokens\": {}, \"categories\": {{{}}}, \"unique_identifiers\": {}, \"directives\": {}, \"directive_categories\": {{{}}}}}\n",
+        stats.total_tokens,
+        categories.join(", "),
+        stats.unique_identifiers,
+        stats.directives,
+        directives.join(", ")
+    )
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// FUNCTION: filter_by_category
+// -----------------------------------------------------------------------------
+// Selects the tokens of a given `TokenCategory`, in source order.
+//
+// # Parameters:
+// - `tokens` (`&[Token]`): The tokens to search.
+// - `category` (`TokenCategory`): The category to keep.
+//
+// # Returns:
+// - `Vec<&Token>`: The matching tokens.
+////////////////////////////////////////////////////////////////////////////////
+pub fn filter_by_category(tokens: &[Token], category: TokenCategory) -> Vec<&Token> {
+    tokens
+        .iter()
+        .filter(|token| token.category == category)
+        .collect()
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// FUNCTION: filter_directives
+// -----------------------------------------------------------------------------
+// Selects the directive tokens of a given `DirectiveCategory`, in source order.
+//
+// # Parameters:
+// - `tokens` (`&[Token]`): The tokens to search.
+// - `category` (`DirectiveCategory`): The directive category to keep.
+//
+// # Returns:
+// - `Vec<&Token>`: The matching directive tokens.
+////////////////////////////////////////////////////////////////////////////////
+pub fn filter_directives(tokens: &[Token], category: DirectiveCategory) -> Vec<&Token> {
+    tokens
+        .iter()
+        .filter(|token| token.directive_category.as_ref() == Some(&category))
+        .collect()
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// FUNCTION: strip_comments
+// -----------------------------------------------------------------------------
+// Drops comments from a token stream, for passes that do not care about them.
+// `/* ... */` comment tokens are removed, and a `%COMMENT` directive is removed
+// together with its text, up to and including the terminating `;` (or to the
+// end of the stream if there is none).
+//
+// # Parameters:
+// - `tokens` (`Vec<Token>`): The tokens to filter.
+//
+// # Returns:
+// - `Vec<Token>`: The remaining tokens, in source order.
+////////////////////////////////////////////////////////////////////////////////
+pub fn strip_comments(tokens: Vec<Token>) -> Vec<Token> {
+    let mut in_comment = false;
+    tokens
+        .into_iter()
+        .filter(|token| {
+            if in_comment {
+                in_comment = !(token.category == TokenCategory::Separator && token.value == ";");
+                return false;
+            }
+            in_comment = token.directive_category == Some(DirectiveCategory::Comment);
+            !in_comment && token.category != TokenCategory::Comment
+        })
+        .collect()
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// FUNCTION: classify_line
+// -----------------------------------------------------------------------------
+// Classifies a source line from its first meaningful token, so callers do not
+// have to re-derive it from the token vector.
+//
+// # Parameters:
+// - `line` (`&str`): The source line to classify.
+//
+// # Returns:
+// - `LineClass`: `Blank` for empty or whitespace-only lines, `Comment` for
+//   `/* ... */` and `%COMMENT` lines, `Directive` for other directive lines,
+//   and `Code` for everything else.
+////////////////////////////////////////////////////////////////////////////////
+pub fn classify_line(line: &str) -> LineClass {
+    if line.trim_start().starts_with("/*") {
+        return LineClass::Comment;
+    }
+    match tokenize_pli(line).into_iter().next() {
+        None => LineClass::Blank,
+        Some(token) => match token.directive_category {
+            Some(DirectiveCategory::Comment) => LineClass::Comment,
+            Some(category) => LineClass::Directive(category),
+            None => LineClass::Code,
+        },
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// FUNCTION: join_continued_tokens
+// -----------------------------------------------------------------------------
+// Stitches a token split across physical lines back together before
+// tokenization. A line whose last non-blank character is `indicator`, and
+// which ends inside an open string literal, continues on the next line: the
+// indicator is dropped and the next line, without its leading whitespace, is
+// appended directly, so `'HELLO ` + `-` followed by `WORLD'` becomes the
+// single literal `'HELLO WORLD'`. Outside a literal the indicator is ordinary
+// source text, so `A = B -` followed by `C;` keeps its subtraction. Chains of
+// continued lines are joined in turn. A blank line does not end a
+// continuation: it is consumed, and the continuation resumes on the next
+// non-blank line.
+//
+// Each consumed line is replaced by an empty line, so the joined text keeps
+// the line number of its first line and later lines keep theirs.
+//
+// # Parameters:
+// - `source` (`&str`): The source text.
+// - `indicator` (`char`): The continuation character.
+//
+// # Returns:
+// - `String`: The source with continued lines joined.
+////////////////////////////////////////////////////////////////////////////////
+pub fn join_continued_tokens(source: &str, indicator: char) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut consumed = 0;
+    let mut continuing = false;
+    for line in source.lines() {
+        if continuing && line.trim().is_empty() {
+            consumed += 1;
+            continue;
+        }
+        let line = if continuing {
+            consumed += 1;
+            let joined = lines.pop().unwrap_or_default();
+            joined + line.trim_start()
+        } else {
+            lines.extend(std::iter::repeat_n(String::new(), consumed));
+            consumed = 0;
+            line.to_string()
+        };
+        let head = line
+            .trim_end()
+            .strip_suffix(indicator)
+            .filter(|head| ends_in_open_literal(head));
+        continuing = head.is_some();
+        lines.push(match head {
+            Some(head) => head.to_string(),
+            None => line,
+        });
+    }
+    lines.extend(std::iter::repeat_n(String::new(), consumed));
+    let mut joined = lines.join("\n");
+    if source.ends_with('\n') {
+        joined.push('\n');
+    }
+    joined
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// FUNCTION: ends_in_open_literal
+// -----------------------------------------------------------------------------
+// Reports whether a line ends inside a `'` string literal that it opened. A
+// doubled quote inside a literal is an escaped quote, and quotes inside a
+// `/* ... */` comment are ignored.
+//
+// # Parameters:
+// - `text` (`&str`): The line, without any continuation indicator.
+//
+// # Returns:
+// - `bool`: `true` if a literal is still open at the end of `text`.
+////////////////////////////////////////////////////////////////////////////////
+fn ends_in_open_literal(text: &str) -> bool {
+    let mut in_string = false;
+    let mut in_comment = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_comment {
+            in_comment = !(c == '*' && chars.next_if_eq(&'/').is_some());
+        } else if c == '\'' {
+            // A doubled quote toggles twice, leaving the literal open.
+            in_string = !in_string;
+        } else if !in_string && c == '/' && chars.next_if_eq(&'*').is_some() {
+            in_comment = true;
+        }
+    }
+    in_string
 }
 
 ////////////////////////////////////////////////////////////////////////////////
 // FUNCTION: handle_string_literal
 // -----------------------------------------------------------------------------
 // Handles string literals, ensuring proper tokenization and detection of errors.
+// A `B`, `B4`, `X`, or `GX` suffix directly after the closing quote is folded
+// into the literal token and recorded as its `LiteralKind`.
 //
 // # Parameters:
 // - `current_char`: The current character, typically `'`.
+// - `column`: The 1-based column of `current_char`.
 // - `chars`: The character iterator for processing the input.
 // - `in_string`: A mutable reference to a flag tracking string literals.
 // - `current_token`: A mutable reference to the current token string.
@@ -304,27 +1201,40 @@ pub fn is_valid_preprocessor_directive(tokens: &[Token]) -> bool {
 ////////////////////////////////////////////////////////////////////////////////
 pub fn handle_string_literal(
     current_char: char,
-    chars: &mut Peekable<Chars>,
+    column: usize,
+    chars: &mut CharStream,
     in_string: &mut bool,
     current_token: &mut String,
     tokens: &mut Vec<Token>,
 ) {
     debug!("Starting string literal handling: {}", current_char);
+    finalize_token(current_token, column, tokens);
     *in_string = true;
     current_token.push(current_char);
 
-    while let Some(&next_char) = chars.peek() {
+    while let Some(&(_, next_char)) = chars.peek() {
         current_token.push(next_char);
         chars.next();
 
         if next_char == '\'' {
+            // A doubled quote (`''`) is an escaped quote inside the literal.
+            if let Some(&(_, '\'')) = chars.peek() {
+                current_token.push('\'');
+                chars.next();
+                continue;
+            }
             *in_string = false;
-            debug!("String literal completed: {}", current_token);
-            tokens.push(Token::new(
-                current_token.trim(),
-                TokenCategory::Literal,
-                None,
-            ));
+            let kind = take_literal_suffix(chars, current_token);
+            debug!("String literal completed: {} ({:?})", current_token, kind);
+            // The suffix keeps its source case in `original` but is normalized in `value`.
+            let body = &current_token[..current_token.len() - kind.suffix().len()];
+            let value = format!("{}{}", body, kind.suffix());
+            tokens.push(
+                Token::new(&value, TokenCategory::Literal, None)
+                    .with_literal_kind(kind)
+                    .with_column(column)
+                    .with_original(current_token),
+            );
             current_token.clear();
             return;
         }
@@ -332,38 +1242,132 @@ pub fn handle_string_literal(
 
     // Handle unmatched string literal
     debug!("Unmatched string literal detected: {}", current_token);
-    tokens.push(Token::new(
-        current_token.trim(),
-        TokenCategory::Literal,
-        None,
-    ));
+    tokens.push(
+        Token::new(current_token.trim(), TokenCategory::Literal, None)
+            .with_literal_kind(LiteralKind::Character)
+            .with_column(column)
+            .with_original(current_token)
+            .with_unterminated(true),
+    );
     current_token.clear();
 }
 
+////////////////////////////////////////////////////////////////////////////////
+// FUNCTION: take_literal_suffix
+// -----------------------------------------------------------------------------
+// Consumes a literal suffix (`B`, `B4`, `X`, `GX`) following a closing quote.
+//
+// The suffix is only consumed when the whole alphanumeric run after the quote
+// is a known suffix, so `'A'BC` leaves `BC` to be tokenized as an identifier.
+//
+// # Parameters:
+// - `chars`: The character iterator, positioned just after the closing quote.
+// - `current_token`: The literal being built; the suffix is appended as written.
+//
+// # Returns:
+// - `LiteralKind`: The kind of literal, `Character` when there is no suffix.
+////////////////////////////////////////////////////////////////////////////////
+fn take_literal_suffix(chars: &mut CharStream, current_token: &mut String) -> LiteralKind {
+    let mut lookahead = chars.clone();
+    let mut suffix = String::new();
+    while let Some(&(_, next_char)) = lookahead.peek() {
+        if next_char.is_alphanumeric() {
+            suffix.push(next_char);
+            lookahead.next();
+        } else {
+            break;
+        }
+    }
+
+    let kind = match suffix.to_ascii_uppercase().as_str() {
+        "B" => LiteralKind::Bit,
+        "B4" => LiteralKind::BitHex,
+        "X" => LiteralKind::Hex,
+        "GX" => LiteralKind::GraphicHex,
+        _ => return LiteralKind::Character,
+    };
+
+    *chars = lookahead;
+    current_token.push_str(&suffix);
+    kind
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // FUNCTION: handle_special_characters
 // -----------------------------------------------------------------------------
 // Processes special characters and assigns appropriate token categories.
+// A `-` immediately followed by `>` becomes the single `->` locator-qualifier
+// operator (as in `P -> FIELD`). A `/` that opens a `/*` comment starts a
+// `Comment` token (see `handle_block_comment`), so it is never mistaken for
+// division. `;` ends a statement and `:` ends a label, so both are separators.
+// Other characters are operators when `classes` lists them, else `Unknown`.
+// By default `&` and `|`, PL/I's symbolic logical AND and OR, are operators.
+// Each character is its own token: `||` (concatenation) is two `|` tokens,
+// which the evaluator merges back, and `&&` is two `&` tokens, which no
+// expression accepts.
 //
 // # Parameters:
 // - `c` (`char`): The current special character being processed.
-// - `_chars`: A mutable reference to the character iterator (unused).
+// - `column` (`usize`): The 1-based column of `c`.
+// - `chars`: A mutable reference to the character iterator, used to look ahead.
+// - `classes`: The `CharClassConfig` naming the operator characters.
 // - `current_token`: A mutable reference to the current token being constructed.
 // - `tokens`: A mutable reference to the list of generated tokens.
 ////////////////////////////////////////////////////////////////////////////////
 pub fn handle_special_characters(
     c: char,
-    _chars: &mut Peekable<Chars>,
+    column: usize,
+    chars: &mut CharStream,
+    classes: &CharClassConfig,
     current_token: &mut String,
     tokens: &mut Vec<Token>,
 ) {
-    finalize_token(current_token, tokens);
+    finalize_token(current_token, column, tokens);
+
+    if c == '-' && chars.next_if(|&(_, next)| next == '>').is_some() {
+        tokens.push(Token::new("->", TokenCategory::Operator, None).with_column(column));
+        return;
+    }
+
+    if c == '/' && chars.next_if(|&(_, next)| next == '*').is_some() {
+        tokens.push(handle_block_comment(column, chars));
+        return;
+    }
 
     let token_category = match c {
-        '=' | '#' | '*' => TokenCategory::Operator,
-        ';' => TokenCategory::Separator,
+        ';' | ':' => TokenCategory::Separator,
+        _ if classes.is_operator(c) => TokenCategory::Operator,
         _ => TokenCategory::Unknown,
     };
 
-    tokens.push(Token::new(&c.to_string(), token_category, None));
+    tokens.push(Token::new(&c.to_string(), token_category, None).with_column(column));
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// FUNCTION: handle_block_comment
+// -----------------------------------------------------------------------------
+// Reads a `/* ... */` comment whose `/*` has already been consumed. PL/I
+// comments do not nest: a `/*` inside a comment opens nothing, and the first
+// `*/` closes the comment, so `/* a /* b */ c` is the comment `/* a /* b */`
+// followed by the code `c`. A comment left open runs to the end of the line;
+// the pipeline carries it over to the lines that follow.
+//
+// # Parameters:
+// - `column` (`usize`): The 1-based column of the opening `/`.
+// - `chars`: The character iterator, positioned just after the opening `/*`.
+//
+// # Returns:
+// - `Token`: A `Comment` token holding the comment text, delimiters included.
+////////////////////////////////////////////////////////////////////////////////
+fn handle_block_comment(column: usize, chars: &mut CharStream) -> Token {
+    let mut text = String::from("/*");
+    let mut previous = None;
+    for (_, c) in chars.by_ref() {
+        text.push(c);
+        if previous == Some('*') && c == '/' {
+            break;
+        }
+        previous = Some(c);
+    }
+    Token::new(&text, TokenCategory::Comment, None).with_column(column)
 }
@@ -13,8 +13,16 @@
 // - Detects unrecognized or invalid tokens.
 //
 // USAGE:
-// - Use `validate_syntax` to validate a vector of tokens representing a PL/I line.
+// - Use `validate_syntax` to validate a vector of tokens representing a PL/I line,
+//   or `validate_syntax_line` to get the first error as a `Diagnostic` located
+//   at its line and column.
 // - Call `is_valid_directive` for directive-specific validation.
+// - Call `validate_directive_arity` to check that a directive statement has
+//   the arguments its directive expects.
+// - Use `check_directives` to push warnings for unknown directives into a
+//   `DiagnosticSink`, or `check_directive_tokens` to also record their columns.
+// - Feed each tokenized line to a `SemicolonCheck` to warn about code
+//   statements that never reach their terminating `;`.
 //
 // AUTHOR: FirstLink Consulting Services (FLCS)
 // LICENSE: MIT License
@@ -22,6 +30,97 @@
 // VERSION: 1.0.1
 ////////////////////////////////////////////////////////////////////////////////
 
+////////////////////////////////////////////////////////////////////////////////
+// IMPORTS
+////////////////////////////////////////////////////////////////////////////////
+
+use crate::modules::context::parse_declaration;
+use crate::modules::diagnostics::{Diagnostic, DiagnosticSink, Severity};
+use crate::modules::directive::canonicalize;
+use crate::modules::labels::parse_goto;
+use crate::modules::parser::split_statements;
+use crate::modules::procedures::parse_return;
+use crate::modules::tokenizer::{Token, TokenCategory};
+
+////////////////////////////////////////////////////////////////////////////////
+// PUBLIC STRUCTURES
+////////////////////////////////////////////////////////////////////////////////
+
+/// Warns about code statements that are missing their terminating `;`.
+///
+/// A statement may continue over several lines, so a missing `;` is only
+/// reported once the statement can no longer continue: when a directive starts
+/// or the source ends. The warning points just past the statement's last
+/// token. Statements that start with a directive are exempt, and `/* */`
+/// comments are ignored.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::diagnostics::DiagnosticSink;
+/// # use pli_preprocessor::modules::tokenizer::tokenize_pli;
+/// # use pli_preprocessor::modules::validator::SemicolonCheck;
+/// let mut sink = DiagnosticSink::new();
+/// let mut check = SemicolonCheck::new();
+/// check.check_line(1, &tokenize_pli("A = B +"), &mut sink);
+/// check.check_line(2, &tokenize_pli("C;"), &mut sink);
+/// check.check_line(3, &tokenize_pli("D = E"), &mut sink);
+/// check.finish(&mut sink);
+/// assert_eq!(sink.diagnostics()[0].line, 3);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SemicolonCheck {
+    // The line and column just past the open statement's last token; the
+    // column is 0 when the tokens carry no positions.
+    open: Option<(usize, usize)>,
+}
+
+impl SemicolonCheck {
+    /// Creates a check with no statement open.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checks the statements of one tokenized line.
+    pub fn check_line(&mut self, line: usize, tokens: &[Token], sink: &mut DiagnosticSink) {
+        let code: Vec<Token> = tokens
+            .iter()
+            .filter(|t| t.category != TokenCategory::Comment)
+            .cloned()
+            .collect();
+        for statement in split_statements(&code) {
+            let (first, last) = match (statement.first(), statement.last()) {
+                (Some(first), Some(last)) => (first, last),
+                _ => continue,
+            };
+            if first.category == TokenCategory::Directive {
+                self.report(sink);
+            } else if last.value == ";" && last.category == TokenCategory::Separator {
+                self.open = None;
+            } else if last.column > 0 {
+                self.open = Some((line, last.column + last.original.chars().count()));
+            } else {
+                self.open = Some((line, 0));
+            }
+        }
+    }
+
+    /// Reports a statement still open at the end of the source.
+    pub fn finish(mut self, sink: &mut DiagnosticSink) {
+        self.report(sink);
+    }
+
+    fn report(&mut self, sink: &mut DiagnosticSink) {
+        if let Some((line, column)) = self.open.take() {
+            let mut diagnostic =
+                Diagnostic::new(Severity::Warning, line, "Missing semicolon").with_code("V005");
+            if column > 0 {
+                diagnostic = diagnostic.with_column(column);
+            }
+            sink.push(diagnostic);
+        }
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // PUBLIC FUNCTIONS
 ////////////////////////////////////////////////////////////////////////////////
@@ -37,6 +136,7 @@
 ///
 /// # Example
 /// ```rust
+/// # use pli_preprocessor::modules::validator::validate_syntax;
 /// let tokens = vec!["%IF".to_string(), "DEBUG".to_string(), "%THEN".to_string()];
 /// match validate_syntax(&tokens) {
 ///     Ok(_) => println!("Syntax is valid."),
@@ -44,40 +144,52 @@
 /// }
 /// ```
 pub fn validate_syntax(tokens: &[String]) -> Result<(), String> {
-    if tokens.is_empty() {
-        return Err("Empty token line".to_string());
-    }
-
-    let mut stack = Vec::new();
+    first_syntax_error(tokens.iter().map(|token| (token.as_str(), 0)))
+        .map_or(Ok(()), |(_, message, _)| Err(message))
+}
 
-    for token in tokens {
-        match token.as_str() {
-            "%IF" => stack.push("%IF"),
-            "%ENDIF" => {
-                if stack.pop() != Some("%IF") {
-                    return Err("Unmatched %ENDIF found".to_string());
-                }
-            }
-            "%THEN" => {
-                if stack.last() != Some(&"%IF") {
-                    return Err("%THEN without matching %IF".to_string());
-                }
-            }
-            _ if token.starts_with('%') && !is_valid_directive(token) => {
-                return Err(format!("Invalid directive: {}", token));
+/// Validates the syntax of a tokenized PL/I line like `validate_syntax`,
+/// locating the first error.
+///
+/// The diagnostic points at the offending token: the `%ENDIF`, `%THEN`, or
+/// invalid directive, or the innermost `%IF` left open. It has no column when
+/// the token's column is unknown (0) or the line is empty.
+///
+/// # Arguments
+/// - `tokens`: The tokenizer output for the line.
+/// - `line_number`: The 1-based number of the line.
+///
+/// # Returns
+/// - `Result<(), Diagnostic>`: `Ok(())` if the syntax is valid, or an error
+///   `Diagnostic` at `line_number`.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::tokenizer::tokenize_pli;
+/// # use pli_preprocessor::modules::validator::validate_syntax_line;
+/// let error = validate_syntax_line(&tokenize_pli("A = 1; %ENDIF;"), 12).unwrap_err();
+/// assert_eq!((error.line, error.column), (12, Some(8)));
+/// assert_eq!(error.message, "Unmatched %ENDIF found");
+/// ```
+pub fn validate_syntax_line(tokens: &[Token], line_number: usize) -> Result<(), Diagnostic> {
+    let error = first_syntax_error(tokens.iter().map(|t| (t.value.as_str(), t.column)));
+    match error {
+        None => Ok(()),
+        Some((code, message, column)) => {
+            let mut diagnostic = Diagnostic::new(Severity::Error, line_number, &message);
+            if let Some(code) = code {
+                diagnostic = diagnostic.with_code(code);
             }
-            _ => {}
+            Err(match column {
+                0 => diagnostic,
+                column => diagnostic.with_column(column),
+            })
         }
     }
-
-    if !stack.is_empty() {
-        return Err("Unmatched %IF found".to_string());
-    }
-
-    Ok(())
 }
 
-/// Checks if a directive token is valid.
+/// Checks if a directive token is valid, i.e. listed in the directive registry's
+/// built-in table once normalized by `directive::canonicalize`.
 ///
 /// # Arguments
 /// - `directive`: A `&str` containing the directive token to validate.
@@ -87,12 +199,211 @@ pub fn validate_syntax(tokens: &[String]) -> Result<(), String> {
 ///
 /// # Example
 /// ```rust
+/// # use pli_preprocessor::modules::validator::is_valid_directive;
 /// assert!(is_valid_directive("%IF"));
+/// assert!(is_valid_directive("%if "));
 /// assert!(!is_valid_directive("%INVALID"));
 /// ```
 pub fn is_valid_directive(directive: &str) -> bool {
-    let valid_directives = vec![
-        "%IF", "%ENDIF", "%ELSE", "%THEN", "%DO", "%END", "%SWITCH", "%CASE", "%DEFAULT",
-    ];
-    valid_directives.contains(&directive.to_uppercase().as_str())
+    canonicalize(directive).is_some()
+}
+
+/// Checks that a directive statement has the arguments its directive expects.
+///
+/// - `%INCLUDE` and `%XINCLUDE` take a comma-separated list of file names,
+///   each either a single token or a `LIBRARY(MEMBER)` reference.
+/// - `%IF` needs a condition before `%THEN`.
+/// - `%SET` needs `name = expression`.
+/// - `%DECLARE`/`%DCL` need names with type attributes (see
+///   `context::parse_declaration`).
+/// - `%MACRO`, `%ACTIVATE`, and `%DEACTIVATE` need a name.
+/// - `%RETURN` needs a parenthesized expression.
+/// - `%GOTO` (or `%GO TO`) needs a single label.
+/// - `%ENDIF` and `%ENDMACRO` take no arguments, and `%END` at most the label
+///   of the block it closes.
+///
+/// Other directives, unknown directives, and statements that do not start
+/// with a directive are accepted.
+///
+/// # Arguments
+/// - `tokens`: One statement's tokens, starting with the directive; a
+///   trailing `;` is ignored.
+///
+/// # Returns
+/// - `Result<(), String>`: `Ok(())` if the arguments fit the directive, or an
+///   error message describing the expected form.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::tokenizer::tokenize_pli;
+/// # use pli_preprocessor::modules::validator::validate_directive_arity;
+/// assert!(validate_directive_arity(&tokenize_pli("%SET X = 1;")).is_ok());
+/// assert_eq!(
+///     validate_directive_arity(&tokenize_pli("%INCLUDE;")),
+///     Err("%INCLUDE requires a file name".to_string())
+/// );
+/// ```
+pub fn validate_directive_arity(tokens: &[Token]) -> Result<(), String> {
+    let directive = match tokens.first().and_then(|t| canonicalize(&t.value)) {
+        Some(directive) => directive,
+        None => return Ok(()),
+    };
+    let args = match tokens.last() {
+        Some(last) if last.value == ";" && last.category == TokenCategory::Separator => {
+            &tokens[1..tokens.len() - 1]
+        }
+        _ => &tokens[1..],
+    };
+    let values: Vec<&str> = args.iter().map(|t| t.value.as_str()).collect();
+
+    match directive.as_str() {
+        "%INCLUDE" | "%XINCLUDE" if values.is_empty() => {
+            Err(format!("{} requires a file name", directive))
+        }
+        "%INCLUDE" | "%XINCLUDE" => {
+            let well_formed = values
+                .split(|v| *v == ",")
+                .all(|name| matches!(name, [_] | [_, "(", _, ")"]));
+            if well_formed {
+                Ok(())
+            } else {
+                Err(format!(
+                    "{} takes a comma-separated list of file names",
+                    directive
+                ))
+            }
+        }
+        "%IF" => match values.iter().position(|v| *v == "%THEN") {
+            Some(0) => Err("%IF requires a condition before %THEN".to_string()),
+            Some(_) => Ok(()),
+            None => Err("%IF requires %THEN after its condition".to_string()),
+        },
+        "%SET" => match args {
+            [name, equals, _, ..]
+                if name.category == TokenCategory::Identifier && equals.value == "=" =>
+            {
+                Ok(())
+            }
+            _ => Err("%SET requires 'name = expression'".to_string()),
+        },
+        "%DECLARE" | "%DCL" => parse_declaration(tokens).map(|_| ()),
+        "%MACRO" | "%ACTIVATE" | "%DEACTIVATE" => match args.first() {
+            Some(name) if name.category == TokenCategory::Identifier => Ok(()),
+            _ => Err(format!("{} requires a macro name", directive)),
+        },
+        "%RETURN" => parse_return(tokens).map(|_| ()),
+        "%GOTO" | "%GO" => parse_goto(tokens).unwrap_or(Ok(String::new())).map(|_| ()),
+        "%END" => match args {
+            [] => Ok(()),
+            [label] if label.category == TokenCategory::Identifier => Ok(()),
+            _ => Err("%END takes at most a label".to_string()),
+        },
+        "%ENDIF" | "%ENDMACRO" | "%PRINT" | "%NOPRINT" if !values.is_empty() => {
+            Err(format!("{} takes no arguments", directive))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Pushes a warning for every unrecognized directive in a tokenized line.
+///
+/// # Arguments
+/// - `tokens`: A `&[String]` slice containing the tokenized PL/I line.
+/// - `line`: The 1-based line number the tokens came from.
+/// - `sink`: The `DiagnosticSink` receiving the warnings.
+///
+/// # Example
+/// ```rust
+/// # use pli_preprocessor::modules::diagnostics::DiagnosticSink;
+/// # use pli_preprocessor::modules::validator::check_directives;
+/// let mut sink = DiagnosticSink::new();
+/// check_directives(&["%FOO".to_string()], 1, &mut sink);
+/// assert_eq!(sink.warning_count(), 1);
+/// ```
+pub fn check_directives(tokens: &[String], line: usize, sink: &mut DiagnosticSink) {
+    for token in tokens {
+        if token.starts_with('%') && token.len() > 1 && !is_valid_directive(token) {
+            sink.push(
+                Diagnostic::new(
+                    Severity::Warning,
+                    line,
+                    &format!("Invalid directive: {}", token),
+                )
+                .with_code("V001"),
+            );
+        }
+    }
+}
+
+/// Like `check_directives`, but takes tokenizer output so each warning carries
+/// the column of the offending directive. A bare `%` with no directive name
+/// is reported as an empty directive.
+///
+/// # Arguments
+/// - `tokens`: A `&[Token]` slice produced by the tokenizer.
+/// - `line`: The 1-based line number the tokens came from.
+/// - `sink`: The `DiagnosticSink` receiving the warnings.
+pub fn check_directive_tokens(tokens: &[Token], line: usize, sink: &mut DiagnosticSink) {
+    for token in tokens {
+        let value = &token.value;
+        let message = if value == "%" {
+            "Empty directive: '%' is not followed by a directive name".to_string()
+        } else if value.starts_with('%') && !is_valid_directive(value) {
+            format!("Invalid directive: {}", value)
+        } else {
+            continue;
+        };
+        let mut diagnostic = Diagnostic::new(Severity::Warning, line, &message).with_code("V001");
+        if token.column > 0 {
+            diagnostic = diagnostic.with_column(token.column);
+        }
+        sink.push(diagnostic);
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// PRIVATE FUNCTIONS
+////////////////////////////////////////////////////////////////////////////////
+
+/// Finds the first syntax error among `(value, column)` token pairs, as
+/// reported by `validate_syntax`, with its stable code and the column of the
+/// offending token.
+fn first_syntax_error<'a>(
+    tokens: impl Iterator<Item = (&'a str, usize)>,
+) -> Option<(Option<&'static str>, String, usize)> {
+    // The columns of the open `%IF`s, innermost last.
+    let mut stack = Vec::new();
+    let mut empty = true;
+
+    for (token, column) in tokens {
+        empty = false;
+        match token {
+            "%IF" => stack.push(column),
+            "%ENDIF" if stack.pop().is_none() => {
+                return Some((Some("V002"), "Unmatched %ENDIF found".to_string(), column));
+            }
+            "%THEN" if stack.is_empty() => {
+                return Some((
+                    Some("V006"),
+                    "%THEN without matching %IF".to_string(),
+                    column,
+                ));
+            }
+            _ if token.starts_with('%') && !is_valid_directive(token) => {
+                return Some((
+                    Some("V001"),
+                    format!("Invalid directive: {}", token),
+                    column,
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    if empty {
+        return Some((None, "Empty token line".to_string(), 0));
+    }
+    stack
+        .pop()
+        .map(|column| (Some("V003"), "Unmatched %IF found".to_string(), column))
 }
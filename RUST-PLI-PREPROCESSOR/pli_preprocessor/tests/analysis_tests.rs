@@ -0,0 +1,76 @@
+////////////////////////////////////////////////////////////////////////////////
+// MODULE NAME: Static Analysis Tests
+// ----------------------------------------------------------------------------
+// DESCRIPTION:
+// Tests for the `Static Analysis` module.
+//
+// FUNCTIONALITY:
+// - Verifies constant-false `%IF` conditions are flagged as never active.
+// - Verifies conditions referencing variables, and constant-true conditions,
+//   are not flagged.
+// - Verifies only indentation mixing tabs and spaces is flagged.
+//
+// AUTHOR: FirstLink Consulting Services (FLCS)
+// LICENSE: MIT License
+// DATE: 10/16/2026
+// VERSION: 1.0.0
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use pli_preprocessor::modules::analysis::{check_constant_condition, check_mixed_indentation};
+    use pli_preprocessor::modules::diagnostics::{Diagnostic, DiagnosticSink, Severity};
+    use pli_preprocessor::modules::tokenizer::tokenize_pli;
+
+    fn notes_for(line: &str) -> Vec<Diagnostic> {
+        let mut sink = DiagnosticSink::new();
+        check_constant_condition(&tokenize_pli(line), 1, &mut sink);
+        sink.into_diagnostics()
+    }
+
+    #[test]
+    fn test_constant_false_block_is_flagged() {
+        assert_eq!(
+            notes_for("  %IF 0 = 1 %THEN;"),
+            vec![Diagnostic::new(Severity::Note, 1, "block is never active")
+                .with_column(3)
+                .with_code("A001")]
+        );
+        assert_eq!(notes_for("%IF 2 * 3 ^= 6 %THEN;").len(), 1);
+        assert_eq!(notes_for("%IF 1 - 1 %THEN;").len(), 1);
+    }
+
+    #[test]
+    fn test_variable_condition_is_not_flagged() {
+        assert!(notes_for("%IF DEBUG = 0 %THEN;").is_empty());
+        assert!(notes_for("%IF 'A' = 'B' %THEN;").is_empty());
+    }
+
+    #[test]
+    fn test_constant_true_and_non_if_lines_are_not_flagged() {
+        assert!(notes_for("%IF 1 = 1 %THEN;").is_empty());
+        assert!(notes_for("%IF 2 >= 1 %THEN;").is_empty());
+        assert!(notes_for("X = 0 = 1;").is_empty());
+        assert!(notes_for("%IF %THEN;").is_empty());
+    }
+
+    #[test]
+    fn test_check_mixed_indentation() {
+        let check = |line: &str| {
+            let mut sink = DiagnosticSink::new();
+            check_mixed_indentation(line, 1, &mut sink);
+            sink.into_diagnostics()
+        };
+        assert!(check("\t\tA = 1;").is_empty());
+        assert!(check("    A = 1;").is_empty());
+        assert!(check("A = '\t ';").is_empty());
+        assert_eq!(
+            check("  \tA = 1;"),
+            vec![
+                Diagnostic::new(Severity::Note, 1, "indentation mixes tabs and spaces")
+                    .with_column(1)
+                    .with_code("A002")
+            ]
+        );
+    }
+}
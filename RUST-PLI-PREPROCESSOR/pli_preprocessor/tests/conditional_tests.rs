@@ -8,6 +8,9 @@
 // - Tests `process_condition` for various scenarios.
 // - Validates nested conditional block structures using
 //   `validate_conditional_structure`.
+// - Tests the `%IF` nesting-depth limit with 300 nested blocks.
+// - Tests `%IF UNDEF = 0` under each `UndefinedPolicy`.
+// - Tests string-equality conditions with single- and multi-word literals.
 //
 // AUTHOR: FirstLink Consulting Services (FLCS)
 // LICENSE: MIT License
@@ -17,7 +20,15 @@
 
 #[cfg(test)]
 mod tests {
-    use pli_tokenizer::modules::conditional::{process_condition, validate_conditional_structure};
+    use pli_preprocessor::modules::conditional::{
+        check_conditional_structure, check_conditional_structure_with_limit,
+        evaluate_condition_tokens, process_condition, process_condition_with_context,
+        split_inline_if, validate_conditional_structure, validate_conditional_structure_with_limit,
+        MAX_IF_DEPTH,
+    };
+    use pli_preprocessor::modules::context::{Context, UndefinedPolicy, Value};
+    use pli_preprocessor::modules::diagnostics::{Diagnostic, DiagnosticSink, Severity};
+    use pli_preprocessor::modules::tokenizer::tokenize_pli;
 
     #[test]
     fn test_process_condition_valid() {
@@ -70,4 +81,276 @@ mod tests {
         let tokens = vec!["%IF".to_string(), "%IF".to_string(), "%ENDIF".to_string()];
         assert!(validate_conditional_structure(&tokens).is_err());
     }
+
+    #[test]
+    fn test_check_conditional_structure_reports_lines() {
+        let directives = vec![
+            (1, "%IF".to_string()),
+            (2, "%ENDIF".to_string()),
+            (3, "%ENDIF".to_string()),
+            (5, "%IF".to_string()),
+        ];
+        let mut sink = DiagnosticSink::new();
+        check_conditional_structure(&directives, &mut sink);
+
+        assert_eq!(
+            sink.into_diagnostics(),
+            vec![
+                Diagnostic::new(Severity::Error, 3, "Unmatched %ENDIF directive").with_code("V002"),
+                Diagnostic::new(Severity::Error, 5, "Unmatched %IF directive").with_code("V003"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_conditional_structure_reports_stray_else() {
+        let directives = vec![
+            (1, "%ELSE".to_string()),
+            (2, "%IF".to_string()),
+            (3, "%ELSE".to_string()),
+            (4, "%IF".to_string()),
+            (5, "%ELSE".to_string()),
+            (6, "%ENDIF".to_string()),
+            (7, "%ELSE".to_string()),
+            (8, "%ENDIF".to_string()),
+        ];
+        let mut sink = DiagnosticSink::new();
+        check_conditional_structure(&directives, &mut sink);
+
+        assert_eq!(
+            sink.into_diagnostics(),
+            vec![
+                Diagnostic::new(Severity::Error, 1, "%ELSE without matching %IF").with_code("V007"),
+                Diagnostic::new(Severity::Error, 7, "Duplicate %ELSE in %IF block")
+                    .with_code("V008"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_endif_closes_block_with_or_without_semicolon() {
+        for source in [
+            "%IF DEBUG %THEN; X = 1; %ENDIF;",
+            "%IF DEBUG %THEN; X = 1; %ENDIF",
+            "%IF DEBUG %THEN; X = 1; %ENDIF Y = 2;",
+        ] {
+            let directives: Vec<String> = tokenize_pli(source)
+                .into_iter()
+                .filter(|t| t.value.starts_with('%'))
+                .map(|t| t.value)
+                .collect();
+            assert!(
+                validate_conditional_structure(&directives).is_ok(),
+                "for {:?}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn test_nesting_depth_limit() {
+        let mut tokens = vec!["%IF".to_string(); 300];
+        tokens.extend(vec!["%ENDIF".to_string(); 300]);
+        assert_eq!(MAX_IF_DEPTH, 256);
+        assert_eq!(
+            validate_conditional_structure(&tokens),
+            Err("%IF blocks nested deeper than the limit of 256".to_string())
+        );
+        assert_eq!(
+            validate_conditional_structure_with_limit(&tokens, 300),
+            Ok(())
+        );
+
+        let mut directives: Vec<(usize, String)> =
+            (1..=300).map(|line| (line, "%IF".to_string())).collect();
+        directives.extend((301..=600).map(|line| (line, "%ENDIF".to_string())));
+        let mut sink = DiagnosticSink::new();
+        check_conditional_structure(&directives, &mut sink);
+        assert_eq!(
+            sink.into_diagnostics(),
+            vec![Diagnostic::new(
+                Severity::Error,
+                257,
+                "%IF blocks nested deeper than the limit of 256"
+            )
+            .with_code("V004")]
+        );
+
+        let mut sink = DiagnosticSink::new();
+        check_conditional_structure_with_limit(&directives[..3], 2, &mut sink);
+        assert_eq!(
+            sink.into_diagnostics(),
+            vec![
+                Diagnostic::new(
+                    Severity::Error,
+                    3,
+                    "%IF blocks nested deeper than the limit of 2"
+                )
+                .with_code("V004"),
+                Diagnostic::new(Severity::Error, 1, "Unmatched %IF directive").with_code("V003"),
+                Diagnostic::new(Severity::Error, 2, "Unmatched %IF directive").with_code("V003"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_process_condition_with_context() {
+        let mut context = Context::new();
+        context.set("LEVEL", Value::Str("2".to_string()));
+        context.set("MODE", Value::Str("FAST".to_string()));
+
+        assert_eq!(
+            process_condition_with_context("LEVEL = 2", &context),
+            Ok(true)
+        );
+        assert_eq!(
+            process_condition_with_context("LEVEL != 2", &context),
+            Ok(false)
+        );
+        assert_eq!(
+            process_condition_with_context("MODE = 'FAST'", &context),
+            Ok(true)
+        );
+        assert_eq!(
+            process_condition_with_context("MODE = SLOW", &context),
+            Ok(false)
+        );
+        assert!(process_condition_with_context("MODE = 1", &context).is_err());
+        assert!(process_condition_with_context("DEBUG = 1", &context).is_err());
+    }
+
+    #[test]
+    fn test_split_inline_if() {
+        let tokens = tokenize_pli("%IF DEBUG %THEN A = 1;");
+        let (condition, statement) = split_inline_if(&tokens).unwrap();
+        assert_eq!(condition.len(), 1);
+        let values: Vec<&str> = statement.iter().map(|t| t.value.as_str()).collect();
+        assert_eq!(values, vec!["A", "=", "1", ";"]);
+
+        assert!(split_inline_if(&tokenize_pli("%IF DEBUG %THEN;")).is_none());
+        assert!(split_inline_if(&tokenize_pli("A = 1;")).is_none());
+    }
+
+    #[test]
+    fn test_string_condition_single_word() {
+        let mut context = Context::new();
+        context.set("OSTYPE", Value::Str("MVS".to_string()));
+        assert_eq!(
+            process_condition_with_context("OSTYPE = 'MVS'", &context),
+            Ok(true)
+        );
+        assert_eq!(
+            process_condition_with_context("OSTYPE='MVS'", &context),
+            Ok(true)
+        );
+        assert_eq!(
+            process_condition_with_context("OSTYPE != 'VM'", &context),
+            Ok(true)
+        );
+        assert_eq!(
+            evaluate_condition_tokens(&tokenize_pli("OSTYPE = 'MVS'"), &context),
+            Ok(true)
+        );
+    }
+
+    #[test]
+    fn test_string_condition_multi_word() {
+        let mut context = Context::new();
+        context.set("MODE", Value::Str("FULL SCAN".to_string()));
+        assert_eq!(
+            process_condition_with_context("MODE = 'FULL SCAN'", &context),
+            Ok(true)
+        );
+        assert_eq!(
+            process_condition_with_context("MODE = 'FULL  SCAN'", &context),
+            Ok(false)
+        );
+        assert_eq!(
+            evaluate_condition_tokens(&tokenize_pli("MODE = 'FULL SCAN'"), &context),
+            Ok(true)
+        );
+
+        context.set("NAME", Value::Str("IT'S ON".to_string()));
+        assert_eq!(
+            process_condition_with_context("NAME = 'IT''S ON'", &context),
+            Ok(true)
+        );
+        assert!(process_condition_with_context("MODE = 'FULL SCAN", &context).is_err());
+    }
+
+    #[test]
+    fn test_undefined_policy_error() {
+        let context = Context::new();
+        assert_eq!(
+            evaluate_condition_tokens(&tokenize_pli("UNDEF = 0"), &context),
+            Err("Undefined variable: UNDEF".to_string())
+        );
+        assert_eq!(
+            process_condition_with_context("UNDEF = 0", &context),
+            Err("Unknown variable: UNDEF".to_string())
+        );
+    }
+
+    #[test]
+    fn test_undefined_policy_zero() {
+        let mut context = Context::new();
+        context.set_undefined_policy(UndefinedPolicy::Zero);
+        assert_eq!(
+            evaluate_condition_tokens(&tokenize_pli("UNDEF = 0"), &context),
+            Ok(true)
+        );
+        assert_eq!(
+            process_condition_with_context("UNDEF = 0", &context),
+            Ok(true)
+        );
+    }
+
+    #[test]
+    fn test_undefined_policy_empty() {
+        let mut context = Context::new();
+        context.set_undefined_policy(UndefinedPolicy::Empty);
+        // The empty string is not a number, so it cannot be compared with one.
+        assert_eq!(
+            evaluate_condition_tokens(&tokenize_pli("UNDEF = 0"), &context),
+            Err("Type mismatch: cannot compare '' with 0".to_string())
+        );
+        assert_eq!(
+            evaluate_condition_tokens(&tokenize_pli("UNDEF = ''"), &context),
+            Ok(true)
+        );
+        assert_eq!(
+            process_condition_with_context("UNDEF != 'X'", &context),
+            Ok(true)
+        );
+    }
+
+    #[test]
+    fn test_evaluate_condition_tokens() {
+        let mut context = Context::new();
+        context.set("DEBUG", Value::Int(1));
+        context.set("MODE", Value::Str("FAST".to_string()));
+        assert_eq!(
+            evaluate_condition_tokens(&tokenize_pli("DEBUG"), &context),
+            Ok(true)
+        );
+        assert_eq!(
+            evaluate_condition_tokens(&tokenize_pli("MODE = 'SLOW'"), &context),
+            Ok(false)
+        );
+        assert!(evaluate_condition_tokens(&tokenize_pli("MODE"), &context).is_err());
+        assert!(evaluate_condition_tokens(&[], &context).is_err());
+
+        assert_eq!(
+            evaluate_condition_tokens(&tokenize_pli("0 = 1 AND UNDEFINED > 0"), &context),
+            Ok(false)
+        );
+        assert_eq!(
+            evaluate_condition_tokens(&tokenize_pli("DEBUG = 1 OR UNDEFINED > 0"), &context),
+            Ok(true)
+        );
+        assert_eq!(
+            evaluate_condition_tokens(&tokenize_pli("DEBUG = 1 AND UNDEFINED > 0"), &context),
+            Err("Undefined variable: UNDEFINED".to_string())
+        );
+    }
 }
@@ -0,0 +1,170 @@
+////////////////////////////////////////////////////////////////////////////////
+// MODULE NAME: Preprocessor Context Tests
+// ----------------------------------------------------------------------------
+// DESCRIPTION:
+// Tests for the `Preprocessor Context` module.
+//
+// FUNCTIONALITY:
+// - Verifies declarations register default values and types.
+// - Verifies `parse_declaration` on simple, factored, and malformed statements.
+// - Verifies a context and macro table survive a `save_state`/`load_state`
+//   round trip.
+//
+// AUTHOR: FirstLink Consulting Services (FLCS)
+// LICENSE: MIT License
+// DATE: 10/16/2026
+// VERSION: 1.0.0
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use pli_preprocessor::modules::context::{
+        load_state, parse_declaration, save_state, Context, UndefinedPolicy, Value, VarType,
+    };
+    use pli_preprocessor::modules::error::PreprocessorError;
+    use pli_preprocessor::modules::macro_expander::MacroTable;
+    use pli_preprocessor::modules::tokenizer::tokenize_pli;
+    use std::fs;
+
+    #[test]
+    fn test_declare_registers_defaults() {
+        let mut context = Context::new();
+        context.declare("count", VarType::Fixed);
+        context.declare("NAME", VarType::Character);
+
+        assert!(context.is_declared("COUNT"));
+        assert_eq!(context.get("COUNT"), Some(&Value::Int(0)));
+        assert_eq!(context.get("name"), Some(&Value::Str(String::new())));
+        assert_eq!(context.var_type("NAME"), Some(VarType::Character));
+    }
+
+    #[test]
+    fn test_lookup_follows_undefined_policy() {
+        let mut context = Context::new();
+        assert_eq!(
+            context.get_int("UNDEF"),
+            Err("Undefined variable: UNDEF".to_string())
+        );
+        context.set_undefined_policy(UndefinedPolicy::Zero);
+        assert_eq!(context.get_int("undef"), Ok(0));
+        context.set_undefined_policy(UndefinedPolicy::Empty);
+        assert_eq!(context.get_str("UNDEF"), Ok(String::new()));
+        assert_eq!("ZERO".parse(), Ok(UndefinedPolicy::Zero));
+        assert!("none".parse::<UndefinedPolicy>().is_err());
+    }
+
+    #[test]
+    fn test_set_without_declare_is_implicit() {
+        let mut context = Context::new();
+        context.set("X", Value::Int(5));
+        assert_eq!(context.get("X"), Some(&Value::Int(5)));
+        assert!(!context.is_declared("X"));
+
+        context.declare("X", VarType::Fixed);
+        context.set("X", Value::Int(7));
+        assert!(context.is_declared("X"));
+        assert_eq!(context.get("X"), Some(&Value::Int(7)));
+    }
+
+    #[test]
+    fn test_parse_declaration() {
+        assert_eq!(
+            parse_declaration(&tokenize_pli("%DECLARE X FIXED;")),
+            Ok(vec![("X".to_string(), VarType::Fixed)])
+        );
+        assert_eq!(
+            parse_declaration(&tokenize_pli("%DCL (A, B) CHAR, N FIXED;")),
+            Ok(vec![
+                ("A".to_string(), VarType::Character),
+                ("B".to_string(), VarType::Character),
+                ("N".to_string(), VarType::Fixed),
+            ])
+        );
+        assert_eq!(
+            parse_declaration(&tokenize_pli("%DCL X;")),
+            Err("Missing type attribute for X".to_string())
+        );
+        assert!(parse_declaration(&tokenize_pli("%DCL FIXED;")).is_err());
+    }
+
+    #[test]
+    fn test_get_int_coerces_numeric_strings() {
+        let mut context = Context::new();
+        context.set("COUNT", Value::Str(" 42 ".to_string()));
+        context.set("NAME", Value::Str("ABC".to_string()));
+        context.set("N", Value::Int(-3));
+
+        assert_eq!(context.get_int("count"), Ok(42));
+        assert_eq!(context.get_int("N"), Ok(-3));
+        assert_eq!(
+            context.get_int("NAME"),
+            Err("Variable NAME is not a number: 'ABC'".to_string())
+        );
+        assert_eq!(
+            context.get_int("MISSING"),
+            Err("Undefined variable: MISSING".to_string())
+        );
+    }
+
+    #[test]
+    fn test_get_str_formats_integers() {
+        let mut context = Context::new();
+        context.set("N", Value::Int(7));
+        context.set("NAME", Value::Str("ABC".to_string()));
+
+        assert_eq!(context.get_str("N"), Ok("7".to_string()));
+        assert_eq!(context.get_str("name"), Ok("ABC".to_string()));
+        assert!(context.get_str("MISSING").is_err());
+    }
+
+    #[test]
+    fn test_save_and_load_state_round_trip() {
+        let dir = std::env::temp_dir().join("pli_context_state");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("state.json");
+
+        let mut context = Context::new();
+        context.declare("COUNT", VarType::Fixed);
+        context.set("COUNT", Value::Int(3));
+        context.declare("NAME", VarType::Character);
+        context.set("NAME", Value::Str("ABC".to_string()));
+        context.set("RATE", Value::Decimal(2.5));
+        context.set("FLAG", Value::Boolean(true));
+        context.set_undefined_policy(UndefinedPolicy::Zero);
+        let mut macros = MacroTable::new();
+        macros.define("GREETING", "'HELLO'");
+        macros.define("LIMIT", "COUNT + 1");
+        macros.deactivate("LIMIT").unwrap();
+        macros.set_macro_rescan("GREETING", false).unwrap();
+
+        save_state(&path, &context, &macros).unwrap();
+        let (loaded_context, loaded_macros) = load_state(&path).unwrap();
+        assert_eq!(loaded_context, context);
+        assert_eq!(loaded_macros, macros);
+
+        // A later run resumes with the earlier declarations and macros.
+        assert_eq!(loaded_context.var_type("COUNT"), Some(VarType::Fixed));
+        assert_eq!(loaded_context.lookup("UNDEF"), Ok(Value::Int(0)));
+        assert_eq!(loaded_macros.expand_line("X = GREETING;"), "X = 'HELLO';");
+        assert_eq!(loaded_macros.expand_line("Y = LIMIT;"), "Y = LIMIT;");
+    }
+
+    #[test]
+    fn test_load_state_rejects_bad_files() {
+        let dir = std::env::temp_dir().join("pli_context_state");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("invalid.json");
+        fs::write(&path, "{\"context\": 1}").unwrap();
+
+        match load_state(&path) {
+            Err(PreprocessorError::Other(message)) => {
+                assert!(message.starts_with("Invalid state file"), "{}", message)
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(matches!(
+            load_state(&dir.join("absent.json")),
+            Err(PreprocessorError::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,86 @@
+////////////////////////////////////////////////////////////////////////////////
+// TESTS FOR: Regression Corpus
+// ----------------------------------------------------------------------------
+// Tokenizes every `.pli` file in `tests/corpus/` line by line and compares the
+// result with the JSON stored next to it in `<name>.tokens.json`.
+//
+// To regenerate the expected files after an intentional tokenizer change, run:
+//
+//     UPDATE_CORPUS=1 cargo test --test corpus_tests
+//
+// and review the resulting diff before committing it.
+// ----------------------------------------------------------------------------
+// AUTHOR: FirstLink Consulting Services (FLCS)
+// DATE: 10/16/2026
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use pli_preprocessor::modules::tokenizer::{tokenize_pli, Token};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    /// Returns the `.pli` inputs of the corpus, sorted by name.
+    fn corpus_inputs() -> Vec<PathBuf> {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+        let mut inputs: Vec<PathBuf> = fs::read_dir(&dir)
+            .expect("tests/corpus should exist")
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "pli"))
+            .collect();
+        inputs.sort();
+        inputs
+    }
+
+    /// Tokenizes each line of `source`, keeping one token list per line.
+    fn tokenize_lines(source: &str) -> Vec<Vec<Token>> {
+        source.lines().map(tokenize_pli).collect()
+    }
+
+    #[test]
+    fn test_corpus_matches_expected_tokens() {
+        let update = std::env::var("UPDATE_CORPUS").is_ok_and(|value| value == "1");
+        let inputs = corpus_inputs();
+        assert!(!inputs.is_empty(), "the corpus has no .pli inputs");
+
+        let mut mismatches = Vec::new();
+        for input in inputs {
+            let source = fs::read_to_string(&input).unwrap();
+            let actual = tokenize_lines(&source);
+            let expected_path = input.with_extension("tokens.json");
+
+            if update {
+                let json = serde_json::to_string_pretty(&actual).unwrap();
+                fs::write(&expected_path, json + "\n").unwrap();
+                continue;
+            }
+
+            let expected: Vec<Vec<Token>> = match fs::read_to_string(&expected_path) {
+                Ok(json) => serde_json::from_str(&json).unwrap(),
+                Err(_) => {
+                    mismatches.push(format!("{}: missing", expected_path.display()));
+                    continue;
+                }
+            };
+            for (index, (want, got)) in expected.iter().zip(&actual).enumerate() {
+                if want != got {
+                    mismatches.push(format!("{} line {}", input.display(), index + 1));
+                }
+            }
+            if expected.len() != actual.len() {
+                mismatches.push(format!(
+                    "{}: expected {} lines, found {}",
+                    input.display(),
+                    expected.len(),
+                    actual.len()
+                ));
+            }
+        }
+
+        assert!(
+            mismatches.is_empty(),
+            "corpus mismatches (rerun with UPDATE_CORPUS=1 to accept):\n{}",
+            mismatches.join("\n")
+        );
+    }
+}
@@ -0,0 +1,215 @@
+////////////////////////////////////////////////////////////////////////////////
+// MODULE NAME: Diagnostics Tests
+// ----------------------------------------------------------------------------
+// DESCRIPTION:
+// Tests for the `Diagnostics` module.
+//
+// FUNCTIONALITY:
+// - Verifies `format_diagnostic_with_caret` places the caret under the given
+//   column, including lines containing tabs.
+// - Verifies the exact text of each `format_diagnostics` format.
+// - Verifies a deduplicating sink collapses identical diagnostics.
+// - Verifies diagnostic codes, including those of known pipeline errors, and
+//   the `explain_code` help text.
+//
+// AUTHOR: FirstLink Consulting Services (FLCS)
+// LICENSE: MIT License
+// DATE: 10/16/2026
+// VERSION: 1.0.0
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use pli_preprocessor::modules::diagnostics::{
+        explain_code, format_diagnostic_with_caret, format_diagnostics, Diagnostic,
+        DiagnosticFormat, DiagnosticSink, Severity, DIAGNOSTIC_CODES,
+    };
+    use pli_preprocessor::modules::pipeline::{process_reader, ProcessOptions};
+    use std::io::Cursor;
+
+    fn sample_diagnostics() -> Vec<Diagnostic> {
+        vec![
+            Diagnostic::new(Severity::Error, 4, "Unmatched string literal")
+                .with_column(9)
+                .with_code("T001"),
+            Diagnostic::new(Severity::Warning, 7, "Invalid directive: \"%FOO\"").with_code("V001"),
+        ]
+    }
+
+    #[test]
+    fn test_dedup_collapses_identical_diagnostics() {
+        let mut sink = DiagnosticSink::new();
+        sink.set_dedup(true);
+        sink.warning(2, "Invalid directive: %FOO");
+        sink.warning(5, "Invalid directive: %FOO");
+        sink.error(6, "Invalid directive: %FOO");
+        sink.warning(8, "Invalid directive: %FOO");
+        sink.warning(9, "Invalid directive: %BAR");
+
+        assert_eq!(sink.warning_count(), 4);
+        assert_eq!(
+            sink.into_diagnostics(),
+            vec![
+                Diagnostic::new(Severity::Warning, 2, "Invalid directive: %FOO").with_count(3),
+                Diagnostic::new(Severity::Error, 6, "Invalid directive: %FOO"),
+                Diagnostic::new(Severity::Warning, 9, "Invalid directive: %BAR"),
+            ]
+        );
+
+        let collapsed =
+            Diagnostic::new(Severity::Warning, 2, "Invalid directive: %FOO").with_count(3);
+        assert_eq!(
+            collapsed.to_string(),
+            "line 2: warning: Invalid directive: %FOO (and 2 more)"
+        );
+    }
+
+    #[test]
+    fn test_format_diagnostics_human() {
+        assert_eq!(
+            format_diagnostics(&sample_diagnostics(), DiagnosticFormat::Human, "main.pli"),
+            "line 4: error: Unmatched string literal\n\
+             line 7: warning: Invalid directive: \"%FOO\"\n"
+        );
+    }
+
+    #[test]
+    fn test_format_diagnostics_json() {
+        assert_eq!(
+            format_diagnostics(&sample_diagnostics(), DiagnosticFormat::Json, "main.pli"),
+            "{\"file\": \"main.pli\", \"line\": 4, \"column\": 9, \"severity\": \"error\", \
+             \"code\": \"T001\", \"message\": \"Unmatched string literal\"}\n\
+             {\"file\": \"main.pli\", \"line\": 7, \"column\": null, \"severity\": \"warning\", \
+             \"code\": \"V001\", \"message\": \"Invalid directive: \\\"%FOO\\\"\"}\n"
+        );
+
+        // Control characters are escaped too, so each line stays valid JSON.
+        let tricky = vec![Diagnostic::new(Severity::Note, 1, "tab\there\nbell\u{7}")];
+        let json = format_diagnostics(&tricky, DiagnosticFormat::Json, "a\\b.pli");
+        assert_eq!(json.lines().count(), 1);
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["message"], "tab\there\nbell\u{7}");
+        assert_eq!(value["file"], "a\\b.pli");
+    }
+
+    #[test]
+    fn test_format_diagnostics_gcc() {
+        assert_eq!(
+            format_diagnostics(&sample_diagnostics(), DiagnosticFormat::Gcc, "main.pli"),
+            "main.pli:4:9: error: Unmatched string literal\n\
+             main.pli:7: warning: Invalid directive: \"%FOO\"\n"
+        );
+        assert_eq!("GCC".parse::<DiagnosticFormat>(), Ok(DiagnosticFormat::Gcc));
+        assert_eq!(
+            "xml".parse::<DiagnosticFormat>(),
+            Err("Unknown diagnostic format: xml".to_string())
+        );
+    }
+
+    #[test]
+    fn test_caret_under_column() {
+        assert_eq!(
+            format_diagnostic_with_caret("NAME = 'oops;", 8, "Unmatched string literal"),
+            "Unmatched string literal\nNAME = 'oops;\n       ^"
+        );
+        assert_eq!(
+            format_diagnostic_with_caret("%FOO X;", 1, "Invalid directive"),
+            "Invalid directive\n%FOO X;\n^"
+        );
+    }
+
+    #[test]
+    fn test_caret_after_tab_expansion() {
+        // The tab at column 1 expands to eight spaces, so column 2 renders at offset 8.
+        assert_eq!(
+            format_diagnostic_with_caret("\t%FOO;", 2, "msg"),
+            "msg\n        %FOO;\n        ^"
+        );
+        // "AB" then a tab pads to the next stop at offset 8.
+        assert_eq!(
+            format_diagnostic_with_caret("AB\tX = 'Y", 8, "msg"),
+            "msg\nAB      X = 'Y\n            ^"
+        );
+    }
+
+    #[test]
+    fn test_caret_past_end_of_line() {
+        assert_eq!(
+            format_diagnostic_with_caret("A = 1", 0, "msg"),
+            "msg\nA = 1\n     ^"
+        );
+    }
+
+    #[test]
+    fn test_diagnostic_codes() {
+        // A code comes from where the diagnostic is raised, never from
+        // the text of its message.
+        let diagnostic = Diagnostic::new(Severity::Error, 1, "Unmatched %ENDIF directive");
+        assert_eq!(diagnostic.code, None);
+        assert_eq!(diagnostic.with_code("V002").code, Some("V002"));
+
+        // Codes and former codes are unique across the whole table.
+        let mut seen: Vec<&str> = Vec::new();
+        for entry in DIAGNOSTIC_CODES {
+            for code in std::iter::once(&entry.code).chain(entry.former) {
+                assert!(!seen.contains(code), "{} is used twice", code);
+                seen.push(code);
+            }
+        }
+    }
+
+    #[test]
+    fn test_known_errors_produce_their_codes() {
+        let codes = |source: &str| -> Vec<Option<&'static str>> {
+            let mut output: Vec<String> = Vec::new();
+            process_reader(Cursor::new(source), &mut output, &ProcessOptions::default())
+                .unwrap()
+                .diagnostics
+                .iter()
+                .map(|d| d.code)
+                .collect()
+        };
+        assert_eq!(codes("X = 'oops;\n"), vec![Some("T001")]);
+        assert_eq!(codes("%FOO;\n"), vec![Some("V001")]);
+        assert_eq!(codes("%ENDIF;\n"), vec![Some("V002")]);
+        assert_eq!(codes("%IF 1 = 1 %THEN;\nA = 1;\n"), vec![Some("V003")]);
+        assert_eq!(codes("%ELSE;\nA = 1;\n"), vec![Some("V007")]);
+        assert_eq!(
+            codes("%IF 1 = 1 %THEN;\n%ELSE;\n%ELSE;\n%ENDIF;\n"),
+            vec![Some("V008")]
+        );
+        assert_eq!(codes("%GOTO NOWHERE;\n"), vec![Some("D004")]);
+        assert_eq!(codes("%A: ;\n%A: ;\n"), vec![Some("D005")]);
+        assert_eq!(codes("%SET N = UNDEFINED;\n"), vec![Some("X001")]);
+        assert_eq!(codes("%SET N = 1 / 0;\n"), vec![Some("X001")]);
+        assert_eq!(codes("%COMMENT open\n"), vec![Some("D001")]);
+        assert_eq!(codes("%MACRO M;\n"), vec![Some("D002")]);
+        assert_eq!(codes("%INCLUDE 'defs.pli';\n"), vec![Some("I004")]);
+        assert_eq!(codes("%DO;\n%END;\n"), vec![Some("D007"), Some("D007")]);
+
+        // Errors without an entry in the table carry no code.
+        assert_eq!(codes("%SET = 1;\n"), vec![None]);
+    }
+
+    #[test]
+    fn test_explain_code() {
+        let help = explain_code("V002").unwrap();
+        assert!(help.starts_with("V002: Unmatched %ENDIF directive\n\n"));
+        assert!(help.contains("Example fix:\n"));
+        assert_eq!(explain_code("v002"), Ok(help.clone()));
+
+        for entry in DIAGNOSTIC_CODES {
+            assert!(!explain_code(entry.code).unwrap().is_empty());
+        }
+
+        // A former code is still explained, under the code that replaced it.
+        assert_eq!(
+            explain_code("e001"),
+            Ok(format!("E001 is now V002.\n\n{}", help))
+        );
+        assert_eq!(
+            explain_code("E999"),
+            Err("Unknown diagnostic code: E999".to_string())
+        );
+    }
+}
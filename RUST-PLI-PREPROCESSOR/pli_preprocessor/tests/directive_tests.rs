@@ -0,0 +1,99 @@
+////////////////////////////////////////////////////////////////////////////////
+// MODULE NAME: Directive Registry Tests
+// ----------------------------------------------------------------------------
+// DESCRIPTION:
+// Tests for the `Directive Registry` module.
+//
+// FUNCTIONALITY:
+// - Verifies custom directives registered at runtime are categorized by the
+//   tokenizer.
+// - Verifies the tokenizer and validator agree with the built-in table.
+// - Verifies `canonicalize` normalizes case and spacing.
+//
+// AUTHOR: FirstLink Consulting Services (FLCS)
+// LICENSE: MIT License
+// DATE: 10/16/2026
+// VERSION: 1.0.0
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use pli_preprocessor::modules::directive::{
+        builtin_category, canonicalize, DirectiveRegistry, BUILTIN_DIRECTIVES,
+    };
+    use pli_preprocessor::modules::tokenizer::{
+        get_directive_category, tokenize_pli, tokenize_pli_with_config, DirectiveCategory,
+        TokenizerConfig,
+    };
+    use pli_preprocessor::modules::validator::is_valid_directive;
+
+    #[test]
+    fn test_custom_directive_is_categorized() {
+        let before = tokenize_pli("%MYDIR X;");
+        assert_eq!(before[0].directive_category, Some(DirectiveCategory::Other));
+
+        let mut config = TokenizerConfig::default();
+        config
+            .directives
+            .register("mydir", DirectiveCategory::ControlFlow);
+        let tokens = tokenize_pli_with_config("%MyDir X;", &config);
+
+        assert_eq!(tokens[0].value, "%MYDIR");
+        assert_eq!(
+            tokens[0].directive_category,
+            Some(DirectiveCategory::ControlFlow)
+        );
+    }
+
+    #[test]
+    fn test_registered_directive_overrides_builtin() {
+        let mut registry = DirectiveRegistry::new();
+        assert!(registry.is_known("%IF"));
+        assert!(!registry.is_known("%MYDIR"));
+
+        registry.register("%IF", DirectiveCategory::Other);
+        registry.register("%MYDIR", DirectiveCategory::Comment);
+        assert_eq!(registry.category("%IF"), DirectiveCategory::Other);
+        assert_eq!(registry.category("%mydir"), DirectiveCategory::Comment);
+        assert_eq!(registry.category("%UNKNOWN"), DirectiveCategory::Other);
+    }
+
+    #[test]
+    fn test_builtin_table_is_shared() {
+        for (directive, category) in BUILTIN_DIRECTIVES {
+            assert_eq!(get_directive_category(directive), *category);
+            assert!(is_valid_directive(directive));
+        }
+        assert_eq!(
+            get_directive_category("%XINCLUDE"),
+            DirectiveCategory::MacroHandling
+        );
+        assert_eq!(builtin_category("%NOPE"), None);
+        assert!(!is_valid_directive("%NOPE"));
+    }
+
+    #[test]
+    fn test_canonicalize_mixed_case() {
+        assert_eq!(canonicalize("%if"), Some("%IF".to_string()));
+        assert_eq!(canonicalize("%EndMacro"), Some("%ENDMACRO".to_string()));
+        assert_eq!(canonicalize("%dcl"), Some("%DCL".to_string()));
+    }
+
+    #[test]
+    fn test_canonicalize_surrounding_whitespace() {
+        assert_eq!(canonicalize("%if "), Some("%IF".to_string()));
+        assert_eq!(canonicalize("\t%Include\n"), Some("%INCLUDE".to_string()));
+        assert_eq!(canonicalize("% set"), Some("%SET".to_string()));
+    }
+
+    #[test]
+    fn test_canonicalize_unknown_directives() {
+        assert_eq!(canonicalize("%MYDIR"), None);
+        assert_eq!(canonicalize("IF"), None);
+        assert_eq!(canonicalize("%"), None);
+        assert_eq!(canonicalize(""), None);
+        assert!(BUILTIN_DIRECTIVES
+            .iter()
+            .all(|(name, _)| canonicalize(name).as_deref() == Some(*name)));
+    }
+}
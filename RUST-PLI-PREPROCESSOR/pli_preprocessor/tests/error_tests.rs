@@ -0,0 +1,134 @@
+////////////////////////////////////////////////////////////////////////////////
+// TEST MODULE: Error Tests
+// ----------------------------------------------------------------------------
+// DESCRIPTION:
+// This test module validates `PreprocessorError`: its conversions, its
+// `std::error::Error` implementation, `?` propagation across the pipeline,
+// parser, evaluator, and include handler entry points, and its diagnostic
+// codes.
+//
+// AUTHOR: FirstLink Consulting Services (FLCS)
+// LICENSE: MIT License
+// DATE: 10/16/2026
+// VERSION: 1.0.0
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use pli_preprocessor::modules::error::PreprocessorError;
+    use pli_preprocessor::modules::evaluator::evaluate_value;
+    use pli_preprocessor::modules::include_handler::{
+        expand_includes, process_include_with, MapIncludeResolver,
+    };
+    use pli_preprocessor::modules::parser::parse_program;
+    use pli_preprocessor::modules::pipeline::{process_reader, ProcessOptions};
+    use std::error::Error;
+    use std::io;
+
+    /// Expands, parses, and preprocesses `source`, then evaluates `expression`,
+    /// propagating every failure with `?`.
+    fn run(
+        source: &str,
+        expression: &str,
+        resolver: &MapIncludeResolver,
+    ) -> Result<(usize, String), PreprocessorError> {
+        let expanded = expand_includes(source, resolver, false)?;
+        let nodes = parse_program(&expanded)?;
+        let mut output: Vec<String> = Vec::new();
+        process_reader(expanded.as_bytes(), &mut output, &ProcessOptions::default())?;
+        let value = evaluate_value(expression)?;
+        Ok((nodes.len(), format!("{} {}", output.join(" "), value)))
+    }
+
+    #[test]
+    fn test_question_mark_propagates_across_pipeline_call() {
+        let mut resolver = MapIncludeResolver::new();
+        resolver.insert("defs.pli", "A = 1;");
+
+        assert_eq!(
+            run("%INCLUDE 'defs.pli';\nB = 2;\n", "1 + 2", &resolver),
+            Ok((2, "A = 1; B = 2; 3".to_string()))
+        );
+        assert_eq!(
+            run("%INCLUDE 'absent.pli';\n", "1", &resolver),
+            Err(PreprocessorError::Include {
+                code: "I002",
+                message: "Include not found: absent.pli".to_string(),
+            })
+        );
+        assert_eq!(
+            run("END;\n", "1", &resolver),
+            Err(PreprocessorError::Parser {
+                code: "P001",
+                message: "END without an open block at line 1".to_string(),
+            })
+        );
+        assert_eq!(
+            run("A = 1;\n", "1 / 0", &resolver),
+            Err(PreprocessorError::Evaluator("Division by zero".to_string()))
+        );
+        assert_eq!(
+            run("A = 1;\n", "'open", &resolver),
+            Err(PreprocessorError::Tokenizer {
+                code: "T001",
+                message: "Unterminated string literal: 'open".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_errors_carry_their_codes() {
+        let mut resolver = MapIncludeResolver::new();
+        resolver.insert("self.pli", "%INCLUDE 'self.pli';");
+        let code =
+            |source: &str, expression: &str| run(source, expression, &resolver).unwrap_err().code();
+
+        assert_eq!(code("%INCLUDE 'absent.pli';\n", "1"), Some("I002"));
+        assert_eq!(code("%INCLUDE 'self.pli';\n", "1"), Some("I003"));
+        assert_eq!(
+            process_include_with("%INCLUDE;", &resolver)
+                .unwrap_err()
+                .code(),
+            Some("I001")
+        );
+        assert_eq!(code("END;\n", "1"), Some("P001"));
+        assert_eq!(code("DO;\n", "1"), Some("P002"));
+        assert_eq!(code("A = 1;\n", "'open"), Some("T001"));
+        assert_eq!(code("A = 1;\n", "1 / 0"), Some("X001"));
+        assert_eq!(PreprocessorError::Other("odd".to_string()).code(), None);
+    }
+
+    #[test]
+    fn test_pipeline_io_error_converts_into_boxed_error() {
+        fn run_boxed(bytes: &[u8]) -> Result<usize, Box<dyn Error>> {
+            let mut output: Vec<String> = Vec::new();
+            let result = process_reader(bytes, &mut output, &ProcessOptions::default())?;
+            Ok(result.stats.lines_written)
+        }
+
+        assert_eq!(run_boxed(b"A = 1;\n").unwrap(), 1);
+        let error = run_boxed(b"A = '\xFF';\n").unwrap_err();
+        let error = error.downcast_ref::<PreprocessorError>().unwrap();
+        let source = error.source().unwrap().downcast_ref::<io::Error>().unwrap();
+        assert_eq!(source.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), source.to_string());
+    }
+
+    #[test]
+    fn test_conversions_from_existing_errors() {
+        let from_string: PreprocessorError = String::from("Undefined variable: X").into();
+        assert_eq!(
+            from_string,
+            PreprocessorError::Other("Undefined variable: X".to_string())
+        );
+        assert_eq!(from_string.to_string(), "Undefined variable: X");
+        assert!(from_string.source().is_none());
+
+        let from_io = PreprocessorError::from(io::Error::new(io::ErrorKind::NotFound, "gone"));
+        assert_eq!(
+            from_io,
+            PreprocessorError::Io(io::Error::new(io::ErrorKind::NotFound, "gone"))
+        );
+        assert_ne!(from_io, PreprocessorError::Io(io::Error::other("gone")));
+    }
+}
@@ -16,9 +16,14 @@
 
 #[cfg(test)]
 mod tests {
-    use pli_tokenizer::modules::evaluator::{
-        evaluate_expression, evaluate_operator, parse_and_evaluate, tokenize_expression,
+    use pli_preprocessor::modules::context::{Context, Value};
+    use pli_preprocessor::modules::error::PreprocessorError;
+    use pli_preprocessor::modules::evaluator::{
+        evaluate_condition, evaluate_decimal_operator, evaluate_expression, evaluate_operator,
+        evaluate_tokens, evaluate_value, parse_and_evaluate, parse_and_evaluate_value,
+        parse_expression, parse_expression_with_positions, resolve_variables, tokenize_expression,
     };
+    use pli_preprocessor::modules::tokenizer::tokenize_pli;
 
     #[test]
     fn test_evaluate_expression_simple() {
@@ -69,4 +74,373 @@ mod tests {
     fn test_evaluate_expression_unsupported_operator() {
         assert!(evaluate_expression("3 ^ 5").is_err());
     }
+
+    #[test]
+    fn test_evaluate_condition() {
+        assert_eq!(evaluate_condition("0 = 1"), Ok(false));
+        assert_eq!(evaluate_condition("2 * 3 = 6"), Ok(true));
+        assert_eq!(evaluate_condition("1 ^= 1"), Ok(false));
+        assert_eq!(evaluate_condition("4 <= 3"), Ok(false));
+        assert_eq!(evaluate_condition("5"), Ok(true));
+        assert_eq!(evaluate_condition("5 - 5"), Ok(false));
+        assert!(evaluate_condition("DEBUG = 1").is_err());
+        assert!(evaluate_condition("= 1").is_err());
+    }
+
+    #[test]
+    fn test_string_and_number_comparisons() {
+        assert_eq!(evaluate_value("'10' = 10"), Ok(Value::Boolean(true)));
+        assert_eq!(evaluate_value("'ABC' < 'ABD'"), Ok(Value::Boolean(true)));
+        assert_eq!(
+            evaluate_value("'ABC' > 1"),
+            Err(PreprocessorError::Evaluator(
+                "Type mismatch: cannot compare 'ABC' with 1".to_string()
+            ))
+        );
+        assert_eq!(
+            evaluate_value("2.5 = 'X'"),
+            Err(PreprocessorError::Evaluator(
+                "Type mismatch: cannot compare 2.5 with 'X'".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_decimal_comparisons() {
+        assert_eq!(evaluate_condition("3.5 > 3.4"), Ok(true));
+        assert_eq!(evaluate_condition("3.5 < 3.4"), Ok(false));
+        assert_eq!(evaluate_condition("2.0 = 2"), Ok(true));
+        assert_eq!(evaluate_condition("2 ^= 2.0"), Ok(false));
+        assert_eq!(evaluate_condition(".5 = 0.5"), Ok(true));
+        // Decimal rounding stays within the comparison tolerance...
+        assert_eq!(evaluate_condition("0.1 + 0.2 = 0.3"), Ok(true));
+        assert_eq!(evaluate_condition("0.1 + 0.2 <= 0.3"), Ok(true));
+        // ...but a real difference does not.
+        assert_eq!(evaluate_condition("0.3001 = 0.3"), Ok(false));
+        assert_eq!(evaluate_value("1.5 >= 2"), Ok(Value::Boolean(false)));
+    }
+
+    #[test]
+    fn test_decimal_arithmetic() {
+        assert_eq!(evaluate_value("1.5 * 2"), Ok(Value::Decimal(3.0)));
+        assert_eq!(evaluate_value("7 / 2.0"), Ok(Value::Decimal(3.5)));
+        assert_eq!(evaluate_value("7 / 2"), Ok(Value::Int(3)));
+        assert_eq!(
+            evaluate_value("'R' || 2.50"),
+            Ok(Value::Str("R2.5".to_string()))
+        );
+        assert_eq!(Value::Decimal(3.0).to_string(), "3.0");
+        assert_eq!(evaluate_expression("1.5 * 2"), Ok(3));
+        assert_eq!(
+            evaluate_expression("1.5 + 1"),
+            Err(PreprocessorError::Evaluator(
+                "Expected an integer, found 2.5".to_string()
+            ))
+        );
+        assert_eq!(evaluate_decimal_operator(-1.5, 1.0, "MOD"), Ok(0.5));
+        assert_eq!(
+            evaluate_decimal_operator(1.0, 0.0, "/"),
+            Err("Division by zero".to_string())
+        );
+        assert!(evaluate_value("1.2.3").is_err());
+        assert!(evaluate_value("1e5").is_err());
+    }
+
+    #[test]
+    fn test_resolve_decimal_variables() {
+        let mut context = Context::new();
+        context.set("RATE", Value::Decimal(3.5));
+
+        let tokens = tokenize_pli("RATE > 3.4");
+        let terms = resolve_variables(&tokens.iter().collect::<Vec<_>>(), &context).unwrap();
+        assert_eq!(terms, vec!["3.5", ">", "3.4"]);
+        assert_eq!(parse_and_evaluate_value(&terms), Ok(Value::Boolean(true)));
+        assert_eq!(
+            context.get_int("RATE"),
+            Err("Variable RATE is not an integer: 3.5".to_string())
+        );
+    }
+
+    #[test]
+    fn test_evaluate_beyond_i32_range() {
+        assert_eq!(evaluate_expression("100000 * 100000"), Ok(10_000_000_000));
+        assert_eq!(evaluate_expression("2147483647 + 1"), Ok(2_147_483_648));
+    }
+
+    #[test]
+    fn test_evaluate_overflow_is_an_error() {
+        let overflow = Err(PreprocessorError::Evaluator(
+            "Arithmetic overflow".to_string(),
+        ));
+        assert_eq!(evaluate_expression("9223372036854775807 + 1"), overflow);
+        assert_eq!(evaluate_expression("4294967296 * 4294967296"), overflow);
+        assert_eq!(evaluate_expression("99999999999999999999"), overflow);
+        assert_eq!(evaluate_expression("1 + -99999999999999999999"), overflow);
+        let overflow = Err("Arithmetic overflow".to_string());
+        assert_eq!(evaluate_operator(i64::MIN, -1, "/"), overflow);
+        assert_eq!(evaluate_operator(i64::MIN, 1, "-"), overflow);
+    }
+
+    #[test]
+    fn test_mod_operator() {
+        assert_eq!(evaluate_expression("10 MOD 3"), Ok(1));
+        assert_eq!(evaluate_condition("10 MOD 3 = 1"), Ok(true));
+        assert_eq!(evaluate_expression("2 + 10 mod 4 * 3"), Ok(8));
+        assert_eq!(evaluate_operator(-7, 3, "MOD"), Ok(2));
+        assert_eq!(evaluate_operator(7, -3, "MOD"), Ok(-2));
+        assert_eq!(
+            evaluate_expression("10 MOD 0"),
+            Err(PreprocessorError::Evaluator("Division by zero".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_concatenate_strings() {
+        assert_eq!(
+            evaluate_value("'AB' || 'CD'"),
+            Ok(Value::Str("ABCD".to_string()))
+        );
+        assert_eq!(
+            evaluate_value("'A B'||'C'"),
+            Ok(Value::Str("A BC".to_string()))
+        );
+        assert_eq!(
+            evaluate_value("'IT''S' || ''"),
+            Ok(Value::Str("IT'S".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_concatenate_string_and_number() {
+        assert_eq!(evaluate_value("'V' || 2"), Ok(Value::Str("V2".to_string())));
+        // Arithmetic binds tighter than concatenation.
+        assert_eq!(
+            evaluate_value("1 + 2 || 'X'"),
+            Ok(Value::Str("3X".to_string()))
+        );
+        assert_eq!(evaluate_value("7 * 6"), Ok(Value::Int(42)));
+        assert!(evaluate_value("'A' + 1").is_err());
+    }
+
+    #[test]
+    fn test_concatenate_chained() {
+        assert_eq!(
+            evaluate_value("'A' || 'B' || 'C' || 1"),
+            Ok(Value::Str("ABC1".to_string()))
+        );
+        assert!(evaluate_value("'A' ||").is_err());
+        assert!(evaluate_value("'unterminated || 'B'").is_err());
+    }
+
+    #[test]
+    fn test_resolve_variables_from_context() {
+        let mut context = Context::new();
+        context.set("N", Value::Int(2));
+        context.set("NAME", Value::Str("O'K".to_string()));
+
+        let tokens = tokenize_pli("N * 3 || NAME");
+        let terms = resolve_variables(&tokens.iter().collect::<Vec<_>>(), &context).unwrap();
+        assert_eq!(terms, vec!["2", "*", "3", "||", "'O''K'"]);
+
+        let tokens = tokenize_pli("MISSING + 1");
+        assert_eq!(
+            resolve_variables(&tokens.iter().collect::<Vec<_>>(), &context),
+            Err("Undefined variable: MISSING".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_expression_with_positions() {
+        let tokens = tokenize_expression("A + B * C").unwrap();
+        let rpn = parse_expression_with_positions(&tokens).unwrap();
+        assert_eq!(
+            rpn,
+            vec![
+                ("A".to_string(), 0),
+                ("B".to_string(), 2),
+                ("C".to_string(), 4),
+                ("*".to_string(), 3),
+                ("+".to_string(), 1),
+            ]
+        );
+        for (term, index) in &rpn {
+            assert_eq!(&tokens[*index], term);
+        }
+        assert_eq!(
+            parse_expression(&tokens),
+            Ok(vec!["A", "B", "C", "*", "+"]
+                .into_iter()
+                .map(String::from)
+                .collect())
+        );
+        assert!(parse_expression(&tokenize_expression("A +").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_hex_and_binary_constants() {
+        assert_eq!(evaluate_condition("'FF'X + 1 = 256"), Ok(true));
+        assert_eq!(evaluate_expression("'ff'x"), Ok(255));
+        assert_eq!(evaluate_expression("'1010'B * 2"), Ok(20));
+        assert_eq!(
+            evaluate_expression("'FG'X + 1"),
+            Err(PreprocessorError::Evaluator(
+                "Invalid hexadecimal constant: 'FG'X".to_string()
+            ))
+        );
+        assert!(evaluate_expression("'102'B").is_err());
+        assert_eq!(
+            evaluate_value("'AB' || 'CD'"),
+            Ok(Value::Str("ABCD".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_logical_negation() {
+        assert_eq!(evaluate_value("NOT 1 = 0"), Ok(Value::Boolean(true)));
+        assert_eq!(evaluate_value("NOT NOT 1"), Ok(Value::Boolean(true)));
+        assert_eq!(evaluate_value("^ ^ 0"), Ok(Value::Boolean(false)));
+        assert_eq!(
+            evaluate_value("NOT 0 AND 1 = 2 OR 1"),
+            Ok(Value::Boolean(true))
+        );
+
+        let mut context = Context::new();
+        context.set("A", Value::Int(1));
+        context.set("B", Value::Int(2));
+        let tokens = tokenize_pli("^(A = B)");
+        let terms = resolve_variables(&tokens.iter().collect::<Vec<_>>(), &context).unwrap();
+        assert_eq!(parse_and_evaluate_value(&terms), Ok(Value::Boolean(true)));
+        assert_eq!(evaluate_value("^(1 = 1)"), Ok(Value::Boolean(false)));
+
+        assert!(evaluate_value("1 NOT 2").is_err());
+        assert!(evaluate_value("(1 = 1").is_err());
+    }
+
+    #[test]
+    fn test_short_circuit_evaluation() {
+        let mut context = Context::new();
+        context.set("N", Value::Int(0));
+        let evaluate = |source: &str| {
+            let tokens = tokenize_pli(source);
+            evaluate_tokens(&tokens.iter().collect::<Vec<_>>(), &context)
+        };
+
+        // The right operand is never needed, so its errors are ignored.
+        assert_eq!(
+            evaluate("0 = 1 AND UNDEFINED > 0"),
+            Ok(Value::Boolean(false))
+        );
+        assert_eq!(evaluate("1 = 1 OR UNDEFINED > 0"), Ok(Value::Boolean(true)));
+        assert_eq!(evaluate("N ^= 0 & 10 / N > 1"), Ok(Value::Boolean(false)));
+        assert_eq!(
+            evaluate("0 = 1 AND UNDEFINED > 0 OR N = 0"),
+            Ok(Value::Boolean(true))
+        );
+
+        // Otherwise both operands are evaluated and errors surface.
+        let undefined = Err("Undefined variable: UNDEFINED".to_string());
+        assert_eq!(evaluate("1 = 1 AND UNDEFINED > 0"), undefined);
+        assert_eq!(evaluate("0 = 1 OR UNDEFINED > 0"), undefined);
+        assert_eq!(evaluate("UNDEFINED > 0 AND 0 = 1"), undefined);
+        assert_eq!(
+            evaluate("N ^= 1 & 10 / N > 1"),
+            Err("Division by zero".to_string())
+        );
+        assert_eq!(evaluate("1 = 1 AND N = 0"), Ok(Value::Boolean(true)));
+    }
+
+    #[test]
+    fn test_symbolic_logical_operators() {
+        let mut context = Context::new();
+        for (name, value) in [("A", 1), ("B", 1), ("C", 2), ("D", 3)] {
+            context.set(name, Value::Int(value));
+        }
+        let evaluate = |source: &str| {
+            let tokens = tokenize_pli(source);
+            evaluate_tokens(&tokens.iter().collect::<Vec<_>>(), &context)
+        };
+
+        // `&` and `|` evaluate exactly like `AND` and `OR`.
+        for (symbolic, keyword) in [
+            ("A & B", "A AND B"),
+            ("A = B & C = D", "A = B AND C = D"),
+            ("A = C | C = D", "A = C OR C = D"),
+            ("A = B | C = D & 0", "A = B OR C = D AND 0"),
+        ] {
+            assert_eq!(evaluate(symbolic), evaluate(keyword), "for {}", symbolic);
+        }
+        assert_eq!(evaluate("A = B & C = D"), Ok(Value::Boolean(false)));
+        assert_eq!(evaluate("A = B | C = D"), Ok(Value::Boolean(true)));
+
+        // `||` concatenates, while `&&` is not an operator.
+        assert_eq!(evaluate("A || B"), Ok(Value::Str("11".to_string())));
+        assert_eq!(
+            evaluate("A && B"),
+            Err("Operator '&' without operand".to_string())
+        );
+    }
+
+    #[test]
+    fn test_keyword_operators_as_operands() {
+        let mut context = Context::new();
+        context.set("A", Value::Int(1));
+        context.set("B", Value::Int(0));
+        context.set("AND", Value::Int(5));
+        context.set("MOD", Value::Int(7));
+        context.set("NOT", Value::Int(2));
+        let evaluate = |source: &str| {
+            let tokens = tokenize_pli(source);
+            evaluate_tokens(&tokens.iter().collect::<Vec<_>>(), &context)
+        };
+
+        // In operator position the keywords are operators.
+        assert_eq!(evaluate("A AND B"), Ok(Value::Boolean(false)));
+        assert_eq!(evaluate("A OR B"), Ok(Value::Boolean(true)));
+        assert_eq!(evaluate("NOT B"), Ok(Value::Boolean(true)));
+
+        // In operand position they name variables.
+        assert_eq!(evaluate("AND = 5"), Ok(Value::Boolean(true)));
+        assert_eq!(evaluate("AND AND A"), Ok(Value::Boolean(true)));
+        assert_eq!(evaluate("MOD MOD 4"), Ok(Value::Int(3)));
+        assert_eq!(
+            evaluate("A + (OR)"),
+            Err("Undefined variable: OR".to_string())
+        );
+
+        // NOT negates what follows it, and is a variable when nothing does.
+        assert_eq!(evaluate("NOT AND = 5"), Ok(Value::Boolean(false)));
+        assert_eq!(evaluate("NOT * 3"), Ok(Value::Int(6)));
+        assert_eq!(evaluate("A + NOT"), Ok(Value::Int(3)));
+
+        let tokens = tokenize_expression("AND AND B").unwrap();
+        let rpn = parse_expression_with_positions(&tokens).unwrap();
+        let positions: Vec<usize> = rpn.iter().map(|(_, index)| *index).collect();
+        assert_eq!(positions, vec![0, 2, 1]);
+    }
+
+    #[test]
+    fn test_prefix_signs_in_tokens() {
+        let mut context = Context::new();
+        context.set("X", Value::Int(3));
+        let evaluate = |source: &str| {
+            let tokens = tokenize_pli(source);
+            evaluate_tokens(&tokens.iter().collect::<Vec<_>>(), &context)
+        };
+
+        assert_eq!(evaluate("-1"), Ok(Value::Int(-1)));
+        assert_eq!(evaluate("+1"), Ok(Value::Int(1)));
+        assert_eq!(evaluate("X > -1"), Ok(Value::Boolean(true)));
+        assert_eq!(evaluate("X - -X"), Ok(Value::Int(6)));
+        assert_eq!(evaluate("-2 * X"), Ok(Value::Int(-6)));
+        assert_eq!(evaluate("-(X + 1)"), Ok(Value::Int(-4)));
+        assert_eq!(evaluate("-1.5"), Ok(Value::Decimal(-1.5)));
+        assert_eq!(
+            evaluate("-'A'"),
+            Err("Expected a number, found 'A'".to_string())
+        );
+        assert_eq!(
+            evaluate("X -"),
+            Err("Expression ends with operator".to_string())
+        );
+    }
 }
@@ -0,0 +1,79 @@
+////////////////////////////////////////////////////////////////////////////////
+// TEST MODULE: Formatter Tests
+// ----------------------------------------------------------------------------
+// DESCRIPTION:
+// This test module validates the `formatter` module: whitespace and directive
+// normalization, block indentation, and the preservation of string literals
+// and comments.
+//
+// AUTHOR: FirstLink Consulting Services (FLCS)
+// LICENSE: MIT License
+// DATE: 10/16/2026
+// VERSION: 1.0.0
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use pli_preprocessor::modules::formatter::{format_line, format_source};
+
+    #[test]
+    fn test_format_messy_source() {
+        let source = concat!(
+            "   %dcl   N  fixed;\n",
+            "%if N  >  0   %then   %do;\n",
+            "        PUT SKIP LIST('A   B',  N);   /*  keep   this  */\n",
+            "   %if   DEBUG %then;\n",
+            "x  =  B(1)  +  2;\n",
+            "        %else;\n",
+            "     x = 0;\n",
+            "  %endif;\n",
+            "\n",
+            "          %end;\n",
+            "/* a comment\n",
+            "      spanning   lines */\n",
+            "  %comment   left    as  is;\n",
+            "%If DEBUG %Then %Goto   DONE;\n",
+        );
+        let expected = concat!(
+            "%DCL N fixed;\n",
+            "%IF N > 0 %THEN %DO;\n",
+            "  PUT SKIP LIST('A   B', N); /*  keep   this  */\n",
+            "  %IF DEBUG %THEN;\n",
+            "    x = B(1) + 2;\n",
+            "  %ELSE;\n",
+            "    x = 0;\n",
+            "  %ENDIF;\n",
+            "\n",
+            "%END;\n",
+            "/* a comment\n",
+            "      spanning   lines */\n",
+            "%COMMENT   left    as  is;\n",
+            "%IF DEBUG %THEN %GOTO DONE;\n",
+        );
+        assert_eq!(format_source(source), expected);
+    }
+
+    #[test]
+    fn test_format_source_is_idempotent() {
+        let source = "%IF A %THEN %DO;\n%DO I = 1 TO 2;\nX;\n%END;\n%END;\n%ELSE %DO;\nY;\n%END;\n";
+        let formatted = format_source(source);
+        assert_eq!(
+            formatted,
+            "%IF A %THEN %DO;\n  %DO I = 1 TO 2;\n    X;\n  %END;\n%END;\n%ELSE %DO;\n  Y;\n%END;\n"
+        );
+        assert_eq!(format_source(&formatted), formatted);
+    }
+
+    #[test]
+    fn test_format_line() {
+        assert_eq!(
+            format_line("  %set  X =  'A  B' ;  ", 1),
+            "  %SET X = 'A  B' ;"
+        );
+        assert_eq!(format_line("   ", 2), "");
+        // Unbalanced `%END` lines stay at the left margin.
+        assert_eq!(format_source("%END;\nA;\n"), "%END;\nA;\n");
+        // A line the tokenizer rejects is kept as written.
+        assert_eq!(format_source("X  = 'open\n"), "X  = 'open\n");
+    }
+}
@@ -14,9 +14,14 @@
 // IMPORTS
 ////////////////////////////////////////////////////////////////////////////////
 
-use pli_tokenizer::modules::include_handler::*;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use pli_preprocessor::modules::error::PreprocessorError;
+use pli_preprocessor::modules::include_handler::*;
+use pli_preprocessor::modules::tokenizer::tokenize_pli;
 use std::fs;
-use std::path::Path;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 
 ////////////////////////////////////////////////////////////////////////////////
 // TESTS
@@ -48,6 +53,98 @@ mod tests {
 
         // Empty directive
         assert_eq!(extract_file_path("%INCLUDE '';"), None);
+
+        // Several members: the first one
+        assert_eq!(
+            extract_file_path("%INCLUDE 'a.pli', 'b.pli';"),
+            Some("a.pli".to_string())
+        );
+    }
+
+    #[test]
+    fn test_extract_file_paths_multiple_members() {
+        assert_eq!(
+            extract_file_paths("%INCLUDE 'a.pli', B,'c d.pli' ; /* x, y */"),
+            vec!["a.pli", "B", "c d.pli"]
+        );
+        assert_eq!(extract_file_paths("%INCLUDE 'a,b.pli';"), vec!["a,b.pli"]);
+        assert_eq!(extract_file_paths("%INCLUDE 'a.pli', , ;"), vec!["a.pli"]);
+        assert!(extract_file_paths("%INCLUDE ;").is_empty());
+        assert!(extract_file_paths("INVALID").is_empty());
+        assert_eq!(
+            include_targets("  %include A, B;"),
+            vec!["A".to_string(), "B".to_string()]
+        );
+        assert_eq!(xinclude_targets("%XINCLUDE A, B;"), vec!["A", "B"]);
+    }
+
+    #[test]
+    fn test_multi_member_include_concatenates_in_order() {
+        let mut resolver = MapIncludeResolver::new();
+        resolver.insert("a.pli", "A = 1;");
+        resolver.insert("b.pli", "B = 2;\n");
+        resolver.insert("c.pli", "C = 3;");
+
+        assert_eq!(
+            process_include_with("%INCLUDE 'a.pli', 'b.pli', 'c.pli';", &resolver),
+            Ok("A = 1;\nB = 2;\nC = 3;".to_string())
+        );
+        let (text, count) = expand_includes_counted(
+            "X = 0;\n%INCLUDE 'a.pli', 'b.pli', 'c.pli';\nY = 4;",
+            &resolver,
+            InlineOptions {
+                markers: true,
+                keep_directives: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            text,
+            concat!(
+                "X = 0;\n",
+                "/* %INCLUDE 'a.pli', 'b.pli', 'c.pli'; */\n",
+                "/* BEGIN INCLUDE a.pli */\nA = 1;\n/* END INCLUDE a.pli */\n",
+                "/* BEGIN INCLUDE b.pli */\nB = 2;\n/* END INCLUDE b.pli */\n",
+                "/* BEGIN INCLUDE c.pli */\nC = 3;\n/* END INCLUDE c.pli */\n",
+                "Y = 4;\n"
+            )
+        );
+        assert_eq!(count, 3);
+        assert_eq!(
+            expand_includes("%INCLUDE 'a.pli', 'absent.pli';", &resolver, false),
+            Err(PreprocessorError::Include {
+                code: "I002",
+                message: "Include not found: absent.pli".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_flatten_multi_member_include_from_files() {
+        let dir = std::env::temp_dir().join("pli_flatten_multi_member");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("main.pli"),
+            "%INCLUDE 'a.pli', 'b.pli', 'c.pli';\n",
+        )
+        .unwrap();
+        fs::write(dir.join("a.pli"), "DCL A FIXED;\n").unwrap();
+        fs::write(dir.join("b.pli"), "DCL B FIXED;\n").unwrap();
+        fs::write(dir.join("c.pli"), "DCL C FIXED;\n").unwrap();
+
+        let root = dir.join("main.pli");
+        assert_eq!(
+            flatten_includes(&root, false).unwrap(),
+            "DCL A FIXED;\nDCL B FIXED;\nDCL C FIXED;\n"
+        );
+        let edges = collect_include_edges(&root).unwrap();
+        let targets: Vec<_> = edges.iter().map(|edge| edge.to.clone()).collect();
+        assert_eq!(
+            targets,
+            vec![dir.join("a.pli"), dir.join("b.pli"), dir.join("c.pli")]
+        );
+        assert!(edges.iter().all(|edge| edge.line == 1));
     }
 
     #[test]
@@ -72,6 +169,36 @@ mod tests {
         fs::remove_file(temp_file).unwrap();
     }
 
+    #[test]
+    fn test_read_gzipped_file() {
+        let dir = std::env::temp_dir().join("pli_read_gzipped");
+        fs::create_dir_all(&dir).unwrap();
+        let source = "%SET N = 1;\n%IF N = 1 %THEN;\n  X = 'IT''S';\n%ENDIF;\n";
+        let plain = dir.join("deck.pli");
+        fs::write(&plain, source).unwrap();
+        let gzipped = dir.join("deck.pli.GZ");
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(source.as_bytes()).unwrap();
+        fs::write(&gzipped, encoder.finish().unwrap()).unwrap();
+
+        let expected = read_file(&plain).unwrap();
+        let content = read_file(&gzipped).unwrap();
+        assert_eq!(content, expected);
+        let tokens = |text: &str| text.lines().map(tokenize_pli).collect::<Vec<_>>();
+        assert_eq!(tokens(&content), tokens(&expected));
+
+        // A `.gz` file that is not gzip data is an I/O error naming the file.
+        let corrupt = dir.join("corrupt.pli.gz");
+        fs::write(&corrupt, source).unwrap();
+        let error = read_file(&corrupt).unwrap_err();
+        assert!(matches!(error, PreprocessorError::Io(_)));
+        assert!(error
+            .to_string()
+            .starts_with(&format!("Failed to decompress file {}", corrupt.display())));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     fn test_process_include() {
         let current_dir = Path::new("/tmp");
@@ -82,4 +209,436 @@ mod tests {
         assert_eq!(content.unwrap(), "Test content");
         fs::remove_file(temp_file).unwrap();
     }
+
+    #[test]
+    fn test_include_target_ignores_case_and_indentation() {
+        assert_eq!(
+            include_target("  %include 'defs.pli';"),
+            Some("defs.pli".to_string())
+        );
+        assert_eq!(include_target("X = 1;"), None);
+        assert_eq!(include_target("%INC"), None);
+    }
+
+    #[test]
+    fn test_collect_include_edges_walks_nested_includes() {
+        let dir = std::env::temp_dir().join("pli_include_graph");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(
+            dir.join("main.pli"),
+            "%INCLUDE 'defs.pli';\nX = 1;\n%INCLUDE 'sub/util.pli';\n",
+        )
+        .unwrap();
+        fs::write(dir.join("defs.pli"), "%include 'types.pli';\n").unwrap();
+        fs::write(dir.join("types.pli"), "DCL T FIXED;\n").unwrap();
+        // Includes resolve relative to the including file, and cycles terminate.
+        fs::write(dir.join("sub/util.pli"), "%INCLUDE '../main.pli';\n").unwrap();
+
+        let root = dir.join("main.pli");
+        let edges = collect_include_edges(&root).unwrap();
+        let pairs: Vec<(PathBuf, PathBuf, usize)> = edges
+            .iter()
+            .map(|e| (e.from.clone(), e.to.clone(), e.line))
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![
+                (root.clone(), dir.join("defs.pli"), 1),
+                (dir.join("defs.pli"), dir.join("types.pli"), 1),
+                (root.clone(), dir.join("sub/util.pli"), 3),
+                (dir.join("sub/util.pli"), root.clone(), 1),
+            ]
+        );
+
+        let tree = format_include_tree(&root, &edges);
+        let lines: Vec<&str> = tree.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert!(lines[1].starts_with("  ") && lines[1].ends_with("defs.pli"));
+        assert!(lines[2].starts_with("    ") && lines[2].ends_with("types.pli"));
+
+        let dot = format_include_dot(&edges);
+        assert!(dot.starts_with("digraph includes {"));
+        assert_eq!(dot.matches(" -> ").count(), 4);
+
+        let json = format_include_json(&edges);
+        assert_eq!(json.matches("\"line\"").count(), 4);
+    }
+
+    #[test]
+    fn test_collect_include_edges_reports_missing_file() {
+        let dir = std::env::temp_dir().join("pli_include_graph_missing");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("main.pli"), "%INCLUDE 'absent.pli';\n").unwrap();
+        let error = collect_include_edges(&dir.join("main.pli")).unwrap_err();
+        assert_eq!(error.code(), Some("I002"));
+    }
+
+    /// Writes `path` and sets its modification time to `seconds` after the epoch.
+    fn touch(path: &Path, seconds: u64) {
+        fs::write(path, "X = 1;\n").unwrap();
+        let time = std::time::UNIX_EPOCH + std::time::Duration::from_secs(seconds);
+        fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(time)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_needs_rebuild_up_to_date() {
+        let dir = std::env::temp_dir().join("pli_needs_rebuild_fresh");
+        fs::create_dir_all(&dir).unwrap();
+        let (input, include, output) = (dir.join("a.pli"), dir.join("b.pli"), dir.join("a.out"));
+        touch(&input, 1_000);
+        touch(&include, 2_000);
+        touch(&output, 3_000);
+
+        assert!(!needs_rebuild(&input, &[include], &output));
+    }
+
+    #[test]
+    fn test_needs_rebuild_stale() {
+        let dir = std::env::temp_dir().join("pli_needs_rebuild_stale");
+        fs::create_dir_all(&dir).unwrap();
+        let (input, include, output) = (dir.join("a.pli"), dir.join("b.pli"), dir.join("a.out"));
+        touch(&input, 1_000);
+        touch(&output, 3_000);
+
+        // An include edited after the output was written.
+        touch(&include, 4_000);
+        assert!(needs_rebuild(
+            &input,
+            std::slice::from_ref(&include),
+            &output
+        ));
+
+        // The input itself edited after the output was written.
+        touch(&include, 2_000);
+        touch(&input, 5_000);
+        assert!(needs_rebuild(&input, &[include], &output));
+    }
+
+    #[test]
+    fn test_needs_rebuild_missing_files() {
+        let dir = std::env::temp_dir().join("pli_needs_rebuild_missing");
+        fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("a.pli");
+        touch(&input, 1_000);
+
+        assert!(needs_rebuild(&input, &[], &dir.join("absent.out")));
+        touch(&dir.join("a.out"), 3_000);
+        assert!(needs_rebuild(
+            &input,
+            &[dir.join("absent.pli")],
+            &dir.join("a.out")
+        ));
+    }
+
+    #[test]
+    fn test_flatten_includes_with_markers() {
+        let dir = std::env::temp_dir().join("pli_flatten_markers");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("main.pli"),
+            "A = 1;\n%INCLUDE 'defs.pli';\nB = 2;\n",
+        )
+        .unwrap();
+        fs::write(dir.join("defs.pli"), "DCL X FIXED;\nDCL Y FIXED;\n").unwrap();
+
+        let root = dir.join("main.pli");
+        assert_eq!(
+            flatten_includes(&root, true).unwrap(),
+            "A = 1;\n/* BEGIN INCLUDE defs.pli */\nDCL X FIXED;\nDCL Y FIXED;\n/* END INCLUDE defs.pli */\nB = 2;\n"
+        );
+        assert_eq!(
+            flatten_includes(&root, false).unwrap(),
+            "A = 1;\nDCL X FIXED;\nDCL Y FIXED;\nB = 2;\n"
+        );
+    }
+
+    #[test]
+    fn test_flatten_includes_rejects_cycles() {
+        let dir = std::env::temp_dir().join("pli_flatten_cycle");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.pli"), "%INCLUDE 'b.pli';\n").unwrap();
+        fs::write(dir.join("b.pli"), "%INCLUDE 'a.pli';\n").unwrap();
+
+        let error = flatten_includes(&dir.join("a.pli"), false).unwrap_err();
+        assert!(matches!(error, PreprocessorError::Include { .. }));
+        let error = error.to_string();
+        assert!(error.starts_with("Recursive include of"), "{}", error);
+    }
+
+    #[test]
+    fn test_read_file_rejects_non_utf8() {
+        let path = std::env::temp_dir().join("pli_binary_input.pli");
+        fs::write(&path, [0x41, 0x0A, 0xC3, 0x28]).unwrap();
+
+        let error = read_file(&path).unwrap_err();
+        assert!(
+            matches!(&error, PreprocessorError::Io(e) if e.kind() == std::io::ErrorKind::InvalidData)
+        );
+        let error = error.to_string();
+        assert!(
+            error.starts_with("Input file is not valid UTF-8 text: "),
+            "{}",
+            error
+        );
+        assert!(
+            error.ends_with("invalid byte 0xC3 at line 2, column 1"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_map_resolver_expands_includes_in_memory() {
+        let mut resolver = MapIncludeResolver::new();
+        resolver.insert("defs.pli", "%INCLUDE 'types.pli';\nDCL X FIXED;");
+        resolver.insert("types.pli", "DCL T CHAR;");
+
+        assert_eq!(
+            process_include_with("%INCLUDE 'types.pli';", &resolver),
+            Ok("DCL T CHAR;".to_string())
+        );
+        assert_eq!(
+            expand_includes("A = 1;\n%include 'defs.pli';", &resolver, true).unwrap(),
+            "A = 1;\n/* BEGIN INCLUDE defs.pli */\n/* BEGIN INCLUDE types.pli */\nDCL T CHAR;\n/* END INCLUDE types.pli */\nDCL X FIXED;\n/* END INCLUDE defs.pli */\n"
+        );
+        assert_eq!(
+            expand_includes("%INCLUDE 'absent.pli';", &resolver, false),
+            Err(PreprocessorError::Include {
+                code: "I002",
+                message: "Include not found: absent.pli".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_xinclude_copies_without_expanding_includes() {
+        let mut resolver = MapIncludeResolver::new();
+        resolver.insert("defs.pli", "%INCLUDE 'types.pli';\nDCL X FIXED;");
+        resolver.insert("types.pli", "DCL T CHAR;");
+
+        assert_eq!(
+            xinclude_target("  %XInclude 'defs.pli';"),
+            Some("defs.pli".to_string())
+        );
+        assert_eq!(xinclude_target("%INCLUDE 'defs.pli';"), None);
+        assert_eq!(include_target("%XINCLUDE 'defs.pli';"), None);
+
+        // The `%XINCLUDE`d file's own `%INCLUDE` is copied as written...
+        assert_eq!(
+            expand_includes("%XINCLUDE 'defs.pli';", &resolver, false).unwrap(),
+            "%INCLUDE 'types.pli';\nDCL X FIXED;\n"
+        );
+        // ...while that of an `%INCLUDE`d one is expanded.
+        assert_eq!(
+            expand_includes("%INCLUDE 'defs.pli';", &resolver, false).unwrap(),
+            "DCL T CHAR;\nDCL X FIXED;\n"
+        );
+        assert_eq!(
+            expand_includes("%XINCLUDE 'defs.pli';", &resolver, true).unwrap(),
+            "/* BEGIN INCLUDE defs.pli */\n%INCLUDE 'types.pli';\nDCL X FIXED;\n/* END INCLUDE defs.pli */\n"
+        );
+        // For the pipeline, the copy is bracketed inside the markers.
+        let options = InlineOptions {
+            markers: true,
+            mark_verbatim: true,
+            ..Default::default()
+        };
+        let (text, _) =
+            expand_includes_counted("%XINCLUDE 'defs.pli';", &resolver, options).unwrap();
+        assert_eq!(
+            text,
+            format!(
+                "/* BEGIN INCLUDE defs.pli */\n{}\n%INCLUDE 'types.pli';\nDCL X FIXED;\n{}\n/* END INCLUDE defs.pli */\n",
+                VERBATIM_BEGIN, VERBATIM_END
+            )
+        );
+    }
+
+    #[test]
+    fn test_flatten_xinclude_does_not_recurse() {
+        let dir = std::env::temp_dir().join("pli_flatten_xinclude");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("main.pli"),
+            "%XINCLUDE 'self.pli';\n%INCLUDE 'defs.pli';\n",
+        )
+        .unwrap();
+        // A verbatim copy may name itself, since it is never expanded.
+        fs::write(dir.join("self.pli"), "%INCLUDE 'self.pli';\n").unwrap();
+        fs::write(dir.join("defs.pli"), "%XINCLUDE 'self.pli';\n").unwrap();
+
+        let root = dir.join("main.pli");
+        assert_eq!(
+            flatten_includes(&root, false).unwrap(),
+            "%INCLUDE 'self.pli';\n%INCLUDE 'self.pli';\n"
+        );
+        let edges = collect_include_edges(&root).unwrap();
+        let targets: Vec<_> = edges.iter().map(|edge| edge.to.clone()).collect();
+        assert_eq!(
+            targets,
+            vec![
+                dir.join("self.pli"),
+                dir.join("defs.pli"),
+                dir.join("self.pli")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_keep_directives_comments_each_include() {
+        let mut resolver = MapIncludeResolver::new();
+        resolver.insert("defs.pli", "%INCLUDE 'types.pli';\nDCL X FIXED;");
+        resolver.insert("types.pli", "DCL T CHAR;");
+        resolver.insert("raw.pli", "%INCLUDE 'types.pli';");
+        let options = InlineOptions {
+            keep_directives: true,
+            ..Default::default()
+        };
+
+        let (text, count) = expand_includes_counted(
+            "A = 1;\n  %include 'defs.pli'; /* shared */\n%XINCLUDE 'raw.pli';",
+            &resolver,
+            options,
+        )
+        .unwrap();
+        assert_eq!(
+            text,
+            "A = 1;\n/* %include 'defs.pli'; /* shared * / */\n/* %INCLUDE 'types.pli'; */\nDCL T CHAR;\nDCL X FIXED;\n/* %XINCLUDE 'raw.pli'; */\n%INCLUDE 'types.pli';\n"
+        );
+        assert_eq!(count, 3);
+
+        // The commented directive comes before the `BEGIN` marker.
+        let options = InlineOptions {
+            markers: true,
+            keep_directives: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            expand_includes_counted("%INCLUDE 'types.pli';", &resolver, options)
+                .unwrap()
+                .0,
+            "/* %INCLUDE 'types.pli'; */\n/* BEGIN INCLUDE types.pli */\nDCL T CHAR;\n/* END INCLUDE types.pli */\n"
+        );
+    }
+
+    #[test]
+    fn test_map_resolver_rejects_cycles() {
+        let mut resolver = MapIncludeResolver::new();
+        resolver.insert("a.pli", "%INCLUDE 'b.pli';");
+        resolver.insert("b.pli", "%INCLUDE 'a.pli';");
+        assert_eq!(
+            expand_includes("%INCLUDE 'a.pli';", &resolver, false),
+            Err(PreprocessorError::Include {
+                code: "I003",
+                message: "Recursive include of a.pli".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_fs_resolver_reads_relative_to_base_dir() {
+        let dir = std::env::temp_dir().join("pli_fs_resolver");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("defs.pli"), "DCL X FIXED;\n").unwrap();
+
+        let resolver = FsIncludeResolver::new(&dir);
+        assert_eq!(
+            resolver.resolve("defs.pli"),
+            Ok("DCL X FIXED;\n".to_string())
+        );
+        assert!(resolver.resolve("absent.pli").is_err());
+    }
+
+    #[test]
+    fn test_fs_resolver_resolves_nested_includes_next_to_includer() {
+        let dir = std::env::temp_dir().join("pli_fs_resolver_nested");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("main.pli"), "%INCLUDE 'sub/a.pli';\n").unwrap();
+        fs::write(dir.join("sub/a.pli"), "%INCLUDE 'b.pli';\n").unwrap();
+        fs::write(dir.join("sub/b.pli"), "B = 1;\n").unwrap();
+        // A `b.pli` next to the source must not shadow the one next to `a.pli`.
+        fs::write(dir.join("b.pli"), "B = 0;\n").unwrap();
+
+        let resolver = FsIncludeResolver::new(&dir);
+        assert_eq!(
+            expand_includes("%INCLUDE 'sub/a.pli';", &resolver, false).unwrap(),
+            "B = 1;\n"
+        );
+        assert_eq!(
+            flatten_includes(&dir.join("main.pli"), false).unwrap(),
+            "B = 1;\n"
+        );
+
+        // A missing include is an `Include` error on both paths.
+        fs::remove_file(dir.join("sub/b.pli")).unwrap();
+        let error = flatten_includes(&dir.join("main.pli"), false).unwrap_err();
+        assert_eq!(error.code(), Some("I002"));
+        let error = expand_includes("%INCLUDE 'sub/a.pli';", &resolver, false).unwrap_err();
+        assert_eq!(error.code(), Some("I002"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_find_include_path_searches_directories_in_order() {
+        let dir = std::env::temp_dir().join("pli_include_search_paths");
+        let (local, first, second) = (dir.join("src"), dir.join("first"), dir.join("second"));
+        for sub in [&local, &first, &second] {
+            fs::create_dir_all(sub).unwrap();
+        }
+        fs::write(local.join("local.pli"), "L = 1;\n").unwrap();
+        fs::write(first.join("local.pli"), "L = 2;\n").unwrap();
+        fs::write(first.join("defs.pli"), "D = 1;\n").unwrap();
+        fs::write(second.join("defs.pli"), "D = 2;\n").unwrap();
+        fs::write(second.join("types.pli"), "T = 2;\n").unwrap();
+        let search = vec![first.clone(), second.clone()];
+
+        // The including file's directory comes first, then the search paths.
+        assert_eq!(
+            find_include_path("local.pli", &local, &search).unwrap(),
+            local.join("local.pli")
+        );
+        assert_eq!(
+            find_include_path("defs.pli", &local, &search).unwrap(),
+            first.join("defs.pli")
+        );
+        assert_eq!(
+            find_include_path("types.pli", &local, &search).unwrap(),
+            second.join("types.pli")
+        );
+        assert_eq!(
+            find_include_path("absent.pli", &local, &search).unwrap(),
+            local.join("absent.pli")
+        );
+
+        let resolver = FsIncludeResolver::new(&local).with_search_paths(search.clone());
+        assert_eq!(resolver.resolve("defs.pli"), Ok("D = 1;\n".to_string()));
+
+        fs::write(
+            local.join("main.pli"),
+            "%INCLUDE 'defs.pli';\n%INCLUDE 'types.pli';\n",
+        )
+        .unwrap();
+        let root = local.join("main.pli");
+        let targets: Vec<PathBuf> = collect_include_edges_with_paths(&root, &search)
+            .unwrap()
+            .into_iter()
+            .map(|edge| edge.to)
+            .collect();
+        assert_eq!(
+            targets,
+            vec![first.join("defs.pli"), second.join("types.pli")]
+        );
+        assert_eq!(
+            flatten_includes_with_paths(&root, false, &search).unwrap(),
+            "D = 1;\nT = 2;\n"
+        );
+        assert!(flatten_includes(&root, false).is_err());
+    }
 }
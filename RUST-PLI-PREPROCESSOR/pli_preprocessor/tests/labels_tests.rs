@@ -0,0 +1,74 @@
+////////////////////////////////////////////////////////////////////////////////
+// TEST MODULE: Labels Tests
+// ----------------------------------------------------------------------------
+// DESCRIPTION:
+// This test module validates the `labels` module: `%LABEL:` prefixes,
+// `%GOTO`/`%GO TO` statements, and the label map of a source.
+//
+// AUTHOR: FirstLink Consulting Services (FLCS)
+// LICENSE: MIT License
+// DATE: 10/16/2026
+// VERSION: 1.0.0
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use pli_preprocessor::modules::diagnostics::{Diagnostic, DiagnosticSink, Severity};
+    use pli_preprocessor::modules::labels::{collect_labels, parse_goto, split_label};
+    use pli_preprocessor::modules::tokenizer::tokenize_pli;
+
+    #[test]
+    fn test_split_label() {
+        let tokens = tokenize_pli("%again: %SET N = N + 1;");
+        let (label, rest) = split_label(&tokens).unwrap();
+        assert_eq!(label, "AGAIN");
+        let rest: Vec<&str> = rest.iter().map(|t| t.value.as_str()).collect();
+        assert_eq!(rest, vec!["%SET", "N", "=", "N", "+", "1", ";"]);
+
+        assert!(split_label(&tokenize_pli("%DONE: ;")).is_some());
+        assert!(split_label(&tokenize_pli("%DOUBLE: PROCEDURE(N);")).is_none());
+        assert!(split_label(&tokenize_pli("LOOP: DO I = 1 TO 2;")).is_none());
+        assert!(split_label(&tokenize_pli("%SET X = 1;")).is_none());
+    }
+
+    #[test]
+    fn test_parse_goto() {
+        assert_eq!(
+            parse_goto(&tokenize_pli("%GOTO done;")),
+            Some(Ok("DONE".to_string()))
+        );
+        assert_eq!(
+            parse_goto(&tokenize_pli("%GO TO DONE;")),
+            Some(Ok("DONE".to_string()))
+        );
+        assert_eq!(
+            parse_goto(&tokenize_pli("%GOTO;")),
+            Some(Err("%GOTO requires a label".to_string()))
+        );
+        assert_eq!(
+            parse_goto(&tokenize_pli("%GOTO A B;")),
+            Some(Err("%GOTO takes a single label".to_string()))
+        );
+        assert_eq!(
+            parse_goto(&tokenize_pli("%GO DONE;")),
+            Some(Err("%GO must be followed by TO".to_string()))
+        );
+        assert_eq!(parse_goto(&tokenize_pli("GOTO DONE;")), None);
+    }
+
+    #[test]
+    fn test_collect_labels_reports_duplicates() {
+        let lines: Vec<String> = ["%START: ;", "A = 1;", "%END1: ;", "%START: ;"]
+            .iter()
+            .map(|line| line.to_string())
+            .collect();
+        let mut sink = DiagnosticSink::new();
+        let labels = collect_labels(&lines, &mut sink);
+        assert_eq!(labels.get("START"), Some(&1));
+        assert_eq!(labels.get("END1"), Some(&3));
+        assert_eq!(
+            sink.into_diagnostics(),
+            vec![Diagnostic::new(Severity::Error, 4, "Duplicate label %START").with_code("D005")]
+        );
+    }
+}
@@ -0,0 +1,74 @@
+////////////////////////////////////////////////////////////////////////////////
+// MODULE NAME: Logger Tests
+// ----------------------------------------------------------------------------
+// DESCRIPTION:
+// Tests for the `Logger` module.
+//
+// FUNCTIONALITY:
+// - Verifies `init_capture_logger` captures records with their levels.
+// - Verifies that a log file that cannot be opened yields an error naming it.
+//
+// AUTHOR: FirstLink Consulting Services (FLCS)
+// LICENSE: MIT License
+// DATE: 10/16/2026
+// VERSION: 1.0.0
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use log::Level;
+    use pli_preprocessor::modules::logger::{init_capture_logger, init_logger, open_log_file};
+
+    #[test]
+    fn test_capture_logger_records_levels() {
+        let records = init_capture_logger();
+        log::info!("capture self-test: info");
+        log::error!("capture self-test: error");
+
+        let captured: Vec<(Level, String)> = records
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, message)| message.starts_with("capture self-test"))
+            .cloned()
+            .collect();
+        assert_eq!(
+            captured,
+            vec![
+                (Level::Info, "capture self-test: info".to_string()),
+                (Level::Error, "capture self-test: error".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_capture_logger_is_installed_once() {
+        let first = init_capture_logger();
+        let second = init_capture_logger();
+        assert!(std::sync::Arc::ptr_eq(&first, &second));
+    }
+
+    #[test]
+    fn test_unwritable_log_file_error_names_path() {
+        let path = "/nonexistent/pli_logs/run.log";
+        let error = open_log_file(path).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+        let message = error.to_string();
+        assert!(
+            message.starts_with("Cannot open log file '/nonexistent/pli_logs/run.log': "),
+            "{}",
+            message
+        );
+        assert!(
+            message.ends_with("Check that its directory exists and is writable"),
+            "{}",
+            message
+        );
+
+        // The file is opened before any logger is installed.
+        match init_logger(path, false, 2) {
+            Err(fern::InitError::Io(e)) => assert_eq!(e.to_string(), message),
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -187,3 +187,90 @@ mod tests {
         );
     }
 }
+
+#[cfg(test)]
+mod macro_table_tests {
+    use pli_preprocessor::modules::macro_expander::{split_macro_body, MacroTable};
+
+    #[test]
+    fn test_macro_table_expand_line() {
+        let mut table = MacroTable::new();
+        table.define("greeting", "'HELLO'");
+
+        assert_eq!(table.expand_line("X = GREETING;"), "X = 'HELLO';");
+        assert_eq!(table.expand_line("X = greeting;"), "X = 'HELLO';");
+        assert_eq!(
+            table.expand_line("X = 'GREETING' || GREETINGS;"),
+            "X = 'GREETING' || GREETINGS;"
+        );
+    }
+
+    #[test]
+    fn test_macro_table_deactivate_and_activate() {
+        let mut table = MacroTable::new();
+        table.define("GREETING", "'HELLO'");
+
+        table.deactivate("GREETING").unwrap();
+        assert!(!table.get("GREETING").unwrap().active);
+        assert_eq!(table.expand_line("X = GREETING;"), "X = GREETING;");
+
+        table.activate("GREETING").unwrap();
+        assert_eq!(table.expand_line("X = GREETING;"), "X = 'HELLO';");
+
+        assert!(table.deactivate("UNKNOWN").is_err());
+    }
+
+    #[test]
+    fn test_split_macro_body() {
+        assert_eq!(
+            split_macro_body(" 'HELLO' %endmacro;"),
+            ("'HELLO'".to_string(), true)
+        );
+        assert_eq!(split_macro_body("A = 1;"), ("A = 1;".to_string(), false));
+    }
+
+    #[test]
+    fn test_macro_table_rescan() {
+        let mut table = MacroTable::new();
+        table.define("OUTER", "INNER + 1");
+        table.define("INNER", "42");
+        assert_eq!(table.expand_line("X = OUTER;"), "X = 42 + 1;");
+
+        table.set_macro_rescan("OUTER", false).unwrap();
+        assert_eq!(table.expand_line("X = OUTER;"), "X = INNER + 1;");
+        table.set_macro_rescan("OUTER", true).unwrap();
+
+        table.set_rescan(false);
+        assert_eq!(table.expand_line("X = OUTER;"), "X = INNER + 1;");
+        assert!(table.set_macro_rescan("UNKNOWN", false).is_err());
+    }
+
+    #[test]
+    fn test_macro_table_rescan_stops_at_recursion() {
+        let mut table = MacroTable::new();
+        table.define("A", "B + A");
+        table.define("B", "A * 2");
+        assert_eq!(table.expand_line("X = A;"), "X = A * 2 + A;");
+    }
+
+    #[test]
+    fn test_macro_table_expand_line_within_budget() {
+        // Each macro doubles the one before it, so `D64` would expand to
+        // 2^64 copies of `X`; the budget stops it after a few kilobytes.
+        let mut table = MacroTable::new();
+        table.define("D0", "X");
+        for level in 1..=64 {
+            let previous = format!("D{}", level - 1);
+            table.define(
+                &format!("D{}", level),
+                &format!("{} {}", previous, previous),
+            );
+        }
+        assert_eq!(table.expand_line_within("Y = D64;", 4096), None);
+
+        let (line, names) = table.expand_line_within("Y = D2;", 4096).unwrap();
+        assert_eq!(line, "Y = X X X X;");
+        assert_eq!(names.len(), 7);
+        assert_eq!(table.expand_line_within("Y = D2;", 11), None);
+    }
+}
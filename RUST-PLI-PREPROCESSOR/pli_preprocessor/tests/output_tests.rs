@@ -10,7 +10,13 @@
 
 #[cfg(test)]
 mod tests {
-    use pli_tokenizer::modules::output::{append_log_message, write_line_to_file};
+    use pli_preprocessor::modules::include_handler::{VERBATIM_BEGIN, VERBATIM_END};
+    use pli_preprocessor::modules::output::{
+        append_log_message, apply_keyword_case, format_listing, reconstruct_line, source_to_html,
+        tokens_to_csv, tokens_to_html, unified_diff, write_line_to_file, KeywordCase,
+        TOKEN_CSV_HEADER,
+    };
+    use pli_preprocessor::modules::tokenizer::{tokenize_pli, tokenize_pli_preserve_ws};
     use std::fs;
     use std::path::Path;
 
@@ -48,4 +54,150 @@ mod tests {
         // Clean up
         fs::remove_file(test_log).unwrap();
     }
+
+    #[test]
+    fn test_reconstruct_line_round_trips() {
+        let inputs = [
+            "",
+            " ",
+            "\t\t",
+            "%IF DEBUG = 1 %THEN;",
+            "   %include  'file.pli' ;   ",
+            "name\t=\t'It''s  here';",
+            "x = '1010'b | 'ff'X;",
+            "A=B*C;%ENDIF",
+            "msg = 'unterminated   ",
+            "  weird $@# chars ! ~ ",
+        ];
+        for input in inputs {
+            assert_eq!(reconstruct_line(&tokenize_pli_preserve_ws(input)), input);
+        }
+    }
+
+    #[test]
+    fn test_reconstruct_line_pads_dropped_whitespace() {
+        assert_eq!(
+            reconstruct_line(&tokenize_pli("%IF   x %THEN;")),
+            "%IF   x %THEN;"
+        );
+    }
+
+    #[test]
+    fn test_apply_keyword_case() {
+        let line = "%if Debug %Then;  put Skip List('Put Here');";
+        assert_eq!(
+            apply_keyword_case(line, KeywordCase::Upper),
+            "%IF Debug %THEN;  PUT SKIP LIST('Put Here');"
+        );
+        assert_eq!(
+            apply_keyword_case(line, KeywordCase::Lower),
+            "%if Debug %then;  put skip list('Put Here');"
+        );
+        assert_eq!(apply_keyword_case(line, KeywordCase::Preserve), line);
+        assert_eq!("Lower".parse(), Ok(KeywordCase::Lower));
+        assert!("title".parse::<KeywordCase>().is_err());
+    }
+
+    #[test]
+    fn test_format_listing_honors_noprint() {
+        let source = "A = 1;\n  %noprint;\nB = 2;\n/* %PRINT; */\nC = 3;\n%PRINT;\nD = 4;\n";
+        assert_eq!(format_listing(source), "    1 A = 1;\n    7 D = 4;\n");
+        // Without the toggle every line is listed, blank ones included.
+        assert_eq!(
+            format_listing("A = 1;\n\nB = 2;\n"),
+            "    1 A = 1;\n    2 \n    3 B = 2;\n"
+        );
+        // A `%NOPRINT` left open hides the rest of the source.
+        assert_eq!(format_listing("%NOPRINT; A = 1;\nB = 2;\n"), "");
+        // The lines bracketing `%XINCLUDE`d content are not listed.
+        let source = format!("{}\nA = 1;\n{}\nB = 2;\n", VERBATIM_BEGIN, VERBATIM_END);
+        assert_eq!(format_listing(&source), "    2 A = 1;\n    4 B = 2;\n");
+    }
+
+    #[test]
+    fn test_tokens_to_csv() {
+        let lines = vec![tokenize_pli("%IF A %THEN;"), tokenize_pli("X = 'A,\"B';")];
+        let csv = tokens_to_csv(&lines);
+        let rows: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(rows[0], TOKEN_CSV_HEADER);
+        assert_eq!(rows[0], "line,column,value,category,directive_category");
+        assert_eq!(rows[1], "1,1,%IF,Directive,ControlFlow");
+        assert_eq!(rows[2], "1,5,A,Identifier,");
+        // A value with a comma or a double quote is quoted, quotes doubled.
+        assert_eq!(rows[7], "2,5,\"'A,\"\"B'\",Literal,");
+        assert_eq!(rows.len(), 9);
+        assert!(csv.ends_with('\n'));
+
+        assert_eq!(tokens_to_csv(&[]), format!("{}\n", TOKEN_CSV_HEADER));
+    }
+
+    #[test]
+    fn test_tokens_to_html() {
+        let html = tokens_to_html(&tokenize_pli_preserve_ws("%if A<B & C %then;"));
+        assert_eq!(
+            html,
+            "<span class=\"tok-directive\">%if</span> \
+             <span class=\"tok-identifier\">A</span>\
+             <span class=\"tok-operator\">&lt;</span>\
+             <span class=\"tok-identifier\">B</span> \
+             <span class=\"tok-operator\">&amp;</span> \
+             <span class=\"tok-identifier\">C</span> \
+             <span class=\"tok-directive\">%then</span>\
+             <span class=\"tok-separator\">;</span>"
+        );
+
+        // Markup inside literals and comments is escaped too.
+        let html = tokens_to_html(&tokenize_pli("X = '<B>'; /* a & b */"));
+        assert!(html.contains("<span class=\"tok-literal\">'&lt;B&gt;'</span>"));
+        assert!(html.contains("<span class=\"tok-comment\">/* a &amp; b */</span>"));
+    }
+
+    #[test]
+    fn test_source_to_html() {
+        let page = source_to_html("%SET X = 1;\n  A = X;\n");
+        assert!(page.starts_with("<!DOCTYPE html>\n"));
+        assert!(page.contains("<pre class=\"pli\"><span class=\"tok-directive\">%SET</span> "));
+        // Indentation is kept, and each source line ends its own line.
+        assert!(page.contains(";</span>\n  <span class=\"tok-identifier\">A</span>"));
+        assert!(page.ends_with("</pre>\n</body>\n</html>\n"));
+    }
+
+    #[test]
+    fn test_unified_diff_hunks() {
+        let old: String = (1..=12).map(|n| format!("A{};\n", n)).collect();
+        let new = old.replace("A2;", "B2;").replace("A11;\n", "");
+
+        // The changes are too far apart to share context, so they get a hunk each.
+        assert_eq!(
+            unified_diff(&old, &new, "out.pli", "out.pli (processed)"),
+            concat!(
+                "--- out.pli\n",
+                "+++ out.pli (processed)\n",
+                "@@ -1,5 +1,5 @@\n",
+                " A1;\n-A2;\n+B2;\n A3;\n A4;\n A5;\n",
+                "@@ -8,5 +8,4 @@\n",
+                " A8;\n A9;\n A10;\n-A11;\n A12;\n"
+            )
+        );
+
+        // Changes whose context overlaps share one hunk.
+        let near = old.replace("A2;", "B2;").replace("A6;", "B6;");
+        let diff = unified_diff(&old, &near, "a", "b");
+        assert_eq!(diff.matches("@@ ").count(), 1);
+        assert!(diff.contains("@@ -1,9 +1,9 @@\n"));
+
+        // A missing file diffs as empty; identical texts give no diff.
+        assert_eq!(
+            unified_diff("", "X = 1;\nY = 2;\n", "a", "b"),
+            "--- a\n+++ b\n@@ -0,0 +1,2 @@\n+X = 1;\n+Y = 2;\n"
+        );
+        assert_eq!(unified_diff(&old, &old, "a", "b"), "");
+
+        // A one-line change in a large file costs no table of every line pair.
+        let big: String = (1..=50_000).map(|n| format!("A{};\n", n)).collect();
+        let diff = unified_diff(&big, &big.replace("A25000;", "B25000;"), "a", "b");
+        assert!(diff.contains("@@ -24997,7 +24997,7 @@\n"), "{}", diff);
+        assert_eq!(diff.lines().count(), 11);
+    }
 }
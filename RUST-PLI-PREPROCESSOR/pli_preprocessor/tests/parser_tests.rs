@@ -14,7 +14,12 @@
 // IMPORTS
 ////////////////////////////////////////////////////////////////////////////////
 
-use pli_tokenizer::modules::parser::{parse_line, parse_source};
+use pli_preprocessor::modules::error::PreprocessorError;
+use pli_preprocessor::modules::parser::{
+    classify_equals, format_ast_json, format_ast_tree, parse_line, parse_program, parse_source,
+    parse_statement, parse_statement_at, split_statements, AstNode, EqualsRole, Position, Span,
+};
+use pli_preprocessor::modules::tokenizer::{tokenize_pli, TokenCategory};
 use std::collections::HashMap;
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -44,3 +49,265 @@ fn test_parse_source() {
         vec!["%INCLUDE", "'example.pli'", ";"]
     );
 }
+
+#[test]
+fn test_parse_line_keeps_underscored_identifiers() {
+    let tokens = parse_line("MULTI_PART_STATEMENT = _LEADING + TRAILING_;");
+    assert_eq!(
+        tokens,
+        vec![
+            "MULTI_PART_STATEMENT",
+            "=",
+            "_LEADING",
+            "+",
+            "TRAILING_",
+            ";"
+        ]
+    );
+}
+
+#[test]
+fn test_parse_line_matches_tokenizer() {
+    let corpus = [
+        "DECLARE X FIXED;",
+        "%IF debug = 1 %THEN;",
+        "   %INCLUDE   'file.pli';   ",
+        "msg = 'It''s here';",
+        "x = '1010'B || 'ff'x;",
+        "A=B*C;%ENDIF",
+        "name = 'unterminated",
+        "my$var @at #hash",
+        "",
+    ];
+    for input in corpus {
+        let tokens = tokenize_pli(input);
+        let parsed = parse_line(input);
+        assert_eq!(parsed.len(), tokens.len(), "input: {:?}", input);
+        for (text, token) in parsed.iter().zip(&tokens) {
+            assert_eq!(
+                text.to_uppercase(),
+                token.value.to_uppercase(),
+                "input: {:?}",
+                input
+            );
+        }
+    }
+}
+
+#[test]
+fn test_parse_line_escaped_quotes() {
+    assert_eq!(
+        parse_line("MSG = 'It''s';"),
+        vec!["MSG", "=", "'It''s'", ";"]
+    );
+}
+
+#[test]
+fn test_classify_equals_assignment_and_comparison() {
+    assert_eq!(
+        classify_equals(&tokenize_pli("%SET X = 1;")),
+        vec![(8, EqualsRole::Assignment)]
+    );
+    assert_eq!(
+        classify_equals(&tokenize_pli("%IF X = 1 %THEN;")),
+        vec![(7, EqualsRole::Comparison)]
+    );
+    assert_eq!(
+        classify_equals(&tokenize_pli("A = B = C;")),
+        vec![(3, EqualsRole::Assignment), (7, EqualsRole::Comparison)]
+    );
+}
+
+#[test]
+fn test_classify_equals_skips_compound_operators() {
+    assert_eq!(classify_equals(&tokenize_pli("%IF X <= 1 %THEN;")), vec![]);
+    assert_eq!(
+        classify_equals(&tokenize_pli("%SET F = A ^= B;")),
+        vec![(8, EqualsRole::Assignment)]
+    );
+}
+
+#[test]
+fn test_split_statements_at_semicolons() {
+    let statements = split_statements(&tokenize_pli("A = 1; B = 2;"));
+    let values: Vec<Vec<&str>> = statements
+        .iter()
+        .map(|s| s.iter().map(|t| t.value.as_str()).collect())
+        .collect();
+    assert_eq!(
+        values,
+        vec![vec!["A", "=", "1", ";"], vec!["B", "=", "2", ";"]]
+    );
+    assert_eq!(statements[1][0].column, 8);
+}
+
+#[test]
+fn test_split_statements_keeps_quoted_semicolon() {
+    let statements = split_statements(&tokenize_pli("MSG = 'A; B';"));
+    assert_eq!(statements.len(), 1);
+    assert_eq!(statements[0][2].value, "'A; B'");
+
+    // Trailing tokens without a `;` still form a statement.
+    assert_eq!(split_statements(&tokenize_pli("X = 1; Y")).len(), 2);
+    assert!(split_statements(&[]).is_empty());
+}
+
+#[test]
+fn test_split_statements_optional_semicolon_after_endif() {
+    let values = |source: &str| -> Vec<Vec<String>> {
+        split_statements(&tokenize_pli(source))
+            .iter()
+            .map(|s| s.iter().map(|t| t.value.clone()).collect())
+            .collect()
+    };
+    assert_eq!(
+        values("%ENDIF B = 2;"),
+        vec![vec!["%ENDIF"], vec!["B", "=", "2", ";"]]
+    );
+    assert_eq!(
+        values("%END X = 1;"),
+        vec![vec!["%END"], vec!["X", "=", "1", ";"]]
+    );
+    assert_eq!(values("%ENDIF;"), vec![vec!["%ENDIF", ";"]]);
+    assert_eq!(values("%ENDIF"), vec![vec!["%ENDIF"]]);
+    // A comment between the directive and its `;` does not split them.
+    assert_eq!(values("%ENDIF /* done */ ;").len(), 1);
+    // Nor does the label of the block `%END` closes.
+    assert_eq!(
+        values("%END LOOP; X = 1;"),
+        vec![vec!["%END", "LOOP", ";"], vec!["X", "=", "1", ";"]]
+    );
+    assert_eq!(values("%END LOOP /* done */ ;").len(), 1);
+}
+
+#[test]
+fn test_parse_statement_label() {
+    let tokens = tokenize_pli("loop: DO I = 1 TO 10;");
+    assert_eq!(tokens[1].category, TokenCategory::Separator);
+
+    let statement = parse_statement(&tokens);
+    assert_eq!(statement.label.as_deref(), Some("LOOP"));
+    let values: Vec<&str> = statement.tokens.iter().map(|t| t.value.as_str()).collect();
+    assert_eq!(values, vec!["DO", "I", "=", "1", "TO", "10", ";"]);
+
+    let unlabeled = parse_statement(&tokenize_pli("DO I = 1 TO 10;"));
+    assert_eq!(unlabeled.label, None);
+    assert_eq!(unlabeled.tokens.len(), 7);
+}
+
+#[test]
+fn test_parse_statement_spans() {
+    let statement = parse_statement_at(&tokenize_pli("  LOOP: X = 10;"), 3);
+    assert_eq!(
+        statement.span,
+        Span::new(Position::new(3, 3), Position::new(3, 16))
+    );
+    assert_eq!(statement.token_spans.len(), statement.tokens.len());
+    assert_eq!(
+        statement.token_spans[2],
+        Span::new(Position::new(3, 13), Position::new(3, 15))
+    );
+}
+
+#[test]
+fn test_parse_program_do_block_span() {
+    let source = "A = 0;\nLOOP: DO I = 1\n    TO 10;\n  A = A + I;\n  DO;\n  END;\nEND LOOP;\n";
+    let nodes = parse_program(source).unwrap();
+    assert_eq!(nodes.len(), 2);
+
+    let block = match &nodes[1] {
+        AstNode::Block(block) => block,
+        other => panic!("expected a block, got {:?}", other),
+    };
+    assert_eq!(block.header.label.as_deref(), Some("LOOP"));
+    assert_eq!(
+        block.span,
+        Span::new(Position::new(2, 1), Position::new(7, 10))
+    );
+    assert_eq!(block.header.span.end, Position::new(3, 11));
+    assert_eq!(block.body.len(), 2);
+    assert_eq!(
+        block.body[1].span(),
+        Span::new(Position::new(5, 3), Position::new(6, 7))
+    );
+}
+
+#[test]
+fn test_parse_program_unbalanced_blocks() {
+    assert_eq!(
+        parse_program("END;"),
+        Err(PreprocessorError::Parser {
+            code: "P001",
+            message: "END without an open block at line 1".to_string(),
+        })
+    );
+    assert_eq!(
+        parse_program("X = 1;\nDO;\n"),
+        Err(PreprocessorError::Parser {
+            code: "P002",
+            message: "Unterminated DO block at line 2".to_string(),
+        })
+    );
+}
+
+#[test]
+fn test_format_ast_json_nested_do_if() {
+    let source = "DO I = 1 TO 2;\n  IF I = 1 THEN DO;\n    X = I;\n  END;\n  ELSE Y = I;\nEND;\n";
+    let nodes = parse_program(source).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&format_ast_json(&nodes).unwrap()).unwrap();
+
+    let outer = &json[0];
+    assert_eq!(outer["kind"], "Block");
+    assert_eq!(outer["header"]["tokens"][0]["value"], "DO");
+    assert_eq!(outer["span"]["start"]["line"], 1);
+    assert_eq!(outer["span"]["end"]["line"], 6);
+    assert_eq!(outer["end"]["tokens"][0]["value"], "END");
+
+    let body = outer["body"].as_array().unwrap();
+    assert_eq!(body.len(), 2);
+    assert_eq!(body[0]["kind"], "Block");
+    assert_eq!(body[0]["header"]["tokens"][0]["value"], "IF");
+    assert_eq!(body[0]["body"][0]["kind"], "Statement");
+    assert_eq!(body[0]["body"][0]["tokens"][0]["value"], "X");
+    assert_eq!(body[0]["body"][0]["span"]["start"]["column"], 5);
+    assert_eq!(body[1]["kind"], "Statement");
+    assert_eq!(body[1]["tokens"][0]["value"], "ELSE");
+}
+
+#[test]
+fn test_parse_program_on_unit_begin_block() {
+    let source = "ON ENDFILE(SYSIN) BEGIN;\n  EOF = 1;\nEND;\nON ERROR SNAP BEGIN;\nEND;\nX = 1;\n";
+    let nodes = parse_program(source).unwrap();
+    assert_eq!(nodes.len(), 3);
+    match &nodes[0] {
+        AstNode::Block(block) => {
+            assert_eq!(block.header.tokens[0].value, "ON");
+            assert_eq!(block.body.len(), 1);
+            assert_eq!(block.end.span.start.line, 3);
+        }
+        other => panic!("expected a block, got {:?}", other),
+    }
+    assert!(matches!(&nodes[1], AstNode::Block(_)));
+    assert!(matches!(&nodes[2], AstNode::Statement(_)));
+    // An `ON` statement whose on-unit is a single statement opens nothing.
+    assert_eq!(
+        parse_program("ON ERROR CALL FIX;\nX = 1;\n").unwrap().len(),
+        2
+    );
+}
+
+#[test]
+fn test_format_ast_tree() {
+    let nodes = parse_program("LOOP: DO;\n  X = 1;\nEND;").unwrap();
+    assert_eq!(
+        format_ast_tree(&nodes),
+        "Block 1:1-3:5 LOOP: DO;\n  Statement 2:3-2:9 X = 1;\n  Statement 3:1-3:5 END;\n"
+    );
+    assert_eq!(
+        parse_program("IF A THEN DO;\n"),
+        Err(PreprocessorError::Parser {
+            code: "P002",
+            message: "Unterminated DO block at line 1".to_string(),
+        })
+    );
+}
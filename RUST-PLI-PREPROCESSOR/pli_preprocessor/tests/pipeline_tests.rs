@@ -0,0 +1,1561 @@
+////////////////////////////////////////////////////////////////////////////////
+// MODULE NAME: Pipeline Tests
+// ----------------------------------------------------------------------------
+// DESCRIPTION:
+// Tests for the `pipeline` module.
+//
+// FUNCTIONALITY:
+// - Tests construction of `ProcessOptions` through its builder.
+// - Tests `process_reader` writing through an in-memory `OutputSink`.
+// - Tests diagnostic collection during processing.
+// - Tests macro definition, expansion, and `%ACTIVATE`/`%DEACTIVATE` scopes.
+// - Tests lines holding several `;`-separated statements.
+// - Tests `preprocess_string` on in-memory source.
+// - Tests the `trace` events by capturing the log output.
+//
+// AUTHOR: FirstLink Consulting Services (FLCS)
+// LICENSE: MIT License
+// DATE: 10/16/2026
+// VERSION: 1.0.0
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use pli_preprocessor::modules::context::{Context, UndefinedPolicy, Value, VarType};
+    use pli_preprocessor::modules::diagnostics::{Diagnostic, Severity};
+    use pli_preprocessor::modules::error::PreprocessorError;
+    use pli_preprocessor::modules::include_handler::MapIncludeResolver;
+    use pli_preprocessor::modules::logger::init_capture_logger;
+    use pli_preprocessor::modules::output::{format_listing, CallbackSink, KeywordCase};
+    use pli_preprocessor::modules::pipeline::{
+        format_expansion_report, preprocess_string, preprocess_string_with_resolver,
+        process_reader, process_reader_with_context, process_reader_with_line_hook,
+        process_reader_with_resolver, would_emit, ProcessOptions,
+    };
+    use pli_preprocessor::modules::tokenizer::{DirectiveCategory, Token};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_process_options_builder() {
+        let options = ProcessOptions::builder().verbose(true).strict(true).build();
+        assert_eq!(
+            options,
+            ProcessOptions {
+                verbose: true,
+                dry_run: false,
+                stats: false,
+                strict: true,
+                analyze: false,
+                include_markers: false,
+                keep_include_directives: false,
+                no_rescan: false,
+                keyword_case: KeywordCase::Preserve,
+                warn_indentation: false,
+                dedup_diagnostics: false,
+                trace: false,
+                undefined_vars: None,
+                continuation_char: None,
+                include_paths: Vec::new(),
+                max_if_depth: None,
+                max_output_bytes: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_process_options_clone_and_modify() {
+        let original = ProcessOptions::builder().dry_run(true).build();
+        let mut modified = original.clone();
+        modified.stats = true;
+
+        assert!(!original.stats);
+        assert!(modified.stats);
+        assert!(modified.dry_run);
+    }
+
+    #[test]
+    fn test_process_reader_vec_sink() {
+        let source = Cursor::new("DECLARE X FIXED;\n\n%IF DEBUG = 1 %THEN;\nX = 1;\n");
+        let mut output: Vec<String> = Vec::new();
+
+        let stats = process_reader(source, &mut output, &ProcessOptions::default())
+            .unwrap()
+            .stats;
+
+        // `DEBUG` is undefined, so the block is not taken.
+        assert_eq!(output, vec!["DECLARE X FIXED;"]);
+        assert_eq!(stats.lines_read, 4);
+        assert_eq!(stats.lines_written, 1);
+    }
+
+    #[test]
+    fn test_process_reader_dry_run_and_callback_sink() {
+        let mut seen = Vec::new();
+        let mut sink = CallbackSink(|line: &str| {
+            seen.push(line.to_string());
+            Ok(())
+        });
+
+        let options = ProcessOptions::builder().dry_run(true).build();
+        let stats = process_reader(Cursor::new("A = 1;\n"), &mut sink, &options)
+            .unwrap()
+            .stats;
+        assert_eq!(stats.lines_written, 0);
+
+        process_reader(
+            Cursor::new("A = 1;\n"),
+            &mut sink,
+            &ProcessOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(seen, vec!["A = 1;"]);
+    }
+
+    #[test]
+    fn test_process_reader_collects_diagnostics() {
+        let source = Cursor::new("%IF DEBUG = 1 %THEN;\n%FOO X;\n%ENDIF;\nNAME = 'unterminated;\n");
+        let mut output: Vec<String> = Vec::new();
+
+        let result = process_reader(source, &mut output, &ProcessOptions::default()).unwrap();
+
+        assert_eq!(
+            result.diagnostics,
+            vec![
+                Diagnostic::new(Severity::Error, 1, "Undefined variable: DEBUG").with_code("X001"),
+                Diagnostic::new(Severity::Warning, 2, "Invalid directive: %FOO")
+                    .with_code("V001")
+                    .with_column(1),
+                Diagnostic::new(
+                    Severity::Error,
+                    4,
+                    "Unmatched string literal: 'unterminated;"
+                )
+                .with_code("T001")
+                .with_column(8),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_process_reader_macro_deactivation_scope() {
+        let source = Cursor::new(
+            "%MACRO GREETING;\n'HELLO'\n%ENDMACRO;\nX = GREETING;\n%DEACTIVATE GREETING;\n\
+             Y = GREETING;\n%ACTIVATE GREETING;\nZ = GREETING;\n",
+        );
+        let mut output: Vec<String> = Vec::new();
+
+        let result = process_reader(source, &mut output, &ProcessOptions::default()).unwrap();
+
+        assert_eq!(
+            output,
+            vec!["X = 'HELLO';", "Y = GREETING;", "Z = 'HELLO';"]
+        );
+        assert!(result.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_process_reader_analyze_flags_dead_blocks() {
+        let source = "%IF 0 = 1 %THEN;\nA = 1;\n%ENDIF;\n";
+        let mut output: Vec<String> = Vec::new();
+
+        let result =
+            process_reader(Cursor::new(source), &mut output, &ProcessOptions::default()).unwrap();
+        assert!(result.diagnostics.is_empty());
+
+        let options = ProcessOptions::builder().analyze(true).build();
+        let result = process_reader(Cursor::new(source), &mut output, &options).unwrap();
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic::new(Severity::Note, 1, "block is never active")
+                .with_code("A001")
+                .with_column(1)]
+        );
+    }
+
+    #[test]
+    fn test_process_reader_declare_then_set() {
+        let source = "%DECLARE COUNT FIXED;\n%DCL LABEL CHAR;\n%SET COUNT = 2 * 3;\n\
+                      %SET LABEL = 'DONE';\n%SET COUNT = COUNT + 1;\nA = 1;\n";
+        let mut output: Vec<String> = Vec::new();
+        let mut context = Context::new();
+        let options = ProcessOptions::builder().strict(true).build();
+
+        let result =
+            process_reader_with_context(Cursor::new(source), &mut output, &options, &mut context)
+                .unwrap();
+
+        assert!(result.diagnostics.is_empty());
+        assert_eq!(output, vec!["A = 1;"]);
+        assert_eq!(context.get("COUNT"), Some(&Value::Int(7)));
+        assert_eq!(context.get("LABEL"), Some(&Value::Str("DONE".to_string())));
+        assert_eq!(context.var_type("LABEL"), Some(VarType::Character));
+    }
+
+    #[test]
+    fn test_process_reader_set_concatenation() {
+        let source = "%DCL LABEL CHAR;\n%SET LABEL = 'N' || 1 + 1;\nA = 1;\n";
+        let mut output: Vec<String> = Vec::new();
+        let mut context = Context::new();
+        let options = ProcessOptions::builder().strict(true).build();
+
+        let result =
+            process_reader_with_context(Cursor::new(source), &mut output, &options, &mut context)
+                .unwrap();
+
+        assert!(result.diagnostics.is_empty());
+        assert_eq!(output, vec!["A = 1;"]);
+        assert_eq!(context.get("LABEL"), Some(&Value::Str("N2".to_string())));
+        assert_eq!(context.var_type("LABEL"), Some(VarType::Character));
+    }
+
+    #[test]
+    fn test_process_reader_prefix_minus() {
+        let source = "%DCL X FIXED;\n%SET X = -1;\n%IF X > -2 %THEN;\nA = 1;\n%ENDIF;\n\
+                      %IF X > -1 %THEN;\nB = 2;\n%ENDIF;\n";
+        let mut output: Vec<String> = Vec::new();
+        let mut context = Context::new();
+
+        let result = process_reader_with_context(
+            Cursor::new(source),
+            &mut output,
+            &ProcessOptions::default(),
+            &mut context,
+        )
+        .unwrap();
+
+        assert!(result.diagnostics.is_empty());
+        assert_eq!(output, vec!["A = 1;"]);
+        assert_eq!(context.get("X"), Some(&Value::Int(-1)));
+    }
+
+    #[test]
+    fn test_process_reader_rejects_do_groups() {
+        let source = "%IF 1 = 1 %THEN %DO;\nA = 1;\n%END;\n%DO;\nB = 2;\n%END;\n\
+                      %IF 0 = 1 %THEN;\n%ELSE %DO;\n%ENDIF;\n";
+        let mut output: Vec<String> = Vec::new();
+
+        let result =
+            process_reader(Cursor::new(source), &mut output, &ProcessOptions::default()).unwrap();
+
+        let error = |line: usize, message: &str| {
+            Diagnostic::new(Severity::Error, line, message).with_code("D007")
+        };
+        let end = "%END without %PROCEDURE; %DO groups are not supported";
+        assert_eq!(
+            result.diagnostics,
+            vec![
+                error(1, "%DO groups are not supported"),
+                error(3, end),
+                error(4, "%DO groups are not supported"),
+                error(6, end),
+                error(8, "%DO groups are not supported"),
+            ]
+        );
+        assert_eq!(output, vec!["A = 1;", "B = 2;"]);
+
+        // A `%DO` after a false condition is reported all the same.
+        let mut output: Vec<String> = Vec::new();
+        let result = process_reader(
+            Cursor::new("%IF 0 = 1 %THEN %DO;\n"),
+            &mut output,
+            &ProcessOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            result.diagnostics,
+            vec![error(1, "%DO groups are not supported")]
+        );
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn test_process_reader_set_literal_too_large() {
+        let source = "%DCL X FIXED;\n%SET X = 99999999999999999999;\nA = 1;\n";
+        let mut output: Vec<String> = Vec::new();
+        let mut context = Context::new();
+
+        let result = process_reader_with_context(
+            Cursor::new(source),
+            &mut output,
+            &ProcessOptions::default(),
+            &mut context,
+        )
+        .unwrap();
+
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic::new(Severity::Error, 2, "Arithmetic overflow").with_code("X001")]
+        );
+        assert_eq!(context.get("X"), Some(&Value::Int(0)));
+    }
+
+    #[test]
+    fn test_process_reader_string_compared_with_number() {
+        let source = "%DCL X CHAR;\n%SET X = 'ABC';\n%IF X > 1 %THEN;\nA = 1;\n%ENDIF;\n";
+        let mut output: Vec<String> = Vec::new();
+
+        let result =
+            process_reader(Cursor::new(source), &mut output, &ProcessOptions::default()).unwrap();
+
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic::new(
+                Severity::Error,
+                3,
+                "Type mismatch: cannot compare 'ABC' with 1"
+            )
+            .with_code("X001")]
+        );
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn test_process_reader_extended_identifier_chars() {
+        let source = "%DCL MY$VAR FIXED;\n%SET MY$VAR = 1;\n%SET @N#2 = MY$VAR + 1;\n\
+                      %IF MY$VAR = 1 %THEN;\nA = 1;\n%ENDIF;\n";
+        let mut output: Vec<String> = Vec::new();
+        let mut context = Context::new();
+
+        let result = process_reader_with_context(
+            Cursor::new(source),
+            &mut output,
+            &ProcessOptions::default(),
+            &mut context,
+        )
+        .unwrap();
+
+        assert!(result.diagnostics.is_empty());
+        assert_eq!(output, vec!["A = 1;"]);
+        assert_eq!(context.get("MY$VAR"), Some(&Value::Int(1)));
+        assert_eq!(context.get("@N#2"), Some(&Value::Int(2)));
+    }
+
+    #[test]
+    fn test_process_reader_set_calls_procedure() {
+        let source = "%TWENTY: PROCEDURE;\n%RETURN(10 * 2);\n%END;\n%SET X = TWENTY + 1;\nA = X;\n";
+        let mut output: Vec<String> = Vec::new();
+        let mut context = Context::new();
+        let options = ProcessOptions::default();
+
+        let result =
+            process_reader_with_context(Cursor::new(source), &mut output, &options, &mut context)
+                .unwrap();
+
+        assert!(result.diagnostics.is_empty());
+        assert_eq!(output, vec!["A = X;"]);
+        assert_eq!(context.get("X"), Some(&Value::Int(21)));
+    }
+
+    #[test]
+    fn test_process_reader_set_without_declare() {
+        let source = "%SET FLAG = 1;\n";
+        let mut output: Vec<String> = Vec::new();
+
+        let mut context = Context::new();
+        let strict = ProcessOptions::builder().strict(true).build();
+        let result =
+            process_reader_with_context(Cursor::new(source), &mut output, &strict, &mut context)
+                .unwrap();
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic::new(
+                Severity::Error,
+                1,
+                "%SET target FLAG is not declared"
+            )]
+        );
+        assert_eq!(context.get("FLAG"), None);
+
+        let result = process_reader_with_context(
+            Cursor::new(source),
+            &mut output,
+            &ProcessOptions::default(),
+            &mut context,
+        )
+        .unwrap();
+        assert!(result.diagnostics.is_empty());
+        assert_eq!(context.get("FLAG"), Some(&Value::Int(1)));
+    }
+
+    #[test]
+    fn test_process_reader_multi_statement_lines() {
+        let source = "A = 1; B = 2;\n  %SET N = 3; X = 'a;b'; %SET M = N;\n%SET K = 1; %BOGUS;\n";
+        let mut output: Vec<String> = Vec::new();
+        let mut context = Context::new();
+
+        let result = process_reader_with_context(
+            Cursor::new(source),
+            &mut output,
+            &ProcessOptions::default(),
+            &mut context,
+        )
+        .unwrap();
+        assert_eq!(output, vec!["A = 1; B = 2;", "  X = 'a;b';", "%BOGUS;"]);
+        assert_eq!(context.get("N"), Some(&Value::Int(3)));
+        assert_eq!(context.get("M"), Some(&Value::Int(3)));
+        assert_eq!(context.get("K"), Some(&Value::Int(1)));
+        assert_eq!(
+            result.diagnostics,
+            vec![
+                Diagnostic::new(Severity::Warning, 3, "Invalid directive: %BOGUS")
+                    .with_code("V001")
+                    .with_column(13)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_process_reader_rejects_non_utf8_input() {
+        let source: Vec<u8> = b"A = 1;\nB = '\xFF\xFE';\n".to_vec();
+        let mut output: Vec<String> = Vec::new();
+
+        let error = process_reader(Cursor::new(source), &mut output, &ProcessOptions::default())
+            .unwrap_err();
+        assert!(
+            matches!(&error, PreprocessorError::Io(e) if e.kind() == std::io::ErrorKind::InvalidData)
+        );
+        assert_eq!(
+            error.to_string(),
+            "Input file is not valid UTF-8 text: input has invalid byte 0xFF at line 2, column 6"
+        );
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn test_process_reader_drops_comment_directive_text() {
+        let source = "%COMMENT this isn't code = 1;\nA = 1;\n  %comment spans\n  two lines; B = 2;\n%COMMENTARY = 3;\n";
+        let mut output: Vec<String> = Vec::new();
+
+        let result =
+            process_reader(Cursor::new(source), &mut output, &ProcessOptions::default()).unwrap();
+        assert_eq!(output, vec!["A = 1;", "  B = 2;", "%COMMENTARY = 3;"]);
+        assert_eq!(
+            result.diagnostics,
+            vec![
+                Diagnostic::new(Severity::Warning, 5, "Invalid directive: %COMMENTARY")
+                    .with_code("V001")
+                    .with_column(1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_process_reader_unterminated_macro() {
+        let dir = std::env::temp_dir().join("pli_unterminated_macro");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("open_macro.pli");
+        std::fs::write(
+            &path,
+            "A = 1;\n%MACRO GREET;\n  PUT SKIP LIST('HELLO');\nB = 2;\n",
+        )
+        .unwrap();
+
+        let mut output: Vec<String> = Vec::new();
+        let source = std::fs::File::open(&path).unwrap();
+        let result = process_reader(
+            std::io::BufReader::new(source),
+            &mut output,
+            &ProcessOptions::default(),
+        )
+        .unwrap();
+        // The rest of the file was swallowed by the open definition.
+        assert_eq!(output, vec!["A = 1;"]);
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic::new(
+                Severity::Error,
+                2,
+                "Unterminated %MACRO 'GREET' at end of file"
+            )
+            .with_code("D002")]
+        );
+        assert_eq!(result.diagnostics[0].code, Some("D002"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_process_reader_unterminated_comment_directive() {
+        let mut output: Vec<String> = Vec::new();
+        let result = process_reader(
+            Cursor::new("%COMMENT never closed\nA = 1\n"),
+            &mut output,
+            &ProcessOptions::default(),
+        )
+        .unwrap();
+        assert!(output.is_empty());
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic::new(Severity::Error, 2, "Unterminated %COMMENT").with_code("D001")]
+        );
+    }
+
+    #[test]
+    fn test_preprocess_string_expands_macros_in_memory() {
+        let source =
+            "%MACRO GREET; 'HELLO' %ENDMACRO;\nX = GREET;\n%DEACTIVATE GREET;\nY = GREET;\n";
+        let text =
+            preprocess_string(source, &ProcessOptions::default(), &mut Context::new()).unwrap();
+        assert_eq!(text, "X = 'HELLO';\nY = GREET;\n");
+    }
+
+    #[test]
+    fn test_preprocess_string_updates_context() {
+        let mut context = Context::new();
+        let source = "%DCL N FIXED;\n%SET N = 2 * 3;\nA = 1;\n";
+        let text = preprocess_string(source, &ProcessOptions::default(), &mut context).unwrap();
+        assert_eq!(text, "A = 1;\n");
+        assert_eq!(context.get("N"), Some(&Value::Int(6)));
+    }
+
+    #[test]
+    fn test_preprocess_string_reports_conditional_errors() {
+        let mut context = Context::new();
+        context.set("DEBUG", Value::Int(1));
+        let source = "%IF DEBUG = 1 %THEN;\nA = 1;\n%ENDIF;\n%ENDIF;\n";
+        let diagnostics =
+            preprocess_string(source, &ProcessOptions::default(), &mut context.clone())
+                .unwrap_err();
+        assert_eq!(
+            diagnostics,
+            vec![
+                Diagnostic::new(Severity::Error, 4, "Unmatched %ENDIF directive").with_code("V002")
+            ]
+        );
+
+        let balanced = "%IF DEBUG = 1 %THEN;\nA = 1;\n%ENDIF;\n";
+        assert!(preprocess_string(balanced, &ProcessOptions::default(), &mut context).is_ok());
+    }
+
+    #[test]
+    fn test_preprocess_string_rejects_includes_without_resolver() {
+        let source = "A = 1;\n%INCLUDE 'defs.pli';\n%XINCLUDE 'raw.pli';\n";
+        let diagnostics =
+            preprocess_string(source, &ProcessOptions::default(), &mut Context::new()).unwrap_err();
+        assert_eq!(
+            diagnostics,
+            vec![
+                Diagnostic::new(
+                    Severity::Error,
+                    2,
+                    "%INCLUDE was not expanded: no include resolver"
+                )
+                .with_code("I004"),
+                Diagnostic::new(
+                    Severity::Error,
+                    3,
+                    "%XINCLUDE was not expanded: no include resolver"
+                )
+                .with_code("I004"),
+            ]
+        );
+
+        let mut output: Vec<String> = Vec::new();
+        process_reader(Cursor::new(source), &mut output, &ProcessOptions::default()).unwrap();
+        assert_eq!(output, vec!["A = 1;"]);
+    }
+
+    #[test]
+    fn test_preprocess_string_expands_includes_from_resolver() {
+        let mut resolver = MapIncludeResolver::new();
+        resolver.insert("macros.pli", "%MACRO NAME; 'WIDGET' %ENDMACRO;");
+        resolver.insert("vars.pli", "%DCL N FIXED;\n%SET N = 4;");
+
+        let mut context = Context::new();
+        let source = "%INCLUDE 'macros.pli';\n%INCLUDE 'vars.pli';\nX = NAME;\n";
+        let text = preprocess_string_with_resolver(
+            source,
+            &ProcessOptions::default(),
+            &mut context,
+            &resolver,
+        )
+        .unwrap();
+        assert_eq!(text, "X = 'WIDGET';\n");
+        assert_eq!(context.get("N"), Some(&Value::Int(4)));
+
+        let missing = preprocess_string_with_resolver(
+            "%INCLUDE 'absent.pli';\n",
+            &ProcessOptions::default(),
+            &mut context,
+            &resolver,
+        );
+        assert_eq!(
+            missing,
+            Err(vec![Diagnostic::new(
+                Severity::Error,
+                0,
+                "Include not found: absent.pli"
+            )
+            .with_code("I002")])
+        );
+
+        // Includes are expanded before `%IF` is evaluated, so one in code that
+        // is never active must still resolve.
+        let inactive = preprocess_string_with_resolver(
+            "%IF 0 %THEN;\n%INCLUDE 'absent.pli';\n%ENDIF;\n",
+            &ProcessOptions::default(),
+            &mut context,
+            &resolver,
+        );
+        assert_eq!(inactive.unwrap_err()[0].code, Some("I002"));
+    }
+
+    #[test]
+    fn test_process_reader_with_resolver_and_markers() {
+        let mut resolver = MapIncludeResolver::new();
+        resolver.insert("defs.pli", "DCL X FIXED;");
+
+        let mut output: Vec<String> = Vec::new();
+        let options = ProcessOptions::builder().include_markers(true).build();
+        process_reader_with_resolver(
+            Cursor::new("%INCLUDE 'defs.pli';\nX = 1;\n"),
+            &mut output,
+            &options,
+            &mut Context::new(),
+            &resolver,
+        )
+        .unwrap();
+        assert_eq!(
+            output,
+            vec![
+                "/* BEGIN INCLUDE defs.pli */",
+                "DCL X FIXED;",
+                "/* END INCLUDE defs.pli */",
+                "X = 1;"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_xinclude_content_is_not_preprocessed() {
+        let mut resolver = MapIncludeResolver::new();
+        resolver.insert("raw.pli", "X = NAME;\n\n%SET N = 2;");
+
+        let mut context = Context::new();
+        let source = "%MACRO NAME; 'WIDGET' %ENDMACRO;\n%DCL N FIXED;\n%SET N = 1;\n%XINCLUDE 'raw.pli';\nY = NAME;\n";
+        let mut output: Vec<String> = Vec::new();
+        let result = process_reader_with_resolver(
+            Cursor::new(source),
+            &mut output,
+            &ProcessOptions::default(),
+            &mut context,
+            &resolver,
+        )
+        .unwrap();
+        // The macro name and the `%SET` are copied as written, blank line
+        // included, and the `%SET` never runs.
+        assert_eq!(
+            output,
+            vec!["X = NAME;", "", "%SET N = 2;", "Y = 'WIDGET';"]
+        );
+        assert_eq!(context.get("N"), Some(&Value::Int(1)));
+        assert_eq!(result.stats.lines_read, 7);
+        assert!(result.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_keep_include_directives_option() {
+        let mut resolver = MapIncludeResolver::new();
+        resolver.insert("defs.pli", "DCL X FIXED;\nDCL Y FIXED;");
+
+        let options = ProcessOptions::builder()
+            .keep_include_directives(true)
+            .build();
+        let text = preprocess_string_with_resolver(
+            "%INCLUDE 'defs.pli';\nX = 1;\n",
+            &options,
+            &mut Context::new(),
+            &resolver,
+        );
+        assert_eq!(
+            text,
+            Ok("/* %INCLUDE 'defs.pli'; */\nDCL X FIXED;\nDCL Y FIXED;\nX = 1;\n".to_string())
+        );
+    }
+
+    #[test]
+    fn test_expansion_counters_and_report() {
+        let mut resolver = MapIncludeResolver::new();
+        resolver.insert("pair.pli", "%MACRO PAIR; A = 1;\nB = 2 %ENDMACRO;");
+
+        let mut output: Vec<String> = Vec::new();
+        let result = process_reader_with_resolver(
+            Cursor::new("%INCLUDE 'pair.pli';\nPAIR; PAIR;\n"),
+            &mut output,
+            &ProcessOptions::default(),
+            &mut Context::new(),
+            &resolver,
+        )
+        .unwrap();
+        assert_eq!(output, vec!["A = 1;", "B = 2; A = 1;", "B = 2;"]);
+        assert_eq!(result.stats.macros_expanded, 2);
+        assert_eq!(result.stats.includes_resolved, 1);
+        assert_eq!(
+            format_expansion_report("main.pli", 2, &result.stats),
+            "main.pli\n  Input lines:       2\n  Output lines:      3\n  Macros expanded:   2\n  Includes resolved: 1\n  Expansion ratio:   1.50\n"
+        );
+
+        let empty = format_expansion_report("empty.pli", 0, &Default::default());
+        assert!(empty.ends_with("  Expansion ratio:   -\n"), "{}", empty);
+    }
+
+    #[test]
+    fn test_line_hook_receives_each_line() {
+        let source = "%SET N = 1;\nA = N;\n\n%COMMENT skip;\nB = 2; %SET N = 2;\n";
+        let mut calls: Vec<(usize, Vec<String>, bool)> = Vec::new();
+        let mut record = |line: usize, tokens: &[Token], emitted: bool| {
+            let values = tokens.iter().map(|t| t.value.clone()).collect();
+            calls.push((line, values, emitted));
+        };
+        let mut output: Vec<String> = Vec::new();
+        process_reader_with_line_hook(
+            Cursor::new(source),
+            &mut output,
+            &ProcessOptions::default(),
+            &mut Context::new(),
+            Some(&mut record),
+        )
+        .unwrap();
+
+        let words = |s: &str| s.split(' ').map(String::from).collect::<Vec<_>>();
+        assert_eq!(
+            calls,
+            vec![
+                (1, words("%SET N = 1 ;"), false),
+                (2, words("A = N ;"), true),
+                (4, vec![], false),
+                (5, words("B = 2 ; %SET N = 2 ;"), true),
+            ]
+        );
+        assert_eq!(output, vec!["A = N;", "B = 2;"]);
+    }
+
+    #[test]
+    fn test_macro_rescan_options() {
+        let source = "%MACRO INNER; 42 %ENDMACRO;\n\
+                      %MACRO OUTER; INNER %ENDMACRO;\n\
+                      X = OUTER;\n\
+                      %ACTIVATE OUTER NORESCAN;\n\
+                      Y = OUTER;\n";
+        let mut output: Vec<String> = Vec::new();
+        process_reader(Cursor::new(source), &mut output, &ProcessOptions::default()).unwrap();
+        assert_eq!(output, vec!["X = 42;", "Y = INNER;"]);
+
+        let options = ProcessOptions::builder().no_rescan(true).build();
+        let mut output: Vec<String> = Vec::new();
+        process_reader(
+            Cursor::new(
+                "%MACRO INNER; 42 %ENDMACRO;\n%MACRO OUTER; INNER %ENDMACRO;\nX = OUTER;\n",
+            ),
+            &mut output,
+            &options,
+        )
+        .unwrap();
+        assert_eq!(output, vec!["X = INNER;"]);
+    }
+
+    #[test]
+    fn test_inline_then_statement_follows_condition() {
+        let source = "%SET DEBUG = 1;\n\
+                      %IF DEBUG %THEN A = 1;\n\
+                      %IF DEBUG = 0 %THEN B = 2;\n\
+                      C = 3; %IF ^DEBUG %THEN D = 4;\n";
+        let mut output: Vec<String> = Vec::new();
+        let result =
+            process_reader(Cursor::new(source), &mut output, &ProcessOptions::default()).unwrap();
+        assert_eq!(output, vec!["A = 1;", "C = 3;"]);
+        assert!(result.diagnostics.is_empty(), "{:?}", result.diagnostics);
+
+        let mut output: Vec<String> = Vec::new();
+        let result = process_reader(
+            Cursor::new("%IF MISSING %THEN A = 1;\n"),
+            &mut output,
+            &ProcessOptions::default(),
+        )
+        .unwrap();
+        assert!(output.is_empty());
+        assert_eq!(
+            result.diagnostics,
+            vec![
+                Diagnostic::new(Severity::Error, 1, "Undefined variable: MISSING")
+                    .with_code("X001")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_inline_then_applies_directives() {
+        let source = "%SET A = 1;\n\
+                      %IF A = 1 %THEN %SET B = 2;\n\
+                      %IF A = 1 %THEN %DCL C FIXED;\n\
+                      %IF A = 0 %THEN %SET D = 3;\n\
+                      X = 1;\n";
+        let mut context = Context::new();
+        let mut output: Vec<String> = Vec::new();
+        let result = process_reader_with_context(
+            Cursor::new(source),
+            &mut output,
+            &ProcessOptions::default(),
+            &mut context,
+        )
+        .unwrap();
+
+        // The directives are applied, not written as text.
+        assert_eq!(output, vec!["X = 1;"]);
+        assert!(result.diagnostics.is_empty(), "{:?}", result.diagnostics);
+        assert_eq!(context.get("B"), Some(&Value::Int(2)));
+        assert_eq!(context.var_type("C"), Some(VarType::Fixed));
+        assert_eq!(context.get("D"), None);
+    }
+
+    #[test]
+    fn test_keyword_case_option() {
+        let source = "dcl MyCount Fixed; Call Report('Mixed Case');\n";
+        let run = |case: KeywordCase| {
+            let options = ProcessOptions::builder().keyword_case(case).build();
+            let mut output: Vec<String> = Vec::new();
+            process_reader(Cursor::new(source), &mut output, &options).unwrap();
+            output
+        };
+        assert_eq!(
+            run(KeywordCase::Upper),
+            vec!["DCL MyCount FIXED; CALL Report('Mixed Case');"]
+        );
+        assert_eq!(
+            run(KeywordCase::Lower),
+            vec!["dcl MyCount fixed; call Report('Mixed Case');"]
+        );
+        assert_eq!(
+            run(KeywordCase::Preserve),
+            vec!["dcl MyCount Fixed; Call Report('Mixed Case');"]
+        );
+    }
+
+    #[test]
+    fn test_dedup_diagnostics_option() {
+        let source = "%FOO;\n%FOO;\n%BAR;\n%FOO;\n";
+        let options = ProcessOptions::builder().dedup_diagnostics(true).build();
+        let mut output: Vec<String> = Vec::new();
+        let result = process_reader(Cursor::new(source), &mut output, &options).unwrap();
+        assert_eq!(result.diagnostics.len(), 2);
+        assert_eq!(result.diagnostics[0].line, 1);
+        assert_eq!(result.diagnostics[0].count, 3);
+        assert_eq!(result.diagnostics[1].count, 1);
+        assert!(result.diagnostics[0].to_string().ends_with("(and 2 more)"));
+
+        let mut output: Vec<String> = Vec::new();
+        let result =
+            process_reader(Cursor::new(source), &mut output, &ProcessOptions::default()).unwrap();
+        assert_eq!(result.diagnostics.len(), 4);
+    }
+
+    #[test]
+    fn test_dedup_diagnostics_logs_repeat_counts() {
+        let records = init_capture_logger();
+        let source = "%DEDUPLOGGED;\n%DEDUPLOGGED;\n%DEDUPLOGGED;\n";
+        let options = ProcessOptions::builder().dedup_diagnostics(true).build();
+        let mut output: Vec<String> = Vec::new();
+        process_reader(Cursor::new(source), &mut output, &options).unwrap();
+
+        // The collapsed diagnostic is logged once, with its repeat count.
+        let logged: Vec<String> = records
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(level, message)| {
+                *level == log::Level::Warn && message.contains("%DEDUPLOGGED")
+            })
+            .map(|(_, message)| message.clone())
+            .collect();
+        assert_eq!(logged.len(), 1, "{:?}", logged);
+        assert!(
+            logged[0].contains("%DEDUPLOGGED (and 2 more)"),
+            "{}",
+            logged[0]
+        );
+    }
+
+    #[test]
+    fn test_trace_events_in_order() {
+        let records = init_capture_logger();
+        let mut resolver = MapIncludeResolver::new();
+        resolver.insert("defs.pli", "%MACRO NAME; 'WIDGET' %ENDMACRO;\n%FOO;");
+        resolver.insert("use.pli", "X = NAME;");
+        let source = "%INCLUDE 'defs.pli';\n%INCLUDE 'use.pli';\n";
+        let options = ProcessOptions::builder().trace(true).build();
+        let mut output: Vec<String> = Vec::new();
+
+        process_reader_with_resolver(
+            Cursor::new(source),
+            &mut output,
+            &options,
+            &mut Context::new(),
+            &resolver,
+        )
+        .unwrap();
+
+        // Only this test turns tracing on, so every TRACE record is its own.
+        let events: Vec<String> = records
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(level, _)| *level == log::Level::Trace)
+            .map(|(_, message)| message.clone())
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                // Lines are numbered in the source with its includes inlined,
+                // where `use.pli` starts on line 3.
+                "line=1 phase=include target=defs.pli",
+                "line=3 phase=include target=use.pli",
+                "line=1 phase=tokenize tokens=[\"%MACRO\", \"NAME\", \";\", \"'WIDGET'\", \"%ENDMACRO\", \";\"]",
+                "line=1 phase=validate diagnostics=0",
+                "line=1 phase=emit emitted=false",
+                "line=2 phase=tokenize tokens=[\"%FOO\", \";\"]",
+                "line=2 phase=validate diagnostics=1",
+                "line=2 phase=macro expanded=[]",
+                "line=2 phase=emit emitted=true",
+                "line=3 phase=tokenize tokens=[\"X\", \"=\", \"NAME\", \";\"]",
+                "line=3 phase=validate diagnostics=0",
+                "line=3 phase=macro expanded=[\"NAME\"]",
+                "line=3 phase=emit emitted=true",
+            ]
+        );
+        assert_eq!(output, vec!["%FOO;", "X = 'WIDGET';"]);
+    }
+
+    #[test]
+    fn test_undefined_vars_policy() {
+        let source = "%IF UNDEF = 0 %THEN A = 1;\n";
+        let run = |policy: UndefinedPolicy| {
+            let options = ProcessOptions::builder()
+                .undefined_vars(Some(policy))
+                .build();
+            let mut output: Vec<String> = Vec::new();
+            let result = process_reader(Cursor::new(source), &mut output, &options).unwrap();
+            (output, result.diagnostics)
+        };
+
+        assert_eq!(
+            run(UndefinedPolicy::Zero),
+            (vec!["A = 1;".to_string()], vec![])
+        );
+        assert_eq!(
+            run(UndefinedPolicy::Empty),
+            (
+                vec![],
+                vec![Diagnostic::new(
+                    Severity::Error,
+                    1,
+                    "Type mismatch: cannot compare '' with 0"
+                )
+                .with_code("X001")]
+            )
+        );
+        assert_eq!(
+            run(UndefinedPolicy::Error),
+            (
+                vec![],
+                vec![
+                    Diagnostic::new(Severity::Error, 1, "Undefined variable: UNDEF")
+                        .with_code("X001")
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn test_undefined_vars_keeps_context_policy() {
+        let source = "%IF UNDEF = 0 %THEN A = 1;\n";
+        let run = |policy: Option<UndefinedPolicy>| {
+            let options = ProcessOptions::builder().undefined_vars(policy).build();
+            let mut context = Context::new();
+            context.set_undefined_policy(UndefinedPolicy::Zero);
+            let mut output: Vec<String> = Vec::new();
+            let result = process_reader_with_context(
+                Cursor::new(source),
+                &mut output,
+                &options,
+                &mut context,
+            )
+            .unwrap();
+            (output, result.diagnostics.len(), context.undefined_policy())
+        };
+
+        assert_eq!(
+            run(None),
+            (vec!["A = 1;".to_string()], 0, UndefinedPolicy::Zero)
+        );
+        assert_eq!(
+            run(Some(UndefinedPolicy::Error)),
+            (vec![], 1, UndefinedPolicy::Error)
+        );
+    }
+
+    #[test]
+    fn test_continuation_spans_blank_line() {
+        let source = "%SET MSG = 'HELLO -\n\n    WORLD';\nA = MSG;\n";
+        let options = ProcessOptions::builder()
+            .continuation_char(Some('-'))
+            .build();
+        let mut context = Context::new();
+        let mut output: Vec<String> = Vec::new();
+        let result =
+            process_reader_with_context(Cursor::new(source), &mut output, &options, &mut context)
+                .unwrap();
+        assert!(result.diagnostics.is_empty());
+        assert_eq!(
+            context.get("MSG"),
+            Some(&Value::Str("HELLO WORLD".to_string()))
+        );
+        assert_eq!(output, vec!["A = MSG;"]);
+    }
+
+    #[test]
+    fn test_continuation_char_joins_string_literal() {
+        let source = "MSG = 'HELLO -\n    WORLD';\nX = 1;\n";
+        let options = ProcessOptions::builder()
+            .continuation_char(Some('-'))
+            .build();
+        let mut lines = Vec::new();
+        let mut hook = |line: usize, tokens: &[Token], _: bool| {
+            lines.push((
+                line,
+                tokens.iter().map(|t| t.value.clone()).collect::<Vec<_>>(),
+            ))
+        };
+        let mut output: Vec<String> = Vec::new();
+        process_reader_with_line_hook(
+            Cursor::new(source),
+            &mut output,
+            &options,
+            &mut Context::new(),
+            Some(&mut hook),
+        )
+        .unwrap();
+        assert_eq!(output, vec!["MSG = 'HELLO WORLD';", "X = 1;"]);
+        assert_eq!(lines[0].0, 1);
+        assert_eq!(lines[0].1[2], "'HELLO WORLD'");
+        assert_eq!(lines[1].0, 3);
+
+        // Without the option the literal is left unterminated.
+        let mut output: Vec<String> = Vec::new();
+        let result =
+            process_reader(Cursor::new(source), &mut output, &ProcessOptions::default()).unwrap();
+        assert!(!result.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_goto_skips_forward_to_label() {
+        let source = "A = 1;\n%GOTO DONE;\nB = 2;\n%SET X = 1;\n%DONE: ;\nC = 3;\n";
+        let mut context = Context::new();
+        let mut output: Vec<String> = Vec::new();
+        let result = process_reader_with_context(
+            Cursor::new(source),
+            &mut output,
+            &ProcessOptions::default(),
+            &mut context,
+        )
+        .unwrap();
+        assert_eq!(output, vec!["A = 1;", "C = 3;"]);
+        assert!(result.diagnostics.is_empty());
+        assert_eq!(context.get("X"), None);
+    }
+
+    #[test]
+    fn test_goto_jumps_backward_until_condition_fails() {
+        let source =
+            "%DCL N FIXED;\n%AGAIN: %SET N = N + 1;\n%IF N < 3 %THEN %GO TO AGAIN;\nX = 1;\n";
+        let mut context = Context::new();
+        let mut output: Vec<String> = Vec::new();
+        process_reader_with_context(
+            Cursor::new(source),
+            &mut output,
+            &ProcessOptions::default(),
+            &mut context,
+        )
+        .unwrap();
+        assert_eq!(output, vec!["X = 1;"]);
+        assert_eq!(context.get("N"), Some(&Value::Int(3)));
+    }
+
+    #[test]
+    fn test_goto_loops_through_block_if() {
+        let source = concat!(
+            "%DCL N FIXED;\n",
+            "%SET N = 0;\n",
+            "%AGAIN:;\n",
+            "%IF N < 3 %THEN;\n",
+            "%SET N = N + 1;\n",
+            "%GO TO AGAIN;\n",
+            "%ELSE;\n",
+            "DONE = N;\n",
+            "%ENDIF;\n",
+            "AFTER = 1;\n",
+        );
+        let mut context = Context::new();
+        let mut output: Vec<String> = Vec::new();
+        let result = process_reader_with_context(
+            Cursor::new(source),
+            &mut output,
+            &ProcessOptions::default(),
+            &mut context,
+        )
+        .unwrap();
+        assert!(result.diagnostics.is_empty());
+        assert_eq!(output, vec!["DONE = N;", "AFTER = 1;"]);
+        assert_eq!(context.get("N"), Some(&Value::Int(3)));
+    }
+
+    #[test]
+    fn test_goto_revisited_lines_report_once() {
+        let source =
+            "%DCL N FIXED;\n%AGAIN: %SET N = N + 1;\n%FOO;\nY = N\n%IF N < 3 %THEN %GO TO AGAIN;\n";
+        let mut context = Context::new();
+        let mut output: Vec<String> = Vec::new();
+        let result = process_reader_with_context(
+            Cursor::new(source),
+            &mut output,
+            &ProcessOptions::default(),
+            &mut context,
+        )
+        .unwrap();
+        // The loop body runs three times, but its diagnostics and directive
+        // counts are those of a single pass.
+        assert_eq!(output, ["%FOO;", "Y = N"].repeat(3));
+        assert_eq!(context.get("N"), Some(&Value::Int(3)));
+        let lines: Vec<usize> = result.diagnostics.iter().map(|d| d.line).collect();
+        assert_eq!(lines, vec![3, 4], "{:?}", result.diagnostics);
+        // `%IF`, `%THEN`, and `%GO` on the last line.
+        assert_eq!(
+            result.stats.directive_counts[&DirectiveCategory::ControlFlow],
+            3
+        );
+    }
+
+    #[test]
+    fn test_goto_undefined_label_and_jump_limit() {
+        let mut output: Vec<String> = Vec::new();
+        let result = process_reader(
+            Cursor::new("%GOTO NOWHERE;\nA = 1;\n"),
+            &mut output,
+            &ProcessOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(output, vec!["A = 1;"]);
+        assert_eq!(
+            result.diagnostics,
+            vec![
+                Diagnostic::new(Severity::Error, 1, "Undefined label: %NOWHERE").with_code("D004")
+            ]
+        );
+
+        let mut output: Vec<String> = Vec::new();
+        let result = process_reader(
+            Cursor::new("%LOOP: ;\n%GOTO LOOP;\nA = 1;\n"),
+            &mut output,
+            &ProcessOptions::default(),
+        )
+        .unwrap();
+        assert!(output.is_empty());
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic::new(
+                Severity::Error,
+                2,
+                "%GOTO LOOP exceeded the limit of 10000 jumps"
+            )
+            .with_code("D006")]
+        );
+    }
+
+    #[test]
+    fn test_if_nesting_depth_limit() {
+        let source = format!(
+            "{}A = 1;\n{}",
+            "%IF 1 = 1 %THEN;\n".repeat(300),
+            "%ENDIF;\n".repeat(300)
+        );
+        let mut output: Vec<String> = Vec::new();
+        let result = process_reader(
+            Cursor::new(source.clone()),
+            &mut output,
+            &ProcessOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic::new(
+                Severity::Error,
+                257,
+                "%IF blocks nested deeper than the limit of 256"
+            )
+            .with_code("V004")]
+        );
+        // Code inside blocks past the limit is never written.
+        assert!(output.is_empty());
+
+        let options = ProcessOptions::builder().max_if_depth(Some(300)).build();
+        let mut output: Vec<String> = Vec::new();
+        let result = process_reader(Cursor::new(source), &mut output, &options).unwrap();
+        assert!(result.diagnostics.is_empty());
+        assert_eq!(output, vec!["A = 1;"]);
+    }
+
+    #[test]
+    fn test_blocks_past_depth_limit_are_not_opened() {
+        let source = "%IF 1 = 1 %THEN;\n%IF 1 = 1 %THEN;\nA = 1;\n%ELSE;\nB = 2;\n%ENDIF;\n\
+                      C = 3;\n%ENDIF;\nD = 4;\n";
+        let options = ProcessOptions::builder().max_if_depth(Some(1)).build();
+        let mut output: Vec<String> = Vec::new();
+
+        let result = process_reader(Cursor::new(source), &mut output, &options).unwrap();
+
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic::new(
+                Severity::Error,
+                2,
+                "%IF blocks nested deeper than the limit of 1"
+            )
+            .with_code("V004")]
+        );
+        assert_eq!(output, vec!["C = 3;", "D = 4;"]);
+    }
+
+    #[test]
+    fn test_max_output_bytes_stops_expansion_bomb() {
+        // Each macro repeats the previous one ten times, so `E` expands to
+        // 10,000 copies of `XXXXXXXXX`.
+        let source = concat!(
+            "%MACRO A; XXXXXXXXX %ENDMACRO;\n",
+            "%MACRO B; A A A A A A A A A A %ENDMACRO;\n",
+            "%MACRO C; B B B B B B B B B B %ENDMACRO;\n",
+            "%MACRO D; C C C C C C C C C C %ENDMACRO;\n",
+            "%MACRO E; D D D D D D D D D D %ENDMACRO;\n",
+            "Y = 1;\n",
+            "X = E;\n",
+        );
+        let options = ProcessOptions::builder()
+            .max_output_bytes(Some(10_000))
+            .build();
+        let mut output: Vec<String> = Vec::new();
+        let result = process_reader(Cursor::new(source), &mut output, &options);
+        assert_eq!(
+            result.unwrap_err(),
+            PreprocessorError::Other(
+                "Output exceeds the limit of 10000 bytes at line 7".to_string()
+            )
+        );
+        assert_eq!(output, vec!["Y = 1;"]);
+
+        // Without a limit the whole expansion is written.
+        let mut output: Vec<String> = Vec::new();
+        process_reader(Cursor::new(source), &mut output, &ProcessOptions::default()).unwrap();
+        assert!(output.concat().len() > 100_000);
+    }
+
+    #[test]
+    fn test_max_output_bytes_stops_self_doubling_macro() {
+        // `D64` doubles 64 times; the run must stop at the limit rather than
+        // try to build the whole line first.
+        let mut source = String::from("%MACRO D0; X %ENDMACRO;\n");
+        for level in 1..=64 {
+            source.push_str(&format!(
+                "%MACRO D{}; D{} D{} %ENDMACRO;\n",
+                level,
+                level - 1,
+                level - 1
+            ));
+        }
+        source.push_str("Y = D64;\n");
+        let options = ProcessOptions::builder()
+            .max_output_bytes(Some(1_000_000))
+            .build();
+        let mut output: Vec<String> = Vec::new();
+        let result = process_reader(Cursor::new(source), &mut output, &options);
+        assert_eq!(
+            result.unwrap_err(),
+            PreprocessorError::Other(
+                "Output exceeds the limit of 1000000 bytes at line 66".to_string()
+            )
+        );
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn test_multiline_comment_hides_directives() {
+        let source = "/* x\n%IF 1 = 0 %THEN;\n*/\nA;\n";
+        let mut output: Vec<String> = Vec::new();
+        let result =
+            process_reader(Cursor::new(source), &mut output, &ProcessOptions::default()).unwrap();
+        assert!(result.diagnostics.is_empty());
+        assert_eq!(output, vec!["/* x", "%IF 1 = 0 %THEN;", "*/", "A;"]);
+    }
+
+    #[test]
+    fn test_multiline_comment_does_not_apply_set() {
+        let source = "%DCL X FIXED;\n%SET X = 1;\nA = 1; /* old:\n   %SET X = 2;\n*/ %SET X = X + 10; B = X;\n";
+        let mut context = Context::new();
+        let mut output: Vec<String> = Vec::new();
+        let result = process_reader_with_context(
+            Cursor::new(source),
+            &mut output,
+            &ProcessOptions::default(),
+            &mut context,
+        )
+        .unwrap();
+        assert!(result.diagnostics.is_empty());
+        assert_eq!(context.get("X"), Some(&Value::Int(11)));
+        assert_eq!(
+            output,
+            vec!["A = 1; /* old:", "   %SET X = 2;", "*/ B = X;"]
+        );
+    }
+
+    #[test]
+    fn test_multiline_comment_keeps_keyword_case() {
+        let source = "dcl a; /* dcl\n   declare end\n   end */ dcl b;\n";
+        let options = ProcessOptions::builder()
+            .keyword_case(KeywordCase::Upper)
+            .build();
+        let mut output: Vec<String> = Vec::new();
+        process_reader(Cursor::new(source), &mut output, &options).unwrap();
+        assert_eq!(
+            output,
+            vec!["DCL a; /* dcl", "   declare end", "   end */ DCL b;"]
+        );
+    }
+
+    #[test]
+    fn test_block_if_selects_branch() {
+        let source = concat!(
+            "%IF DEBUG = 1 %THEN;\n",
+            "%SET MODE = 'TRACE';\n",
+            "A = 1;\n",
+            "%ELSE;\n",
+            "%SET MODE = 'QUIET';\n",
+            "B = 2;\n",
+            "%IF 1 = 1 %THEN; C = 3; %ENDIF;\n",
+            "%ENDIF;\n",
+            "D = MODE;\n",
+        );
+        let run = |debug: i64| {
+            let mut context = Context::new();
+            context.set("DEBUG", Value::Int(debug));
+            let mut output: Vec<String> = Vec::new();
+            let result = process_reader_with_context(
+                Cursor::new(source),
+                &mut output,
+                &ProcessOptions::default(),
+                &mut context,
+            )
+            .unwrap();
+            assert!(result.diagnostics.is_empty());
+            (output, context.get("MODE").cloned())
+        };
+
+        assert_eq!(
+            run(1),
+            (
+                vec!["A = 1;".to_string(), "D = MODE;".to_string()],
+                Some(Value::Str("TRACE".to_string()))
+            )
+        );
+        assert_eq!(
+            run(0),
+            (
+                vec![
+                    "B = 2;".to_string(),
+                    "C = 3;".to_string(),
+                    "D = MODE;".to_string()
+                ],
+                Some(Value::Str("QUIET".to_string()))
+            )
+        );
+    }
+
+    #[test]
+    fn test_block_if_undefined_condition() {
+        let source = "%IF UNDEF = 1 %THEN;\nA = 1;\n%ELSE;\nB = 2;\n%ENDIF;\n";
+        let mut output: Vec<String> = Vec::new();
+        let result =
+            process_reader(Cursor::new(source), &mut output, &ProcessOptions::default()).unwrap();
+        assert_eq!(output, vec!["B = 2;"]);
+        assert_eq!(
+            result.diagnostics,
+            vec![
+                Diagnostic::new(Severity::Error, 1, "Undefined variable: UNDEF").with_code("X001")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_would_emit_agrees_with_pipeline() {
+        let source = concat!(
+            "A = 1;\n",
+            "%IF DEBUG = 1 %THEN;\n",
+            "B = 2;\n",
+            "%SET LEVEL = LEVEL + 1;\n",
+            "%IF LEVEL > 2 %THEN;\n",
+            "C = 3;\n",
+            "%ELSE;\n",
+            "D = 4;\n",
+            "%ENDIF;\n",
+            "%ELSE;\n",
+            "E = 5;\n",
+            "%IF 1 = 1 %THEN; F = 6; %ENDIF;\n",
+            "%ENDIF;\n",
+            "G = 7;\n",
+        );
+        for (debug, level) in [(1, 1), (1, 2), (1, 5), (0, 5)] {
+            let mut ctx = Context::new();
+            ctx.set("DEBUG", Value::Int(debug));
+            ctx.set("LEVEL", Value::Int(level));
+
+            let mut emitted: Vec<usize> = Vec::new();
+            let mut record = |line: usize, _: &[Token], written: bool| {
+                if written {
+                    emitted.push(line);
+                }
+            };
+            let mut output: Vec<String> = Vec::new();
+            process_reader_with_line_hook(
+                Cursor::new(source),
+                &mut output,
+                &ProcessOptions::default(),
+                &mut ctx.clone(),
+                Some(&mut record),
+            )
+            .unwrap();
+
+            // Directive lines are active but never written.
+            let expected: Vec<usize> = source
+                .lines()
+                .enumerate()
+                .filter(|(_, line)| !line.starts_with('%') || line.contains("F = 6;"))
+                .map(|(index, _)| index + 1)
+                .filter(|&line| would_emit(source, line, &ctx))
+                .collect();
+            assert_eq!(emitted, expected, "for DEBUG={} LEVEL={}", debug, level);
+        }
+    }
+
+    #[test]
+    fn test_would_emit_nested_conditionals() {
+        let source = concat!(
+            "A = 1;\n",               // 1
+            "%IF DEBUG = 1 %THEN;\n", // 2
+            "B = 2;\n",               // 3
+            "%IF LEVEL > 2 %THEN;\n", // 4
+            "C = 3;\n",               // 5
+            "%ELSE;\n",               // 6
+            "D = 4;\n",               // 7
+            "%ENDIF;\n",              // 8
+            "%ELSE;\n",               // 9
+            "E = 5;\n",               // 10
+            "%ENDIF;\n",              // 11
+            "F = 6;\n",               // 12
+        );
+        let status = |ctx: &Context| -> Vec<bool> {
+            (1..=12).map(|line| would_emit(source, line, ctx)).collect()
+        };
+
+        let mut ctx = Context::new();
+        ctx.set("DEBUG", Value::Int(1));
+        ctx.set("LEVEL", Value::Int(3));
+        assert_eq!(
+            status(&ctx),
+            vec![true, true, true, true, true, true, false, true, true, false, true, true]
+        );
+
+        ctx.set("LEVEL", Value::Int(1));
+        assert_eq!(
+            status(&ctx),
+            vec![true, true, true, true, false, true, true, true, true, false, true, true]
+        );
+
+        ctx.set("DEBUG", Value::Int(0));
+        assert_eq!(
+            status(&ctx),
+            vec![true, true, false, false, false, false, false, false, true, true, true, true]
+        );
+
+        assert!(!would_emit(source, 0, &ctx));
+        assert!(!would_emit(source, 13, &ctx));
+    }
+
+    #[test]
+    fn test_would_emit_applies_set_in_active_code() {
+        let source = "%SET MODE = 'FAST';\n%IF MODE = 'FAST' %THEN;\nA = 1;\n%ENDIF;\n";
+        assert!(would_emit(source, 3, &Context::new()));
+
+        let source = "%IF 0 %THEN;\n%SET MODE = 'FAST';\n%ENDIF;\n%IF MODE = 'FAST' %THEN;\nA = 1;\n%ENDIF;\n";
+        let mut ctx = Context::new();
+        ctx.set("MODE", Value::Str("SLOW".to_string()));
+        assert!(!would_emit(source, 5, &ctx));
+        assert_eq!(ctx.get("MODE"), Some(&Value::Str("SLOW".to_string())));
+    }
+
+    #[test]
+    fn test_would_emit_endif_with_or_without_semicolon() {
+        for endif in ["%ENDIF;", "%ENDIF", "%endif"] {
+            let source = format!(
+                "%IF 0 %THEN;\nA = 1;\n{}\nB = 2;\n%IF 1 %THEN;\nC = 3;\n{}\n",
+                endif, endif
+            );
+            let emitted: Vec<bool> = (1..=7)
+                .map(|line| would_emit(&source, line, &Context::new()))
+                .collect();
+            assert_eq!(
+                emitted,
+                vec![true, false, true, true, true, true, true],
+                "for {:?}",
+                endif
+            );
+
+            let mut output: Vec<String> = Vec::new();
+            let result =
+                process_reader(Cursor::new(source), &mut output, &ProcessOptions::default())
+                    .unwrap();
+            assert!(result.diagnostics.is_empty(), "for {:?}", endif);
+        }
+
+        // Code after a bare `%ENDIF` on the same line is outside the block.
+        let source = "%IF 0 %THEN;\n%SET MODE = 'A';\n%ENDIF %SET MODE = 'B';\n%IF MODE = 'B' %THEN;\nX = 1;\n%ENDIF\n";
+        assert!(would_emit(source, 5, &Context::new()));
+    }
+
+    #[test]
+    fn test_print_directives_do_not_affect_output() {
+        let source = "A = 1;\n%NOPRINT;\nB = 2;\n%PRINT;\nC = 3;\n";
+        let mut output: Vec<String> = Vec::new();
+        let result =
+            process_reader(Cursor::new(source), &mut output, &ProcessOptions::default()).unwrap();
+        assert_eq!(output, vec!["A = 1;", "B = 2;", "C = 3;"]);
+        assert!(result.diagnostics.is_empty());
+        assert_eq!(format_listing(source), "    1 A = 1;\n    5 C = 3;\n");
+    }
+
+    #[test]
+    fn test_warn_indentation_option() {
+        let source = "\tA = 1;\n    B = 2;\n \tC = 3;\n";
+        let options = ProcessOptions::builder().warn_indentation(true).build();
+        let mut output: Vec<String> = Vec::new();
+        let result = process_reader(Cursor::new(source), &mut output, &options).unwrap();
+        assert_eq!(
+            result.diagnostics,
+            vec![
+                Diagnostic::new(Severity::Note, 3, "indentation mixes tabs and spaces")
+                    .with_code("A002")
+                    .with_column(1)
+            ]
+        );
+
+        let mut output: Vec<String> = Vec::new();
+        let result =
+            process_reader(Cursor::new(source), &mut output, &ProcessOptions::default()).unwrap();
+        assert!(result.diagnostics.is_empty());
+    }
+}
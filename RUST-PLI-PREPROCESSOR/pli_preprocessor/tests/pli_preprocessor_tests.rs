@@ -18,6 +18,10 @@
 // - test_handle_special_characters: Verifies proper handling of special characters.
 // - test_string_literals: Ensures string literals are correctly tokenized.
 // - test_edge_cases: Validates edge case handling like empty or malformed input.
+// - test_directive_histogram: Validates directive category counts for a file.
+// - test_extended_identifier_chars: Validates `$`, `@`, and `#` in identifiers.
+// - test_extended_identifier_chars_disallowed: Validates splitting when disabled.
+// - test_suffixed_literals: Validates bit and hex string constants.
 //
 // -----------------------------------------------------------------------------
 // AUTHOR:
@@ -36,10 +40,13 @@
 // -----------------------------------------------------------------------------
 ////////////////////////////////////////////////////////////////////////////////
 
-
 #[cfg(test)]
 mod tests {
-    use pli_preprocessor::modules::tokenizer::{tokenize_pli, DirectiveCategory, TokenCategory};
+    use pli_preprocessor::modules::tokenizer::{
+        directive_histogram, has_tokenizer_error, tokenize_pli, tokenize_pli_with_config,
+        CharClassConfig, DirectiveCategory, LiteralKind, TokenCategory, TokenizerConfig,
+    };
+    use std::fs;
 
     ////////////////////////////////////////////////////////////////////////////////
     // TEST: test_case_insensitivity
@@ -151,12 +158,144 @@ mod tests {
         let empty_tokens = tokenize_pli(empty_input);
         assert_eq!(empty_tokens.len(), 0, "Expected 0 tokens for empty input");
 
-        let unexpected_input = "@invalid";
+        let unexpected_input = "!invalid";
         let unexpected_tokens = tokenize_pli(unexpected_input);
         assert_eq!(
             unexpected_tokens[0].category,
             TokenCategory::Unknown,
-            "Expected 'Unknown' category for '@'"
+            "Expected 'Unknown' category for '!'"
         );
     }
+
+    ////////////////////////////////////////////////////////////////////////////////
+    // TEST: test_directive_histogram
+    // -----------------------------------------------------------------------------
+    // Verifies that directive categories are counted across a whole file.
+    // -----------------------------------------------------------------------------
+    #[test]
+    fn test_directive_histogram() {
+        let source = fs::read_to_string("tests/input/directive_mix.pli")
+            .expect("Failed to read directive_mix.pli");
+        let tokens: Vec<_> = source.lines().flat_map(tokenize_pli).collect();
+
+        let histogram = directive_histogram(&tokens);
+
+        assert_eq!(histogram.get(&DirectiveCategory::MacroHandling), Some(&3));
+        assert_eq!(histogram.get(&DirectiveCategory::ControlFlow), Some(&6));
+        assert_eq!(histogram.get(&DirectiveCategory::Comment), Some(&1));
+        assert_eq!(histogram.get(&DirectiveCategory::Conditional), None);
+    }
+
+    ////////////////////////////////////////////////////////////////////////////////
+    // TEST: test_extended_identifier_chars
+    // -----------------------------------------------------------------------------
+    // Verifies that `$`, `@`, and `#` are accepted in identifiers by default.
+    // -----------------------------------------------------------------------------
+    #[test]
+    fn test_extended_identifier_chars() {
+        for input in ["MY$VAR", "@FLAG", "REC#NO"] {
+            let tokens = tokenize_pli(input);
+            assert_eq!(
+                tokens.len(),
+                1,
+                "Expected 1 token for {}, got {:?}",
+                input,
+                tokens
+            );
+            assert_eq!(tokens[0].value, input);
+            assert_eq!(tokens[0].category, TokenCategory::Identifier);
+        }
+    }
+
+    ////////////////////////////////////////////////////////////////////////////////
+    // TEST: test_extended_identifier_chars_disallowed
+    // -----------------------------------------------------------------------------
+    // Verifies that extended characters split identifiers when not configured.
+    // -----------------------------------------------------------------------------
+    #[test]
+    fn test_extended_identifier_chars_disallowed() {
+        let config = TokenizerConfig {
+            char_classes: CharClassConfig {
+                identifier_start: vec![],
+                identifier_continue: vec![],
+                ..CharClassConfig::default()
+            },
+            ..TokenizerConfig::default()
+        };
+
+        let tokens = tokenize_pli_with_config("MY$VAR", &config);
+        let values: Vec<&str> = tokens.iter().map(|t| t.value.as_str()).collect();
+        assert_eq!(values, vec!["MY", "$", "VAR"]);
+        assert_eq!(tokens[1].category, TokenCategory::Unknown);
+
+        let tokens = tokenize_pli_with_config("@FLAG", &config);
+        assert_eq!(tokens[0].value, "@");
+        assert_eq!(tokens[0].category, TokenCategory::Unknown);
+
+        let tokens = tokenize_pli_with_config("REC#NO", &config);
+        let values: Vec<&str> = tokens.iter().map(|t| t.value.as_str()).collect();
+        assert_eq!(values, vec!["REC", "#", "NO"]);
+        assert_eq!(tokens[1].category, TokenCategory::Operator);
+    }
+
+    ////////////////////////////////////////////////////////////////////////////////
+    // TEST: test_spaced_directives
+    // -----------------------------------------------------------------------------
+    // Verifies that `% IF` is only joined into `%IF` when the option is enabled.
+    // -----------------------------------------------------------------------------
+    #[test]
+    fn test_spaced_directives() {
+        let spaced = TokenizerConfig {
+            allow_spaced_directives: true,
+            ..TokenizerConfig::default()
+        };
+
+        for input in ["% IF X = 1 %THEN;", "%IF X = 1 %THEN;"] {
+            let tokens = tokenize_pli_with_config(input, &spaced);
+            assert_eq!(tokens[0].value, "%IF", "for {}", input);
+            assert_eq!(tokens[0].category, TokenCategory::Directive);
+            assert_eq!(
+                tokens[0].directive_category,
+                Some(DirectiveCategory::ControlFlow)
+            );
+            assert_eq!(tokens[1].value, "X");
+        }
+        let tokens = tokenize_pli_with_config("%  if X", &spaced);
+        assert_eq!(tokens[0].value, "%IF");
+        assert_eq!(tokens[0].original, "%  if");
+
+        // A `%` followed by something other than a keyword is left alone.
+        let tokens = tokenize_pli_with_config("% = 1", &spaced);
+        assert_eq!(tokens[0].value, "%");
+
+        // Off by default: the `%` stands alone and the keyword is an identifier.
+        let tokens = tokenize_pli_with_config("% IF X", &TokenizerConfig::default());
+        let values: Vec<&str> = tokens.iter().map(|t| t.value.as_str()).collect();
+        assert_eq!(values, vec!["%", "IF", "X"]);
+    }
+
+    ////////////////////////////////////////////////////////////////////////////////
+    // TEST: test_suffixed_literals
+    // -----------------------------------------------------------------------------
+    // Verifies that `B` and `X` suffixes are folded into the literal token.
+    // -----------------------------------------------------------------------------
+    #[test]
+    fn test_suffixed_literals() {
+        let tokens = tokenize_pli("FLAGS = '1010'B;");
+        assert_eq!(tokens.len(), 4, "Expected 4 tokens, got {:?}", tokens);
+        assert_eq!(tokens[2].value, "'1010'B");
+        assert_eq!(tokens[2].category, TokenCategory::Literal);
+        assert_eq!(tokens[2].literal_kind, Some(LiteralKind::Bit));
+
+        let tokens = tokenize_pli("MASK = 'FF'x;");
+        assert_eq!(tokens.len(), 4, "Expected 4 tokens, got {:?}", tokens);
+        assert_eq!(tokens[2].value, "'FF'X");
+        assert_eq!(tokens[2].literal_kind, Some(LiteralKind::Hex));
+
+        let tokens = tokenize_pli("NAME = 'text';");
+        assert_eq!(tokens[2].value, "'text'");
+        assert_eq!(tokens[2].literal_kind, Some(LiteralKind::Character));
+
+        assert!(!has_tokenizer_error(&tokenize_pli("'1010'B 'FF'X")));
+    }
 }
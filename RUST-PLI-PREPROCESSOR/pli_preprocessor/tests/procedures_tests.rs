@@ -0,0 +1,105 @@
+////////////////////////////////////////////////////////////////////////////////
+// MODULE NAME: Preprocessor Procedures Tests
+// ----------------------------------------------------------------------------
+// DESCRIPTION:
+// Tests for the `Preprocessor Procedures` module.
+//
+// FUNCTIONALITY:
+// - Verifies procedures are defined from `%PROCEDURE` ... `%END` statements.
+// - Verifies calls with and without arguments inside expressions.
+// - Verifies arity, undefined-procedure, and missing-`%RETURN` errors.
+//
+// AUTHOR: FirstLink Consulting Services (FLCS)
+// LICENSE: MIT License
+// DATE: 10/16/2026
+// VERSION: 1.0.0
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use pli_preprocessor::modules::context::{Context, Value};
+    use pli_preprocessor::modules::procedures::{parse_proc_header, ProcBuilder, ProcTable};
+    use pli_preprocessor::modules::tokenizer::tokenize_pli;
+
+    fn define(procs: &mut ProcTable, statements: &[&str]) {
+        let header = parse_proc_header(&tokenize_pli(statements[0]))
+            .unwrap()
+            .unwrap();
+        let mut builder = ProcBuilder::new(&header.0, header.1);
+        for statement in &statements[1..] {
+            if builder.add_statement(&tokenize_pli(statement)).unwrap() {
+                break;
+            }
+        }
+        builder.finish(procs).unwrap();
+    }
+
+    #[test]
+    fn test_parse_proc_header() {
+        assert_eq!(
+            parse_proc_header(&tokenize_pli("%TWENTY: PROCEDURE;")),
+            Some(Ok(("TWENTY".to_string(), vec![])))
+        );
+        assert_eq!(
+            parse_proc_header(&tokenize_pli("%ADD: PROC(A, B);")),
+            Some(Ok((
+                "ADD".to_string(),
+                vec!["A".to_string(), "B".to_string()]
+            )))
+        );
+        assert_eq!(parse_proc_header(&tokenize_pli("%SET X = 1;")), None);
+    }
+
+    #[test]
+    fn test_call_without_arguments() {
+        let mut procs = ProcTable::new();
+        define(
+            &mut procs,
+            &["%TWENTY: PROCEDURE;", "%RETURN(10 * 2);", "%END;"],
+        );
+        let context = Context::new();
+
+        assert!(procs.is_defined("twenty"));
+        assert_eq!(procs.call("TWENTY", &[], &context), Ok(Value::Int(20)));
+        assert_eq!(
+            procs.evaluate(&tokenize_pli("TWENTY + 1"), &context),
+            Ok(Value::Int(21))
+        );
+    }
+
+    #[test]
+    fn test_call_with_arguments() {
+        let mut procs = ProcTable::new();
+        define(
+            &mut procs,
+            &["%DOUBLE: PROC(N);", "%RETURN(N * 2);", "%END;"],
+        );
+        let mut context = Context::new();
+        context.set("X", Value::Int(4));
+
+        assert_eq!(
+            procs.evaluate(&tokenize_pli("DOUBLE(X + 1) + DOUBLE(1)"), &context),
+            Ok(Value::Int(12))
+        );
+        assert_eq!(
+            procs.call("DOUBLE", &[], &context),
+            Err("Procedure DOUBLE expects 1 argument(s), got 0".to_string())
+        );
+    }
+
+    #[test]
+    fn test_procedure_errors() {
+        let procs = ProcTable::new();
+        assert_eq!(
+            procs.call("MISSING", &[], &Context::new()),
+            Err("Undefined procedure: MISSING".to_string())
+        );
+
+        let mut builder = ProcBuilder::new("EMPTY", vec![]);
+        assert_eq!(builder.add_statement(&tokenize_pli("%END;")), Ok(true));
+        assert_eq!(
+            builder.finish(&mut ProcTable::new()),
+            Err("Procedure EMPTY has no %RETURN".to_string())
+        );
+    }
+}
@@ -0,0 +1,62 @@
+////////////////////////////////////////////////////////////////////////////////
+// MODULE NAME: Document Symbols Tests
+// ----------------------------------------------------------------------------
+// DESCRIPTION:
+// Tests for the `Document Symbols` module.
+//
+// FUNCTIONALITY:
+// - Verifies `document_symbols` extracts macros, includes, and declarations
+//   with their line ranges from a mixed source.
+// - Verifies `declared_names` on factored and attributed declarations.
+//
+// AUTHOR: FirstLink Consulting Services (FLCS)
+// LICENSE: MIT License
+// DATE: 10/16/2026
+// VERSION: 1.0.0
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use pli_preprocessor::modules::symbols::{
+        declared_names, document_symbols, DocumentSymbol, SymbolKind,
+    };
+    use pli_preprocessor::modules::tokenizer::tokenize_pli;
+
+    #[test]
+    fn test_document_symbols_mixed_source() {
+        let source = "\
+%INCLUDE 'common.pli';
+%MACRO GREETING;
+  'HELLO'
+%ENDMACRO;
+DCL COUNT FIXED BIN(31);
+%DECLARE MODE CHAR;
+declare (first, last) char(10),
+        total fixed;
+%MACRO INLINE; 1 %ENDMACRO;
+X = GREETING;
+";
+        assert_eq!(
+            document_symbols(source),
+            vec![
+                DocumentSymbol::new("common.pli", SymbolKind::Include, 1, 1),
+                DocumentSymbol::new("GREETING", SymbolKind::Macro, 2, 4),
+                DocumentSymbol::new("COUNT", SymbolKind::Declare, 5, 5),
+                DocumentSymbol::new("MODE", SymbolKind::Declare, 6, 6),
+                DocumentSymbol::new("FIRST", SymbolKind::Declare, 7, 8),
+                DocumentSymbol::new("LAST", SymbolKind::Declare, 7, 8),
+                DocumentSymbol::new("TOTAL", SymbolKind::Declare, 7, 8),
+                DocumentSymbol::new("INLINE", SymbolKind::Macro, 9, 9),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_declared_names() {
+        assert_eq!(
+            declared_names(&tokenize_pli("DCL (A, B) FIXED, C CHAR(8);")),
+            vec!["A", "B", "C"]
+        );
+        assert_eq!(declared_names(&tokenize_pli("DECLARE X;")), vec!["X"]);
+    }
+}
@@ -1,19 +1,30 @@
 #[cfg(test)]
 mod tests {
-    use pli_tokenizer::modules::tokenizer::tokenize_pli;
+    use pli_preprocessor::modules::tokenizer::{
+        classify_line, filter_by_category, filter_directives, format_token_stats_json,
+        format_token_stats_table, has_tokenizer_error, is_valid_preprocessor_directive,
+        join_continued_tokens, strip_comments, token_stats, tokenize_pli, tokenize_pli_preserve_ws,
+        tokenize_pli_with_config, CharClassConfig, DirectiveCategory, LineClass, Token,
+        TokenCategory, TokenIter, TokenizerConfig,
+    };
+
+    /// Tokenizes `input` and returns only the token values.
+    fn token_values(input: &str) -> Vec<String> {
+        tokenize_pli(input).into_iter().map(|t| t.value).collect()
+    }
 
     #[test]
     fn test_basic_directives() {
         let input = "%IF DEBUG %THEN;";
         let expected = vec!["%IF", "DEBUG", "%THEN", ";"];
-        assert_eq!(tokenize_pli(input), expected);
+        assert_eq!(token_values(input), expected);
     }
 
     #[test]
     fn test_edge_case_incomplete_directive() {
         let input = "%IF DEBUG";
         let expected = vec!["%IF", "DEBUG"];
-        assert_eq!(tokenize_pli(input), expected);
+        assert_eq!(token_values(input), expected);
     }
 
     #[test]
@@ -33,7 +44,7 @@ mod tests {
             "%ENDIF",
             ";",
         ];
-        assert_eq!(tokenize_pli(input), expected);
+        assert_eq!(token_values(input), expected);
     }
 
     #[test]
@@ -49,23 +60,599 @@ mod tests {
             "%THEN",
             ";",
         ];
-        assert_eq!(tokenize_pli(input), expected);
+        assert_eq!(token_values(input), expected);
     }
 
     #[test]
     fn test_special_characters() {
         let input = "%IF DEBUG *&^%$#@!(){}[]<>;";
         let expected = vec![
-            "%IF", "DEBUG", "*", "&", "^", "%", "$", "#", "@", "!", "(", ")", "{", "}", "[", "]",
-            "<", ">", ";",
+            "%IF", "DEBUG", "*", "&", "^", "%", "$#@", "!", "(", ")", "{", "}", "[", "]", "<", ">",
+            ";",
         ];
-        assert_eq!(tokenize_pli(input), expected);
+        assert_eq!(token_values(input), expected);
     }
 
     #[test]
     fn test_empty_input() {
         let input = "";
         let expected: Vec<String> = vec![];
-        assert_eq!(tokenize_pli(input), expected);
+        assert_eq!(token_values(input), expected);
+    }
+
+    #[test]
+    fn test_underscored_identifiers() {
+        assert_eq!(token_values("A_B_C"), vec!["A_B_C"]);
+        assert_eq!(
+            token_values("MULTI_PART_STATEMENT = 1;"),
+            vec!["MULTI_PART_STATEMENT", "=", "1", ";"]
+        );
+        assert_eq!(
+            token_values("_leading trailing_"),
+            vec!["_LEADING", "TRAILING_"]
+        );
+    }
+
+    #[test]
+    fn test_token_columns() {
+        let columns: Vec<(String, usize)> = tokenize_pli("%IF X='A'B %THEN;")
+            .into_iter()
+            .map(|t| (t.value, t.column))
+            .collect();
+        assert_eq!(
+            columns,
+            vec![
+                ("%IF".to_string(), 1),
+                ("X".to_string(), 5),
+                ("=".to_string(), 6),
+                ("'A'B".to_string(), 7),
+                ("%THEN".to_string(), 12),
+                (";".to_string(), 17),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_preserve_ws_round_trips() {
+        for line in ["  %IF  debug = 1\t%THEN ;  ", "name = 'a  b'x;", "", "\t"] {
+            let tokens = tokenize_pli_preserve_ws(line);
+            let joined: String = tokens.iter().map(|t| t.original.as_str()).collect();
+            assert_eq!(joined, line);
+        }
+
+        let tokens = tokenize_pli_preserve_ws("A  =\tb");
+        let categories: Vec<TokenCategory> = tokens.iter().map(|t| t.category.clone()).collect();
+        assert_eq!(
+            categories,
+            vec![
+                TokenCategory::Identifier,
+                TokenCategory::Whitespace,
+                TokenCategory::Operator,
+                TokenCategory::Whitespace,
+                TokenCategory::Identifier,
+            ]
+        );
+        assert_eq!(tokens[1].value, "  ");
+        assert_eq!(tokens[4].value, "B");
+        assert_eq!(tokens[4].original, "b");
+    }
+
+    #[test]
+    fn test_category_names_round_trip() {
+        for category in TokenCategory::ALL {
+            assert_eq!(category.as_str().parse::<TokenCategory>(), Ok(category));
+        }
+        for category in DirectiveCategory::ALL {
+            assert_eq!(category.as_str().parse::<DirectiveCategory>(), Ok(category));
+        }
+    }
+
+    #[test]
+    fn test_category_names_ignore_case() {
+        assert_eq!("identifier".parse(), Ok(TokenCategory::Identifier));
+        assert_eq!("WHITESPACE".parse(), Ok(TokenCategory::Whitespace));
+        assert_eq!("controlflow".parse(), Ok(DirectiveCategory::ControlFlow));
+        assert_eq!(
+            "MacroHANDLING".parse(),
+            Ok(DirectiveCategory::MacroHandling)
+        );
+    }
+
+    #[test]
+    fn test_category_names_reject_unknown() {
+        assert_eq!(
+            "Keyword".parse::<TokenCategory>(),
+            Err("Unknown token category: Keyword".to_string())
+        );
+        assert!("".parse::<DirectiveCategory>().is_err());
+        assert!("Control Flow".parse::<DirectiveCategory>().is_err());
+    }
+
+    #[test]
+    fn test_classify_line() {
+        assert_eq!(classify_line(""), LineClass::Blank);
+        assert_eq!(classify_line(" \t "), LineClass::Blank);
+        assert_eq!(
+            classify_line("  %IF DEBUG %THEN;"),
+            LineClass::Directive(DirectiveCategory::ControlFlow)
+        );
+        assert_eq!(
+            classify_line("%include 'file.pli';"),
+            LineClass::Directive(DirectiveCategory::MacroHandling)
+        );
+        assert_eq!(classify_line("/* a comment */"), LineClass::Comment);
+        assert_eq!(classify_line("%COMMENT notes here;"), LineClass::Comment);
+        assert_eq!(classify_line("X = 1; /* trailing */"), LineClass::Code);
+        assert_eq!(classify_line("'literal' %IF"), LineClass::Code);
+    }
+
+    #[test]
+    fn test_escaped_quotes_stay_in_one_literal() {
+        assert_eq!(
+            token_values("X = 'IT''S' || '''';"),
+            vec!["X", "=", "'IT''S'", "|", "|", "''''", ";"]
+        );
+        let tokens = tokenize_pli("'A''B");
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].value, "'A''B");
+    }
+
+    #[test]
+    fn test_literal_ending_in_escaped_quote_is_unterminated() {
+        let tokens = tokenize_pli("X = 'abc''");
+        assert_eq!(tokens[2].value, "'abc''");
+        assert!(tokens[2].unterminated);
+        assert!(has_tokenizer_error(&tokens));
+
+        assert!(!has_tokenizer_error(&tokenize_pli("X = 'abc''';")));
+        assert!(!has_tokenizer_error(&tokenize_pli("X = '';")));
+        assert!(has_tokenizer_error(&tokenize_pli("X = '")));
+    }
+
+    #[test]
+    fn test_pointer_qualifier_is_one_operator() {
+        let tokens = tokenize_pli("P->Q");
+        assert_eq!(token_values("P->Q"), vec!["P", "->", "Q"]);
+        assert_eq!(tokens[1].category, TokenCategory::Operator);
+        assert_eq!(tokens[1].column, 2);
+        assert_eq!(tokens[2].column, 4);
+
+        assert_eq!(token_values("P -> FIELD;"), vec!["P", "->", "FIELD", ";"]);
+    }
+
+    #[test]
+    fn test_lone_minus_is_operator() {
+        let tokens = tokenize_pli("X = A-B;");
+        assert_eq!(token_values("X = A-B;"), vec!["X", "=", "A", "-", "B", ";"]);
+        assert_eq!(tokens[3].category, TokenCategory::Operator);
+
+        // A minus followed by a space and `>` stays two tokens.
+        assert_eq!(token_values("A - > B"), vec!["A", "-", ">", "B"]);
+    }
+
+    #[test]
+    fn test_arithmetic_and_comparison_operators() {
+        for (input, operator) in [
+            ("A - B", "-"),
+            ("A / B", "/"),
+            ("A < B", "<"),
+            ("A + B", "+"),
+            ("A > B", ">"),
+        ] {
+            let tokens = tokenize_pli(input);
+            assert_eq!(token_values(input), vec!["A", operator, "B"]);
+            assert_eq!(tokens[1].category, TokenCategory::Operator, "for {}", input);
+        }
+
+        let tokens = tokenize_pli("%IF A - B > 0 %THEN;");
+        let operators: Vec<&str> = tokens
+            .iter()
+            .filter(|t| t.category == TokenCategory::Operator)
+            .map(|t| t.value.as_str())
+            .collect();
+        assert_eq!(operators, vec!["-", ">"]);
+    }
+
+    #[test]
+    fn test_custom_char_classes() {
+        // A dialect with qualified names such as `REC.FIELD` as one identifier.
+        let dotted = TokenizerConfig {
+            char_classes: CharClassConfig {
+                identifier_continue: vec!['.'],
+                ..CharClassConfig::default()
+            },
+            ..TokenizerConfig::default()
+        };
+        let values = |input: &str, config: &TokenizerConfig| -> Vec<String> {
+            tokenize_pli_with_config(input, config)
+                .into_iter()
+                .map(|t| t.value)
+                .collect()
+        };
+
+        assert_eq!(
+            values("rec.field = a.b.c;", &dotted),
+            vec!["REC.FIELD", "=", "A.B.C", ";"]
+        );
+        let tokens = tokenize_pli_with_config("X = REC.FIELD;", &dotted);
+        assert_eq!(tokens[2].category, TokenCategory::Identifier);
+        assert_eq!(tokens[3].column, 14);
+        // `.` only continues identifiers, so a leading one stands alone.
+        assert_eq!(values(".X", &dotted), vec![".", "X"]);
+        // The default splits at the dot.
+        assert_eq!(token_values("REC.FIELD"), vec!["REC", ".", "FIELD"]);
+
+        // `identifier_start` characters may also begin an identifier.
+        let leading_dot = TokenizerConfig {
+            char_classes: CharClassConfig {
+                identifier_start: vec!['.'],
+                ..CharClassConfig::default()
+            },
+            ..TokenizerConfig::default()
+        };
+        assert_eq!(values(".X.Y", &leading_dot), vec![".X.Y"]);
+
+        // Operator characters are configurable too.
+        let tokens = tokenize_pli_with_config("A ! B ^ C", &TokenizerConfig::default());
+        assert_eq!(tokens[1].category, TokenCategory::Unknown);
+        let bang = TokenizerConfig {
+            char_classes: CharClassConfig {
+                operator_chars: vec!['!', '^'],
+                ..CharClassConfig::default()
+            },
+            ..TokenizerConfig::default()
+        };
+        let tokens = tokenize_pli_with_config("A ! B ^ C = D", &bang);
+        assert_eq!(tokens[1].category, TokenCategory::Operator);
+        assert_eq!(tokens[3].category, TokenCategory::Operator);
+        assert_eq!(tokens[5].category, TokenCategory::Unknown);
+    }
+
+    #[test]
+    fn test_bare_percent_is_not_a_directive() {
+        for (input, values) in [
+            ("%;", vec!["%", ";"]),
+            ("% ", vec!["%"]),
+            ("%", vec!["%"]),
+            ("A = % ;", vec!["A", "=", "%", ";"]),
+        ] {
+            let tokens = tokenize_pli(input);
+            assert_eq!(token_values(input), values, "for {:?}", input);
+            let percent = tokens.iter().find(|t| t.value == "%").unwrap();
+            assert_eq!(percent.category, TokenCategory::Unknown, "for {:?}", input);
+            assert_eq!(percent.directive_category, None, "for {:?}", input);
+        }
+        assert_eq!(tokenize_pli("A = % ;")[2].column, 5);
+
+        // With spaced directives, `%` is still bare unless a keyword follows.
+        let spaced = TokenizerConfig {
+            allow_spaced_directives: true,
+            ..TokenizerConfig::default()
+        };
+        let tokens = tokenize_pli_with_config("%   ", &spaced);
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].category, TokenCategory::Unknown);
+    }
+
+    #[test]
+    fn test_logical_symbols_are_operators() {
+        let tokens = tokenize_pli("%IF A = B & C = D | E %THEN;");
+        let operators: Vec<&str> = filter_by_category(&tokens, TokenCategory::Operator)
+            .into_iter()
+            .map(|t| t.value.as_str())
+            .collect();
+        assert_eq!(operators, vec!["=", "&", "=", "|"]);
+
+        // Doubled symbols stay one token per character.
+        assert_eq!(token_values("A && B"), vec!["A", "&", "&", "B"]);
+        let tokens = tokenize_pli("A || B");
+        assert_eq!(tokens[1].category, TokenCategory::Operator);
+        assert_eq!(tokens[2].category, TokenCategory::Operator);
+    }
+
+    #[test]
+    fn test_comment_opener_is_not_division() {
+        let tokens = tokenize_pli("/* note */");
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].value, "/* note */");
+        assert_eq!(tokens[0].category, TokenCategory::Comment);
+        assert_eq!(classify_line("/* note */"), LineClass::Comment);
+    }
+
+    #[test]
+    fn test_block_comments_do_not_nest() {
+        // The first `*/` closes the comment; the inner `/*` opens nothing.
+        let tokens = tokenize_pli("/* a /* b */ c");
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens[0].value, "/* a /* b */");
+        assert_eq!(tokens[0].category, TokenCategory::Comment);
+        assert_eq!(tokens[1].value, "C");
+        assert_eq!(tokens[1].category, TokenCategory::Identifier);
+        assert_eq!(tokens[1].column, 14);
+
+        assert_eq!(
+            token_values("X = 1; /*/ still open */ Y"),
+            vec!["X", "=", "1", ";", "/*/ still open */", "Y"]
+        );
+        assert_eq!(
+            token_values("A /* runs to the end"),
+            vec!["A", "/* runs to the end"]
+        );
+    }
+
+    #[test]
+    fn test_filter_by_category_extracts_operators() {
+        let tokens = tokenize_pli("X = A + B * C - 1;");
+        let operators: Vec<&str> = filter_by_category(&tokens, TokenCategory::Operator)
+            .iter()
+            .map(|t| t.value.as_str())
+            .collect();
+        assert_eq!(operators, vec!["=", "+", "*", "-"]);
+        assert!(filter_by_category(&tokens, TokenCategory::Literal).is_empty());
+    }
+
+    #[test]
+    fn test_filter_directives_extracts_control_flow() {
+        let tokens = tokenize_pli("%IF A %THEN %IF B %THEN %INCLUDE 'X'; %ELSE; %ENDIF; %ENDIF;");
+        let control: Vec<&str> = filter_directives(&tokens, DirectiveCategory::ControlFlow)
+            .iter()
+            .map(|t| t.value.as_str())
+            .collect();
+        assert_eq!(
+            control,
+            vec!["%IF", "%THEN", "%IF", "%THEN", "%ELSE", "%ENDIF", "%ENDIF"]
+        );
+        assert_eq!(
+            filter_directives(&tokens, DirectiveCategory::MacroHandling).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_tokenize_one_million_character_line() {
+        // 125,000 eight-character statements of four tokens each.
+        let line = "ab = 1; ".repeat(125_000);
+        assert_eq!(line.chars().count(), 1_000_000);
+
+        let tokens = tokenize_pli(&line);
+        assert_eq!(tokens.len(), 500_000);
+        assert_eq!(tokens[0].value, "AB");
+        assert_eq!(tokens[0].original, "ab");
+        assert_eq!(tokens[499_999].value, ";");
+        assert_eq!(tokens[499_999].column, 999_999);
+    }
+
+    #[test]
+    fn test_strip_comments() {
+        let stripped = strip_comments(tokenize_pli("A = 1; %COMMENT set 'A;' here; B = 2;"));
+        let values: Vec<String> = stripped.into_iter().map(|t| t.value).collect();
+        assert_eq!(values, token_values("A = 1; B = 2;"));
+
+        let stripped = strip_comments(tokenize_pli("A = /* one */ 1;"));
+        assert_eq!(stripped, tokenize_pli("A =           1;"));
+
+        let unterminated = strip_comments(tokenize_pli("X; %comment no end"));
+        assert_eq!(unterminated.len(), 2);
+        assert_eq!(
+            strip_comments(tokenize_pli("A = 1;")),
+            tokenize_pli("A = 1;")
+        );
+    }
+
+    #[test]
+    fn test_token_iter_yields_expected_tokens() {
+        use TokenCategory::{Directive, Identifier, Literal, Operator, Separator};
+        type Expected<'a> = &'a [(&'a str, TokenCategory, usize)];
+        let summary = |tokens: Vec<_>| -> Vec<(String, TokenCategory, usize)> {
+            tokens
+                .into_iter()
+                .map(|t: Token| (t.value, t.category, t.column))
+                .collect()
+        };
+        let expected =
+            |tokens: &[(&str, TokenCategory, usize)]| -> Vec<(String, TokenCategory, usize)> {
+                tokens
+                    .iter()
+                    .map(|(value, category, column)| (value.to_string(), category.clone(), *column))
+                    .collect()
+            };
+        let cases: [(&str, Expected); 6] = [
+            ("", &[]),
+            ("   ", &[]),
+            (
+                "%IF DEBUG = 1 %THEN;",
+                &[
+                    ("%IF", Directive, 1),
+                    ("DEBUG", Identifier, 5),
+                    ("=", Operator, 11),
+                    ("1", Identifier, 13),
+                    ("%THEN", Directive, 15),
+                    (";", Separator, 20),
+                ],
+            ),
+            (
+                "SET MESSAGE = 'Hello, World!' || 'unterminated",
+                &[
+                    ("SET", Identifier, 1),
+                    ("MESSAGE", Identifier, 5),
+                    ("=", Operator, 13),
+                    ("'Hello, World!'", Literal, 15),
+                    ("|", Operator, 31),
+                    ("|", Operator, 32),
+                    ("'unterminated", Literal, 34),
+                ],
+            ),
+            (
+                "P -> FIELD = '1010'B; LOOP: X = Y / 2;",
+                &[
+                    ("P", Identifier, 1),
+                    ("->", Operator, 3),
+                    ("FIELD", Identifier, 6),
+                    ("=", Operator, 12),
+                    ("'1010'B", Literal, 14),
+                    (";", Separator, 21),
+                    ("LOOP", Identifier, 23),
+                    (":", Separator, 27),
+                    ("X", Identifier, 29),
+                    ("=", Operator, 31),
+                    ("Y", Identifier, 33),
+                    ("/", Operator, 35),
+                    ("2", Identifier, 37),
+                    (";", Separator, 38),
+                ],
+            ),
+            (
+                "%comment note; A = B",
+                &[
+                    ("%COMMENT", Directive, 1),
+                    ("NOTE", Identifier, 10),
+                    (";", Separator, 14),
+                    ("A", Identifier, 16),
+                    ("=", Operator, 18),
+                    ("B", Identifier, 20),
+                ],
+            ),
+        ];
+        for (input, tokens) in cases {
+            assert_eq!(
+                summary(TokenIter::new(input).collect()),
+                expected(tokens),
+                "for {:?}",
+                input
+            );
+        }
+
+        let config = TokenizerConfig {
+            allow_spaced_directives: true,
+            ..TokenizerConfig::default()
+        };
+        assert_eq!(
+            summary(TokenIter::with_config("% IF X %THEN;", &config).collect()),
+            expected(&[
+                ("%IF", Directive, 1),
+                ("X", Identifier, 6),
+                ("%THEN", Directive, 8),
+                (";", Separator, 13),
+            ])
+        );
+
+        // Tokens are produced on demand.
+        let mut lazy = TokenIter::new("A = 1;");
+        assert_eq!(lazy.next().map(|t| t.value), Some("A".to_string()));
+        assert_eq!(lazy.next().map(|t| t.column), Some(3));
+    }
+
+    #[test]
+    fn test_join_continued_tokens() {
+        let source = "MSG = 'PART ONE, +\n  PART TWO, +\n  PART THREE';\nX = 1;\n";
+        let joined = join_continued_tokens(source, '+');
+        assert_eq!(
+            joined,
+            "MSG = 'PART ONE, PART TWO, PART THREE';\n\n\nX = 1;\n"
+        );
+        let first = joined.lines().next().unwrap();
+        assert_eq!(
+            token_values(first),
+            vec!["MSG", "=", "'PART ONE, PART TWO, PART THREE'", ";"]
+        );
+
+        assert_eq!(
+            join_continued_tokens("A = 1;\nB = 2;", '+'),
+            "A = 1;\nB = 2;"
+        );
+
+        // Blank lines inside a continuation are consumed, not joined.
+        assert_eq!(
+            join_continued_tokens("%SET MSG = 'AB+\n\n   \nCD';\nX = 1;\n", '+'),
+            "%SET MSG = 'ABCD';\n\n\n\nX = 1;\n"
+        );
+    }
+
+    #[test]
+    fn test_join_continued_tokens_outside_literal_keeps_operator() {
+        // A trailing `-` outside a literal is the subtraction operator.
+        let source = "A = B -\n C;\n";
+        assert_eq!(join_continued_tokens(source, '-'), source);
+
+        // Quotes that close on the line, are doubled, or sit in a comment do
+        // not leave a literal open.
+        assert_eq!(
+            join_continued_tokens("X = 'A' -\nY;\n", '-'),
+            "X = 'A' -\nY;\n"
+        );
+        assert_eq!(
+            join_continued_tokens("X = Y /* it's */ -\nZ;\n", '-'),
+            "X = Y /* it's */ -\nZ;\n"
+        );
+        assert_eq!(
+            join_continued_tokens("X = 'IT''S -\nDONE';\n", '-'),
+            "X = 'IT''S DONE';\n\n"
+        );
+    }
+
+    #[test]
+    fn test_token_stats() {
+        let source = "%IF DEBUG %THEN;\nTotal = Total + 1;\n%SET X = 'TOTAL';\n%ENDIF;\n";
+        let tokens: Vec<_> = source.lines().flat_map(tokenize_pli).collect();
+        let stats = token_stats(&tokens);
+
+        assert_eq!(stats.total_tokens, tokens.len());
+        assert_eq!(stats.category_counts[&TokenCategory::Directive], 4);
+        assert_eq!(stats.category_counts[&TokenCategory::Literal], 1);
+        assert!(!stats.category_counts.contains_key(&TokenCategory::Comment));
+        // DEBUG, TOTAL (twice, in either case), and X; `1` is not an identifier.
+        assert_eq!(stats.unique_identifiers, 3);
+        assert_eq!(stats.directives, 4);
+        assert_eq!(stats.directive_counts[&DirectiveCategory::ControlFlow], 3);
+        assert_eq!(stats.directive_counts[&DirectiveCategory::MacroHandling], 1);
+    }
+
+    #[test]
+    fn test_token_stats_empty() {
+        let stats = token_stats(&[]);
+        assert_eq!(stats.total_tokens, 0);
+        assert_eq!(stats.unique_identifiers, 0);
+        assert_eq!(stats.directives, 0);
+        assert!(stats.category_counts.is_empty());
+    }
+
+    #[test]
+    fn test_format_token_stats() {
+        let stats = token_stats(&tokenize_pli("%SET X = 1;"));
+
+        let table = format_token_stats_table(&stats);
+        assert!(table.starts_with("Tokens               5\n"));
+        assert!(table.contains("Unique identifiers   1\n"));
+        assert!(table.contains("Directives           1\n"));
+        // Categories that do not occur are listed with a zero count.
+        assert!(table.contains("  Comment            0\n"));
+
+        let json = format_token_stats_json(&stats);
+        assert!(json.starts_with("{\"total_tokens\": 5, \"categories\": {"));
+        assert!(json.contains("\"unique_identifiers\": 1, \"directives\": 1"));
+        assert!(json.ends_with("}}\n"));
+    }
+
+    #[test]
+    fn test_token_is_one_of() {
+        let tokens = tokenize_pli("%if X %THEN;");
+        assert!(tokens[0].is_one_of(&["%ELSE", "%IF"]));
+        assert!(tokens[2].is_one_of(&["%THEN"]));
+        assert!(!tokens[0].is_one_of(&["%ENDIF", "%THEN"]));
+        assert!(!tokens[1].is_one_of(&["%IF", "%THEN"]));
+        assert!(!tokens[0].is_one_of(&[]));
+    }
+
+    #[test]
+    fn test_is_valid_preprocessor_directive() {
+        assert!(is_valid_preprocessor_directive(&tokenize_pli(
+            "%INCLUDE 'a.pli';"
+        )));
+        assert!(is_valid_preprocessor_directive(&tokenize_pli("%endif;")));
+        assert!(!is_valid_preprocessor_directive(&tokenize_pli(
+            "%SET X = 1;"
+        )));
+        assert!(!is_valid_preprocessor_directive(&tokenize_pli("X = %IF;")));
+        assert!(!is_valid_preprocessor_directive(&[]));
     }
 }
@@ -8,6 +8,7 @@
 // - Basic validations for valid and invalid syntax.
 // - Edge case testing for unmatched directives.
 // - Comprehensive testing of valid and invalid directives.
+// - Located diagnostics from `validate_syntax_line`.
 //
 // AUTHOR: FirstLink Consulting Services (FLCS)
 // LICENSE: MIT License
@@ -16,7 +17,12 @@
 
 #[cfg(test)]
 mod tests {
-    use pli_tokenizer::modules::validator::{is_valid_directive, validate_syntax};
+    use pli_preprocessor::modules::diagnostics::{Diagnostic, DiagnosticSink, Severity};
+    use pli_preprocessor::modules::tokenizer::tokenize_pli;
+    use pli_preprocessor::modules::validator::{
+        check_directive_tokens, check_directives, is_valid_directive, validate_directive_arity,
+        validate_syntax, validate_syntax_line, SemicolonCheck,
+    };
 
     #[test]
     fn test_validate_syntax_basic() {
@@ -74,9 +80,165 @@ mod tests {
         assert!(result.is_ok(), "Nested syntax validation failed.");
     }
 
+    #[test]
+    fn test_validate_syntax_line_locates_errors() {
+        assert_eq!(
+            validate_syntax_line(&tokenize_pli("%IF DEBUG %THEN; %ENDIF;"), 3),
+            Ok(())
+        );
+        assert_eq!(
+            validate_syntax_line(&tokenize_pli("A = 1; %ENDIF;"), 12),
+            Err(
+                Diagnostic::new(Severity::Error, 12, "Unmatched %ENDIF found")
+                    .with_column(8)
+                    .with_code("V002")
+            )
+        );
+        assert_eq!(
+            validate_syntax_line(&tokenize_pli("X = 2; %FOO;"), 4),
+            Err(
+                Diagnostic::new(Severity::Error, 4, "Invalid directive: %FOO")
+                    .with_column(8)
+                    .with_code("V001")
+            )
+        );
+
+        // An open `%IF` is reported at the innermost one.
+        let error =
+            validate_syntax_line(&tokenize_pli("%IF A %THEN; %IF B %THEN;"), 9).unwrap_err();
+        assert_eq!((error.line, error.column), (9, Some(14)));
+        assert_eq!(error.code, Some("V003"));
+
+        let error = validate_syntax_line(&[], 5).unwrap_err();
+        assert_eq!((error.line, error.column), (5, None));
+        assert_eq!(error.message, "Empty token line");
+    }
+
     #[test]
     fn test_is_valid_directive() {
         assert!(is_valid_directive("%IF"));
         assert!(!is_valid_directive("%INVALID"));
     }
+
+    #[test]
+    fn test_check_directives() {
+        let tokens = vec![
+            "%INCLUDE".to_string(),
+            "'file.pli'".to_string(),
+            "%FOO".to_string(),
+        ];
+        let mut sink = DiagnosticSink::new();
+        check_directives(&tokens, 7, &mut sink);
+
+        let diagnostics = sink.into_diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, 7);
+        assert_eq!(diagnostics[0].message, "Invalid directive: %FOO");
+    }
+
+    #[test]
+    fn test_check_directive_tokens_reports_empty_directive() {
+        let empty = "Empty directive: '%' is not followed by a directive name";
+        for (input, column) in [("%;", 1), ("  % ", 3), ("X = 1 %", 7)] {
+            let mut sink = DiagnosticSink::new();
+            check_directive_tokens(&tokenize_pli(input), 2, &mut sink);
+            assert_eq!(
+                sink.into_diagnostics(),
+                vec![Diagnostic::new(Severity::Warning, 2, empty)
+                    .with_column(column)
+                    .with_code("V001")],
+                "for {:?}",
+                input
+            );
+        }
+    }
+
+    /// Runs a `SemicolonCheck` over `lines` and returns its diagnostics.
+    fn check_semicolons(lines: &[&str]) -> Vec<Diagnostic> {
+        let mut sink = DiagnosticSink::new();
+        let mut check = SemicolonCheck::new();
+        for (index, line) in lines.iter().enumerate() {
+            check.check_line(index + 1, &tokenize_pli(line), &mut sink);
+        }
+        check.finish(&mut sink);
+        sink.into_diagnostics()
+    }
+
+    #[test]
+    fn test_semicolon_check() {
+        assert_eq!(
+            check_semicolons(&["A = B"]),
+            vec![Diagnostic::new(Severity::Warning, 1, "Missing semicolon")
+                .with_code("V005")
+                .with_column(6)]
+        );
+        assert!(check_semicolons(&["A = B;"]).is_empty());
+        assert!(check_semicolons(&["A = B; /* done */", "/* BEGIN */"]).is_empty());
+        assert!(check_semicolons(&["A = B +", "    C;"]).is_empty());
+        assert!(check_semicolons(&["%IF DEBUG %THEN", "%ENDIF;"]).is_empty());
+        assert_eq!(
+            check_semicolons(&["X = 1; A = B", "%ENDIF;"]),
+            vec![Diagnostic::new(Severity::Warning, 1, "Missing semicolon")
+                .with_code("V005")
+                .with_column(13)]
+        );
+    }
+
+    fn arity(source: &str) -> Result<(), String> {
+        validate_directive_arity(&tokenize_pli(source))
+    }
+
+    #[test]
+    fn test_validate_directive_arity_accepts_well_formed_directives() {
+        for source in [
+            "%INCLUDE 'common.pli';",
+            "%INCLUDE SYSLIB(COMMON);",
+            "%INCLUDE A, B, C;",
+            "%INCLUDE 'a.pli', SYSLIB(COMMON);",
+            "%XINCLUDE 'common.pli';",
+            "%IF DEBUG = 1 %THEN;",
+            "%SET COUNT = COUNT + 1;",
+            "%DCL (A, B) FIXED;",
+            "%MACRO GREETING;",
+            "%ACTIVATE GREETING RESCAN;",
+            "%RETURN(X * 2);",
+            "%GO TO DONE;",
+            "%ENDIF;",
+            "%END LOOP;",
+            "%ELSE;",
+            "X = 1;",
+        ] {
+            assert_eq!(arity(source), Ok(()), "for {:?}", source);
+        }
+    }
+
+    #[test]
+    fn test_validate_directive_arity_rejects_malformed_directives() {
+        let cases = [
+            ("%INCLUDE;", "%INCLUDE requires a file name"),
+            (
+                "%INCLUDE 'a.pli' 'b.pli';",
+                "%INCLUDE takes a comma-separated list of file names",
+            ),
+            (
+                "%INCLUDE A, , B;",
+                "%INCLUDE takes a comma-separated list of file names",
+            ),
+            ("%xinclude;", "%XINCLUDE requires a file name"),
+            ("%IF %THEN;", "%IF requires a condition before %THEN"),
+            ("%IF DEBUG;", "%IF requires %THEN after its condition"),
+            ("%SET COUNT;", "%SET requires 'name = expression'"),
+            ("%SET = 1;", "%SET requires 'name = expression'"),
+            ("%SET COUNT =;", "%SET requires 'name = expression'"),
+            ("%DCL;", "%DCL requires a variable name"),
+            ("%MACRO;", "%MACRO requires a macro name"),
+            ("%RETURN X;", "%RETURN requires a parenthesized expression"),
+            ("%ENDIF DEBUG;", "%ENDIF takes no arguments"),
+            ("%END LOOP 2;", "%END takes at most a label"),
+            ("%GOTO;", "%GOTO requires a label"),
+        ];
+        for (source, message) in cases {
+            assert_eq!(arity(source), Err(message.to_string()), "for {:?}", source);
+        }
+    }
 }